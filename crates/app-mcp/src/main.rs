@@ -1,15 +1,37 @@
 mod mcp;
+mod platforms;
 
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
-
-use nexus_discord::{DiscordAdapter, DiscordConfig};
-use nexus_google::{GmailAdapter, GmailConfig};
-use nexus_messaging::AgentService;
-use nexus_slack::{SlackAdapter, SlackConfig};
-use nexus_tdlib::{AuthConfig, TdClient, TdlibAdapter};
-use nexus_whatsapp::{WhatsAppAdapter, WhatsAppConfig};
-use tracing::{error, info};
+use std::time::Duration;
+
+use nexus_discord::DiscordConfig;
+use nexus_domain::Platform;
+use nexus_error::AgentError;
+use nexus_google::GmailConfig;
+use nexus_messaging::middleware::{
+    AuditLayer, CacheLayer, DirectoryLayer, Guard, Metrics, PolicyLayer, RateLimitLayer, Stack, StoreLayer,
+    TimingLayer, TraceLayer,
+};
+use nexus_messaging::redact::Kind;
+use nexus_messaging::{
+    approval, audit, format, schedule, store, time, AgentService, ApprovalQueue, ApprovalState,
+    AuditLog, ExportFormat, ExportRequest, Format, MessageStore, Outbox, PendingMessage, Policy,
+    Redactor, RetryPolicy, ScheduleQueue, UserDirectory,
+};
+use nexus_slack::SlackConfig;
+use nexus_tdlib::{AuthConfig, TdClient};
+use nexus_whatsapp::{WebhookConfig, WhatsAppConfig};
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use platforms::Platforms;
+
+// How often the server looks for queued scheduled messages that are due.
+const SCHEDULE_TICK: Duration = Duration::from_secs(30);
+// How often the server looks for approved messages to send.
+const APPROVAL_TICK: Duration = Duration::from_secs(10);
 
 fn init_tracing() {
     tracing_subscriber::fmt()
@@ -22,6 +44,247 @@ fn init_tracing() {
         .init();
 }
 
+fn load_timezone() {
+    let Ok(name) = env::var("NEXUS_TIMEZONE") else {
+        return;
+    };
+    match time::parse_timezone(&name) {
+        Ok(tz) => {
+            time::set_timezone(tz);
+            info!(%tz, "using timezone");
+        }
+        Err(e) => warn!(%e, "ignoring NEXUS_TIMEZONE, falling back to UTC"),
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    let value = env::var(name).ok().filter(|v| !v.is_empty())?;
+    match value.trim().parse() {
+        Ok(n) => Some(n),
+        Err(_) => {
+            warn!(name, value, "ignoring invalid number");
+            None
+        }
+    }
+}
+
+const PLATFORMS: [Platform; 5] = [
+    Platform::Telegram,
+    Platform::Gmail,
+    Platform::WhatsApp,
+    Platform::Slack,
+    Platform::Discord,
+];
+
+// NEXUS_RETRY_ATTEMPTS applies to every platform; NEXUS_RETRY_ATTEMPTS_<PLATFORM>
+// overrides it for one.
+fn load_retry_policies(agent: &mut AgentService) {
+    let mut policy = RetryPolicy::default();
+    if let Some(n) = env_u64("NEXUS_RETRY_ATTEMPTS") {
+        policy.max_attempts = n.clamp(1, 10) as u32;
+    }
+    if let Some(secs) = env_u64("NEXUS_RETRY_MAX_WAIT") {
+        policy.max_retry_after = Duration::from_secs(secs);
+    }
+    agent.set_retry_policy(policy);
+
+    for platform in PLATFORMS {
+        let var = format!("NEXUS_RETRY_ATTEMPTS_{}", platform.to_string().to_uppercase());
+        if let Some(n) = env_u64(&var) {
+            let max_attempts = n.clamp(1, 10) as u32;
+            info!(%platform, max_attempts, "platform retry policy");
+            agent.set_platform_retry_policy(platform, RetryPolicy { max_attempts, ..policy });
+        }
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "yes"))
+}
+
+// Tracing, timing and the audit notes always run and the rate limit does
+// unless switched off; the read-only mode, access policy, cache and store
+// are opt-in. Policy sits outside the cache so denied reads are never
+// served from it,
+// and the cache outside the rate limit so hits are free. The store sits
+// inside so it only sees what adapters returned, and the directory
+// innermost so stored and cached messages carry names.
+fn build_stack(
+    metrics: &Metrics,
+    store: Option<&Arc<MessageStore>>,
+    directory: &Arc<UserDirectory>,
+    guard: Option<&Guard>,
+) -> Stack {
+    let mut stack = Stack::new()
+        .layer(TraceLayer)
+        .layer(TimingLayer::new(metrics.clone()))
+        .layer(AuditLayer);
+    if env_flag("NEXUS_READ_ONLY") {
+        info!("read-only mode: write operations are denied");
+        stack = stack.layer(PolicyLayer::read_only());
+    }
+    if let Some(guard) = guard {
+        stack = stack.layer(PolicyLayer::access(guard.clone()));
+    }
+    if let Some(secs) = env_u64("NEXUS_CACHE_TTL").filter(|&s| s > 0) {
+        stack = stack.layer(CacheLayer::new(Duration::from_secs(secs)));
+    }
+    if let Some(rate_limit) = rate_limit() {
+        stack = stack.layer(rate_limit);
+    }
+    if let Some(store) = store {
+        stack = stack.layer(StoreLayer::new(store.clone()));
+    }
+    stack.layer(DirectoryLayer::new(directory.clone()))
+}
+
+// Each platform's published limits apply unless NEXUS_RATE_LIMIT is off or
+// a number of calls per minute for every platform.
+// NEXUS_RATE_LIMIT_<PLATFORM> then adjusts one platform's budgets.
+fn rate_limit() -> Option<RateLimitLayer> {
+    let mut layer = match env::var("NEXUS_RATE_LIMIT").ok().filter(|v| !v.is_empty()) {
+        None => RateLimitLayer::defaults(),
+        Some(v) if matches!(v.trim(), "0" | "off" | "false" | "no") => return None,
+        Some(v) => match v.trim().parse::<u32>() {
+            Ok(calls) => RateLimitLayer::per_minute(calls),
+            Err(_) => {
+                warn!(name = "NEXUS_RATE_LIMIT", value = v, "ignoring invalid number");
+                RateLimitLayer::defaults()
+            }
+        },
+    };
+    for platform in PLATFORMS {
+        let var = format!("NEXUS_RATE_LIMIT_{}", platform.to_string().to_uppercase());
+        let Some(spec) = env::var(&var).ok().filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        match layer.limits(platform).with_overrides(&spec) {
+            Ok(limits) => {
+                info!(%platform, ?limits, "platform rate limits");
+                layer = layer.with_limits(platform, limits);
+            }
+            Err(e) => warn!(name = var, value = spec, error = %e, "ignoring invalid rate limits"),
+        }
+    }
+    if let Some(secs) = env_u64("NEXUS_RATE_LIMIT_WAIT") {
+        layer = layer.max_wait(Duration::from_secs(secs));
+    }
+    Some(layer)
+}
+
+// NEXUS_REDACT lists the kinds of personal data to mask in tool output
+// (phone, email, card, iban, or all) and NEXUS_REDACT_PATTERN_<NAME> adds a
+// regex masked as [NAME_n]. Bad settings stop the server rather than let
+// unmasked text through.
+fn load_redactor() -> Result<Option<Redactor>, AgentError> {
+    let kinds = match env::var("NEXUS_REDACT").ok().filter(|v| !v.is_empty()) {
+        None => Vec::new(),
+        Some(v) if matches!(v.trim(), "1" | "true" | "yes" | "all") => Kind::ALL.to_vec(),
+        Some(v) => v
+            .split(',')
+            .filter(|k| !k.trim().is_empty())
+            .map(|k| k.parse::<Kind>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| AgentError::invalid_input(e).with_argument("NEXUS_REDACT"))?,
+    };
+    let mut redactor = Redactor::new(&kinds)?;
+    let mut patterns: Vec<(String, String)> = env::vars()
+        .filter_map(|(k, v)| Some((k.strip_prefix("NEXUS_REDACT_PATTERN_")?.to_string(), v)))
+        .collect();
+    patterns.sort();
+    for (name, pattern) in patterns {
+        redactor = redactor.pattern(&name, &pattern)?;
+    }
+    if redactor.is_empty() {
+        return Ok(None);
+    }
+    let reversible = env_flag("NEXUS_REDACT_REVERSIBLE");
+    info!(?kinds, reversible, "redacting tool output");
+    Ok(Some(redactor.reversible(reversible)))
+}
+
+// NEXUS_POLICY names the access policy file; without it
+// NEXUS_DATA_DIR/policy.json is used when present. A policy that fails to
+// load stops the server rather than leaving every tool open.
+fn load_policy() -> Result<Option<Policy>, AgentError> {
+    let path = match env::var("NEXUS_POLICY").ok().filter(|v| !v.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let path = data_dir().join("policy.json");
+            if !path.exists() {
+                return Ok(None);
+            }
+            path
+        }
+    };
+    let policy = Policy::load(&path)?;
+    info!(path = %path.display(), rules = policy.rule_count(), "access policy loaded");
+    Ok(Some(policy))
+}
+
+// NEXUS_APPROVAL holds sends on the platforms it lists (comma-separated,
+// or all) for a reviewer, in NEXUS_DATA_DIR/approvals. A setting that does
+// not parse stops the server rather than let messages out unreviewed.
+fn load_approval() -> Result<Option<ApprovalQueue>, AgentError> {
+    let platforms: Vec<Platform> = match env::var("NEXUS_APPROVAL").ok().filter(|v| !v.trim().is_empty()) {
+        None => return Ok(None),
+        Some(v) if matches!(v.trim(), "0" | "off" | "false" | "no") => return Ok(None),
+        Some(v) if matches!(v.trim(), "1" | "true" | "yes" | "all") => PLATFORMS.to_vec(),
+        Some(v) => v
+            .split(',')
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| p.trim().parse::<Platform>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| AgentError::invalid_input(e.to_string()).with_argument("NEXUS_APPROVAL"))?,
+    };
+    let queue = ApprovalQueue::open(&data_dir().join("approvals"))?.for_platforms(platforms.iter().copied());
+    queue.recover()?;
+    info!(?platforms, "sends on these platforms wait for approval");
+    Ok(Some(queue))
+}
+
+// Write tool calls, and the sends the server makes from its queues, are
+// recorded in NEXUS_DATA_DIR/audit unless NEXUS_AUDIT is off.
+// NEXUS_AUDIT_MAX_BYTES caps each file's size and NEXUS_AUDIT_CHAIN links
+// records by hash. A log that cannot be opened stops the server
+// rather than letting writes go unrecorded.
+fn load_audit() -> Result<Option<AuditLog>, AgentError> {
+    if env::var("NEXUS_AUDIT").is_ok_and(|v| matches!(v.trim(), "0" | "off" | "false" | "no")) {
+        return Ok(None);
+    }
+    let mut log = AuditLog::open(&data_dir().join("audit"))?.chained(env_flag("NEXUS_AUDIT_CHAIN"));
+    if let Some(bytes) = env_u64("NEXUS_AUDIT_MAX_BYTES").filter(|&b| b > 0) {
+        log = log.max_bytes(bytes);
+    }
+    info!(dir = %log.dir().display(), "audit log enabled");
+    Ok(Some(log))
+}
+
+// NEXUS_STORE=1 keeps messages in NEXUS_DATA_DIR/messages.db. A store that
+// fails to open is logged and left out rather than stopping the server.
+fn open_store() -> Option<Arc<MessageStore>> {
+    if !env_flag("NEXUS_STORE") {
+        return None;
+    }
+    match MessageStore::open(&data_dir().join("messages.db")) {
+        Ok(store) => Some(Arc::new(store)),
+        Err(e) => {
+            warn!(%e, "message store disabled");
+            None
+        }
+    }
+}
+
+fn data_dir() -> PathBuf {
+    match env::var("NEXUS_DATA_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+            PathBuf::from(format!("{home}/.nexus"))
+        }
+    }
+}
+
 fn load_telegram_config() -> Result<AuthConfig, String> {
     let api_id: i32 = env::var("TELEGRAM_API_ID")
         .map_err(|_| "TELEGRAM_API_ID env var not set".to_string())?
@@ -31,10 +294,7 @@ fn load_telegram_config() -> Result<AuthConfig, String> {
     let api_hash = env::var("TELEGRAM_API_HASH")
         .map_err(|_| "TELEGRAM_API_HASH env var not set".to_string())?;
 
-    let data_dir = env::var("NEXUS_DATA_DIR").unwrap_or_else(|_| {
-        let home = env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-        format!("{home}/.nexus")
-    });
+    let data_dir = data_dir().display().to_string();
 
     let db_dir = format!("{data_dir}/tdlib");
     let files_dir = format!("{data_dir}/tdlib/files");
@@ -68,9 +328,27 @@ fn load_whatsapp_config() -> Result<WhatsAppConfig, String> {
         .map_err(|_| "WHATSAPP_ACCESS_TOKEN env var not set".to_string())?;
     let phone_number_id = env::var("WHATSAPP_PHONE_NUMBER_ID")
         .map_err(|_| "WHATSAPP_PHONE_NUMBER_ID env var not set".to_string())?;
+    let webhook = match env::var("WHATSAPP_WEBHOOK_ADDR").ok().filter(|a| !a.is_empty()) {
+        Some(addr) => Some(WebhookConfig {
+            addr: addr
+                .parse()
+                .map_err(|e| format!("invalid WHATSAPP_WEBHOOK_ADDR '{addr}': {e}"))?,
+            verify_token: env::var("WHATSAPP_VERIFY_TOKEN").map_err(|_| {
+                "WHATSAPP_VERIFY_TOKEN is required with WHATSAPP_WEBHOOK_ADDR".to_string()
+            })?,
+            app_secret: env::var("WHATSAPP_APP_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| {
+                    "WHATSAPP_APP_SECRET is required with WHATSAPP_WEBHOOK_ADDR".to_string()
+                })?,
+        }),
+        None => None,
+    };
     Ok(WhatsAppConfig {
         access_token,
         phone_number_id,
+        webhook,
     })
 }
 
@@ -124,90 +402,335 @@ async fn run_auth_telegram() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn run_mcp_server() -> Result<(), Box<dyn std::error::Error>> {
+// nexus mcp [--dry-run]
+async fn run_mcp_server(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dry_run = env_flag("NEXUS_DRY_RUN");
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            other => return Err(format!("unknown option {other}\nUsage: nexus mcp [--dry-run]").into()),
+        }
+    }
     let mut agent = AgentService::new();
+    load_retry_policies(&mut agent);
+    let metrics = Metrics::new();
+    let store = open_store();
+    let policy = load_policy()?.map(Arc::new);
+    let guard = policy
+        .as_ref()
+        .map(|p| Guard::new(p.clone(), agent.chats().clone()));
+    let stack = build_stack(&metrics, store.as_ref(), agent.directory(), guard.as_ref());
+    if let Some(guard) = guard {
+        agent.set_guard(guard);
+    }
+
+    let platforms = Platforms::connect(&mut agent, &stack).await?;
+    if let Some(ref whatsapp) = platforms.whatsapp {
+        whatsapp.inner().start_webhook();
+    }
 
-    let telegram = match load_telegram_config() {
-        Ok(cfg) => {
-            let client = Arc::new(TdClient::new());
-            let mut auth_rx = client
-                .take_auth_rx()
-                .ok_or("failed to get auth receiver")?;
+    if let Some(store) = store {
+        agent.set_store(store);
+    }
+    if let Some(redactor) = load_redactor()? {
+        agent.set_redactor(Arc::new(redactor));
+    }
+    // NEXUS_IDEMPOTENCY_TTL: seconds to remember idempotency keys.
+    let key_ttl = Duration::from_secs(env_u64("NEXUS_IDEMPOTENCY_TTL").unwrap_or(86_400));
+    match Outbox::open(&data_dir().join("outbox.json"), key_ttl) {
+        Ok(outbox) => agent.set_outbox(Arc::new(outbox)),
+        Err(e) => warn!(%e, "outbox disabled; idempotency keys will be rejected"),
+    }
+    match ScheduleQueue::open(&data_dir().join("scheduled.json")) {
+        Ok(queue) => agent.set_schedule_queue(Arc::new(queue)),
+        Err(e) => warn!(%e, "schedule queue disabled"),
+    }
+    if let Some(queue) = load_approval()? {
+        agent.set_approval_queue(Arc::new(queue));
+    }
+    let audit = load_audit()?.map(Arc::new);
+    if let Some(ref log) = audit {
+        agent.set_audit_log(log.clone());
+    }
+    let agent = Arc::new(agent);
+    // A dry-run server sends nothing, queued jobs and approved messages
+    // included; they wait for the next real run, which sends them (or marks
+    // jobs missed) as usual.
+    if dry_run {
+        info!("dry run: write tools report what they would do and change nothing");
+    } else {
+        tokio::spawn(schedule::run_schedule(agent.clone(), SCHEDULE_TICK));
+        tokio::spawn(approval::run_approvals(agent.clone(), APPROVAL_TICK));
+    }
+    // NEXUS_STORE_SYNC=0 turns the background sync off.
+    let sync_every = env_u64("NEXUS_STORE_SYNC").unwrap_or(900);
+    if agent.store().is_some() && sync_every > 0 {
+        info!(every_secs = sync_every, "background store sync enabled");
+        tokio::spawn(store::sync_periodically(
+            agent.clone(),
+            Duration::from_secs(sync_every.max(60)),
+        ));
+    }
 
-            nexus_tdlib::auth::wait_for_ready(&client, &mut auth_rx, &cfg).await?;
-            info!("telegram connected");
+    let mut server = mcp::McpServer::new(agent, platforms, data_dir());
+    if let Some(policy) = policy {
+        server = server.with_policy(policy);
+    }
+    if dry_run {
+        server = server.dry_run();
+    }
+    if let Some(log) = audit {
+        server = server.with_audit(log);
+    }
+    server.run().await?;
 
-            let adapter = Arc::new(TdlibAdapter::new(client));
-            agent.register(adapter.clone());
-            Some(adapter)
-        }
-        Err(e) => {
-            info!("telegram not configured: {e}");
-            None
+    for (platform, operation, stats) in metrics.snapshot() {
+        info!(
+            %platform,
+            operation,
+            calls = stats.calls,
+            errors = stats.errors,
+            mean_ms = stats.mean_ms(),
+            max_ms = stats.max_ms,
+            "call stats"
+        );
+    }
+    Ok(())
+}
+
+// nexus export <platform> <channel> [--format F] [--output PATH] [--since T]
+//   [--until T] [--attachments] [--max-messages N]
+async fn run_export(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "Usage: nexus export <platform> <channel> [--format jsonl|mbox|html] [--output PATH] [--since T] [--until T] [--attachments] [--max-messages N]";
+    let mut positional = Vec::new();
+    let mut flags = std::collections::HashMap::new();
+    let mut attachments = false;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--attachments" => attachments = true,
+            "--format" | "--output" | "--since" | "--until" | "--max-messages" => {
+                let value = rest.next().ok_or_else(|| format!("{arg} needs a value\n{usage}"))?;
+                flags.insert(arg.trim_start_matches("--"), value.as_str());
+            }
+            other if other.starts_with("--") => return Err(format!("unknown option {other}\n{usage}").into()),
+            other => positional.push(other),
         }
+    }
+    let [platform, channel] = positional[..] else {
+        return Err(usage.into());
+    };
+    let platform: Platform = platform.parse().map_err(|e| format!("{e}\n{usage}"))?;
+    let output = flags.get("output").map(PathBuf::from);
+    let export_format = match (flags.get("format"), &output) {
+        (Some(f), _) => ExportFormat::parse(f)?,
+        (None, Some(path)) => ExportFormat::from_path(path).unwrap_or(ExportFormat::Jsonl),
+        (None, None) if platform == Platform::Gmail => ExportFormat::Mbox,
+        (None, None) => ExportFormat::Jsonl,
+    };
+    let output = output.unwrap_or_else(|| {
+        let name: String = channel
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        PathBuf::from(format!("{platform}-{name}.{}", export_format.extension()))
+    });
+    let max_messages = match flags.get("max-messages") {
+        Some(n) => Some(n.parse::<usize>().map_err(|e| format!("invalid --max-messages: {e}"))?),
+        None => None,
+    };
+    let request = ExportRequest {
+        platform,
+        channel: channel.to_string(),
+        format: export_format,
+        output,
+        range: time::parse_range(flags.get("since").copied(), flags.get("until").copied())?,
+        attachments,
+        max_messages,
     };
 
-    let gmail = match load_gmail_config() {
-        Ok(cfg) => {
-            let adapter = Arc::new(GmailAdapter::new(cfg));
-            agent.register(adapter.clone());
-            info!("gmail configured");
-            Some(adapter)
+    let mut agent = AgentService::new();
+    load_retry_policies(&mut agent);
+    let metrics = Metrics::new();
+    let stack = build_stack(&metrics, None, agent.directory(), None);
+    let platforms = Platforms::connect(&mut agent, &stack).await?;
+
+    match agent.export_channel(&request, Some(&platforms)).await {
+        Ok(summary) => {
+            eprintln!("{}", format::format_export(&summary, Format::Compact));
+            Ok(())
         }
-        Err(e) => {
-            info!("gmail not configured: {e}");
-            None
+        Err(e) => Err(format!("export interrupted: {e}\nRun the same command again to resume.").into()),
+    }
+}
+
+// nexus approve list [--all] | show <id> | approve <id>... |
+//   reject <id> [--reason TEXT] | edit <id> --text TEXT
+fn run_approve(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "Usage: nexus approve list [--all] | show <id> | approve <id>... | reject <id> [--reason TEXT] | edit <id> --text TEXT";
+    let queue = ApprovalQueue::open(&data_dir().join("approvals"))?;
+    let (command, rest) = args.split_first().ok_or(usage)?;
+    let mut ids = Vec::new();
+    let mut all = false;
+    let (mut reason, mut text) = (None, None);
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--all" if command == "list" => all = true,
+            "--reason" | "--text" => {
+                let value = rest.next().ok_or_else(|| format!("{arg} needs a value\n{usage}"))?;
+                match arg.as_str() {
+                    "--reason" if command == "reject" => reason = Some(value.as_str()),
+                    "--text" if command == "edit" => text = Some(value.as_str()),
+                    _ => return Err(format!("{command} does not take {arg}\n{usage}").into()),
+                }
+            }
+            other if other.starts_with("--") => return Err(format!("unknown option {other}\n{usage}").into()),
+            id => ids.push(id),
         }
+    }
+    // Decisions go in the audit log next to the sends they lead to.
+    let log = match command.as_str() {
+        "approve" | "reject" | "edit" => load_audit()?,
+        _ => None,
     };
-
-    let whatsapp = match load_whatsapp_config() {
-        Ok(cfg) => {
-            let adapter = Arc::new(WhatsAppAdapter::new(cfg));
-            agent.register(adapter.clone());
-            info!("whatsapp configured");
-            Some(adapter)
+    match (command.as_str(), &ids[..]) {
+        ("list", []) => {
+            let mut items = queue.list()?;
+            if !all {
+                items.retain(|i| i.state == ApprovalState::Pending);
+            }
+            println!("{}", format::format_pending_list(&items, Format::Compact, None));
         }
-        Err(e) => {
-            info!("whatsapp not configured: {e}");
-            None
+        ("show", [id]) => println!("{}", format::format_pending(&queue.get(id)?, Format::Markdown)),
+        ("approve", ids) if !ids.is_empty() => {
+            for id in ids {
+                let decided = queue.approve(id);
+                record_decision(log.as_ref(), "approve_message", json!({ "id": id }), &decided);
+                decided?;
+                println!("approved {id}; the running server sends it within {}s", APPROVAL_TICK.as_secs());
+            }
         }
-    };
-
-    let slack = match load_slack_config() {
-        Ok(cfg) => {
-            let adapter = Arc::new(SlackAdapter::new(cfg));
-            agent.register(adapter.clone());
-            info!("slack configured");
-            Some(adapter)
+        ("reject", [id]) => {
+            let decided = queue.reject(id, reason);
+            record_decision(log.as_ref(), "reject_message", json!({ "id": id, "reason": reason }), &decided);
+            decided?;
+            println!("rejected {id}");
         }
-        Err(e) => {
-            info!("slack not configured: {e}");
-            None
+        ("edit", [id]) => {
+            let text = text.ok_or_else(|| format!("edit needs --text\n{usage}"))?;
+            let decided = queue.edit(id, text);
+            record_decision(log.as_ref(), "edit_message", json!({ "id": id, "text": text }), &decided);
+            println!("{}", format::format_pending(&decided?, Format::Expanded));
         }
-    };
+        _ => return Err(usage.into()),
+    }
+    Ok(())
+}
 
-    let discord = match load_discord_config() {
-        Ok(cfg) => {
-            let adapter = Arc::new(DiscordAdapter::new(cfg));
-            agent.register(adapter.clone());
-            info!("discord configured");
-            Some(adapter)
+fn record_decision(
+    log: Option<&AuditLog>,
+    tool: &str,
+    arguments: serde_json::Value,
+    decided: &Result<PendingMessage, AgentError>,
+) {
+    let Some(log) = log else {
+        return;
+    };
+    let mut record = audit::Record::new(tool, &arguments);
+    record.client = Some(audit::Client {
+        name: "nexus approve".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    });
+    let result = match decided {
+        Ok(item) => {
+            record.platform = Some(item.platform);
+            Ok(format!("{} is {}", item.id, item.state))
         }
-        Err(e) => {
-            info!("discord not configured: {e}");
-            None
+        Err(e) => Err(e.to_string()),
+    };
+    let outcome = if result.is_ok() { audit::Outcome::Ok } else { audit::Outcome::Error };
+    record.finish(outcome, &result);
+    if let Err(e) = log.append(record) {
+        warn!(tool, error = %e, "audit record not written");
+    }
+}
+
+// nexus audit [verify] [--since T] [--until T] [--tool NAME] [--platform P]
+//   [--errors] [--limit N] [--json]
+fn run_audit(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "Usage: nexus audit [verify] [--since T] [--until T] [--tool NAME] [--platform P] [--errors] [--limit N] [--json]";
+    let dir = data_dir().join("audit");
+    if args.first().is_some_and(|a| a == "verify") {
+        if args.len() > 1 {
+            return Err(format!("verify takes no options\n{usage}").into());
         }
+        let report = audit::verify(&dir)?;
+        println!("{}", format::format_verification(&report));
+        return match report.problems.len() {
+            0 => Ok(()),
+            n => Err(format!("audit log failed verification with {n} problem(s)").into()),
+        };
+    }
+    let mut query = audit::Query {
+        limit: Some(50),
+        ..Default::default()
     };
+    let (mut since, mut until) = (None, None);
+    let mut json = false;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--errors" => query.errors = true,
+            "--json" => json = true,
+            "--since" | "--until" | "--tool" | "--platform" | "--limit" => {
+                let value = rest.next().ok_or_else(|| format!("{arg} needs a value\n{usage}"))?;
+                match arg.as_str() {
+                    "--since" => since = Some(value.as_str()),
+                    "--until" => until = Some(value.as_str()),
+                    "--tool" => query.tool = Some(value.clone()),
+                    "--platform" => query.platform = Some(value.parse().map_err(|e| format!("{e}\n{usage}"))?),
+                    _ => {
+                        let n: usize = value.parse().map_err(|e| format!("invalid --limit: {e}"))?;
+                        query.limit = (n > 0).then_some(n);
+                    }
+                }
+            }
+            other => return Err(format!("unknown argument {other}\n{usage}").into()),
+        }
+    }
+    query.range = time::parse_range(since, until)?;
 
-    let server = mcp::McpServer::new(agent, telegram, gmail, whatsapp, slack, discord);
-    server.run().await?;
+    let records = audit::read(&dir, &query)?;
+    if json {
+        for record in &records {
+            println!("{}", serde_json::to_string(record)?);
+        }
+    } else {
+        println!("{}", format::format_audit(&records));
+    }
+    Ok(())
+}
 
+// One sync pass, for filling the store without starting the server.
+async fn run_sync() -> Result<(), Box<dyn std::error::Error>> {
+    let store = open_store().ok_or("the message store is not enabled; set NEXUS_STORE=1")?;
+    let mut agent = AgentService::new();
+    load_retry_policies(&mut agent);
+    let metrics = Metrics::new();
+    let stack = build_stack(&metrics, Some(&store), agent.directory(), None);
+    Platforms::connect(&mut agent, &stack).await?;
+    agent.set_store(store);
+
+    let report = agent.sync_store().await?;
+    eprintln!("{}", format::format_sync(&report));
     Ok(())
 }
 
 #[tokio::main]
 async fn main() {
     init_tracing();
+    load_timezone();
 
     let args: Vec<String> = env::args().collect();
     let cmd = args.get(1).map(|s| s.as_str()).unwrap_or("mcp");
@@ -224,13 +747,39 @@ async fn main() {
                 }
             }
         }
-        "mcp" => run_mcp_server().await,
+        "mcp" => run_mcp_server(&args[2..]).await,
+        "export" => run_export(&args[2..]).await,
+        "sync" => run_sync().await,
+        "audit" => run_audit(&args[2..]),
+        "approve" => run_approve(&args[2..]),
         "help" | "--help" | "-h" => {
             eprintln!("Nexus — Universal Agent Tools Platform");
             eprintln!();
             eprintln!("Usage:");
             eprintln!("  nexus auth telegram   Authenticate with Telegram (interactive)");
             eprintln!("  nexus mcp             Start MCP server (stdio, for Claude Code)");
+            eprintln!("      --dry-run         Write tools check their inputs and report what they would do, changing nothing");
+            eprintln!("  nexus export <platform> <channel> [options]");
+            eprintln!("                        Archive a conversation to JSONL, mbox (gmail) or HTML");
+            eprintln!("      --format F        jsonl, mbox or html (default: from --output, else mbox for gmail, jsonl otherwise)");
+            eprintln!("      --output PATH     Output file (default: <platform>-<channel>.<ext>)");
+            eprintln!("      --since/--until T Date bounds, same formats as the tools");
+            eprintln!("      --attachments     Download attachments into <output>_files/");
+            eprintln!("      --max-messages N  Stop after about N messages; run again to continue");
+            eprintln!("  nexus sync            Pull recent history into the message store (needs NEXUS_STORE=1)");
+            eprintln!("  nexus approve list [--all]   Messages waiting for approval (--all: decided ones too)");
+            eprintln!("  nexus approve show <id>      One held message in full");
+            eprintln!("  nexus approve approve <id>...  Approve; the running server sends them");
+            eprintln!("  nexus approve reject <id> [--reason TEXT]");
+            eprintln!("  nexus approve edit <id> --text TEXT  Replace a pending message's text");
+            eprintln!("  nexus audit [verify] [options]");
+            eprintln!("                        Show recorded write tool calls, newest last, or check the hash chain");
+            eprintln!("      --since/--until T Date bounds, same formats as the tools");
+            eprintln!("      --tool NAME       Tool name or glob, e.g. gmail_*");
+            eprintln!("      --platform P      Calls that acted on this platform");
+            eprintln!("      --errors          Only failed or denied calls");
+            eprintln!("      --limit N         Most recent N records, 0 for all (default: 50)");
+            eprintln!("      --json            Print the records as JSON lines");
             eprintln!("  nexus help            Show this help");
             eprintln!();
             eprintln!("Environment variables:");
@@ -240,9 +789,33 @@ async fn main() {
             eprintln!("  GMAIL_APP_PASSWORD       Gmail app password");
             eprintln!("  WHATSAPP_ACCESS_TOKEN    WhatsApp Business Cloud API token");
             eprintln!("  WHATSAPP_PHONE_NUMBER_ID WhatsApp sender phone number ID");
+            eprintln!("  WHATSAPP_WEBHOOK_ADDR    Local listen address for status webhooks behind an HTTPS proxy (e.g. 127.0.0.1:8443)");
+            eprintln!("  WHATSAPP_VERIFY_TOKEN    Webhook verify token (required with WHATSAPP_WEBHOOK_ADDR)");
+            eprintln!("  WHATSAPP_APP_SECRET      App secret for webhook signatures (required with WHATSAPP_WEBHOOK_ADDR)");
             eprintln!("  SLACK_BOT_TOKEN          Slack Bot User OAuth Token (xoxb-...)");
             eprintln!("  DISCORD_BOT_TOKEN        Discord Bot token");
             eprintln!("  NEXUS_DATA_DIR           Data directory (default: ~/.nexus)");
+            eprintln!("  NEXUS_TIMEZONE           IANA timezone for dates (default: UTC)");
+            eprintln!("  NEXUS_RETRY_ATTEMPTS     Attempts per read call, 1 disables retries (default: 3)");
+            eprintln!("  NEXUS_RETRY_ATTEMPTS_<PLATFORM>  Per-platform override, e.g. NEXUS_RETRY_ATTEMPTS_SLACK");
+            eprintln!("  NEXUS_POLICY             Access policy file (default: NEXUS_DATA_DIR/policy.json if present)");
+            eprintln!("  NEXUS_READ_ONLY          Deny every write operation (1/true)");
+            eprintln!("  NEXUS_DRY_RUN            Same as `nexus mcp --dry-run` (1/true)");
+            eprintln!("  NEXUS_APPROVAL           Hold sends for human approval: platforms (comma-separated) or all (default: off)");
+            eprintln!("  NEXUS_AUDIT              Record write tool calls in NEXUS_DATA_DIR/audit, off to disable (default: on)");
+            eprintln!("  NEXUS_AUDIT_MAX_BYTES    Start a new audit file within the day past this size (default: one file per day)");
+            eprintln!("  NEXUS_AUDIT_CHAIN        Link audit records by SHA-256 hash for tamper evidence (1/true)");
+            eprintln!("  NEXUS_CACHE_TTL          Reuse read results for this many seconds (default: off)");
+            eprintln!("  NEXUS_RATE_LIMIT         Calls per minute per platform, or off (default: each platform's published limits)");
+            eprintln!("  NEXUS_RATE_LIMIT_<PLATFORM>  Budgets for one platform, e.g. NEXUS_RATE_LIMIT_SLACK=write=30/m,channel=1/s:3");
+            eprintln!("  NEXUS_RATE_LIMIT_WAIT    Longest a call queues for its budget before it is refused, seconds (default: 15)");
+            eprintln!("  NEXUS_STORE              Keep messages in NEXUS_DATA_DIR/messages.db for local_search and offline reads (1/true)");
+            eprintln!("  NEXUS_STORE_SYNC         Seconds between background store syncs, 0 disables (default: 900)");
+            eprintln!("  NEXUS_REDACT             Mask personal data in tool output: phone,email,card,iban or all (default: off)");
+            eprintln!("  NEXUS_REDACT_PATTERN_<NAME>  Also mask this regex, as [NAME_1], [NAME_2], ...");
+            eprintln!("  NEXUS_REDACT_REVERSIBLE  Swap placeholders in tool arguments back to the original values (1/true)");
+            eprintln!("  NEXUS_IDEMPOTENCY_TTL    Seconds to remember idempotency keys on sends (default: 86400)");
+            eprintln!("  NEXUS_RETRY_MAX_WAIT     Longest rate-limit wait to sit out, seconds (default: 30)");
             eprintln!("  RUST_LOG                 Log level (default: nexus=info)");
             Ok(())
         }
@@ -1,11 +1,21 @@
-use std::sync::Arc;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use nexus_domain::*;
 use nexus_error::ErrorResponse;
 use nexus_discord::DiscordAdapter;
 use nexus_google::GmailAdapter;
-use nexus_messaging::{AgentService, Format};
+use nexus_messaging::{
+    AgentService, AuditLog, BroadcastTarget, Budget, ExportFormat, ExportRequest, Format, Layered, Policy,
+    PolicyRequest, SearchOrder,
+};
+use nexus_messaging::audit::{self, Outcome};
 use nexus_messaging::format;
+use nexus_messaging::middleware::Access;
+use nexus_messaging::outbox;
+use nexus_messaging::policy;
+use nexus_messaging::time;
 use nexus_slack::SlackAdapter;
 use nexus_tdlib::TdlibAdapter;
 use nexus_whatsapp::WhatsAppAdapter;
@@ -13,37 +23,49 @@ use serde_json::{json, Value};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tracing::{debug, warn};
 
-use super::tools;
+use super::tools::{self, Reach};
+use crate::platforms::Platforms;
 use super::types::*;
 
 pub struct McpServer {
-    agent: AgentService,
-    telegram: Option<Arc<TdlibAdapter>>,
-    gmail: Option<Arc<GmailAdapter>>,
-    whatsapp: Option<Arc<WhatsAppAdapter>>,
-    slack: Option<Arc<SlackAdapter>>,
-    discord: Option<Arc<DiscordAdapter>>,
+    agent: Arc<AgentService>,
+    platforms: Platforms,
+    data_dir: PathBuf,
+    policy: Option<Arc<Policy>>,
+    dry_run: bool,
+    audit: Option<Arc<AuditLog>>,
+    client: Mutex<Option<audit::Client>>,
 }
 
 impl McpServer {
-    pub fn new(
-        agent: AgentService,
-        telegram: Option<Arc<TdlibAdapter>>,
-        gmail: Option<Arc<GmailAdapter>>,
-        whatsapp: Option<Arc<WhatsAppAdapter>>,
-        slack: Option<Arc<SlackAdapter>>,
-        discord: Option<Arc<DiscordAdapter>>,
-    ) -> Self {
+    pub fn new(agent: Arc<AgentService>, platforms: Platforms, data_dir: PathBuf) -> Self {
         Self {
             agent,
-            telegram,
-            gmail,
-            whatsapp,
-            slack,
-            discord,
+            platforms,
+            data_dir,
+            policy: None,
+            dry_run: false,
+            audit: None,
+            client: Mutex::new(None),
         }
     }
 
+    pub fn with_policy(mut self, policy: Arc<Policy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    // Runs every write tool as a dry run, whatever its arguments say.
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    pub fn with_audit(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
         let stdin = BufReader::new(tokio::io::stdin());
         let mut stdout = tokio::io::stdout();
@@ -88,7 +110,7 @@ impl McpServer {
                 RpcResponse::err(id, -32600, "missing method")
             } else {
                 match method {
-                    "initialize" => self.handle_initialize(id),
+                    "initialize" => self.handle_initialize(id, msg.params.as_ref()),
                     "tools/list" => self.handle_tools_list(id),
                     "tools/call" => self.handle_tools_call(id, msg.params).await,
                     "ping" => RpcResponse::ok(id, json!({})),
@@ -107,7 +129,13 @@ impl McpServer {
         Ok(())
     }
 
-    fn handle_initialize(&self, id: Value) -> RpcResponse {
+    fn handle_initialize(&self, id: Value, params: Option<&Value>) -> RpcResponse {
+        let client = params
+            .and_then(|p| p.get("clientInfo"))
+            .and_then(|c| serde_json::from_value::<audit::Client>(c.clone()).ok());
+        if let Ok(mut slot) = self.client.lock() {
+            *slot = client;
+        }
         let platforms: Vec<String> = self
             .agent
             .available_platforms()
@@ -132,12 +160,18 @@ impl McpServer {
     }
 
     fn handle_tools_list(&self, id: Value) -> RpcResponse {
-        let has_tg = self.telegram.is_some();
-        let has_gm = self.gmail.is_some();
-        let has_wa = self.whatsapp.is_some();
-        let has_sl = self.slack.is_some();
-        let has_dc = self.discord.is_some();
-        let tool_defs = tools::available_tools(has_tg, has_gm, has_wa, has_sl, has_dc);
+        let has_tg = self.platforms.telegram.is_some();
+        let has_gm = self.platforms.gmail.is_some();
+        let has_wa = self.platforms.whatsapp.is_some();
+        let has_sl = self.platforms.slack.is_some();
+        let has_dc = self.platforms.discord.is_some();
+        let has_store = self.agent.store().is_some();
+        let has_approval = self.agent.approval_queue().is_some();
+        let mut tool_defs =
+            tools::available_tools(has_tg, has_gm, has_wa, has_sl, has_dc, has_store, has_approval);
+        if let Some(ref policy) = self.policy {
+            tool_defs.retain(|t| self.listed(policy, t.name));
+        }
         RpcResponse::ok(id, json!({ "tools": tool_defs }))
     }
 
@@ -147,12 +181,45 @@ impl McpServer {
             None => return RpcResponse::err(id, INVALID_PARAMS, "missing or invalid params"),
         };
 
-        let args = params.arguments.unwrap_or(json!({}));
-        let result = self.dispatch_tool(&params.name, &args).await;
+        let mut args = params.arguments.unwrap_or(json!({}));
+        let redactor = self.agent.redactor();
+        let restored = match redactor {
+            Some(redactor) => redactor.restore_args(&mut args).map_err(fmt_err),
+            None => Ok(()),
+        };
+        let policy = restored
+            .and_then(|()| self.check_policy(&params.name, &args))
+            .and_then(|()| self.check_approval(&params.name, &args));
+        let dry_run = self.is_dry_run(&params.name, &args);
+        let outcome = match (&policy, &dry_run) {
+            (Err(_), _) => Outcome::Denied,
+            (Ok(()), Ok(true)) => Outcome::DryRun,
+            _ => Outcome::Ok,
+        };
+        let call = async {
+            match (policy, dry_run) {
+                (Err(e), _) | (_, Err(e)) => Err(e),
+                (Ok(()), Ok(true)) => {
+                    policy::scope(&params.name, self.dispatch_dry_run(&params.name, &args)).await
+                }
+                (Ok(()), Ok(false)) => {
+                    policy::scope(&params.name, self.dispatch_tool(&params.name, &args)).await
+                }
+            }
+        };
+        let result = match self.audit {
+            Some(ref log) if tools::access(&params.name) == Access::Write => {
+                self.audited(log, &params.name, &args, outcome, call).await
+            }
+            _ => call.await,
+        };
 
-        let tool_result = match result {
-            Ok(text) => ToolResult::success(text),
-            Err(e) => ToolResult::failure(e),
+        // Errors are masked too, since they can quote chats and recipients.
+        let tool_result = match (result, redactor) {
+            (Ok(text), Some(r)) => ToolResult::success(r.redact(&text)),
+            (Err(e), Some(r)) => ToolResult::failure(r.redact(&e)),
+            (Ok(text), None) => ToolResult::success(text),
+            (Err(e), None) => ToolResult::failure(e),
         };
 
         match serde_json::to_value(tool_result) {
@@ -163,6 +230,7 @@ impl McpServer {
 
     async fn dispatch_tool(&self, name: &str, args: &Value) -> Result<String, String> {
         let fmt = Format::parse(args.get("format").and_then(|v| v.as_str()));
+        let budget = parse_budget(args);
 
         match name {
             "get_profile" => {
@@ -178,19 +246,22 @@ impl McpServer {
                     .list_channels(p, limit)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_channels(&channels, fmt))
+                Ok(format::format_channels(&channels, fmt, budget.as_ref()))
             }
             "read_messages" => {
                 let p = parse_platform(args)?;
                 let channel = get_str(args, "channel")?;
                 let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
                 let cursor = args.get("cursor").and_then(|v| v.as_str());
+                let anchor = parse_anchor(args)?;
+                let range = parse_time_range(args)?;
                 let result = self
                     .agent
-                    .read_messages(p, channel, limit, cursor)
+                    .read_messages_or_stored(p, channel, limit, cursor, anchor, range)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_paginated(&result, fmt))
+                let budget = budget.map(|b| b.resuming(cursor));
+                Ok(format::format_read(&result, fmt, budget.as_ref()))
             }
             "send_message" => {
                 let p = parse_platform(args)?;
@@ -201,24 +272,208 @@ impl McpServer {
                     Some(v) if v.is_number() => v.as_i64().map(|n| n.to_string()),
                     _ => None,
                 };
-                let msg = self
+                if self.agent.needs_approval(p) {
+                    let held = self
+                        .once(name, args, || {
+                            self.agent.submit_message(p, channel, text, reply_to_owned.as_deref())
+                        })
+                        .await?;
+                    return Ok(format::format_pending(&held, fmt));
+                }
+                let msg = match idempotency_key(args)? {
+                    Some(key) => self
+                        .agent
+                        .send_message_once(p, channel, text, reply_to_owned.as_deref(), key)
+                        .await,
+                    None => self
+                        .agent
+                        .send_message(p, channel, text, reply_to_owned.as_deref())
+                        .await,
+                }
+                .map_err(fmt_err)?;
+                Ok(format::format_message(&msg, fmt))
+            }
+            "broadcast" => {
+                let targets = parse_targets(args)?;
+                let text = get_str(args, "text")?;
+                let key = idempotency_key(args)?;
+                let report = self
                     .agent
-                    .send_message(p, channel, text, reply_to_owned.as_deref())
+                    .broadcast(&targets, text, key)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_message(&msg, fmt))
+                Ok(format::format_broadcast(&report, fmt))
+            }
+            "get_approval_status" => {
+                let queue = self
+                    .agent
+                    .approval_queue()
+                    .ok_or("no platform needs approval. Set NEXUS_APPROVAL to hold sends for review")?;
+                match args.get("id").and_then(|v| v.as_str()).filter(|id| !id.is_empty()) {
+                    Some(id) => {
+                        let item = queue.get(id).map_err(fmt_err)?;
+                        Ok(format::format_pending(&item, fmt))
+                    }
+                    None => {
+                        let items = queue.list().map_err(fmt_err)?;
+                        Ok(format::format_pending_list(&items, fmt, budget.as_ref()))
+                    }
+                }
+            }
+            "schedule_message" => {
+                let p = parse_platform(args)?;
+                let channel = get_str(args, "channel")?;
+                let text = get_str(args, "text")?;
+                let send_at = get_str_or_num(args, "send_at").ok_or("missing 'send_at' parameter")?;
+                let send_at = time::parse_future(&send_at)
+                    .map_err(|e| fmt_err(e.with_argument("send_at")))?;
+                let reply_to = get_str_or_num(args, "reply_to");
+                let job = self
+                    .once(name, args, || {
+                        self.agent
+                            .schedule_message(p, channel, text, reply_to.as_deref(), send_at)
+                    })
+                    .await?;
+                Ok(format::format_scheduled(&job, fmt))
+            }
+            "list_scheduled" => {
+                let platform = match args.get("platform").and_then(|v| v.as_str()) {
+                    Some(_) => Some(parse_platform(args)?),
+                    None => None,
+                };
+                let channel = args.get("channel").and_then(|v| v.as_str());
+                let list = self
+                    .agent
+                    .list_scheduled(platform, channel)
+                    .await
+                    .map_err(fmt_err)?;
+                Ok(format::format_scheduled_list(&list, fmt, budget.as_ref()))
+            }
+            "cancel_scheduled" => {
+                let p = parse_platform(args)?;
+                let id = get_str(args, "id")?;
+                let channel = args.get("channel").and_then(|v| v.as_str());
+                self.agent
+                    .cancel_scheduled(p, channel, id)
+                    .await
+                    .map_err(fmt_err)?;
+                Ok(format!("Cancelled scheduled message {id}"))
             }
             "search" => {
                 let p = parse_platform(args)?;
                 let query = get_str(args, "query")?;
                 let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
                 let cursor = args.get("cursor").and_then(|v| v.as_str());
+                let range = parse_time_range(args)?;
                 let result = self
                     .agent
-                    .search(p, query, limit, cursor)
+                    .search(p, query, limit, cursor, range)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_paginated(&result, fmt))
+                let budget = budget.map(|b| b.resuming(cursor));
+                Ok(format::format_search(&result, fmt, budget.as_ref()))
+            }
+            "get_delivery_status" => {
+                let p = parse_platform(args)?;
+                let channel = args.get("channel").and_then(|v| v.as_str()).unwrap_or("");
+                let message_id = get_str_or_num(args, "message_id")
+                    .ok_or("missing 'message_id' parameter")?;
+                let status = self
+                    .agent
+                    .get_delivery_status(p, channel, &message_id)
+                    .await
+                    .map_err(fmt_err)?;
+                Ok(format::format_delivery_status(&status, fmt))
+            }
+            "search_all" => {
+                let query = get_str(args, "query")?;
+                let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+                let order = SearchOrder::parse(args.get("order").and_then(|v| v.as_str()))
+                    .map_err(fmt_err)?;
+                let cursor = args.get("cursor").and_then(|v| v.as_str());
+                let timeout = args
+                    .get("timeout")
+                    .and_then(|v| v.as_u64())
+                    .map(|secs| Duration::from_secs(secs.clamp(1, 120)));
+                let range = parse_time_range(args)?;
+                let result = self
+                    .agent
+                    .search_all(query, limit, order, cursor, range, timeout)
+                    .await
+                    .map_err(fmt_err)?;
+                let budget = budget.map(|b| b.resuming(cursor));
+                Ok(format::format_search_all(&result, fmt, budget.as_ref()))
+            }
+            "local_search" => {
+                let query = get_str(args, "query")?;
+                let platform = match args.get("platform").and_then(|v| v.as_str()) {
+                    Some(_) => Some(parse_platform(args)?),
+                    None => None,
+                };
+                let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+                let order = match args.get("order").and_then(|v| v.as_str()) {
+                    None => SearchOrder::Relevance,
+                    order => SearchOrder::parse(order).map_err(fmt_err)?,
+                };
+                let cursor = args.get("cursor").and_then(|v| v.as_str());
+                let range = parse_time_range(args)?;
+                let result = self
+                    .agent
+                    .local_search(query, platform, limit, order, cursor, range)
+                    .await
+                    .map_err(fmt_err)?;
+                let budget = budget.map(|b| b.resuming(cursor));
+                Ok(format::format_local_search(&result, fmt, budget.as_ref()))
+            }
+            "unified_inbox" => {
+                let unread_only = args
+                    .get("unread_only")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(30) as usize;
+                let cursor = args.get("cursor").and_then(|v| v.as_str());
+                let range = parse_time_range(args)?;
+                let page = self
+                    .agent
+                    .unified_inbox(limit, unread_only, cursor, range)
+                    .await
+                    .map_err(fmt_err)?;
+                let budget = budget.map(|b| b.resuming(cursor));
+                Ok(format::format_inbox(&page, fmt, budget.as_ref()))
+            }
+            "export_channel" => {
+                let platform = parse_platform(args)?;
+                let channel = get_str(args, "channel")?;
+                let output = self.export_path(get_str(args, "path")?)?;
+                let export_format = match args.get("file_format").and_then(|v| v.as_str()) {
+                    Some(f) => ExportFormat::parse(f).map_err(fmt_err)?,
+                    None => ExportFormat::from_path(&output).unwrap_or(if platform == Platform::Gmail {
+                        ExportFormat::Mbox
+                    } else {
+                        ExportFormat::Jsonl
+                    }),
+                };
+                let request = ExportRequest {
+                    platform,
+                    channel: channel.to_string(),
+                    format: export_format,
+                    output,
+                    range: parse_time_range(args)?,
+                    attachments: args
+                        .get("attachments")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                    max_messages: args
+                        .get("max_messages")
+                        .and_then(|v| v.as_u64())
+                        .map(|n| n as usize),
+                };
+                let summary = self
+                    .agent
+                    .export_channel(&request, Some(&self.platforms))
+                    .await
+                    .map_err(fmt_err)?;
+                Ok(format::format_export(&summary, fmt))
             }
             "list_platforms" => {
                 let platforms = self.agent.available_platforms();
@@ -241,10 +496,9 @@ impl McpServer {
                 let from = get_str(args, "from_chat")?;
                 let to = get_str(args, "to_chat")?;
                 let msg_id = get_i64(args, "message_id")?;
-                let msg = tg
-                    .forward_message(from, to, msg_id)
-                    .await
-                    .map_err(fmt_err)?;
+                let msg = self
+                    .once(name, args, || tg.forward_message(from, to, msg_id))
+                    .await?;
                 Ok(format::format_message(&msg, fmt))
             }
             "telegram_edit_message" => {
@@ -307,10 +561,9 @@ impl McpServer {
                 let file_path = get_str(args, "file_path")?;
                 let caption = args.get("caption").and_then(|v| v.as_str());
                 let media_type = args.get("media_type").and_then(|v| v.as_str());
-                let msg = tg
-                    .send_media(chat, file_path, caption, media_type)
-                    .await
-                    .map_err(fmt_err)?;
+                let msg = self
+                    .once(name, args, || tg.send_media(chat, file_path, caption, media_type))
+                    .await?;
                 Ok(format::format_message(&msg, fmt))
             }
             "telegram_react" => {
@@ -332,7 +585,7 @@ impl McpServer {
                     .search_chat(chat, query, limit)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_messages(&messages, fmt))
+                Ok(format::format_messages(&messages, fmt, budget.as_ref()))
             }
             "telegram_get_chat_members" => {
                 let tg = self.require_telegram()?;
@@ -342,7 +595,7 @@ impl McpServer {
                     .get_chat_members(chat, limit)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_members(&members, fmt))
+                Ok(format::format_members(&members, fmt, budget.as_ref()))
             }
 
             // --- Gmail tools ---
@@ -355,10 +608,11 @@ impl McpServer {
                 let body = get_str(args, "body")?;
                 let reply_to = args.get("reply_to").and_then(|v| v.as_str());
                 let attachments = get_str_array_opt(args, "attachments");
-                let msg = gm
-                    .send_email(&to, &cc, &bcc, subject, body, reply_to, &attachments)
-                    .await
-                    .map_err(fmt_err)?;
+                let msg = self
+                    .once(name, args, || {
+                        gm.send_email(&to, &cc, &bcc, subject, body, reply_to, &attachments)
+                    })
+                    .await?;
                 Ok(format::format_message(&msg, fmt))
             }
             "gmail_archive" => {
@@ -370,7 +624,7 @@ impl McpServer {
             "gmail_list_labels" => {
                 let gm = self.require_gmail()?;
                 let labels = gm.list_labels().await.map_err(fmt_err)?;
-                Ok(format::format_labels(&labels, fmt))
+                Ok(format::format_labels(&labels, fmt, budget.as_ref()))
             }
             "gmail_add_label" => {
                 let gm = self.require_gmail()?;
@@ -454,10 +708,9 @@ impl McpServer {
                 let chat = get_str(args, "chat")?;
                 let file_path = get_str(args, "file_path")?;
                 let caption = get_str(args, "caption")?;
-                let msg = wa
-                    .send_media(chat, file_path, caption)
-                    .await
-                    .map_err(fmt_err)?;
+                let msg = self
+                    .once(name, args, || wa.send_media(chat, file_path, caption))
+                    .await?;
                 Ok(format::format_message(&msg, fmt))
             }
 
@@ -480,7 +733,7 @@ impl McpServer {
                     .create_channel(name, is_private)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_channels(&[ch], fmt))
+                Ok(format::format_channels(&[ch], fmt, None))
             }
             "slack_invite_to_channel" => {
                 let sl = self.require_slack()?;
@@ -523,17 +776,16 @@ impl McpServer {
                 let channels = get_str_array(args, "channels")?;
                 let file_path = get_str(args, "file_path")?;
                 let title = args.get("title").and_then(|v| v.as_str());
-                let result = sl
-                    .upload_file(&channels, file_path, title)
-                    .await
-                    .map_err(fmt_err)?;
+                let result: String = self
+                    .once(name, args, || sl.upload_file(&channels, file_path, title))
+                    .await?;
                 Ok(result)
             }
             "slack_list_users" => {
                 let sl = self.require_slack()?;
                 let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
                 let members = sl.list_users(limit).await.map_err(fmt_err)?;
-                Ok(format::format_members(&members, fmt))
+                Ok(format::format_members(&members, fmt, budget.as_ref()))
             }
             "slack_get_user_info" => {
                 let sl = self.require_slack()?;
@@ -546,7 +798,7 @@ impl McpServer {
             "discord_list_guilds" => {
                 let dc = self.require_discord()?;
                 let guilds = dc.list_guilds().await.map_err(fmt_err)?;
-                Ok(format::format_channels(&guilds, fmt))
+                Ok(format::format_channels(&guilds, fmt, budget.as_ref()))
             }
             "discord_list_guild_channels" => {
                 let dc = self.require_discord()?;
@@ -555,7 +807,7 @@ impl McpServer {
                     .list_guild_channels(guild_id)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_channels(&channels, fmt))
+                Ok(format::format_channels(&channels, fmt, budget.as_ref()))
             }
             "discord_create_thread" => {
                 let dc = self.require_discord()?;
@@ -566,7 +818,7 @@ impl McpServer {
                     .create_thread(channel, name, msg_id)
                     .await
                     .map_err(fmt_err)?;
-                Ok(format::format_channels(&[thread], fmt))
+                Ok(format::format_channels(&[thread], fmt, None))
             }
             "discord_add_reaction" => {
                 let dc = self.require_discord()?;
@@ -605,32 +857,237 @@ impl McpServer {
         }
     }
 
-    fn require_telegram(&self) -> Result<&Arc<TdlibAdapter>, String> {
-        self.telegram
+    // Reads have nothing to hold back, so only writes run as dry runs.
+    fn is_dry_run(&self, tool: &str, args: &Value) -> Result<bool, String> {
+        let requested = match args.get("dry_run") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(b)) => *b,
+            Some(_) => return Err("'dry_run' must be true or false".to_string()),
+        };
+        Ok((self.dry_run || requested) && tools::access(tool) == Access::Write)
+    }
+
+    // Runs the tool up to the point where it would change something and
+    // reports what it would have done there. A call that fails before then
+    // fails as it would for real.
+    async fn dispatch_dry_run(&self, name: &str, args: &Value) -> Result<String, String> {
+        let (result, plans) = dry_run::scope(self.dispatch_tool(name, args)).await;
+        let header = "Dry run: nothing was changed.";
+        match result {
+            // Broadcasts report each target's plan or error themselves.
+            Ok(text) => Ok(format!("{header}\n\n{text}")),
+            Err(_) if !plans.is_empty() => {
+                let mut out = format!("{header} Would:");
+                for plan in &plans {
+                    out.push_str(&format!("\n- {plan}"));
+                }
+                Ok(out)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Recorded before it runs and again once it is over, with what it did
+    // on the way.
+    async fn audited<F>(
+        &self,
+        log: &AuditLog,
+        tool: &str,
+        args: &Value,
+        outcome: Outcome,
+        call: F,
+    ) -> Result<String, String>
+    where
+        F: std::future::Future<Output = Result<String, String>>,
+    {
+        let mut record = audit::Record::new(tool, args);
+        record.client = self.client.lock().ok().and_then(|c| c.clone());
+        record.platform = match tools::reach(tool) {
+            Reach::Fixed(p) => Some(p),
+            _ => args.get("platform").and_then(|v| v.as_str()).and_then(|p| p.parse().ok()),
+        };
+        log.record(record, outcome, call).await
+    }
+
+    // Writes that cannot be held for approval are refused where it is
+    // needed, rather than going out unreviewed.
+    fn check_approval(&self, tool: &str, args: &Value) -> Result<(), String> {
+        if tools::access(tool) == Access::Read || tools::HELD_WRITES.contains(&tool) {
+            return Ok(());
+        }
+        let platform = match tools::reach(tool) {
+            Reach::Fixed(p) => Some(p),
+            _ => args.get("platform").and_then(|v| v.as_str()).and_then(|p| p.parse().ok()),
+        };
+        match platform {
+            Some(p) if self.agent.needs_approval(p) => Err(fmt_err(
+                nexus_error::AgentError::policy_denied(format!(
+                    "{p} changes need human approval, and {tool} cannot hold them for it"
+                ))
+                .with_suggestion("Use send_message, which holds the message for a reviewer"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Every platform and target the call would touch must be allowed.
+    // Arguments that do not parse are left for the tool to reject. A queued
+    // job is checked against where it is going, not the arguments.
+    fn check_policy(&self, tool: &str, args: &Value) -> Result<(), String> {
+        let Some(ref policy) = self.policy else {
+            return Ok(());
+        };
+        let queued = match tool {
+            "cancel_scheduled" => args
+                .get("id")
+                .and_then(|v| v.as_str())
+                .and_then(|id| self.agent.schedule_queue()?.get(id)),
+            _ => None,
+        };
+        let mut pairs: Vec<(Option<Platform>, Option<String>)> = Vec::new();
+        if tool == "broadcast" {
+            for t in parse_targets(args).unwrap_or_default() {
+                pairs.push((Some(t.platform), Some(t.channel)));
+            }
+        } else if let Some(job) = queued {
+            pairs.push((Some(job.platform), Some(job.channel_id)));
+        } else {
+            let platforms: Vec<Option<Platform>> = match tools::reach(tool) {
+                Reach::Fixed(p) => vec![Some(p)],
+                Reach::Nowhere => vec![None],
+                Reach::All => self.connected(),
+                Reach::Chosen => match args.get("platform").and_then(|v| v.as_str()) {
+                    Some(name) => vec![name.parse().ok()],
+                    None => self.connected(),
+                },
+            };
+            let targets = policy_targets(args);
+            for platform in platforms {
+                if targets.is_empty() {
+                    pairs.push((platform, None));
+                }
+                pairs.extend(targets.iter().map(|t| (platform, Some(t.clone()))));
+            }
+        }
+        let access = tools::access(tool);
+        for (platform, target) in &pairs {
+            policy
+                .check(&PolicyRequest {
+                    tool,
+                    platform: *platform,
+                    access,
+                    target: target.as_deref(),
+                    aliases: &[],
+                })
+                .map_err(fmt_err)?;
+        }
+        Ok(())
+    }
+
+    // Hidden when the policy denies the tool whatever the target: on every
+    // connected platform, or for tools that span them, on any.
+    fn listed(&self, policy: &Policy, tool: &str) -> bool {
+        let access = tools::access(tool);
+        let allowed = |platform| policy.may_use(tool, platform, access);
+        match tools::reach(tool) {
+            Reach::Fixed(p) => allowed(Some(p)),
+            Reach::Nowhere => allowed(None),
+            Reach::Chosen => self.connected().into_iter().any(allowed),
+            Reach::All => self.connected().into_iter().all(allowed),
+        }
+    }
+
+    // With nothing connected, only rules that name no platform apply.
+    fn connected(&self) -> Vec<Option<Platform>> {
+        let platforms = self.agent.available_platforms();
+        if platforms.is_empty() {
+            return vec![None];
+        }
+        platforms.into_iter().map(Some).collect()
+    }
+
+    // Exports are confined to NEXUS_DATA_DIR/exports. The deepest part of
+    // the path that already exists is resolved, so a symlink inside the
+    // root cannot lead the output or its siblings outside it.
+    fn export_path(&self, path: &str) -> Result<PathBuf, String> {
+        let rel = Path::new(path);
+        if rel
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(
+                "path must be relative to NEXUS_DATA_DIR/exports, without '..' components"
+                    .to_string(),
+            );
+        }
+        let root = self.data_dir.join("exports");
+        std::fs::create_dir_all(&root)
+            .map_err(|e| format!("cannot create {}: {e}", root.display()))?;
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("cannot resolve {}: {e}", root.display()))?;
+        let target = root.join(rel);
+        let existing = target
+            .ancestors()
+            .find(|a| a.symlink_metadata().is_ok())
+            .unwrap_or(&root);
+        let resolved = existing
+            .canonicalize()
+            .map_err(|e| format!("cannot resolve {}: {e}", existing.display()))?;
+        if !resolved.starts_with(&root) {
+            return Err(format!("{path} resolves outside NEXUS_DATA_DIR/exports"));
+        }
+        Ok(target)
+    }
+
+    // Runs a send at most once per idempotency_key when the call has one.
+    // The key is bound to the tool and its arguments, less those that only
+    // shape the output.
+    async fn once<T, F, Fut>(&self, tool: &str, args: &Value, send: F) -> Result<T, String>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T, nexus_error::AgentError>>,
+    {
+        let Some(key) = idempotency_key(args)? else {
+            return send().await.map_err(fmt_err);
+        };
+        let mut request = args.clone();
+        if let Some(obj) = request.as_object_mut() {
+            for field in ["idempotency_key", "format", "max_chars", "max_tokens"] {
+                obj.remove(field);
+            }
+        }
+        let request = outbox::fingerprint(&[tool, &request.to_string()]);
+        self.agent.once(key, &request, send).await.map_err(fmt_err)
+    }
+
+    fn require_telegram(&self) -> Result<&Arc<Layered<TdlibAdapter>>, String> {
+        self.platforms.telegram
             .as_ref()
             .ok_or_else(|| "telegram not configured. Set TELEGRAM_API_ID and TELEGRAM_API_HASH env vars".to_string())
     }
 
-    fn require_gmail(&self) -> Result<&Arc<GmailAdapter>, String> {
-        self.gmail
+    fn require_gmail(&self) -> Result<&Arc<Layered<GmailAdapter>>, String> {
+        self.platforms.gmail
             .as_ref()
             .ok_or_else(|| "gmail not configured. Set GMAIL_ADDRESS and GMAIL_APP_PASSWORD env vars".to_string())
     }
 
-    fn require_whatsapp(&self) -> Result<&Arc<WhatsAppAdapter>, String> {
-        self.whatsapp
+    fn require_whatsapp(&self) -> Result<&Arc<Layered<WhatsAppAdapter>>, String> {
+        self.platforms.whatsapp
             .as_ref()
             .ok_or_else(|| "whatsapp not configured. Set WHATSAPP_ACCESS_TOKEN and WHATSAPP_PHONE_NUMBER_ID env vars".to_string())
     }
 
-    fn require_slack(&self) -> Result<&Arc<SlackAdapter>, String> {
-        self.slack
+    fn require_slack(&self) -> Result<&Arc<Layered<SlackAdapter>>, String> {
+        self.platforms.slack
             .as_ref()
             .ok_or_else(|| "slack not configured. Set SLACK_BOT_TOKEN env var".to_string())
     }
 
-    fn require_discord(&self) -> Result<&Arc<DiscordAdapter>, String> {
-        self.discord
+    fn require_discord(&self) -> Result<&Arc<Layered<DiscordAdapter>>, String> {
+        self.platforms.discord
             .as_ref()
             .ok_or_else(|| "discord not configured. Set DISCORD_BOT_TOKEN env var".to_string())
     }
@@ -649,6 +1106,82 @@ fn parse_platform(args: &Value) -> Result<Platform, String> {
     name.parse::<Platform>().map_err(|e| e.to_string())
 }
 
+// max_chars takes precedence when both are given.
+fn parse_budget(args: &Value) -> Option<Budget> {
+    let chars = args.get("max_chars").and_then(|v| v.as_u64());
+    let tokens = args.get("max_tokens").and_then(|v| v.as_u64());
+    match (chars, tokens) {
+        (Some(c), _) => Some(Budget::chars(c as usize)),
+        (None, Some(t)) => Some(Budget::tokens(t as usize)),
+        (None, None) => None,
+    }
+}
+
+fn idempotency_key(args: &Value) -> Result<Option<&str>, String> {
+    match args.get("idempotency_key") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(key)) if !key.trim().is_empty() => Ok(Some(key)),
+        Some(_) => Err("'idempotency_key' must be a non-empty string".to_string()),
+    }
+}
+
+fn policy_targets(args: &Value) -> Vec<String> {
+    let mut targets = Vec::new();
+    for key in tools::TARGET_ARGS {
+        match args.get(*key) {
+            Some(Value::String(s)) => targets.push(s.clone()),
+            Some(Value::Number(n)) => targets.push(n.to_string()),
+            Some(Value::Array(items)) => {
+                targets.extend(items.iter().filter_map(|v| v.as_str().map(str::to_string)));
+            }
+            _ => {}
+        }
+    }
+    targets
+}
+
+fn parse_targets(args: &Value) -> Result<Vec<BroadcastTarget>, String> {
+    let items = args
+        .get("targets")
+        .and_then(|v| v.as_array())
+        .ok_or("missing 'targets' parameter")?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let platform = parse_platform(t).map_err(|e| format!("targets[{i}]: {e}"))?;
+            let channel = get_str(t, "channel").map_err(|e| format!("targets[{i}]: {e}"))?;
+            Ok(BroadcastTarget {
+                platform,
+                channel: channel.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_time_range(args: &Value) -> Result<TimeRange, String> {
+    let since = get_str_or_num(args, "since");
+    let until = get_str_or_num(args, "until");
+    time::parse_range(since.as_deref(), until.as_deref()).map_err(fmt_err)
+}
+
+fn parse_anchor(args: &Value) -> Result<Option<ReadAnchor>, String> {
+    match (get_str_or_num(args, "after"), get_str_or_num(args, "around")) {
+        (Some(_), Some(_)) => Err("use either 'after' or 'around', not both".to_string()),
+        (Some(id), None) => Ok(Some(ReadAnchor::After(id))),
+        (None, Some(id)) => Ok(Some(ReadAnchor::Around(id))),
+        (None, None) => Ok(None),
+    }
+}
+
+fn get_str_or_num(args: &Value, key: &str) -> Option<String> {
+    match args.get(key) {
+        Some(v) if v.is_string() => v.as_str().map(|s| s.to_string()),
+        Some(v) if v.is_number() => v.as_i64().map(|n| n.to_string()),
+        _ => None,
+    }
+}
+
 fn get_str<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
     args.get(key)
         .and_then(|v| v.as_str())
@@ -1,15 +1,112 @@
+use nexus_domain::Platform;
+use nexus_messaging::middleware::Access;
 use serde_json::json;
 
 use super::types::ToolDef;
 
+// Tools that change something on the account. Downloads and exports only
+// write local files and count as reads.
+const WRITE_TOOLS: &[&str] = &[
+    "send_message",
+    "broadcast",
+    "schedule_message",
+    "cancel_scheduled",
+    "telegram_forward_message",
+    "telegram_edit_message",
+    "telegram_delete_messages",
+    "telegram_pin_message",
+    "telegram_unpin_message",
+    "telegram_mark_read",
+    "telegram_send_media",
+    "telegram_react",
+    "gmail_send_email",
+    "gmail_archive",
+    "gmail_add_label",
+    "gmail_mark_read",
+    "gmail_mark_unread",
+    "gmail_star",
+    "gmail_unstar",
+    "gmail_move_to",
+    "gmail_trash",
+    "gmail_remove_label",
+    "gmail_create_draft",
+    "whatsapp_send_media",
+    "slack_set_status",
+    "slack_create_channel",
+    "slack_invite_to_channel",
+    "slack_set_topic",
+    "slack_add_reaction",
+    "slack_remove_reaction",
+    "slack_upload_file",
+    "discord_create_thread",
+    "discord_add_reaction",
+    "discord_remove_reaction",
+    "discord_pin_message",
+];
+
+// The write tools that can hold what they send for approval. On platforms
+// that need approval every other write tool is refused, since edits,
+// topics, threads and reactions publish as directly as a send.
+pub const HELD_WRITES: &[&str] = &["send_message", "broadcast"];
+
+// Arguments that name the chat, channel or recipient a tool acts on.
+pub const TARGET_ARGS: &[&str] = &[
+    "channel", "channels", "chat", "from_chat", "to_chat", "to", "cc", "bcc", "guild_id", "user",
+    "user_id",
+];
+
+pub fn access(tool: &str) -> Access {
+    if WRITE_TOOLS.contains(&tool) {
+        Access::Write
+    } else {
+        Access::Read
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reach {
+    Fixed(Platform),
+    // The platform argument; all connected platforms when it is optional
+    // and left out.
+    Chosen,
+    All,
+    Nowhere,
+}
+
+pub fn reach(tool: &str) -> Reach {
+    let prefixed = [
+        ("telegram_", Platform::Telegram),
+        ("gmail_", Platform::Gmail),
+        ("whatsapp_", Platform::WhatsApp),
+        ("slack_", Platform::Slack),
+        ("discord_", Platform::Discord),
+    ];
+    if let Some(&(_, platform)) = prefixed.iter().find(|(prefix, _)| tool.starts_with(prefix)) {
+        return Reach::Fixed(platform);
+    }
+    match tool {
+        "list_platforms" | "get_approval_status" => Reach::Nowhere,
+        "search_all" | "unified_inbox" => Reach::All,
+        _ => Reach::Chosen,
+    }
+}
+
 pub fn available_tools(
     has_telegram: bool,
     has_gmail: bool,
     has_whatsapp: bool,
     has_slack: bool,
     has_discord: bool,
+    has_store: bool,
+    has_approval: bool,
 ) -> Vec<ToolDef> {
     let mut tools = universal_tools();
+    if has_store {
+        tools.extend(store_tools());
+    }
+    if has_approval {
+        tools.extend(approval_tools());
+    }
     if has_telegram {
         tools.extend(telegram_tools());
     }
@@ -25,14 +122,49 @@ pub fn available_tools(
     if has_discord {
         tools.extend(discord_tools());
     }
+    // Every tool that changes something can be tried out first.
+    for tool in tools.iter_mut().filter(|t| WRITE_TOOLS.contains(&t.name)) {
+        if let Some(props) = tool.input_schema["properties"].as_object_mut() {
+            props.insert("dry_run".to_string(), dry_run_param());
+        }
+    }
     tools
 }
 
 fn format_param() -> serde_json::Value {
     json!({
         "type": "string",
-        "description": "Response format: 'compact' (default, one-liner per item, truncated to 200 chars), 'expanded' (one-liner per item, full text), or 'full' (complete JSON with all fields). Use compact for browsing, expanded for reading full messages, full when you need exact field values.",
-        "enum": ["compact", "expanded", "full"]
+        "description": "Response format: 'compact' (default, one-liner per item, truncated to 200 chars), 'expanded' (one-liner per item, full text), 'full' (complete JSON with all fields), 'markdown' (transcript with a heading per channel, quoted replies, reactions and attachments; tables for lists), or 'html' (the same as an HTML fragment). Use compact for browsing, expanded for reading full messages, full when you need exact field values, markdown or html for reports and exports.",
+        "enum": ["compact", "expanded", "full", "markdown", "html"]
+    })
+}
+
+fn max_chars_param() -> serde_json::Value {
+    json!({
+        "type": "integer",
+        "description": "Keep the text output under this many characters (min: 200). Bodies are shortened first, then runs of messages from one sender are collapsed, then items are dropped from the end behind a summary and a cursor that continues at the first omitted item. The footer reports the real size. Not applied to format 'full'.",
+        "minimum": 200
+    })
+}
+
+fn max_tokens_param() -> serde_json::Value {
+    json!({
+        "type": "integer",
+        "description": "Like max_chars, in approximate tokens (4 characters each). max_chars wins if both are given."
+    })
+}
+
+fn idempotency_key_param() -> serde_json::Value {
+    json!({
+        "type": "string",
+        "description": "Any unique string, e.g. a UUID. If this call is retried with the same key (say after a timeout), the original result is returned instead of sending again. Reusing a key for a different message is an error. Keys are remembered for NEXUS_IDEMPOTENCY_TTL (default: 24 hours)."
+    })
+}
+
+fn dry_run_param() -> serde_json::Value {
+    json!({
+        "type": "boolean",
+        "description": "Check and resolve everything (chats, recipients, messages, files) but change nothing, and report exactly what the call would do. Errors the real call would hit, such as an unknown chat, are reported as usual."
     })
 }
 
@@ -44,6 +176,20 @@ fn platform_param() -> serde_json::Value {
     })
 }
 
+fn since_param() -> serde_json::Value {
+    json!({
+        "type": "string",
+        "description": "Only include messages at or after this time. ISO 8601 ('2025-01-15', '2025-01-15T10:30:00Z'), unix seconds, 'today', 'yesterday', or relative to now ('30m', '6h', '2d', '1w'). Dates without an offset use NEXUS_TIMEZONE."
+    })
+}
+
+fn until_param() -> serde_json::Value {
+    json!({
+        "type": "string",
+        "description": "Only include messages at or before this time. Same formats as 'since'. A bare date like '2025-01-15' includes the whole day."
+    })
+}
+
 fn universal_tools() -> Vec<ToolDef> {
     vec![
         ToolDef {
@@ -77,14 +223,16 @@ fn universal_tools() -> Vec<ToolDef> {
                         "type": "integer",
                         "description": "Max channels to return (default: 20, max: 100)"
                     },
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["platform"]
             }),
         },
         ToolDef {
             name: "read_messages",
-            description: "Read messages from a specific chat, inbox thread, or conversation. Returns newest first. Supports pagination via cursor for browsing history. For Telegram: use chat name, @username, or numeric ID. For Gmail: use folder name like 'INBOX', '[Gmail]/Sent Mail', or a label name. For Slack: use channel ID. For Discord: use channel ID.",
+            description: "Read messages from a specific chat, inbox thread, or conversation. Returns newest first. Supports pagination via cursor in both directions (next_cursor goes older, prev_cursor goes newer), after/around to read from a known message such as a search hit, and since/until to limit the time range. For Telegram: use chat name, @username, or numeric ID. For Gmail: use folder name like 'INBOX', '[Gmail]/Sent Mail', or a label name. For Slack: use channel ID. For Discord: use channel ID.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -99,19 +247,32 @@ fn universal_tools() -> Vec<ToolDef> {
                     },
                     "cursor": {
                         "type": "string",
-                        "description": "Pagination cursor from a previous response to fetch the next page of older messages"
+                        "description": "Pagination cursor from a previous response: the 'more available' cursor pages older, the 'newer messages' cursor pages newer"
                     },
-                    "format": format_param()
+                    "after": {
+                        "type": ["string", "integer"],
+                        "description": "Message ID to read forward from: returns messages newer than it. Cannot be combined with cursor or around"
+                    },
+                    "around": {
+                        "type": ["string", "integer"],
+                        "description": "Message ID to center the page on, e.g. a search result's ID, to see its surrounding context"
+                    },
+                    "since": since_param(),
+                    "until": until_param(),
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["platform", "channel"]
             }),
         },
         ToolDef {
             name: "send_message",
-            description: "Send a message to a chat, email, or conversation. For Telegram: sends to a chat (name, @username, or ID). For Gmail: sends a plain email. For Slack: posts to channel (reply_to = thread_ts). For Discord: sends to channel (reply_to = message ID). For WhatsApp: sends to a phone number.",
+            description: "Send a message to a chat, email, or conversation. For Telegram: sends to a chat (name, @username, or ID). For Gmail: sends a plain email. For Slack: posts to channel (reply_to = thread_ts). For Discord: sends to channel (reply_to = message ID). For WhatsApp: sends to a phone number. Where the platform needs human approval, the message is held for a reviewer and its ID returned; follow it with get_approval_status.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "platform": platform_param(),
                     "channel": {
                         "type": "string",
@@ -130,16 +291,116 @@ fn universal_tools() -> Vec<ToolDef> {
                 "required": ["platform", "channel", "text"]
             }),
         },
+        ToolDef {
+            name: "broadcast",
+            description: "Send the same message to several chats on any platforms in one call, a few at a time. Write the text in Markdown (**bold**, *italic*, ~~strike~~, `code`, fenced code blocks, [label](url)); it is converted for each platform: Slack and WhatsApp markup, Markdown on Discord, plain text with links spelled out on Telegram and Gmail. One target failing does not stop the rest; returns a result per target with the sent message ID or the error.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "idempotency_key": idempotency_key_param(),
+                    "targets": {
+                        "type": "array",
+                        "description": "Where to send, up to 50. Repeated targets are sent to once",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "platform": platform_param(),
+                                "channel": {
+                                    "type": "string",
+                                    "description": "Recipient, as for send_message"
+                                }
+                            },
+                            "required": ["platform", "channel"]
+                        }
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "Message text, in Markdown"
+                    },
+                    "format": format_param()
+                },
+                "required": ["targets", "text"]
+            }),
+        },
+        ToolDef {
+            name: "schedule_message",
+            description: "Schedule a message to be sent later. Telegram and Slack hold the message themselves and send it even if Nexus is not running. Gmail, WhatsApp and Discord messages wait in Nexus's own queue and are sent by the running server; if it is not running when one comes due (within 15 minutes), the job is marked missed instead of going out late. Returns the job ID for list_scheduled and cancel_scheduled.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "idempotency_key": idempotency_key_param(),
+                    "platform": platform_param(),
+                    "channel": {
+                        "type": "string",
+                        "description": "Recipient, as for send_message"
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "Message text to send"
+                    },
+                    "send_at": {
+                        "type": "string",
+                        "description": "When to send: ISO 8601 ('2025-01-20T09:00', '2025-01-20T09:00:00+01:00'), unix seconds, or relative to now ('30m', 'in 2h', '1d'). Times without an offset use NEXUS_TIMEZONE. Must be in the future."
+                    },
+                    "reply_to": {
+                        "type": "string",
+                        "description": "Message ID to reply to, as for send_message"
+                    },
+                    "format": format_param()
+                },
+                "required": ["platform", "channel", "text", "send_at"]
+            }),
+        },
+        ToolDef {
+            name: "list_scheduled",
+            description: "List scheduled messages, soonest first: those Telegram and Slack hold and the jobs in Nexus's queue. Queue jobs stay listed for a week after they are sent, fail or are missed, with their state, so check here for messages that did not go out.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "platform": {
+                        "type": "string",
+                        "description": "Only this platform (default: all)",
+                        "enum": ["telegram", "gmail", "whatsapp", "slack", "discord"]
+                    },
+                    "channel": {
+                        "type": "string",
+                        "description": "Only this chat or channel. Telegram lists scheduled messages per chat, so it needs one."
+                    },
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
+                }
+            }),
+        },
+        ToolDef {
+            name: "cancel_scheduled",
+            description: "Cancel a scheduled message before it is sent.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "platform": platform_param(),
+                    "id": {
+                        "type": "string",
+                        "description": "ID from schedule_message or list_scheduled"
+                    },
+                    "channel": {
+                        "type": "string",
+                        "description": "The message's chat or channel. Required for Telegram and Slack; not needed for queue jobs"
+                    }
+                },
+                "required": ["platform", "id"]
+            }),
+        },
         ToolDef {
             name: "search",
-            description: "Search messages across a platform. For Telegram: searches all chats by text content. For Gmail: uses IMAP search syntax — simple text searches the body, or use IMAP criteria like 'FROM sender@example.com', 'SUBJECT keyword', 'SINCE 01-Jan-2025'. For Slack: searches across workspace. For Discord: searches across guild.",
+            description: "Search messages across a platform using one query language for every platform: free text, \"exact phrases\", from:<sender>, in:<chat/channel/folder>, has:attachment, is:unread, after:<date>, before:<date>. Clauses a platform cannot honour are ignored and listed in the response.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "platform": platform_param(),
                     "query": {
                         "type": "string",
-                        "description": "Search query text, or IMAP search criteria for Gmail"
+                        "description": "Search query, e.g. 'invoice from:alice@example.com has:attachment after:2025-01-01' or '\"quarterly report\" in:#general'. Quote values with spaces: from:\"Alice Smith\""
                     },
                     "limit": {
                         "type": "integer",
@@ -149,14 +410,194 @@ fn universal_tools() -> Vec<ToolDef> {
                         "type": "string",
                         "description": "Pagination cursor from a previous response to fetch the next page"
                     },
-                    "format": format_param()
+                    "since": since_param(),
+                    "until": until_param(),
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["platform", "query"]
             }),
         },
+        ToolDef {
+            name: "get_delivery_status",
+            description: "Check whether a message you sent is pending, sent, delivered, read, or failed. Telegram reports sent/read and send failures. WhatsApp reports delivered/read/failed when the status webhook is configured (WHATSAPP_WEBHOOK_ADDR). Gmail, Slack, and Discord have no receipts and report 'sent' once the message exists.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "platform": platform_param(),
+                    "channel": {
+                        "type": "string",
+                        "description": "Chat or channel the message was sent to, as passed to send_message. Ignored for WhatsApp"
+                    },
+                    "message_id": {
+                        "type": ["string", "integer"],
+                        "description": "ID of the sent message: Telegram numeric ID, Gmail Message-ID, Slack ts, Discord snowflake, or WhatsApp wamid"
+                    },
+                    "format": format_param()
+                },
+                "required": ["platform", "message_id"]
+            }),
+        },
+        ToolDef {
+            name: "search_all",
+            description: "Run one search on every connected platform at once and get the results interleaved, each tagged with its platform. Uses the same query language as search. Platforms that fail, time out, or cannot search are listed in the response.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Search query, same syntax as search, e.g. 'invoice from:alice has:attachment after:2025-01-01'"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Max results across all platforms (default: 20, max: 100)"
+                    },
+                    "order": {
+                        "type": "string",
+                        "enum": ["recent", "relevance"],
+                        "description": "recent: newest first across platforms (default). relevance: alternate between platforms, keeping each platform's own ranking"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "Cursor from a previous search_all response to fetch the next page"
+                    },
+                    "timeout": {
+                        "type": "integer",
+                        "description": "Seconds to wait for each platform before listing it as failed (default: 20, max: 120)"
+                    },
+                    "since": since_param(),
+                    "until": until_param(),
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDef {
+            name: "unified_inbox",
+            description: "What's new everywhere: reads recent (or only unread) messages from every connected platform at once and merges them newest first, each tagged with its platform and channel. Platforms that fail are listed with their error instead of failing the call.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "unread_only": {
+                        "type": "boolean",
+                        "description": "Only unread messages (default: false). Slack and Discord bots cannot see unread counts and contribute nothing in this mode"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Max messages across all platforms (default: 30, max: 200)"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "Cursor from a previous unified_inbox response to fetch older messages"
+                    },
+                    "since": since_param(),
+                    "until": until_param(),
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
+                }
+            }),
+        },
+        ToolDef {
+            name: "export_channel",
+            description: "Archive a whole conversation to a file by paging back through its history until it runs out or passes 'since'. Writes JSON Lines (one message per line), mbox (gmail only) or a self-contained HTML transcript (oldest first). Progress is checkpointed after every page: if the export is interrupted or stops at max_messages, call again with the same path to continue where it left off. Returns the file path, message count and period covered.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "platform": platform_param(),
+                    "channel": {
+                        "type": "string",
+                        "description": "Chat, channel or folder to export, as for read_messages"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Output file, relative to NEXUS_DATA_DIR/exports. Absolute paths and '..' are rejected. Must not already exist unless resuming."
+                    },
+                    "file_format": {
+                        "type": "string",
+                        "description": "jsonl, mbox or html (default: from the path's extension, else mbox for gmail and jsonl otherwise)",
+                        "enum": ["jsonl", "mbox", "html"]
+                    },
+                    "since": since_param(),
+                    "until": until_param(),
+                    "attachments": {
+                        "type": "boolean",
+                        "description": "Also download attachments into <file>_files/ next to the output (default: false). Supported on Telegram; other platforms are counted as skipped."
+                    },
+                    "max_messages": {
+                        "type": "integer",
+                        "description": "Stop after about this many messages (checked between pages of 100). Call again with the same path to continue."
+                    },
+                    "format": format_param()
+                },
+                "required": ["platform", "channel", "path"]
+            }),
+        },
     ]
 }
 
+fn approval_tools() -> Vec<ToolDef> {
+    vec![ToolDef {
+        name: "get_approval_status",
+        description: "Check on messages held for human approval. On platforms that need approval, send_message and broadcast hold each message for a reviewer and return its ID instead of sending. A held message is pending until the reviewer approves, edits or rejects it; approved messages are then sent and show sent with the message ID, or failed with the error. Rejections may carry the reviewer's reason. Poll this rather than sending again.",
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "A held message's ID (apr-...). Leave out to list every held message, including ones decided in the last week"
+                },
+                "format": format_param(),
+                "max_chars": max_chars_param(),
+                "max_tokens": max_tokens_param()
+            }
+        }),
+    }]
+}
+
+fn store_tools() -> Vec<ToolDef> {
+    vec![ToolDef {
+        name: "local_search",
+        description: "Full-text search over every message Nexus has stored, across all platforms, including ones that are offline or cannot search (WhatsApp) and Slack without a user token. Uses the same query language as search; from: matches part of the sender and in: a channel ID or name. Only finds messages Nexus has already read or synced.",
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Search query, same syntax as search, e.g. 'deploy failed from:alice in:ops after:2025-01-01'. Words match regardless of endings (deploy finds deployed)"
+                },
+                "platform": {
+                    "type": "string",
+                    "description": "Only this platform (default: all)",
+                    "enum": ["telegram", "gmail", "whatsapp", "slack", "discord"]
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Max results (default: 20, max: 100)"
+                },
+                "order": {
+                    "type": "string",
+                    "enum": ["relevance", "recent"],
+                    "description": "relevance: best matches first, a subject or sender match counting most (default). recent: newest first"
+                },
+                "cursor": {
+                    "type": "string",
+                    "description": "Cursor from a previous local_search response to fetch the next page"
+                },
+                "since": since_param(),
+                "until": until_param(),
+                "format": format_param(),
+                "max_chars": max_chars_param(),
+                "max_tokens": max_tokens_param()
+            },
+            "required": ["query"]
+        }),
+    }]
+}
+
 fn telegram_tools() -> Vec<ToolDef> {
     vec![
         ToolDef {
@@ -187,6 +628,7 @@ fn telegram_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "from_chat": {
                         "type": "string",
                         "description": "Source chat name, @username, or ID"
@@ -340,6 +782,7 @@ fn telegram_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "chat": {
                         "type": "string",
                         "description": "Chat name, @username, or ID"
@@ -402,7 +845,9 @@ fn telegram_tools() -> Vec<ToolDef> {
                         "type": "integer",
                         "description": "Max results to return (default: 20, max: 100)"
                     },
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["chat", "query"]
             }),
@@ -421,7 +866,9 @@ fn telegram_tools() -> Vec<ToolDef> {
                         "type": "integer",
                         "description": "Max members to return (default: 50, max: 200)"
                     },
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["chat"]
             }),
@@ -437,6 +884,7 @@ fn gmail_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "to": {
                         "type": "array",
                         "items": {"type": "string"},
@@ -494,7 +942,9 @@ fn gmail_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 }
             }),
         },
@@ -679,6 +1129,7 @@ fn whatsapp_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "chat": {
                         "type": "string",
                         "description": "Recipient phone number in international format (e.g. '1234567890')"
@@ -823,6 +1274,7 @@ fn slack_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
+                    "idempotency_key": idempotency_key_param(),
                     "channels": {
                         "type": "array",
                         "items": {"type": "string"},
@@ -850,7 +1302,9 @@ fn slack_tools() -> Vec<ToolDef> {
                         "type": "integer",
                         "description": "Max users to return (default: 50)"
                     },
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 }
             }),
         },
@@ -880,7 +1334,9 @@ fn discord_tools() -> Vec<ToolDef> {
             input_schema: json!({
                 "type": "object",
                 "properties": {
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 }
             }),
         },
@@ -894,7 +1350,9 @@ fn discord_tools() -> Vec<ToolDef> {
                         "type": "string",
                         "description": "Discord guild/server ID"
                     },
-                    "format": format_param()
+                    "format": format_param(),
+                    "max_chars": max_chars_param(),
+                    "max_tokens": max_tokens_param()
                 },
                 "required": ["guild_id"]
             }),
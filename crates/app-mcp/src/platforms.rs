@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nexus_discord::DiscordAdapter;
+use nexus_domain::{Message, Platform, TelegramExt};
+use nexus_error::AgentError;
+use nexus_google::GmailAdapter;
+use nexus_messaging::{AgentService, AttachmentFetcher, Layered, Stack};
+use nexus_slack::SlackAdapter;
+use nexus_tdlib::{TdClient, TdlibAdapter};
+use nexus_whatsapp::WhatsAppAdapter;
+use tracing::info;
+
+use crate::{
+    load_discord_config, load_gmail_config, load_slack_config, load_telegram_config,
+    load_whatsapp_config,
+};
+
+// Each adapter is wrapped in the middleware stack; platforms without
+// configuration are `None`.
+#[derive(Clone, Default)]
+pub struct Platforms {
+    pub telegram: Option<Arc<Layered<TdlibAdapter>>>,
+    pub gmail: Option<Arc<Layered<GmailAdapter>>>,
+    pub whatsapp: Option<Arc<Layered<WhatsAppAdapter>>>,
+    pub slack: Option<Arc<Layered<SlackAdapter>>>,
+    pub discord: Option<Arc<Layered<DiscordAdapter>>>,
+}
+
+impl Platforms {
+    // Connects every platform that has configuration and registers it with
+    // the agent. Event sources are registered too; nothing subscribes to
+    // them until a client asks.
+    pub async fn connect(
+        agent: &mut AgentService,
+        stack: &Stack,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut platforms = Self::default();
+
+        match load_telegram_config() {
+            Ok(cfg) => {
+                let client = Arc::new(TdClient::new());
+                let mut auth_rx = client
+                    .take_auth_rx()
+                    .ok_or("failed to get auth receiver")?;
+
+                nexus_tdlib::auth::wait_for_ready(&client, &mut auth_rx, &cfg).await?;
+                info!("telegram connected");
+
+                let adapter = Arc::new(TdlibAdapter::new(client));
+                agent.register(adapter.clone(), stack);
+                agent.register_events(adapter.clone());
+                let layered = stack.wrap(adapter);
+                agent.register_users(layered.clone());
+                agent.register_scheduler(layered.clone());
+                platforms.telegram = Some(layered);
+            }
+            Err(e) => info!("telegram not configured: {e}"),
+        }
+
+        match load_gmail_config() {
+            Ok(cfg) => {
+                let adapter = Arc::new(GmailAdapter::new(cfg));
+                agent.register(adapter.clone(), stack);
+                agent.register_events(adapter.clone());
+                info!("gmail configured");
+                platforms.gmail = Some(stack.wrap(adapter));
+            }
+            Err(e) => info!("gmail not configured: {e}"),
+        }
+
+        match load_whatsapp_config() {
+            Ok(cfg) => {
+                let adapter = Arc::new(WhatsAppAdapter::new(cfg));
+                agent.register(adapter.clone(), stack);
+                info!("whatsapp configured");
+                platforms.whatsapp = Some(stack.wrap(adapter));
+            }
+            Err(e) => info!("whatsapp not configured: {e}"),
+        }
+
+        match load_slack_config() {
+            Ok(cfg) => {
+                let adapter = Arc::new(SlackAdapter::new(cfg));
+                agent.register(adapter.clone(), stack);
+                agent.register_events(adapter.clone());
+                info!("slack configured");
+                let layered = stack.wrap(adapter);
+                agent.register_users(layered.clone());
+                agent.register_scheduler(layered.clone());
+                platforms.slack = Some(layered);
+            }
+            Err(e) => info!("slack not configured: {e}"),
+        }
+
+        match load_discord_config() {
+            Ok(cfg) => {
+                let adapter = Arc::new(DiscordAdapter::new(cfg));
+                agent.register(adapter.clone(), stack);
+                agent.register_events(adapter.clone());
+                info!("discord configured");
+                let layered = stack.wrap(adapter);
+                agent.register_users(layered.clone());
+                platforms.discord = Some(layered);
+            }
+            Err(e) => info!("discord not configured: {e}"),
+        }
+
+        Ok(platforms)
+    }
+}
+
+// Exports download through the same per-platform support the tools use.
+// Gmail's needs a file name that listings do not carry, so it is skipped.
+#[async_trait]
+impl AttachmentFetcher for Platforms {
+    async fn fetch(&self, msg: &Message, dir: &Path) -> Result<Vec<PathBuf>, AgentError> {
+        match (msg.platform, &self.telegram) {
+            (Platform::Telegram, Some(tg)) => {
+                let msg_id: i64 = msg.id.parse().map_err(|_| {
+                    AgentError::invalid_input(format!("not a telegram message id: {}", msg.id))
+                })?;
+                let local = tg.download_media(&msg.channel_id, msg_id, "").await?;
+                let name = Path::new(&local)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "media".to_string());
+                let dest = dir.join(format!("{}-{name}", msg.id));
+                tokio::fs::copy(&local, &dest)
+                    .await
+                    .map_err(|e| AgentError::internal(format!("copy {local}: {e}")))?;
+                Ok(vec![dest])
+            }
+            (Platform::Gmail, _) => Err(AgentError::not_implemented(
+                "gmail attachments are fetched by file name; use gmail_get_attachment",
+            )),
+            (platform, _) => Err(AgentError::not_implemented(format!(
+                "attachment download is not supported on {platform}"
+            ))),
+        }
+    }
+}
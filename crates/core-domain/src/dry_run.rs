@@ -0,0 +1,56 @@
+// Dry runs. A call made inside `scope` goes through everything that checks
+// and resolves its inputs (chat lookups, message searches, address parsing)
+// and stops right before the request that would change anything. Adapters
+// mark that point with `intercept`, which records what would have happened
+// and aborts with `AgentError::DryRun`.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use nexus_error::AgentError;
+
+tokio::task_local! {
+    static PLANS: Plans;
+}
+
+// Cloned into blocking tasks, which the dry-run task-local does not reach.
+#[derive(Clone, Default)]
+pub struct Plans(Arc<Mutex<Vec<String>>>);
+
+impl Plans {
+    pub fn intercept(&self, plan: String) -> AgentError {
+        if let Ok(mut plans) = self.0.lock() {
+            plans.push(plan.clone());
+        }
+        AgentError::dry_run(plan)
+    }
+
+    fn take(&self) -> Vec<String> {
+        self.0.lock().map(|mut p| std::mem::take(&mut *p)).unwrap_or_default()
+    }
+}
+
+// Plans come back in the order the calls reached their interception points.
+pub async fn scope<F: Future>(fut: F) -> (F::Output, Vec<String>) {
+    let plans = Plans::default();
+    let output = PLANS.scope(plans.clone(), fut).await;
+    (output, plans.take())
+}
+
+pub fn active() -> bool {
+    PLANS.try_with(|_| ()).is_ok()
+}
+
+// The current dry run's plans, for code about to move to a blocking task.
+pub fn plans() -> Option<Plans> {
+    PLANS.try_with(Plans::clone).ok()
+}
+
+// Inside a dry run, records `plan` and returns the error that stops the call
+// before it changes anything.
+pub fn intercept(plan: impl FnOnce() -> String) -> Result<(), AgentError> {
+    match plans() {
+        Some(plans) => Err(plans.intercept(plan())),
+        None => Ok(()),
+    }
+}
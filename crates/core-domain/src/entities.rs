@@ -1,8 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Platform {
     Telegram,
@@ -64,22 +64,26 @@ pub struct Channel {
     pub last_message_date: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
     pub platform: Platform,
     pub channel_id: String,
+    // The sender's platform ID: user ID, email address or phone number.
+    // Stable where `sender`, the display name, is not.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sender_id: String,
     pub sender: String,
     pub text: String,
     pub timestamp: i64,
     pub has_attachment: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reply_to: Option<String>,
-    #[serde(skip_serializing_if = "MessageMeta::is_empty")]
+    #[serde(default, skip_serializing_if = "MessageMeta::is_empty")]
     pub meta: MessageMeta,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MessageMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subject: Option<String>,
@@ -101,6 +105,8 @@ pub struct MessageMeta {
     pub edit_date: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_pinned: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery: Option<DeliveryState>,
 }
 
 impl MessageMeta {
@@ -115,10 +121,93 @@ impl MessageMeta {
             && self.views.is_none()
             && self.edit_date.is_none()
             && self.is_pinned.is_none()
+            && self.delivery.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Pending,
+    Sent,
+    Delivered,
+    Read,
+    Failed,
+}
+
+impl fmt::Display for DeliveryState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Sent => write!(f, "sent"),
+            Self::Delivered => write!(f, "delivered"),
+            Self::Read => write!(f, "read"),
+            Self::Failed => write!(f, "failed"),
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize)]
+pub struct DeliveryStatus {
+    pub platform: Platform,
+    pub channel_id: String,
+    pub message_id: String,
+    pub state: DeliveryState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleState {
+    Scheduled,
+    // Handed to the platform; a job still in this state after a restart may
+    // or may not have gone out.
+    Sending,
+    Sent,
+    Failed,
+    // Came due while nothing was running to send it.
+    Missed,
+    Cancelled,
+}
+
+impl fmt::Display for ScheduleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scheduled => write!(f, "scheduled"),
+            Self::Sending => write!(f, "sending"),
+            Self::Sent => write!(f, "sent"),
+            Self::Failed => write!(f, "failed"),
+            Self::Missed => write!(f, "missed"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+// A message to be sent at `send_at`, held by the platform itself or, when
+// `queued`, by Nexus's own job queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    pub id: String,
+    pub platform: Platform,
+    pub channel_id: String,
+    pub text: String,
+    pub send_at: i64,
+    pub state: ScheduleState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub queued: bool,
+    // The sent message's ID, once it has gone out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Reaction {
     pub emoji: String,
     pub count: i32,
@@ -137,6 +226,35 @@ pub struct Profile {
     pub phone: Option<String>,
 }
 
+// A chat as every name it answers to: the platform ID and whatever titles,
+// handles or aliases also reach it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatRef {
+    pub id: String,
+    pub names: Vec<String>,
+}
+
+impl ChatRef {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            names: Vec::new(),
+        }
+    }
+
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !name.is_empty() && name != self.id && !self.names.contains(&name) {
+            self.names.push(name);
+        }
+        self
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.id.as_str()).chain(self.names.iter().map(String::as_str))
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ChatInfo {
     pub id: String,
@@ -159,6 +277,167 @@ pub struct Paginated<T: serde::Serialize> {
     pub has_more: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadAnchor {
+    After(String),
+    Around(String),
+}
+
+impl ReadAnchor {
+    pub fn message_id(&self) -> &str {
+        match self {
+            Self::After(id) | Self::Around(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TimeRange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<i64>,
+}
+
+impl TimeRange {
+    pub fn new(since: Option<i64>, until: Option<i64>) -> Self {
+        Self { since, until }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    pub fn contains(&self, ts: i64) -> bool {
+        self.since.map_or(true, |s| ts >= s) && self.until.map_or(true, |u| ts <= u)
+    }
+
+    pub fn intersect(self, other: TimeRange) -> TimeRange {
+        let since = match (self.since, other.since) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let until = match (self.until, other.until) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        TimeRange { since, until }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchClause {
+    Phrase,
+    From,
+    In,
+    HasAttachment,
+    IsUnread,
+    After,
+    Before,
+}
+
+impl fmt::Display for SearchClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Phrase => write!(f, "\"exact phrase\""),
+            Self::From => write!(f, "from:"),
+            Self::In => write!(f, "in:"),
+            Self::HasAttachment => write!(f, "has:attachment"),
+            Self::IsUnread => write!(f, "is:unread"),
+            Self::After => write!(f, "after:"),
+            Self::Before => write!(f, "before:"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchQuery {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub phrases: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub has_attachment: bool,
+    pub is_unread: bool,
+    pub range: TimeRange,
+    // Best matches first rather than newest first, where the platform can
+    // rank its results.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub by_relevance: bool,
+}
+
+impl SearchQuery {
+    pub fn text(&self) -> String {
+        self.terms
+            .iter()
+            .chain(self.phrases.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.clauses().is_empty()
+    }
+
+    pub fn clauses(&self) -> Vec<SearchClause> {
+        let mut clauses = Vec::new();
+        if !self.phrases.is_empty() {
+            clauses.push(SearchClause::Phrase);
+        }
+        if self.from.is_some() {
+            clauses.push(SearchClause::From);
+        }
+        if self.channel.is_some() {
+            clauses.push(SearchClause::In);
+        }
+        if self.has_attachment {
+            clauses.push(SearchClause::HasAttachment);
+        }
+        if self.is_unread {
+            clauses.push(SearchClause::IsUnread);
+        }
+        if self.range.since.is_some() {
+            clauses.push(SearchClause::After);
+        }
+        if self.range.until.is_some() {
+            clauses.push(SearchClause::Before);
+        }
+        clauses
+    }
+
+    // Drops the given clauses. An exact phrase degrades to plain terms
+    // rather than disappearing from the query.
+    pub fn without(&self, clauses: &[SearchClause]) -> SearchQuery {
+        let mut q = self.clone();
+        for clause in clauses {
+            match clause {
+                SearchClause::Phrase => {
+                    let phrases = std::mem::take(&mut q.phrases);
+                    q.terms.extend(
+                        phrases
+                            .iter()
+                            .flat_map(|p| p.split_whitespace().map(String::from)),
+                    );
+                }
+                SearchClause::From => q.from = None,
+                SearchClause::In => q.channel = None,
+                SearchClause::HasAttachment => q.has_attachment = false,
+                SearchClause::IsUnread => q.is_unread = false,
+                SearchClause::After => q.range.since = None,
+                SearchClause::Before => q.range.until = None,
+            }
+        }
+        q
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -0,0 +1,123 @@
+use futures::stream::{self, BoxStream};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::entities::{DeliveryState, Message, Platform, Reaction};
+
+pub type EventStream = BoxStream<'static, Event>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    MessageCreated {
+        message: Box<Message>,
+    },
+    MessageEdited {
+        platform: Platform,
+        channel_id: String,
+        message_id: String,
+        text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        edited_at: Option<i64>,
+    },
+    MessageDeleted {
+        platform: Platform,
+        channel_id: String,
+        message_ids: Vec<String>,
+    },
+    ReactionChanged {
+        platform: Platform,
+        channel_id: String,
+        message_id: String,
+        reactions: Vec<Reaction>,
+    },
+    ReadStateChanged {
+        platform: Platform,
+        channel_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_read_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unread_count: Option<i32>,
+    },
+    ChannelUpdated {
+        platform: Platform,
+        channel_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    DeliveryChanged {
+        platform: Platform,
+        channel_id: String,
+        message_id: String,
+        state: DeliveryState,
+    },
+}
+
+impl Event {
+    pub fn platform(&self) -> Platform {
+        match self {
+            Self::MessageCreated { message } => message.platform,
+            Self::MessageEdited { platform, .. }
+            | Self::MessageDeleted { platform, .. }
+            | Self::ReactionChanged { platform, .. }
+            | Self::ReadStateChanged { platform, .. }
+            | Self::ChannelUpdated { platform, .. }
+            | Self::DeliveryChanged { platform, .. } => *platform,
+        }
+    }
+
+    pub fn channel_id(&self) -> &str {
+        match self {
+            Self::MessageCreated { message } => &message.channel_id,
+            Self::MessageEdited { channel_id, .. }
+            | Self::MessageDeleted { channel_id, .. }
+            | Self::ReactionChanged { channel_id, .. }
+            | Self::ReadStateChanged { channel_id, .. }
+            | Self::ChannelUpdated { channel_id, .. }
+            | Self::DeliveryChanged { channel_id, .. } => channel_id,
+        }
+    }
+}
+
+// Fan-out point shared by adapters: producers publish, every subscriber gets
+// its own stream. Slow subscribers skip what they missed rather than block.
+#[derive(Clone)]
+pub struct EventHub {
+    tx: broadcast::Sender<Event>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl EventHub {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
+    pub fn subscribe(&self) -> EventStream {
+        Box::pin(stream::unfold(self.tx.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "event subscriber lagged, dropped events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }))
+    }
+}
@@ -1,5 +1,8 @@
+pub mod dry_run;
 pub mod entities;
+pub mod events;
 pub mod ports;
 
 pub use entities::*;
+pub use events::*;
 pub use ports::*;
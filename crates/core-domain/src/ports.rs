@@ -1,7 +1,11 @@
 use async_trait::async_trait;
 use nexus_error::AgentError;
 
-use crate::entities::{Channel, ChatInfo, ChatMember, Message, Paginated, Platform, Profile};
+use crate::entities::{
+    Channel, ChatInfo, ChatRef, ChatMember, DeliveryStatus, Message, Paginated, Platform, Profile,
+    ReadAnchor, ScheduledMessage, SearchClause, SearchQuery, TimeRange,
+};
+use crate::events::EventStream;
 
 #[async_trait]
 pub trait MessagingPort: Send + Sync {
@@ -16,6 +20,8 @@ pub trait MessagingPort: Send + Sync {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError>;
 
     async fn send_message(
@@ -27,10 +33,53 @@ pub trait MessagingPort: Send + Sync {
 
     async fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         limit: usize,
         cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError>;
+
+    fn unsupported_clauses(&self, query: &SearchQuery) -> Vec<SearchClause>;
+
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError>;
+
+    // The chat a channel argument reaches, under its ID and other names.
+    // Platforms that only take IDs keep the default.
+    async fn resolve_channel(&self, channel: &str) -> Result<ChatRef, AgentError> {
+        Ok(ChatRef::new(channel))
+    }
+}
+
+// Starting a subscription may open connections or start pollers, hence async.
+#[async_trait]
+pub trait EventPort: MessagingPort {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError>;
+}
+
+// Looks up a user by the ID messages carry in `sender_id`.
+#[async_trait]
+pub trait UserPort: MessagingPort {
+    async fn get_user(&self, user_id: &str) -> Result<Profile, AgentError>;
+}
+
+// Platforms that hold scheduled messages themselves and send them even
+// while Nexus is not running.
+#[async_trait]
+pub trait SchedulePort: MessagingPort {
+    async fn schedule_message(
+        &self,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        send_at: i64,
+    ) -> Result<ScheduledMessage, AgentError>;
+
+    async fn list_scheduled(&self, channel: Option<&str>) -> Result<Vec<ScheduledMessage>, AgentError>;
+
+    async fn cancel_scheduled(&self, channel: &str, id: &str) -> Result<(), AgentError>;
 }
 
 #[async_trait]
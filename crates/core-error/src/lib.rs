@@ -1,71 +1,262 @@
+use std::fmt;
+use std::time::Duration;
+
 use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum AgentError {
-    #[error("authentication failed: {0}")]
-    Auth(String),
+    #[error("authentication failed: {message}")]
+    Auth { message: String, ctx: Box<ErrorContext> },
+
+    #[error("api error: {message}")]
+    Api { message: String, ctx: Box<ErrorContext> },
+
+    #[error("network error: {message}")]
+    Network { message: String, ctx: Box<ErrorContext> },
+
+    #[error("session error: {message}")]
+    Session { message: String, ctx: Box<ErrorContext> },
+
+    #[error("not found: {message}")]
+    NotFound { message: String, ctx: Box<ErrorContext> },
+
+    #[error("invalid input: {message}")]
+    InvalidInput { message: String, ctx: Box<ErrorContext> },
+
+    #[error("platform not available: {message}")]
+    PlatformNotAvailable { message: String, ctx: Box<ErrorContext> },
+
+    #[error("not implemented: {message}")]
+    NotImplemented { message: String, ctx: Box<ErrorContext> },
+
+    #[error("internal error: {message}")]
+    Internal { message: String, ctx: Box<ErrorContext> },
 
-    #[error("api error: {0}")]
-    Api(String),
+    #[error("denied by policy: {message}")]
+    PolicyDenied { message: String, ctx: Box<ErrorContext> },
 
-    #[error("network error: {0}")]
-    Network(String),
+    // Not a failure: a dry run stopped the call before it changed
+    // anything. The message says what it would have done.
+    #[error("dry run: {message}")]
+    DryRun { message: String, ctx: Box<ErrorContext> },
 
-    #[error("session error: {0}")]
-    Session(String),
+    #[error("rate limited ({scope}): {message}{}", retry_hint(.retry_after))]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+        scope: RateLimitScope,
+        ctx: Box<ErrorContext>,
+    },
+}
 
-    #[error("not found: {0}")]
-    NotFound(String),
+// Adapters fill in what they know (platform code, API method, the argument
+// that was rejected, a specific fix); the service layer fills in the platform
+// and operation when the adapter did not.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    pub code: Option<String>,
+    pub platform: Option<String>,
+    pub operation: Option<String>,
+    pub argument: Option<String>,
+    pub suggestion: Option<String>,
+}
 
-    #[error("invalid input: {0}")]
-    InvalidInput(String),
+// What the platform is throttling: everything the account/app does, one
+// method or route, or one chat/recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    Global,
+    Method,
+    Resource,
+}
 
-    #[error("platform not available: {0}")]
-    PlatformNotAvailable(String),
+impl fmt::Display for RateLimitScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Method => write!(f, "method"),
+            Self::Resource => write!(f, "resource"),
+        }
+    }
+}
 
-    #[error("not implemented: {0}")]
-    NotImplemented(String),
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(d) => format!(", retry in {}s", retry_secs(*d)),
+        None => String::new(),
+    }
+}
 
-    #[error("internal error: {0}")]
-    Internal(String),
+// Round up so callers never retry a fraction of a second too early.
+fn retry_secs(d: Duration) -> u64 {
+    d.as_secs() + u64::from(d.subsec_nanos() > 0)
 }
 
 impl AgentError {
     pub fn auth(msg: impl Into<String>) -> Self {
-        Self::Auth(msg.into())
+        Self::Auth { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn api(msg: impl Into<String>) -> Self {
-        Self::Api(msg.into())
+        Self::Api { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn network(msg: impl Into<String>) -> Self {
-        Self::Network(msg.into())
+        Self::Network { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn session(msg: impl Into<String>) -> Self {
-        Self::Session(msg.into())
+        Self::Session { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn not_found(msg: impl Into<String>) -> Self {
-        Self::NotFound(msg.into())
+        Self::NotFound { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn invalid_input(msg: impl Into<String>) -> Self {
-        Self::InvalidInput(msg.into())
+        Self::InvalidInput { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn platform_not_available(msg: impl Into<String>) -> Self {
-        Self::PlatformNotAvailable(msg.into())
+        Self::PlatformNotAvailable { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn not_implemented(msg: impl Into<String>) -> Self {
-        Self::NotImplemented(msg.into())
+        Self::NotImplemented { message: msg.into(), ctx: Box::default() }
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
-        Self::Internal(msg.into())
+        Self::Internal { message: msg.into(), ctx: Box::default() }
+    }
+
+    pub fn policy_denied(msg: impl Into<String>) -> Self {
+        Self::PolicyDenied { message: msg.into(), ctx: Box::default() }
+    }
+
+    pub fn dry_run(plan: impl Into<String>) -> Self {
+        Self::DryRun { message: plan.into(), ctx: Box::default() }
+    }
+
+    pub fn rate_limited(
+        msg: impl Into<String>,
+        retry_after: Option<Duration>,
+        scope: RateLimitScope,
+    ) -> Self {
+        Self::RateLimited {
+            message: msg.into(),
+            retry_after,
+            scope,
+            ctx: Box::default(),
+        }
+    }
+
+    // The platform's own code, e.g. a Slack error string or a Discord JSON
+    // error code.
+    pub fn with_code(mut self, platform_code: impl Into<String>) -> Self {
+        self.ctx_mut().code = Some(platform_code.into());
+        self
+    }
+
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.ctx_mut().platform = Some(platform.into());
+        self
+    }
+
+    pub fn with_operation(mut self, operation: impl Into<String>) -> Self {
+        self.ctx_mut().operation = Some(operation.into());
+        self
+    }
+
+    // The argument or resource that was rejected, e.g. `channel` or
+    // `guild 1234`.
+    pub fn with_argument(mut self, argument: impl Into<String>) -> Self {
+        self.ctx_mut().argument = Some(argument.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.ctx_mut().suggestion = Some(suggestion.into());
+        self
+    }
+
+    // Does not override what the adapter already recorded.
+    pub fn in_context(mut self, platform: impl fmt::Display, operation: &str) -> Self {
+        let ctx = self.ctx_mut();
+        if ctx.platform.is_none() {
+            ctx.platform = Some(platform.to_string());
+        }
+        if ctx.operation.is_none() {
+            ctx.operation = Some(operation.to_string());
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Auth { message, .. }
+            | Self::Api { message, .. }
+            | Self::Network { message, .. }
+            | Self::Session { message, .. }
+            | Self::NotFound { message, .. }
+            | Self::InvalidInput { message, .. }
+            | Self::PlatformNotAvailable { message, .. }
+            | Self::NotImplemented { message, .. }
+            | Self::Internal { message, .. }
+            | Self::PolicyDenied { message, .. }
+            | Self::DryRun { message, .. }
+            | Self::RateLimited { message, .. } => message,
+        }
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        match self {
+            Self::Auth { ctx, .. }
+            | Self::Api { ctx, .. }
+            | Self::Network { ctx, .. }
+            | Self::Session { ctx, .. }
+            | Self::NotFound { ctx, .. }
+            | Self::InvalidInput { ctx, .. }
+            | Self::PlatformNotAvailable { ctx, .. }
+            | Self::NotImplemented { ctx, .. }
+            | Self::Internal { ctx, .. }
+            | Self::PolicyDenied { ctx, .. }
+            | Self::DryRun { ctx, .. }
+            | Self::RateLimited { ctx, .. } => ctx,
+        }
+    }
+
+    fn ctx_mut(&mut self) -> &mut ErrorContext {
+        match self {
+            Self::Auth { ctx, .. }
+            | Self::Api { ctx, .. }
+            | Self::Network { ctx, .. }
+            | Self::Session { ctx, .. }
+            | Self::NotFound { ctx, .. }
+            | Self::InvalidInput { ctx, .. }
+            | Self::PlatformNotAvailable { ctx, .. }
+            | Self::NotImplemented { ctx, .. }
+            | Self::Internal { ctx, .. }
+            | Self::PolicyDenied { ctx, .. }
+            | Self::DryRun { ctx, .. }
+            | Self::RateLimited { ctx, .. } => ctx,
+        }
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.context().code.as_deref()
+    }
+
+    pub fn platform(&self) -> Option<&str> {
+        self.context().platform.as_deref()
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
     }
 }
 
@@ -74,64 +265,152 @@ pub struct ErrorResponse {
     pub code: &'static str,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub suggestion: Option<&'static str>,
+    pub suggestion: Option<String>,
     pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argument: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
 }
 
 impl From<&AgentError> for ErrorResponse {
     fn from(err: &AgentError) -> Self {
-        let (code, suggestion, retryable) = match err {
-            AgentError::Auth(_) => (
-                "AUTH_ERROR",
-                Some("Run `nexus auth telegram` or check GMAIL_APP_PASSWORD env var"),
-                false,
-            ),
-            AgentError::Api(_) => ("API_ERROR", None, false),
-            AgentError::Network(_) => (
-                "NETWORK_ERROR",
-                Some("Check internet connection and try again"),
-                true,
-            ),
-            AgentError::Session(_) => (
-                "SESSION_ERROR",
-                Some("Session expired. Re-run `nexus auth telegram`"),
-                false,
-            ),
-            AgentError::NotFound(_) => (
-                "NOT_FOUND",
-                Some("Use list_channels to find valid channel names/IDs"),
-                false,
-            ),
-            AgentError::InvalidInput(_) => ("INVALID_INPUT", None, false),
-            AgentError::PlatformNotAvailable(_) => (
-                "PLATFORM_NOT_AVAILABLE",
-                Some("Platform not configured. Set required env vars and restart"),
-                false,
-            ),
-            AgentError::NotImplemented(_) => (
-                "NOT_IMPLEMENTED",
-                Some("This feature is not yet available"),
-                false,
-            ),
-            AgentError::Internal(_) => ("INTERNAL_ERROR", Some("Unexpected error"), true),
+        let (code, retryable) = match err {
+            AgentError::Auth { .. } => ("AUTH_ERROR", false),
+            AgentError::Api { .. } => ("API_ERROR", false),
+            AgentError::Network { .. } => ("NETWORK_ERROR", true),
+            AgentError::Session { .. } => ("SESSION_ERROR", false),
+            AgentError::NotFound { .. } => ("NOT_FOUND", false),
+            AgentError::InvalidInput { .. } => ("INVALID_INPUT", false),
+            AgentError::PlatformNotAvailable { .. } => ("PLATFORM_NOT_AVAILABLE", false),
+            AgentError::NotImplemented { .. } => ("NOT_IMPLEMENTED", false),
+            AgentError::Internal { .. } => ("INTERNAL_ERROR", true),
+            AgentError::PolicyDenied { .. } => ("POLICY_DENIED", false),
+            AgentError::DryRun { .. } => ("DRY_RUN", false),
+            AgentError::RateLimited { .. } => ("RATE_LIMITED", true),
         };
+        let ctx = err.context();
         Self {
             code,
             message: err.to_string(),
-            suggestion,
+            suggestion: ctx.suggestion.clone().or_else(|| default_suggestion(err)),
             retryable,
+            platform: ctx.platform.clone(),
+            operation: ctx.operation.clone(),
+            argument: ctx.argument.clone(),
+            platform_code: ctx.code.clone(),
+            retry_after_secs: err.retry_after().map(retry_secs),
         }
     }
 }
 
+// Credentials each platform reads at startup, for auth and setup hints.
+fn credential_vars(platform: &str) -> Option<&'static str> {
+    match platform {
+        "telegram" => Some("TELEGRAM_API_ID and TELEGRAM_API_HASH"),
+        "gmail" => Some("GMAIL_ADDRESS and GMAIL_APP_PASSWORD"),
+        "whatsapp" => Some("WHATSAPP_ACCESS_TOKEN and WHATSAPP_PHONE_NUMBER_ID"),
+        "slack" => Some("SLACK_BOT_TOKEN"),
+        "discord" => Some("DISCORD_BOT_TOKEN"),
+        _ => None,
+    }
+}
+
+fn default_suggestion(err: &AgentError) -> Option<String> {
+    let ctx = err.context();
+    let platform = ctx.platform.as_deref();
+    let suggestion = match err {
+        AgentError::Auth { .. } => match platform {
+            Some("telegram") => "Run `nexus auth telegram` to sign in again".to_string(),
+            Some("gmail") => {
+                "Check GMAIL_ADDRESS and GMAIL_APP_PASSWORD (an App Password, not the account password)"
+                    .to_string()
+            }
+            Some("slack") => {
+                "Check SLACK_BOT_TOKEN is a valid bot token (xoxb-...) for an installed app".to_string()
+            }
+            Some("discord") => "Check DISCORD_BOT_TOKEN and the bot's permissions".to_string(),
+            Some("whatsapp") => {
+                "Check WHATSAPP_ACCESS_TOKEN; temporary dashboard tokens expire after 24 hours"
+                    .to_string()
+            }
+            _ => "Check the platform credentials and restart".to_string(),
+        },
+        AgentError::Api { .. } => return None,
+        AgentError::Network { .. } => "Check internet connection and try again".to_string(),
+        AgentError::Session { .. } => match platform {
+            Some("gmail") => "The IMAP session dropped; retry the call".to_string(),
+            _ => "Session expired. Re-run `nexus auth telegram`".to_string(),
+        },
+        AgentError::NotFound { .. } => match (platform, ctx.argument.as_deref()) {
+            (_, Some(arg)) if arg.starts_with("message") => {
+                "Check the message ID; read_messages or search return valid IDs".to_string()
+            }
+            (Some("gmail"), _) => "Use list_channels to see folders and labels".to_string(),
+            (Some("slack"), _) => {
+                "Use list_channels for channel IDs; the bot must be a member of private channels"
+                    .to_string()
+            }
+            (Some("discord"), _) => {
+                "Use discord_list_guilds and discord_list_guild_channels for valid IDs".to_string()
+            }
+            _ => "Use list_channels to find valid channel names/IDs".to_string(),
+        },
+        AgentError::InvalidInput { .. } => match ctx.argument.as_deref() {
+            Some(arg) => format!("Fix the '{arg}' argument and retry"),
+            None => return None,
+        },
+        AgentError::PlatformNotAvailable { .. } => match platform.and_then(credential_vars) {
+            Some(vars) => format!("Set {vars} and restart"),
+            None => "Platform not configured. Set required env vars and restart".to_string(),
+        },
+        AgentError::NotImplemented { .. } => "This feature is not yet available".to_string(),
+        AgentError::Internal { .. } => "Unexpected error".to_string(),
+        AgentError::PolicyDenied { .. } => {
+            "This call is blocked by the server's access policy; ask the operator to allow it"
+                .to_string()
+        }
+        AgentError::DryRun { .. } => "Repeat the call without dry_run to carry it out".to_string(),
+        AgentError::RateLimited { retry_after, .. } => {
+            if retry_after.is_some() {
+                "Wait out the retry-after period before repeating the call".to_string()
+            } else {
+                "Slow down and retry later".to_string()
+            }
+        }
+    };
+    Some(suggestion)
+}
+
 impl ErrorResponse {
     pub fn to_compact(&self) -> String {
         let mut parts = vec![format!("[{}] {}", self.code, self.message)];
-        if let Some(s) = self.suggestion {
+        let origin: Vec<&str> = [self.platform.as_deref(), self.operation.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !origin.is_empty() {
+            parts.push(format!("in: {}", origin.join(" ")));
+        }
+        if let Some(ref a) = self.argument {
+            parts.push(format!("argument: {a}"));
+        }
+        if let Some(ref c) = self.platform_code {
+            parts.push(format!("platform code: {c}"));
+        }
+        if let Some(ref s) = self.suggestion {
             parts.push(format!("Suggestion: {s}"));
         }
-        if self.retryable {
-            parts.push("(retryable)".to_string());
+        match self.retry_after_secs {
+            Some(secs) => parts.push(format!("(retryable after {secs}s)")),
+            None if self.retryable => parts.push("(retryable)".to_string()),
+            None => {}
         }
         parts.join(" | ")
     }
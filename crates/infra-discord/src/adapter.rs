@@ -1,26 +1,53 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use nexus_domain::*;
-use nexus_error::AgentError;
+use nexus_error::{AgentError, RateLimitScope};
 use reqwest::Client;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, warn};
 
 const BASE_URL: &str = "https://discord.com/api/v10";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
 
 pub struct DiscordConfig {
     pub bot_token: String,
 }
 
+#[derive(Clone)]
 pub struct DiscordAdapter {
     auth: String,
     client: Client,
+    events: EventHub,
+    events_started: Arc<AtomicBool>,
 }
 
 impl DiscordAdapter {
     pub fn new(config: DiscordConfig) -> Self {
         let auth = format!("Bot {}", config.bot_token);
         let client = Client::new();
-        Self { auth, client }
+        Self {
+            auth,
+            client,
+            events: EventHub::default(),
+            events_started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // A dry run looks the channel up too, so it fails where the real call
+    // would.
+    async fn dry_run_target(&self, channel: &str) -> Result<String, AgentError> {
+        if !dry_run::active() {
+            return Ok(format!("channel {channel}"));
+        }
+        let chat = self.resolve_channel(channel).await?;
+        Ok(match chat.names.first() {
+            Some(name) => format!("{name} (channel {})", chat.id),
+            None => format!("channel {}", chat.id),
+        })
     }
 
     fn validate_id(id: &str, label: &str) -> Result<(), AgentError> {
@@ -41,20 +68,17 @@ impl DiscordAdapter {
             .header("Authorization", &self.auth)
             .send()
             .await
-            .map_err(|e| AgentError::network(format!("discord request failed: {e}")))?;
-
-        let status = resp.status();
-        let body: serde_json::Value = resp
-            .json()
-            .await
-            .map_err(|e| AgentError::api(format!("discord response parse failed: {e}")))?;
+            .map_err(|e| {
+                AgentError::network(format!("discord request failed: {e}")).with_platform("discord")
+            })?;
 
-        if !status.is_success() {
-            let msg = body["message"].as_str().unwrap_or("unknown error");
-            return Err(parse_discord_error(status.as_u16(), msg));
+        if !resp.status().is_success() {
+            return Err(discord_error(resp, "GET", path).await);
         }
 
-        Ok(body)
+        resp.json()
+            .await
+            .map_err(|e| AgentError::api(format!("discord response parse failed: {e}")))
     }
 
     async fn api_post(
@@ -71,20 +95,17 @@ impl DiscordAdapter {
             .json(body)
             .send()
             .await
-            .map_err(|e| AgentError::network(format!("discord request failed: {e}")))?;
-
-        let status = resp.status();
-        let response_body: serde_json::Value = resp
-            .json()
-            .await
-            .map_err(|e| AgentError::api(format!("discord response parse failed: {e}")))?;
+            .map_err(|e| {
+                AgentError::network(format!("discord request failed: {e}")).with_platform("discord")
+            })?;
 
-        if !status.is_success() {
-            let msg = response_body["message"].as_str().unwrap_or("unknown error");
-            return Err(parse_discord_error(status.as_u16(), msg));
+        if !resp.status().is_success() {
+            return Err(discord_error(resp, "POST", path).await);
         }
 
-        Ok(response_body)
+        resp.json()
+            .await
+            .map_err(|e| AgentError::api(format!("discord response parse failed: {e}")))
     }
 
     async fn api_put_empty(&self, path: &str) -> Result<(), AgentError> {
@@ -97,16 +118,12 @@ impl DiscordAdapter {
             .header("Content-Length", "0")
             .send()
             .await
-            .map_err(|e| AgentError::network(format!("discord request failed: {e}")))?;
-
-        let status = resp.status();
-        if !status.is_success() {
-            let body: serde_json::Value = resp
-                .json()
-                .await
-                .unwrap_or(serde_json::json!({"message": "unknown error"}));
-            let msg = body["message"].as_str().unwrap_or("unknown error");
-            return Err(parse_discord_error(status.as_u16(), msg));
+            .map_err(|e| {
+                AgentError::network(format!("discord request failed: {e}")).with_platform("discord")
+            })?;
+
+        if !resp.status().is_success() {
+            return Err(discord_error(resp, "PUT", path).await);
         }
 
         Ok(())
@@ -121,16 +138,12 @@ impl DiscordAdapter {
             .header("Authorization", &self.auth)
             .send()
             .await
-            .map_err(|e| AgentError::network(format!("discord request failed: {e}")))?;
-
-        let status = resp.status();
-        if !status.is_success() {
-            let body: serde_json::Value = resp
-                .json()
-                .await
-                .unwrap_or(serde_json::json!({"message": "unknown error"}));
-            let msg = body["message"].as_str().unwrap_or("unknown error");
-            return Err(parse_discord_error(status.as_u16(), msg));
+            .map_err(|e| {
+                AgentError::network(format!("discord request failed: {e}")).with_platform("discord")
+            })?;
+
+        if !resp.status().is_success() {
+            return Err(discord_error(resp, "DELETE", path).await);
         }
 
         Ok(())
@@ -145,19 +158,7 @@ impl MessagingPort for DiscordAdapter {
 
     async fn get_profile(&self) -> Result<Profile, AgentError> {
         let user = self.api_get("/users/@me").await?;
-
-        Ok(Profile {
-            platform: Platform::Discord,
-            id: user["id"].as_str().unwrap_or("").to_string(),
-            name: user["global_name"]
-                .as_str()
-                .or_else(|| user["username"].as_str())
-                .unwrap_or("Unknown")
-                .to_string(),
-            username: user["username"].as_str().map(|s| s.to_string()),
-            email: user["email"].as_str().map(|s| s.to_string()),
-            phone: None,
-        })
+        Ok(parse_discord_user(&user))
     }
 
     async fn list_channels(&self, limit: usize) -> Result<Vec<Channel>, AgentError> {
@@ -198,32 +199,63 @@ impl MessagingPort for DiscordAdapter {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
         Self::validate_id(channel, "channel")?;
+        let anchor = match cursor.and_then(|c| c.strip_prefix("dc:after:")) {
+            Some(id) => Some(ReadAnchor::After(id.to_string())),
+            None => anchor.cloned(),
+        };
+
         let mut path = format!("/channels/{channel}/messages?limit={limit}");
-        if let Some(c) = cursor {
-            if let Some(before_id) = c.strip_prefix("dc:") {
-                path.push_str(&format!("&before={before_id}"));
+        match (&anchor, cursor.and_then(|c| c.strip_prefix("dc:"))) {
+            (Some(ReadAnchor::After(id)), _) => {
+                Self::validate_id(id, "message")?;
+                path.push_str(&format!("&after={id}"));
+            }
+            (Some(ReadAnchor::Around(id)), _) => {
+                Self::validate_id(id, "message")?;
+                path.push_str(&format!("&around={id}"));
+            }
+            (None, Some(before_id)) => path.push_str(&format!("&before={before_id}")),
+            (None, None) => {
+                if let Some(until) = range.until {
+                    path.push_str(&format!("&before={}", snowflake_at(until + 1)));
+                }
             }
         }
 
         let resp = self.api_get(&path).await?;
-        let messages: Vec<Message> = resp
+        let mut messages: Vec<Message> = resp
             .as_array()
             .map_or(&[] as &[Value], |v| v)
             .iter()
             .map(|m| parse_discord_message(m, channel))
             .collect();
+        messages.sort_by_key(|m| std::cmp::Reverse(m.id.parse::<u64>().unwrap_or(0)));
 
-        let has_more = messages.len() == limit;
+        let raw_count = messages.len();
         let next_cursor = messages
             .last()
             .map(|m| format!("dc:{}", m.id));
+        let newer_cursor = messages.first().map(|m| format!("dc:after:{}", m.id));
+        messages.retain(|m| range.contains(m.timestamp));
+
+        // `after` pages fill from the anchor forwards, so a full page means
+        // newer messages remain; older ones always exist behind the anchor.
+        let (has_more, prev_cursor) = match anchor {
+            Some(ReadAnchor::After(_)) => (raw_count > 0, newer_cursor.filter(|_| raw_count == limit)),
+            Some(ReadAnchor::Around(_)) => (raw_count == limit, newer_cursor),
+            None if cursor.is_some() => (raw_count == limit && messages.len() == raw_count, newer_cursor),
+            None => (raw_count == limit && messages.len() == raw_count, None),
+        };
 
         Ok(Paginated {
             items: messages,
             has_more,
             next_cursor,
+            prev_cursor,
         })
     }
 
@@ -243,38 +275,77 @@ impl MessagingPort for DiscordAdapter {
                 "message_id": msg_id,
             });
         }
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| {
+            let reply = reply_to.map(|r| format!(" in reply to message {r}")).unwrap_or_default();
+            format!("send to {target}{reply}: {text:?}")
+        })?;
 
         let resp = self
             .api_post(&format!("/channels/{channel}/messages"), &body)
             .await?;
 
-        Ok(parse_discord_message(&resp, channel))
+        let mut msg = parse_discord_message(&resp, channel);
+        msg.meta.delivery = Some(DeliveryState::Sent);
+        Ok(msg)
     }
 
     async fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         limit: usize,
         cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError> {
-        let guilds = self.api_get("/users/@me/guilds").await?;
-        let first_guild_id = guilds
-            .as_array()
-            .and_then(|a| a.first())
-            .and_then(|g| g["id"].as_str())
-            .ok_or_else(|| AgentError::not_found("no guilds found for search (searches first guild only)"))?;
+        // An in: channel pins the search to that channel's guild; otherwise
+        // only the first guild is searched.
+        let guild_id = match query.channel.as_deref() {
+            Some(channel_id) => {
+                let channel = self.api_get(&format!("/channels/{channel_id}")).await?;
+                channel["guild_id"]
+                    .as_str()
+                    .ok_or_else(|| {
+                        AgentError::invalid_input(format!(
+                            "channel {channel_id} is not in a guild; search only covers guild channels"
+                        ))
+                    })?
+                    .to_string()
+            }
+            None => {
+                let guilds = self.api_get("/users/@me/guilds").await?;
+                guilds
+                    .as_array()
+                    .and_then(|a| a.first())
+                    .and_then(|g| g["id"].as_str())
+                    .ok_or_else(|| AgentError::not_found("no guilds found for search (searches first guild only)"))?
+                    .to_string()
+            }
+        };
 
         let offset: usize = cursor
             .and_then(|c| c.strip_prefix("dc:"))
             .and_then(|s| s.parse().ok())
             .unwrap_or(0);
 
-        let path = format!(
-            "/guilds/{first_guild_id}/messages/search?content={}&limit={}&offset={}",
-            urlencoding(query),
-            limit,
-            offset,
-        );
+        let mut path = format!("/guilds/{guild_id}/messages/search?limit={limit}&offset={offset}");
+        let text = query.text();
+        if !text.is_empty() {
+            path.push_str(&format!("&content={}", urlencoding(&text)));
+        }
+        if let Some(ref author) = query.from {
+            path.push_str(&format!("&author_id={author}"));
+        }
+        if let Some(ref channel_id) = query.channel {
+            path.push_str(&format!("&channel_id={channel_id}"));
+        }
+        if query.has_attachment {
+            path.push_str("&has=file");
+        }
+        if let Some(since) = query.range.since {
+            path.push_str(&format!("&min_id={}", snowflake_at(since)));
+        }
+        if let Some(until) = query.range.until {
+            path.push_str(&format!("&max_id={}", snowflake_at(until + 1)));
+        }
 
         let resp = self.api_get(&path).await?;
         let messages: Vec<Message> = resp["messages"]
@@ -302,10 +373,160 @@ impl MessagingPort for DiscordAdapter {
             items: messages,
             has_more,
             next_cursor,
+            prev_cursor: (offset > 0).then(|| format!("dc:{}", offset.saturating_sub(limit))),
+        })
+    }
+
+    // Discord search filters by ID only, matches words rather than exact
+    // phrases, and has no read state.
+    fn unsupported_clauses(&self, query: &SearchQuery) -> Vec<SearchClause> {
+        let mut unsupported = Vec::new();
+        if !query.phrases.is_empty() {
+            unsupported.push(SearchClause::Phrase);
+        }
+        if query.from.as_deref().is_some_and(|f| !is_snowflake(f)) {
+            unsupported.push(SearchClause::From);
+        }
+        if query.channel.as_deref().is_some_and(|c| !is_snowflake(c)) {
+            unsupported.push(SearchClause::In);
+        }
+        if query.is_unread {
+            unsupported.push(SearchClause::IsUnread);
+        }
+        unsupported
+    }
+
+    // Discord exposes no delivery or read receipts for bots.
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        Self::validate_id(channel, "channel")?;
+        Self::validate_id(message_id, "message_id")?;
+        let resp = self
+            .api_get(&format!("/channels/{channel}/messages/{message_id}"))
+            .await?;
+        let msg = parse_discord_message(&resp, channel);
+        Ok(DeliveryStatus {
+            platform: Platform::Discord,
+            channel_id: channel.to_string(),
+            message_id: msg.id,
+            state: DeliveryState::Sent,
+            error: None,
+            updated_at: msg.meta.edit_date.or(Some(msg.timestamp)),
+        })
+    }
+
+    async fn resolve_channel(&self, channel: &str) -> Result<ChatRef, AgentError> {
+        Self::validate_id(channel, "channel")?;
+        let ch = self.api_get(&format!("/channels/{channel}")).await?;
+        let chat_ref = ChatRef::new(channel);
+        Ok(match ch["name"].as_str() {
+            Some(name) => chat_ref.named(format!("#{name}")).named(name),
+            None => chat_ref,
         })
     }
 }
 
+#[async_trait]
+impl EventPort for DiscordAdapter {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        let stream = self.events.subscribe();
+        if !self.events_started.swap(true, Ordering::SeqCst) {
+            tokio::spawn(self.clone().poll_events());
+        }
+        Ok(stream)
+    }
+}
+
+// Polls each guild's channel list and only fetches messages for channels
+// whose last_message_id moved. Edits, deletions and reactions need the
+// Gateway and are not reported.
+impl DiscordAdapter {
+    async fn poll_events(self) {
+        let mut seen: HashMap<String, (String, Option<String>)> = HashMap::new();
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if !self.events.has_subscribers() {
+                continue;
+            }
+            if let Err(e) = self.poll_once(&mut seen).await {
+                warn!(error = %e, "discord event poll failed");
+            }
+        }
+    }
+
+    // `seen` maps channel ID to (name, last message ID). Channels seen for the
+    // first time are recorded without replaying their history.
+    async fn poll_once(
+        &self,
+        seen: &mut HashMap<String, (String, Option<String>)>,
+    ) -> Result<(), AgentError> {
+        let guilds = self.api_get("/users/@me/guilds").await?;
+        let guild_ids: Vec<String> = guilds
+            .as_array()
+            .map_or(&[] as &[Value], |v| v)
+            .iter()
+            .filter_map(|g| g["id"].as_str().map(String::from))
+            .collect();
+
+        for guild_id in guild_ids {
+            let channels = self.api_get(&format!("/guilds/{guild_id}/channels")).await?;
+            for ch in channels.as_array().map_or(&[] as &[Value], |v| v) {
+                let Some(id) = ch["id"].as_str() else { continue };
+                let name = ch["name"].as_str().unwrap_or("unknown").to_string();
+                let last = ch["last_message_id"].as_str().map(String::from);
+
+                let Some((old_name, old_last)) = seen.get(id).cloned() else {
+                    seen.insert(id.to_string(), (name, last));
+                    continue;
+                };
+                if old_name != name {
+                    self.events.publish(Event::ChannelUpdated {
+                        platform: Platform::Discord,
+                        channel_id: id.to_string(),
+                        name: Some(name.clone()),
+                    });
+                }
+                let moved = last != old_last;
+                if let Some(after) = old_last.as_deref().filter(|_| moved) {
+                    let resp = self
+                        .api_get(&format!("/channels/{id}/messages?after={after}&limit=100"))
+                        .await?;
+                    let mut messages: Vec<Message> = resp
+                        .as_array()
+                        .map_or(&[] as &[Value], |v| v)
+                        .iter()
+                        .map(|m| parse_discord_message(m, id))
+                        .collect();
+                    messages.sort_by_key(|m| m.id.parse::<u64>().unwrap_or(0));
+                    for message in messages {
+                        self.events.publish(Event::MessageCreated {
+                            message: Box::new(message),
+                        });
+                    }
+                }
+                seen.insert(id.to_string(), (name, last));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserPort for DiscordAdapter {
+    async fn get_user(&self, user_id: &str) -> Result<Profile, AgentError> {
+        if !is_snowflake(user_id) {
+            return Err(AgentError::invalid_input(format!("not a discord user id: {user_id}"))
+                .with_argument("user_id"));
+        }
+        let user = self.api_get(&format!("/users/{user_id}")).await?;
+        Ok(parse_discord_user(&user))
+    }
+}
+
 #[async_trait]
 impl DiscordExt for DiscordAdapter {
     async fn list_guilds(&self) -> Result<Vec<Channel>, AgentError> {
@@ -358,6 +579,11 @@ impl DiscordExt for DiscordAdapter {
         if let Some(mid) = msg_id {
             Self::validate_id(mid, "message_id")?;
         }
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| match msg_id {
+            Some(mid) => format!("start thread {name:?} from message {mid} in {target}"),
+            None => format!("create thread {name:?} in {target}"),
+        })?;
         let resp = if let Some(mid) = msg_id {
             self.api_post(
                 &format!("/channels/{channel}/messages/{mid}/threads"),
@@ -387,6 +613,8 @@ impl DiscordExt for DiscordAdapter {
     ) -> Result<(), AgentError> {
         Self::validate_id(channel, "channel")?;
         Self::validate_id(msg_id, "message_id")?;
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("react {emoji} to message {msg_id} in {target}"))?;
         let encoded = urlencoding(emoji);
         self.api_put_empty(&format!(
             "/channels/{channel}/messages/{msg_id}/reactions/{encoded}/@me"
@@ -402,6 +630,10 @@ impl DiscordExt for DiscordAdapter {
     ) -> Result<(), AgentError> {
         Self::validate_id(channel, "channel")?;
         Self::validate_id(msg_id, "message_id")?;
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| {
+            format!("remove reaction {emoji} from message {msg_id} in {target}")
+        })?;
         let encoded = urlencoding(emoji);
         self.api_delete(&format!(
             "/channels/{channel}/messages/{msg_id}/reactions/{encoded}/@me"
@@ -412,6 +644,8 @@ impl DiscordExt for DiscordAdapter {
     async fn pin_message(&self, channel: &str, msg_id: &str) -> Result<(), AgentError> {
         Self::validate_id(channel, "channel")?;
         Self::validate_id(msg_id, "message_id")?;
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("pin message {msg_id} in {target}"))?;
         self.api_put_empty(&format!("/channels/{channel}/pins/{msg_id}"))
             .await
     }
@@ -461,11 +695,7 @@ fn parse_discord_message(m: &serde_json::Value, channel: &str) -> Message {
         .map(|dt| dt.timestamp())
         .unwrap_or(0);
 
-    let sender = m["author"]["global_name"]
-        .as_str()
-        .or_else(|| m["author"]["username"].as_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let author = parse_discord_user(&m["author"]);
 
     let has_attachment = m["attachments"]
         .as_array()
@@ -484,7 +714,8 @@ fn parse_discord_message(m: &serde_json::Value, channel: &str) -> Message {
         id: m["id"].as_str().unwrap_or("").to_string(),
         platform: Platform::Discord,
         channel_id: channel.to_string(),
-        sender,
+        sender_id: author.id,
+        sender: author.name,
         text: m["content"].as_str().unwrap_or("").to_string(),
         timestamp,
         has_attachment,
@@ -493,11 +724,140 @@ fn parse_discord_message(m: &serde_json::Value, channel: &str) -> Message {
     }
 }
 
-fn parse_discord_error(status: u16, msg: &str) -> AgentError {
-    if status == 401 || status == 403 {
-        return AgentError::auth(format!("discord auth failed ({status}): {msg}"));
+fn parse_discord_user(user: &serde_json::Value) -> Profile {
+    Profile {
+        platform: Platform::Discord,
+        id: user["id"].as_str().unwrap_or("").to_string(),
+        name: user["global_name"]
+            .as_str()
+            .or_else(|| user["username"].as_str())
+            .unwrap_or("Unknown")
+            .to_string(),
+        username: user["username"].as_str().map(|s| s.to_string()),
+        email: user["email"].as_str().map(|s| s.to_string()),
+        phone: None,
+    }
+}
+
+const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+
+fn is_snowflake(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn snowflake_at(unix: i64) -> u64 {
+    let ms = unix.saturating_mul(1000).saturating_sub(DISCORD_EPOCH_MS).max(0);
+    (ms as u64) << 22
+}
+
+async fn discord_error(resp: reqwest::Response, verb: &str, path: &str) -> AgentError {
+    let status = resp.status().as_u16();
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let reset_after = header("X-RateLimit-Reset-After").or_else(|| header("Retry-After"));
+    let scope = header("X-RateLimit-Scope");
+    let global = header("X-RateLimit-Global").is_some_and(|g| g == "true");
+
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .unwrap_or(serde_json::json!({"message": "unknown error"}));
+    let msg = body["message"].as_str().unwrap_or("unknown error");
+    let json_code = body["code"].as_i64();
+
+    let guild = path_id(path, "guilds").map(|id| format!("guild {id}"));
+    let channel = path_id(path, "channels").map(|id| format!("channel {id}"));
+    let message = path_id(path, "messages").map(|id| format!("message {id}"));
+    // The most specific resource in the route is the likeliest culprit.
+    let resource = message.clone().or(channel.clone()).or(guild.clone());
+
+    let err = match (status, json_code) {
+        (429, _) => {
+            let secs = body["retry_after"]
+                .as_f64()
+                .or_else(|| reset_after.and_then(|v| v.parse().ok()))
+                .filter(|s| s.is_finite() && *s >= 0.0);
+            let global = global || body["global"].as_bool() == Some(true);
+            let scope = if global || scope.as_deref() == Some("global") {
+                RateLimitScope::Global
+            } else if scope.as_deref() == Some("shared") {
+                RateLimitScope::Resource
+            } else {
+                RateLimitScope::Method
+            };
+            AgentError::rate_limited(
+                format!("discord: {msg}"),
+                secs.map(Duration::from_secs_f64),
+                scope,
+            )
+        }
+        (401, _) => AgentError::auth(format!("discord auth failed ({status}): {msg}")),
+        (_, Some(10004)) => {
+            let guild = guild.unwrap_or_else(|| "guild".to_string());
+            AgentError::not_found(format!("discord bot is not in {guild}"))
+                .with_suggestion(format!(
+                    "Invite the bot to {guild} with an OAuth2 URL, or pick a guild from discord_list_guilds"
+                ))
+                .with_argument(guild)
+        }
+        (_, Some(10003)) => with_resource(
+            AgentError::not_found(format!("discord: {msg}")),
+            channel,
+        ),
+        (_, Some(10008)) => with_resource(
+            AgentError::not_found(format!("discord: {msg}")),
+            message,
+        ),
+        (_, Some(50001)) => {
+            let target = resource.clone().unwrap_or_else(|| "this resource".to_string());
+            with_resource(
+                AgentError::auth(format!("discord bot cannot access {target}"))
+                    .with_suggestion(format!(
+                        "Make sure the bot is in the guild and has View Channel and Read Message History on {target}"
+                    )),
+                resource,
+            )
+        }
+        (_, Some(50013)) => {
+            let target = resource.clone().unwrap_or_else(|| "this resource".to_string());
+            with_resource(
+                AgentError::auth(format!("discord bot lacks permissions for {verb} on {target}"))
+                    .with_suggestion(format!(
+                        "Grant the bot's role the permission this action needs (e.g. Send Messages, Manage Messages, Add Reactions) in {target}"
+                    )),
+                resource,
+            )
+        }
+        (403, _) => with_resource(
+            AgentError::auth(format!("discord auth failed ({status}): {msg}")),
+            resource,
+        ),
+        (404, _) => with_resource(AgentError::not_found(format!("discord: {msg}")), resource),
+        _ => AgentError::api(format!("discord api error ({status}): {msg}")),
+    };
+    err.with_code(json_code.map_or_else(|| status.to_string(), |c| c.to_string()))
+        .with_platform("discord")
+        .with_operation(format!("{verb} {path}"))
+}
+
+fn with_resource(err: AgentError, resource: Option<String>) -> AgentError {
+    match resource {
+        Some(r) => err.with_argument(r),
+        None => err,
     }
-    AgentError::api(format!("discord api error ({status}): {msg}"))
+}
+
+// The ID following a collection name in a route, e.g. "guilds" in
+// /guilds/123/channels.
+fn path_id<'a>(path: &'a str, collection: &str) -> Option<&'a str> {
+    let path = path.split('?').next().unwrap_or(path);
+    let mut segments = path.split('/');
+    segments.find(|s| *s == collection)?;
+    segments.next().filter(|id| is_snowflake(id))
 }
 
 fn urlencoding(s: &str) -> String {
@@ -1,5 +1,7 @@
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use imap_proto::types::Address as ImapAddress;
@@ -20,6 +22,8 @@ pub struct GmailConfig {
 pub struct GmailAdapter {
     config: Arc<GmailConfig>,
     session: Arc<std::sync::Mutex<Option<ImapSession>>>,
+    events: EventHub,
+    events_started: Arc<AtomicBool>,
 }
 
 impl GmailAdapter {
@@ -27,6 +31,8 @@ impl GmailAdapter {
         Self {
             config: Arc::new(config),
             session: Arc::new(std::sync::Mutex::new(None)),
+            events: EventHub::default(),
+            events_started: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -54,7 +60,9 @@ impl GmailAdapter {
             result
         })
         .await
-        .map_err(|e| AgentError::internal(format!("spawn: {e}")))?
+        .map_err(|e| AgentError::internal(format!("spawn: {e}")))
+        .and_then(|result| result)
+        .map_err(|e| e.with_platform("gmail"))
     }
 }
 
@@ -98,12 +106,7 @@ fn decode_mime_str(raw: &[u8]) -> String {
     }
 }
 
-fn format_imap_addr(addr: &ImapAddress) -> String {
-    let name = addr
-        .name
-        .as_ref()
-        .map(|n| decode_mime_str(n))
-        .unwrap_or_default();
+fn imap_addr_email(addr: &ImapAddress) -> String {
     let mailbox = addr
         .mailbox
         .as_ref()
@@ -114,18 +117,26 @@ fn format_imap_addr(addr: &ImapAddress) -> String {
         .as_ref()
         .and_then(|h| std::str::from_utf8(h).ok())
         .unwrap_or("");
+    match (mailbox, host) {
+        (m, "") => m.to_string(),
+        (m, h) => format!("{m}@{h}"),
+    }
+}
 
-    let email = if mailbox.is_empty() && host.is_empty() {
+fn format_imap_addr(addr: &ImapAddress) -> String {
+    let name = addr
+        .name
+        .as_ref()
+        .map(|n| decode_mime_str(n))
+        .unwrap_or_default();
+    let email = imap_addr_email(addr);
+    if email.is_empty() {
         return if name.is_empty() {
             "unknown".to_string()
         } else {
             name
         };
-    } else if host.is_empty() {
-        mailbox.to_string()
-    } else {
-        format!("{mailbox}@{host}")
-    };
+    }
 
     if name.is_empty() {
         email
@@ -258,10 +269,11 @@ fn fetch_to_message(fetch: &imap::types::Fetch, folder: &str) -> Option<Message>
         message_id
     };
 
-    let sender = addrs_to_vec(&envelope.from)
-        .into_iter()
-        .next()
+    let from = envelope.from.as_ref().and_then(|list| list.first());
+    let sender = from
+        .map(format_imap_addr)
         .unwrap_or_else(|| "unknown".to_string());
+    let sender_id = from.map(imap_addr_email).unwrap_or_default().to_lowercase();
 
     let subject = envelope
         .subject
@@ -297,6 +309,7 @@ fn fetch_to_message(fetch: &imap::types::Fetch, folder: &str) -> Option<Message>
         id,
         platform: Platform::Gmail,
         channel_id: folder.to_string(),
+        sender_id,
         sender,
         text,
         timestamp,
@@ -369,6 +382,51 @@ fn guess_content_type(path: &Path) -> ContentType {
     mime.parse().unwrap_or(ContentType::TEXT_PLAIN)
 }
 
+// 534/535 mean Gmail rejected the credentials; other permanent replies
+// (e.g. 550 quota or policy) will fail again if retried.
+fn smtp_error(op: &str, e: lettre::transport::smtp::Error) -> AgentError {
+    let code = e.status().map(|c| c.to_string());
+    let err = match code.as_deref() {
+        Some("534" | "535") => AgentError::auth(format!("{op}: {e}")),
+        _ if e.is_permanent() => AgentError::api(format!("{op}: {e}")),
+        _ => AgentError::network(format!("{op}: {e}")),
+    };
+    let err = err.with_platform("gmail").with_operation(op);
+    match code {
+        Some(code) => err.with_code(code),
+        None => err,
+    }
+}
+
+// In a dry run, stops a call once it has found the messages it would act on.
+fn dry_run_stop(
+    plans: &Option<dry_run::Plans>,
+    uids: &[u32],
+    action: impl FnOnce() -> String,
+) -> Result<(), AgentError> {
+    match plans {
+        Some(plans) => {
+            let found = match uids.len() {
+                1 => "1 matching message".to_string(),
+                n => format!("{n} matching messages"),
+            };
+            Err(plans.intercept(format!("{} ({found})", action())))
+        }
+        None => Ok(()),
+    }
+}
+
+fn describe_email(to: &[String], cc: &[String], bcc: &[String], subject: &str) -> String {
+    let mut out = format!("{subject:?} to {}", to.join(", "));
+    if !cc.is_empty() {
+        out.push_str(&format!(", cc {}", cc.join(", ")));
+    }
+    if !bcc.is_empty() {
+        out.push_str(&format!(", bcc {}", bcc.join(", ")));
+    }
+    out
+}
+
 fn imap_find_message(
     session: &mut ImapSession,
     message_id: &str,
@@ -381,12 +439,196 @@ fn imap_find_message(
     if results.is_empty() {
         return Err(AgentError::not_found(format!(
             "message not found: {message_id}"
-        )));
+        ))
+        .with_argument("message_id"));
     }
 
     Ok(results.into_iter().collect())
 }
 
+// IMAP SINCE/BEFORE compare whole days in the server's timezone, so the
+// criteria are widened by a day and results trimmed to the exact range.
+fn imap_date_criteria(range: &TimeRange) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(since) = range.since {
+        parts.push(format!("SINCE {}", imap_date(since - 86_400)));
+    }
+    if let Some(until) = range.until {
+        parts.push(format!("BEFORE {}", imap_date(until + 86_400)));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+fn imap_search_criteria(query: &SearchQuery) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace(['"', '\\'], ""));
+    let mut parts: Vec<String> = query
+        .terms
+        .iter()
+        .chain(query.phrases.iter())
+        .map(|t| format!("TEXT {}", quote(t)))
+        .collect();
+    if let Some(ref from) = query.from {
+        parts.push(format!("FROM {}", quote(from)));
+    }
+    if query.has_attachment {
+        parts.push("X-GM-RAW \"has:attachment\"".to_string());
+    }
+    if query.is_unread {
+        parts.push("UNSEEN".to_string());
+    }
+    if let Some(dates) = imap_date_criteria(&query.range) {
+        parts.push(dates);
+    }
+    if parts.is_empty() {
+        "ALL".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+fn search_folder(name: &str) -> String {
+    match name.to_lowercase().as_str() {
+        "inbox" => "INBOX".to_string(),
+        "all" | "all mail" | "anywhere" => "[Gmail]/All Mail".to_string(),
+        "sent" | "sent mail" => "[Gmail]/Sent Mail".to_string(),
+        "drafts" => "[Gmail]/Drafts".to_string(),
+        "spam" => "[Gmail]/Spam".to_string(),
+        "trash" => "[Gmail]/Trash".to_string(),
+        "starred" => "[Gmail]/Starred".to_string(),
+        "important" => "[Gmail]/Important".to_string(),
+        _ => name.to_string(),
+    }
+}
+
+fn imap_date(unix: i64) -> String {
+    chrono::DateTime::from_timestamp(unix, 0)
+        .map(|dt| dt.format("%d-%b-%Y").to_string())
+        .unwrap_or_else(|| "01-Jan-1970".to_string())
+}
+
+fn read_date_range(
+    session: &mut ImapSession,
+    folder: &str,
+    criteria: &str,
+    range: TimeRange,
+    limit: usize,
+    cursor_seq: Option<u32>,
+) -> Result<Paginated<Message>, AgentError> {
+    let mut seqs: Vec<u32> = session
+        .search(criteria)
+        .map_err(|e| AgentError::network(format!("IMAP SEARCH: {e}")))?
+        .into_iter()
+        .filter(|&seq| cursor_seq.map_or(true, |c| seq < c))
+        .collect();
+    seqs.sort_unstable();
+
+    let page_start = seqs.len().saturating_sub(limit);
+    let page = &seqs[page_start..];
+    let Some(&oldest) = page.first() else {
+        return Ok(Paginated {
+            items: vec![],
+            has_more: false,
+            next_cursor: None,
+            prev_cursor: None,
+        });
+    };
+
+    let fetches = session
+        .fetch(uid_str(page), "(UID ENVELOPE BODY[])")
+        .map_err(|e| AgentError::network(format!("IMAP FETCH: {e}")))?;
+
+    let mut messages: Vec<Message> = fetches
+        .iter()
+        .filter_map(|f| fetch_to_message(f, folder))
+        .filter(|m| range.contains(m.timestamp))
+        .collect();
+    messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+
+    let has_more = page_start > 0;
+    Ok(Paginated {
+        items: messages,
+        has_more,
+        next_cursor: if has_more {
+            Some(format!("gm:{oldest}"))
+        } else {
+            None
+        },
+        prev_cursor: cursor_seq.and_then(|_| page.last()).map(|newest| format!("gm:after:{newest}")),
+    })
+}
+
+enum SeqAnchor {
+    After(u32),
+    Around(u32),
+}
+
+fn imap_anchor_seq(session: &mut ImapSession, message_id: &str) -> Result<u32, AgentError> {
+    if let Some(uid) = message_id.strip_prefix("uid:") {
+        let uid: u32 = uid
+            .parse()
+            .map_err(|_| AgentError::invalid_input(format!("invalid message id '{message_id}'")))?;
+        return session
+            .search(format!("UID {uid}"))
+            .map_err(|e| AgentError::network(format!("IMAP SEARCH: {e}")))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AgentError::not_found(format!("message not found: {message_id}")));
+    }
+    imap_find_message(session, message_id)?
+        .into_iter()
+        .max()
+        .ok_or_else(|| AgentError::not_found(format!("message not found: {message_id}")))
+}
+
+fn read_seq_window(
+    session: &mut ImapSession,
+    folder: &str,
+    total: u32,
+    anchor: &SeqAnchor,
+    range: TimeRange,
+    limit: usize,
+) -> Result<Paginated<Message>, AgentError> {
+    let limit = limit as u32;
+    let (start, end) = match *anchor {
+        SeqAnchor::After(seq) => (seq.saturating_add(1), seq.saturating_add(limit).min(total)),
+        SeqAnchor::Around(seq) => {
+            let end = seq.saturating_add(limit / 2).min(total);
+            (end.saturating_sub(limit).saturating_add(1).max(1), end)
+        }
+    };
+    if start > end {
+        return Ok(Paginated {
+            items: vec![],
+            has_more: start > 1,
+            next_cursor: (start > 1).then(|| format!("gm:{start}")),
+            prev_cursor: None,
+        });
+    }
+
+    let fetches = session
+        .fetch(format!("{start}:{end}"), "(UID ENVELOPE BODY[])")
+        .map_err(|e| AgentError::network(format!("IMAP FETCH: {e}")))?;
+
+    let mut messages: Vec<Message> = fetches
+        .iter()
+        .filter_map(|f| fetch_to_message(f, folder))
+        .filter(|m| range.contains(m.timestamp))
+        .collect();
+    messages.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+
+    let has_more = start > 1;
+    Ok(Paginated {
+        items: messages,
+        has_more,
+        next_cursor: has_more.then(|| format!("gm:{start}")),
+        prev_cursor: (end < total).then(|| format!("gm:after:{end}")),
+    })
+}
+
 fn uid_str(uids: &[u32]) -> String {
     uids.iter()
         .map(|u| u.to_string())
@@ -452,17 +694,26 @@ impl MessagingPort for GmailAdapter {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
         let folder = channel.to_string();
         let limit = limit.min(200);
+        let after_seq = cursor
+            .and_then(|c| c.strip_prefix("gm:after:"))
+            .and_then(|s| s.parse::<u32>().ok());
         let cursor_seq = cursor
             .and_then(|c| c.strip_prefix("gm:"))
             .and_then(|s| s.parse::<u32>().ok());
+        let date_criteria = imap_date_criteria(&range);
+        let anchor = anchor.cloned();
 
         self.with_session(move |session| {
             let mailbox = session
                 .select(&folder)
-                .map_err(|e| AgentError::not_found(format!("folder '{folder}': {e}")))?;
+                .map_err(|e| {
+                    AgentError::not_found(format!("folder '{folder}': {e}")).with_argument("channel")
+                })?;
 
             let total = mailbox.exists;
             if total == 0 {
@@ -470,15 +721,33 @@ impl MessagingPort for GmailAdapter {
                     items: vec![],
                     has_more: false,
                     next_cursor: None,
+                    prev_cursor: None,
                 });
             }
 
+            if let Some(seq) = after_seq {
+                return read_seq_window(session, &folder, total, &SeqAnchor::After(seq), range, limit);
+            }
+            if let Some(ref anchor) = anchor {
+                let seq = imap_anchor_seq(session, anchor.message_id())?;
+                let window = match anchor {
+                    ReadAnchor::After(_) => SeqAnchor::After(seq),
+                    ReadAnchor::Around(_) => SeqAnchor::Around(seq),
+                };
+                return read_seq_window(session, &folder, total, &window, range, limit);
+            }
+
+            if let Some(criteria) = date_criteria {
+                return read_date_range(session, &folder, &criteria, range, limit, cursor_seq);
+            }
+
             let end = cursor_seq.map(|s| s.saturating_sub(1)).unwrap_or(total);
             if end == 0 {
                 return Ok(Paginated {
                     items: vec![],
                     has_more: false,
                     next_cursor: None,
+                    prev_cursor: None,
                 });
             }
 
@@ -503,11 +772,13 @@ impl MessagingPort for GmailAdapter {
             } else {
                 None
             };
+            let prev_cursor = (end < total).then(|| format!("gm:after:{end}"));
 
             Ok(Paginated {
                 items: messages,
                 has_more,
                 next_cursor,
+                prev_cursor,
             })
         })
         .await
@@ -534,11 +805,17 @@ impl MessagingPort for GmailAdapter {
 
     async fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         limit: usize,
         cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError> {
-        let query = query.to_string();
+        let criteria = imap_search_criteria(query);
+        let folder = query
+            .channel
+            .as_deref()
+            .map(search_folder)
+            .unwrap_or_else(|| "INBOX".to_string());
+        let range = query.range;
         let limit = limit.min(100);
         let cursor_offset = cursor
             .and_then(|c| c.strip_prefix("gm:"))
@@ -547,16 +824,12 @@ impl MessagingPort for GmailAdapter {
 
         self.with_session(move |session| {
             session
-                .select("INBOX")
-                .map_err(|e| AgentError::network(format!("IMAP SELECT INBOX: {e}")))?;
+                .select(&folder)
+                .map_err(|e| AgentError::network(format!("IMAP SELECT {folder}: {e}")))?;
 
-            let sanitized = query.replace(['"', '\\'], "");
+            debug!(criteria, folder, "IMAP search");
             let search_result = session
-                .search(&query)
-                .or_else(|_| {
-                    debug!("raw IMAP search failed, trying TEXT search");
-                    session.search(format!("TEXT \"{sanitized}\""))
-                })
+                .search(&criteria)
                 .map_err(|e| AgentError::network(format!("IMAP SEARCH: {e}")))?;
 
             if search_result.is_empty() {
@@ -564,6 +837,7 @@ impl MessagingPort for GmailAdapter {
                     items: vec![],
                     has_more: false,
                     next_cursor: None,
+                    prev_cursor: None,
                 });
             }
 
@@ -582,7 +856,8 @@ impl MessagingPort for GmailAdapter {
 
             let mut messages: Vec<Message> = fetches
                 .iter()
-                .filter_map(|f| fetch_to_message(f, "INBOX"))
+                .filter_map(|f| fetch_to_message(f, &folder))
+                .filter(|m| range.contains(m.timestamp))
                 .collect();
 
             messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
@@ -595,15 +870,144 @@ impl MessagingPort for GmailAdapter {
             } else {
                 None
             };
+            let prev_cursor =
+                (cursor_offset > 0).then(|| format!("gm:{}", cursor_offset.saturating_sub(limit)));
 
             Ok(Paginated {
                 items: messages,
                 has_more,
                 next_cursor,
+                prev_cursor,
             })
         })
         .await
     }
+
+    fn unsupported_clauses(&self, _query: &SearchQuery) -> Vec<SearchClause> {
+        Vec::new()
+    }
+
+    // SMTP only reports acceptance; a copy in Sent Mail is the best evidence
+    // available without parsing bounces.
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        let channel = channel.to_string();
+        let message_id = message_id.to_string();
+
+        self.with_session(move |session| {
+            session
+                .select("[Gmail]/Sent Mail")
+                .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
+            imap_find_message(session, &message_id)?;
+
+            Ok(DeliveryStatus {
+                platform: Platform::Gmail,
+                channel_id: channel,
+                message_id,
+                state: DeliveryState::Sent,
+                error: None,
+                updated_at: None,
+            })
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl EventPort for GmailAdapter {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        let stream = self.events.subscribe();
+        if !self.events_started.swap(true, Ordering::SeqCst) {
+            let config = self.config.clone();
+            let hub = self.events.clone();
+            // IDLE blocks for minutes at a time, so it gets its own thread
+            // instead of tying up the blocking pool.
+            if let Err(e) = std::thread::Builder::new()
+                .name("gmail-idle".into())
+                .spawn(move || idle_loop(&config, &hub))
+            {
+                self.events_started.store(false, Ordering::SeqCst);
+                return Err(AgentError::internal(format!("spawn IDLE thread: {e}")));
+            }
+        }
+        Ok(stream)
+    }
+}
+
+fn idle_loop(config: &GmailConfig, hub: &EventHub) {
+    let mut backoff = Duration::from_secs(5);
+    loop {
+        if let Err(e) = idle_session(config, hub) {
+            warn!(error = %e, retry_in = ?backoff, "gmail IDLE session ended");
+        }
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_secs(300));
+    }
+}
+
+// Watches INBOX only. Expunges arrive without message IDs, so they surface
+// as a ChannelUpdated rather than MessageDeleted.
+fn idle_session(config: &GmailConfig, hub: &EventHub) -> Result<(), AgentError> {
+    let mut session = imap_connect(config)?;
+    let mut exists = session
+        .select("INBOX")
+        .map_err(|e| AgentError::network(format!("IMAP SELECT INBOX: {e}")))?
+        .exists;
+    let mut unseen = count_unseen(&mut session)?;
+    debug!(exists, unseen, "gmail IDLE watching INBOX");
+
+    loop {
+        session
+            .idle()
+            .map_err(|e| AgentError::network(format!("IMAP IDLE: {e}")))?
+            .wait_keepalive()
+            .map_err(|e| AgentError::network(format!("IMAP IDLE wait: {e}")))?;
+
+        let now_exists = session
+            .select("INBOX")
+            .map_err(|e| AgentError::network(format!("IMAP SELECT INBOX: {e}")))?
+            .exists;
+
+        if now_exists > exists {
+            // BODY.PEEK keeps new mail unread.
+            let fetches = session
+                .fetch(format!("{}:{now_exists}", exists + 1), "(UID ENVELOPE BODY.PEEK[])")
+                .map_err(|e| AgentError::network(format!("IMAP FETCH: {e}")))?;
+            for message in fetches.iter().filter_map(|f| fetch_to_message(f, "INBOX")) {
+                hub.publish(Event::MessageCreated {
+                    message: Box::new(message),
+                });
+            }
+        } else if now_exists < exists {
+            hub.publish(Event::ChannelUpdated {
+                platform: Platform::Gmail,
+                channel_id: "INBOX".to_string(),
+                name: None,
+            });
+        }
+        exists = now_exists;
+
+        let now_unseen = count_unseen(&mut session)?;
+        if now_unseen != unseen {
+            hub.publish(Event::ReadStateChanged {
+                platform: Platform::Gmail,
+                channel_id: "INBOX".to_string(),
+                last_read_id: None,
+                unread_count: Some(now_unseen as i32),
+            });
+        }
+        unseen = now_unseen;
+    }
+}
+
+fn count_unseen(session: &mut ImapSession) -> Result<usize, AgentError> {
+    session
+        .search("UNSEEN")
+        .map(|s| s.len())
+        .map_err(|e| AgentError::network(format!("IMAP SEARCH: {e}")))
 }
 
 #[async_trait]
@@ -702,6 +1106,17 @@ impl GmailExt for GmailAdapter {
             .unwrap_or_default()
             .to_string();
 
+        dry_run::intercept(|| {
+            let mut plan = format!("send email {}", describe_email(to, cc, bcc, subject));
+            if let Some(reply_id) = reply_to {
+                plan.push_str(&format!(" in reply to {reply_id}"));
+            }
+            if !attachments.is_empty() {
+                plan.push_str(&format!(" with attachments {}", attachments.join(", ")));
+            }
+            format!("{plan}: {body:?}")
+        })?;
+
         let creds = Credentials::new(
             self.config.email.clone(),
             self.config.app_password.clone(),
@@ -709,14 +1124,14 @@ impl GmailExt for GmailAdapter {
 
         let transport: AsyncSmtpTransport<Tokio1Executor> =
             AsyncSmtpTransport::<Tokio1Executor>::starttls_relay("smtp.gmail.com")
-                .map_err(|e| AgentError::network(format!("SMTP relay: {e}")))?
+                .map_err(|e| smtp_error("SMTP relay", e))?
                 .credentials(creds)
                 .build();
 
         transport
             .send(email)
             .await
-            .map_err(|e| AgentError::network(format!("SMTP send: {e}")))?;
+            .map_err(|e| smtp_error("SMTP send", e))?;
 
         info!(
             to = ?to,
@@ -729,6 +1144,7 @@ impl GmailExt for GmailAdapter {
             id: message_id,
             platform: Platform::Gmail,
             channel_id: "[Gmail]/Sent Mail".to_string(),
+            sender_id: self.config.email.to_lowercase(),
             sender: self.config.email.clone(),
             text: body.to_string(),
             timestamp: now_ts(),
@@ -746,6 +1162,7 @@ impl GmailExt for GmailAdapter {
                 } else {
                     Some(bcc.to_vec())
                 },
+                delivery: Some(DeliveryState::Sent),
                 ..Default::default()
             },
         })
@@ -753,6 +1170,7 @@ impl GmailExt for GmailAdapter {
 
     async fn archive(&self, thread_id: &str) -> Result<(), AgentError> {
         let thread_id = thread_id.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -760,6 +1178,9 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &thread_id)?;
+            dry_run_stop(&plans, &uids, || {
+                format!("archive {thread_id} by moving it from INBOX to [Gmail]/All Mail")
+            })?;
             let ids = uid_str(&uids);
 
             session
@@ -787,6 +1208,7 @@ impl GmailExt for GmailAdapter {
     async fn add_label(&self, thread_id: &str, label: &str) -> Result<(), AgentError> {
         let thread_id = thread_id.to_string();
         let label = label.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -794,6 +1216,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &thread_id)?;
+            dry_run_stop(&plans, &uids, || format!("label {thread_id} with {label:?}"))?;
             let ids = uid_str(&uids);
 
             session
@@ -808,6 +1231,7 @@ impl GmailExt for GmailAdapter {
 
     async fn mark_read(&self, message_id: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -815,6 +1239,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("mark {message_id} as read"))?;
             let ids = uid_str(&uids);
 
             session
@@ -829,6 +1254,7 @@ impl GmailExt for GmailAdapter {
 
     async fn mark_unread(&self, message_id: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -836,6 +1262,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("mark {message_id} as unread"))?;
             let ids = uid_str(&uids);
 
             session
@@ -850,6 +1277,7 @@ impl GmailExt for GmailAdapter {
 
     async fn star(&self, message_id: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -857,6 +1285,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("star {message_id}"))?;
             let ids = uid_str(&uids);
 
             session
@@ -871,6 +1300,7 @@ impl GmailExt for GmailAdapter {
 
     async fn unstar(&self, message_id: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -878,6 +1308,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("unstar {message_id}"))?;
             let ids = uid_str(&uids);
 
             session
@@ -893,6 +1324,7 @@ impl GmailExt for GmailAdapter {
     async fn move_to(&self, message_id: &str, folder: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
         let folder = folder.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
@@ -900,6 +1332,7 @@ impl GmailExt for GmailAdapter {
                 .map_err(|e| AgentError::network(format!("IMAP SELECT: {e}")))?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("move {message_id} from INBOX to {folder:?}"))?;
             let ids = uid_str(&uids);
 
             session
@@ -927,13 +1360,17 @@ impl GmailExt for GmailAdapter {
     async fn remove_label(&self, message_id: &str, label: &str) -> Result<(), AgentError> {
         let message_id = message_id.to_string();
         let label = label.to_string();
+        let plans = dry_run::plans();
 
         self.with_session(move |session| {
             session
                 .select(&label)
-                .map_err(|e| AgentError::not_found(format!("folder '{label}': {e}")))?;
+                .map_err(|e| {
+                    AgentError::not_found(format!("folder '{label}': {e}")).with_argument("label")
+                })?;
 
             let uids = imap_find_message(session, &message_id)?;
+            dry_run_stop(&plans, &uids, || format!("remove label {label:?} from {message_id}"))?;
             let ids = uid_str(&uids);
 
             session
@@ -1058,6 +1495,10 @@ impl GmailExt for GmailAdapter {
             .unwrap_or_default()
             .to_string();
 
+        dry_run::intercept(|| {
+            format!("save a draft of {}: {body:?}", describe_email(to, &[], &[], subject))
+        })?;
+
         let rfc_bytes = email.formatted();
         let subject = subject.to_string();
         let body_text = body.to_string();
@@ -1076,6 +1517,7 @@ impl GmailExt for GmailAdapter {
                 id: mid,
                 platform: Platform::Gmail,
                 channel_id: "[Gmail]/Drafts".to_string(),
+                sender_id: sender.to_lowercase(),
                 sender,
                 text: body_text,
                 timestamp: now_ts(),
@@ -1,53 +1,184 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use nexus_domain::*;
-use nexus_error::AgentError;
+use nexus_error::{AgentError, RateLimitScope};
 use reqwest::Client;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, warn};
 
 const BASE_URL: &str = "https://slack.com/api";
+// conversations.history is Tier 3 (~50 calls/min); 20 channels every 30s
+// stays under that. Polls take turns through the channels when there are
+// more.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const POLL_CHANNELS: usize = 20;
+// Reading after an anchor pages down to it from the newest message; past
+// this many pages the anchor is too far back to reach.
+const AFTER_PAGE_SIZE: usize = 200;
+const AFTER_PAGES: usize = 25;
 
+#[derive(Clone)]
 pub struct SlackConfig {
     pub bot_token: String,
 }
 
+#[derive(Clone)]
 pub struct SlackAdapter {
     config: SlackConfig,
     client: Client,
+    events: EventHub,
+    events_started: Arc<AtomicBool>,
 }
 
 impl SlackAdapter {
     pub fn new(config: SlackConfig) -> Self {
         let client = Client::new();
-        Self { config, client }
+        Self {
+            config,
+            client,
+            events: EventHub::default(),
+            events_started: Arc::new(AtomicBool::new(false)),
+        }
     }
 
     async fn api_post(&self, method: &str, body: &Value) -> Result<Value, AgentError> {
         let url = format!("{BASE_URL}/{method}");
         debug!(url, "slack POST");
-        let resp = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.config.bot_token)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| AgentError::network(format!("slack request failed: {e}")))?;
-        parse_slack_response(resp).await
+        async {
+            let resp = self
+                .client
+                .post(&url)
+                .bearer_auth(&self.config.bot_token)
+                .json(body)
+                .send()
+                .await
+                .map_err(|e| AgentError::network(format!("slack request failed: {e}")))?;
+            parse_slack_response(resp).await
+        }
+        .await
+        .map_err(|e| e.with_platform("slack").with_operation(method))
     }
 
     async fn api_get(&self, method: &str, params: &[(&str, &str)]) -> Result<Value, AgentError> {
         let url = format!("{BASE_URL}/{method}");
         debug!(url, "slack GET");
-        let resp = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.config.bot_token)
-            .query(params)
-            .send()
-            .await
-            .map_err(|e| AgentError::network(format!("slack request failed: {e}")))?;
-        parse_slack_response(resp).await
+        async {
+            let resp = self
+                .client
+                .get(&url)
+                .bearer_auth(&self.config.bot_token)
+                .query(params)
+                .send()
+                .await
+                .map_err(|e| AgentError::network(format!("slack request failed: {e}")))?;
+            parse_slack_response(resp).await
+        }
+        .await
+        .map_err(|e| e.with_platform("slack").with_operation(method))
+    }
+}
+
+impl SlackAdapter {
+    // Inside a dry run the channel is resolved as the real call would, so an
+    // unknown one fails the same way, and the plan names it.
+    async fn dry_run_target(&self, channel: &str) -> Result<String, AgentError> {
+        if !dry_run::active() {
+            return Ok(channel.to_string());
+        }
+        let chat = self.resolve_channel(channel).await?;
+        Ok(match chat.names.first() {
+            Some(name) => format!("{name} ({})", chat.id),
+            None => chat.id,
+        })
+    }
+
+    // Messages come back newest first. `has_more` reports whether Slack has
+    // more messages inside the oldest/latest window.
+    async fn history(
+        &self,
+        channel: &str,
+        limit: usize,
+        oldest: Option<String>,
+        latest: Option<String>,
+        inclusive: bool,
+        cursor: Option<&str>,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let limit_s = limit.to_string();
+        let mut params: Vec<(&str, &str)> = vec![("channel", channel), ("limit", &limit_s)];
+        if let Some(c) = cursor {
+            params.push(("cursor", c));
+        }
+        if let Some(ref o) = oldest {
+            params.push(("oldest", o));
+        }
+        if let Some(ref l) = latest {
+            params.push(("latest", l));
+        }
+        if inclusive {
+            params.push(("inclusive", "true"));
+        }
+
+        let resp = self.api_get("conversations.history", &params).await?;
+
+        let mut messages: Vec<Message> = resp["messages"]
+            .as_array()
+            .map_or(&[] as &[Value], |v| v)
+            .iter()
+            .map(|m| parse_slack_message(m, channel))
+            .collect();
+        messages.sort_by(|a, b| slack_ts_cmp(&b.id, &a.id));
+
+        let has_more = resp["has_more"].as_bool().unwrap_or(false);
+        let next_cursor = resp["response_metadata"]["next_cursor"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("sl:{s}"));
+
+        Ok(Paginated {
+            items: messages,
+            has_more,
+            next_cursor,
+            prev_cursor: None,
+        })
+    }
+
+    // The `limit` messages just after `ts`, newest first, and whether newer
+    // ones follow them. History lists a window newest first, so this pages
+    // down to the anchor and keeps the oldest.
+    async fn history_after(
+        &self,
+        channel: &str,
+        limit: usize,
+        ts: &str,
+        latest: Option<String>,
+    ) -> Result<(Vec<Message>, bool), AgentError> {
+        let mut kept: Vec<Message> = Vec::new();
+        let mut newer = false;
+        let mut cursor: Option<String> = None;
+        for _ in 0..AFTER_PAGES {
+            let slack_cursor = cursor.as_deref().and_then(|c| c.strip_prefix("sl:"));
+            let page = self
+                .history(channel, AFTER_PAGE_SIZE, Some(ts.to_string()), latest.clone(), false, slack_cursor)
+                .await?;
+            kept.extend(page.items);
+            if kept.len() > limit {
+                kept.drain(..kept.len() - limit);
+                newer = true;
+            }
+            match page.next_cursor {
+                Some(c) if page.has_more => cursor = Some(c),
+                _ => return Ok((kept, newer)),
+            }
+        }
+        Err(AgentError::invalid_input(format!(
+            "more than {} messages follow {ts} in {channel}",
+            AFTER_PAGES * AFTER_PAGE_SIZE
+        ))
+        .with_suggestion("Read that stretch with since and until instead"))
     }
 }
 
@@ -98,35 +229,86 @@ impl MessagingPort for SlackAdapter {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
-        let limit_s = limit.to_string();
-        let mut params: Vec<(&str, &str)> = vec![("channel", channel), ("limit", &limit_s)];
-        let cursor_stripped;
-        if let Some(c) = cursor.and_then(|c| c.strip_prefix("sl:")) {
-            cursor_stripped = c.to_string();
-            params.push(("cursor", &cursor_stripped));
-        }
-
-        let resp = self.api_get("conversations.history", &params).await?;
-
-        let messages: Vec<Message> = resp["messages"]
-            .as_array()
-            .map_or(&[] as &[Value], |v| v)
-            .iter()
-            .map(|m| parse_slack_message(m, channel))
-            .collect();
+        let anchor = match cursor.and_then(|c| c.strip_prefix("sl:after:")) {
+            Some(ts) => Some(ReadAnchor::After(ts.to_string())),
+            None => anchor.cloned(),
+        };
+        let oldest = range.since.map(slack_ts);
+        let latest = range.until.map(|u| format!("{u}.999999"));
 
-        let has_more = resp["has_more"].as_bool().unwrap_or(false);
-        let next_cursor = resp["response_metadata"]["next_cursor"]
-            .as_str()
-            .filter(|s| !s.is_empty())
-            .map(|s| format!("sl:{s}"));
+        match anchor {
+            Some(ReadAnchor::After(ts)) => {
+                let (items, newer) = self.history_after(channel, limit, &ts, latest).await?;
+                let prev_cursor = if newer {
+                    items.first().map(|m| format!("sl:after:{}", m.id))
+                } else {
+                    None
+                };
+                let next_cursor = items.last().map(|m| format!("sl:before:{}", m.id));
+                return Ok(Paginated {
+                    has_more: next_cursor.is_some(),
+                    items,
+                    next_cursor,
+                    prev_cursor,
+                });
+            }
+            Some(ReadAnchor::Around(ts)) => {
+                let newer_limit = limit / 2;
+                let (mut items, newer) = if newer_limit > 0 {
+                    self.history_after(channel, newer_limit, &ts, latest).await?
+                } else {
+                    (Vec::new(), false)
+                };
+                let older = self
+                    .history(channel, limit - newer_limit, oldest, Some(ts), true, None)
+                    .await?;
+                let prev_cursor = if newer {
+                    items.first().map(|m| format!("sl:after:{}", m.id))
+                } else {
+                    None
+                };
+                let next_cursor = if older.has_more {
+                    older.items.last().map(|m| format!("sl:before:{}", m.id))
+                } else {
+                    None
+                };
+                items.extend(older.items);
+                return Ok(Paginated {
+                    items,
+                    has_more: next_cursor.is_some(),
+                    next_cursor,
+                    prev_cursor,
+                });
+            }
+            None => {}
+        }
 
-        Ok(Paginated {
-            items: messages,
-            has_more,
-            next_cursor,
-        })
+        let mut page = match cursor.and_then(|c| c.strip_prefix("sl:before:")) {
+            Some(ts) => {
+                let mut page = self
+                    .history(channel, limit, oldest, Some(ts.to_string()), false, None)
+                    .await?;
+                page.next_cursor = if page.has_more {
+                    page.items.last().map(|m| format!("sl:before:{}", m.id))
+                } else {
+                    None
+                };
+                page
+            }
+            None => {
+                let inclusive = oldest.is_some() || latest.is_some();
+                let slack_cursor = cursor.and_then(|c| c.strip_prefix("sl:"));
+                self.history(channel, limit, oldest, latest, inclusive, slack_cursor)
+                    .await?
+            }
+        };
+        if cursor.is_some() {
+            page.prev_cursor = page.items.first().map(|m| format!("sl:after:{}", m.id));
+        }
+        Ok(page)
     }
 
     async fn send_message(
@@ -143,14 +325,21 @@ impl MessagingPort for SlackAdapter {
         if let Some(thread_ts) = reply_to {
             body["thread_ts"] = Value::String(thread_ts.to_string());
         }
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| {
+            let thread = reply_to.map(|ts| format!(" in thread {ts}")).unwrap_or_default();
+            format!("post to {target}{thread}: {text:?}")
+        })?;
 
         let resp = self.api_post("chat.postMessage", &body).await?;
-        Ok(parse_slack_message(&resp["message"], channel))
+        let mut msg = parse_slack_message(&resp["message"], channel);
+        msg.meta.delivery = Some(DeliveryState::Sent);
+        Ok(msg)
     }
 
     async fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         limit: usize,
         cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError> {
@@ -160,14 +349,17 @@ impl MessagingPort for SlackAdapter {
             .and_then(|s| s.parse::<usize>().ok())
             .unwrap_or(1);
         let page_s = page.to_string();
+        let range = query.range;
+        let full_query = slack_query(query);
 
-        let resp = self
-            .api_get(
-                "search.messages",
-                &[("query", query), ("count", &limit_s), ("page", &page_s)],
-            )
-            .await?;
+        let mut params = vec![("query", full_query.as_str()), ("count", &limit_s), ("page", &page_s)];
+        // Slack ranks by score unless told otherwise.
+        if !query.by_relevance {
+            params.extend([("sort", "timestamp"), ("sort_dir", "desc")]);
+        }
+        let resp = self.api_get("search.messages", &params).await?;
 
+        let raw_count = resp["messages"]["matches"].as_array().map_or(0, |a| a.len());
         let messages: Vec<Message> = resp["messages"]["matches"]
             .as_array()
             .map_or(&[] as &[Value], |v| v)
@@ -176,10 +368,12 @@ impl MessagingPort for SlackAdapter {
                 let ch = m["channel"]["id"].as_str().unwrap_or("");
                 parse_slack_message(m, ch)
             })
+            .filter(|m| range.contains(m.timestamp))
+            .filter(|m| !query.has_attachment || m.has_attachment)
             .collect();
 
         let total = resp["messages"]["total"].as_u64().unwrap_or(0) as usize;
-        let seen = (page - 1) * limit + messages.len();
+        let seen = (page - 1) * limit + raw_count;
         let has_more = seen < total;
         let next_cursor = if has_more {
             Some(format!("sl:{}", page + 1))
@@ -191,13 +385,216 @@ impl MessagingPort for SlackAdapter {
             items: messages,
             has_more,
             next_cursor,
+            prev_cursor: (page > 1).then(|| format!("sl:{}", page - 1)),
         })
     }
+
+    fn unsupported_clauses(&self, query: &SearchQuery) -> Vec<SearchClause> {
+        if query.is_unread {
+            vec![SearchClause::IsUnread]
+        } else {
+            Vec::new()
+        }
+    }
+
+    // Slack has no delivery receipts: a message that exists was sent.
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        let ts = Some(message_id.to_string());
+        let page = self.history(channel, 1, ts.clone(), ts, true, None).await?;
+        let found = if page.items.iter().any(|m| m.id == message_id) {
+            true
+        } else {
+            // Thread replies only show up in conversations.replies.
+            let resp = self
+                .api_get(
+                    "conversations.replies",
+                    &[("channel", channel), ("ts", message_id), ("limit", "1")],
+                )
+                .await?;
+            resp["messages"]
+                .as_array()
+                .is_some_and(|a| a.iter().any(|m| m["ts"].as_str() == Some(message_id)))
+        };
+        if !found {
+            return Err(AgentError::not_found(format!(
+                "message {message_id} not found in {channel}"
+            )));
+        }
+        Ok(DeliveryStatus {
+            platform: Platform::Slack,
+            channel_id: channel.to_string(),
+            message_id: message_id.to_string(),
+            state: DeliveryState::Sent,
+            error: None,
+            updated_at: None,
+        })
+    }
+
+    // Channel IDs are looked up for their name; names, with or without the
+    // leading '#', are matched against the channel list.
+    async fn resolve_channel(&self, channel: &str) -> Result<ChatRef, AgentError> {
+        if is_slack_id(channel, &['C', 'G', 'D']) {
+            let resp = self
+                .api_get("conversations.info", &[("channel", channel)])
+                .await?;
+            let chat_ref = ChatRef::new(channel);
+            return Ok(match resp["channel"]["name"].as_str() {
+                Some(name) => chat_ref.named(format!("#{name}")).named(name),
+                None => chat_ref,
+            });
+        }
+        let name = channel.trim_start_matches('#');
+        let resp = self
+            .api_get(
+                "conversations.list",
+                &[
+                    ("types", "public_channel,private_channel"),
+                    ("limit", "1000"),
+                    ("exclude_archived", "true"),
+                ],
+            )
+            .await?;
+        resp["channels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|c| c["name"].as_str() == Some(name))
+            .and_then(|c| c["id"].as_str())
+            .map(|id| ChatRef::new(id).named(format!("#{name}")).named(name))
+            .ok_or_else(|| AgentError::not_found(format!("slack channel '{channel}' not found")))
+    }
+}
+
+#[async_trait]
+impl EventPort for SlackAdapter {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        let stream = self.events.subscribe();
+        if !self.events_started.swap(true, Ordering::SeqCst) {
+            tokio::spawn(self.clone().poll_events());
+        }
+        Ok(stream)
+    }
+}
+
+// Polling only sees new messages and channel renames; edits, deletions and
+// reactions on older messages need the Events API.
+impl SlackAdapter {
+    async fn poll_events(self) {
+        let start = slack_ts(now_secs());
+        let mut seen: HashMap<String, (String, String)> = HashMap::new();
+        let mut turn = 0;
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if !self.events.has_subscribers() {
+                continue;
+            }
+            if let Err(e) = self.poll_once(&start, &mut seen, &mut turn).await {
+                warn!(error = %e, "slack event poll failed");
+            }
+        }
+    }
+
+    // Every channel the bot is in, across all pages.
+    async fn member_channels(&self) -> Result<Vec<Channel>, AgentError> {
+        let mut channels = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut params = vec![
+                ("types", "public_channel,private_channel,im,mpim"),
+                ("limit", "200"),
+                ("exclude_archived", "true"),
+            ];
+            if !cursor.is_empty() {
+                params.push(("cursor", &cursor));
+            }
+            let resp = self.api_get("users.conversations", &params).await?;
+            channels.extend(
+                resp["channels"]
+                    .as_array()
+                    .map_or(&[] as &[Value], |v| v)
+                    .iter()
+                    .map(parse_slack_channel),
+            );
+            match resp["response_metadata"]["next_cursor"].as_str() {
+                Some(next) if !next.is_empty() && next != cursor => cursor = next.to_string(),
+                _ => return Ok(channels),
+            }
+        }
+    }
+
+    // `seen` maps channel ID to (name, newest ts delivered); `turn` is where
+    // in the channel list the next poll starts reading.
+    async fn poll_once(
+        &self,
+        start: &str,
+        seen: &mut HashMap<String, (String, String)>,
+        turn: &mut usize,
+    ) -> Result<(), AgentError> {
+        let channels = self.member_channels().await?;
+        for channel in &channels {
+            let (name, _) = seen
+                .entry(channel.id.clone())
+                .or_insert_with(|| (channel.name.clone(), start.to_string()));
+            if *name != channel.name {
+                *name = channel.name.clone();
+                self.events.publish(Event::ChannelUpdated {
+                    platform: Platform::Slack,
+                    channel_id: channel.id.clone(),
+                    name: Some(channel.name.clone()),
+                });
+            }
+        }
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let first = *turn % channels.len();
+        *turn = first + POLL_CHANNELS;
+        let batch = channels.iter().cycle().skip(first).take(POLL_CHANNELS.min(channels.len()));
+        for channel in batch {
+            let Some((_, newest)) = seen.get_mut(&channel.id) else {
+                continue;
+            };
+            // Everything since the last message delivered, however many
+            // pages, so a busy channel leaves no gap between polls.
+            let items = match self.history_after(&channel.id, usize::MAX, newest, None).await {
+                Ok((items, _)) => items,
+                Err(e @ AgentError::InvalidInput { .. }) => {
+                    warn!(channel = %channel.id, error = %e, "slack backlog too long; skipping to now");
+                    *newest = slack_ts(now_secs());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(latest) = items.first() {
+                *newest = latest.id.clone();
+            }
+            for message in items.into_iter().rev() {
+                self.events.publish(Event::MessageCreated {
+                    message: Box::new(message),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 #[async_trait]
 impl SlackExt for SlackAdapter {
     async fn set_status(&self, text: &str, emoji: &str) -> Result<(), AgentError> {
+        dry_run::intercept(|| format!("set the bot's status to {emoji} {text:?}"))?;
         self.api_post(
             "users.profile.set",
             &serde_json::json!({
@@ -216,6 +613,10 @@ impl SlackExt for SlackAdapter {
         name: &str,
         is_private: bool,
     ) -> Result<Channel, AgentError> {
+        dry_run::intercept(|| {
+            let kind = if is_private { "private" } else { "public" };
+            format!("create {kind} channel #{name}")
+        })?;
         let resp = self
             .api_post(
                 "conversations.create",
@@ -233,6 +634,8 @@ impl SlackExt for SlackAdapter {
         channel: &str,
         user_id: &str,
     ) -> Result<(), AgentError> {
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("invite {user_id} to {target}"))?;
         self.api_post(
             "conversations.invite",
             &serde_json::json!({
@@ -245,6 +648,8 @@ impl SlackExt for SlackAdapter {
     }
 
     async fn set_topic(&self, channel: &str, topic: &str) -> Result<(), AgentError> {
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("set the topic of {target} to {topic:?}"))?;
         self.api_post(
             "conversations.setTopic",
             &serde_json::json!({
@@ -262,6 +667,8 @@ impl SlackExt for SlackAdapter {
         msg_ts: &str,
         emoji: &str,
     ) -> Result<(), AgentError> {
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("react :{emoji}: to message {msg_ts} in {target}"))?;
         self.api_post(
             "reactions.add",
             &serde_json::json!({
@@ -280,6 +687,8 @@ impl SlackExt for SlackAdapter {
         msg_ts: &str,
         emoji: &str,
     ) -> Result<(), AgentError> {
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("remove reaction :{emoji}: from message {msg_ts} in {target}"))?;
         self.api_post(
             "reactions.remove",
             &serde_json::json!({
@@ -314,6 +723,18 @@ impl SlackExt for SlackAdapter {
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("file");
+        let mut targets = Vec::with_capacity(channels.len());
+        for channel in channels {
+            targets.push(self.dry_run_target(channel).await?);
+        }
+        dry_run::intercept(|| {
+            let title = title.map(|t| format!(" titled {t:?}")).unwrap_or_default();
+            format!(
+                "upload {filename} ({} bytes){title} to {}",
+                file_content.len(),
+                targets.join(", ")
+            )
+        })?;
 
         let file_part =
             reqwest::multipart::Part::bytes(file_content).file_name(filename.to_string());
@@ -336,7 +757,9 @@ impl SlackExt for SlackAdapter {
             .send()
             .await
             .map_err(|e| AgentError::network(format!("slack upload failed: {e}")))?;
-        let resp = parse_slack_response(resp).await?;
+        let resp = parse_slack_response(resp)
+            .await
+            .map_err(|e| e.with_platform("slack").with_operation("files.upload"))?;
 
         let file_id = resp["file"]["id"].as_str().unwrap_or("unknown");
         let permalink = resp["file"]["permalink"]
@@ -404,18 +827,127 @@ impl SlackExt for SlackAdapter {
     }
 }
 
+#[async_trait]
+impl UserPort for SlackAdapter {
+    async fn get_user(&self, user_id: &str) -> Result<Profile, AgentError> {
+        self.get_user_info(user_id).await
+    }
+}
+
+#[async_trait]
+impl SchedulePort for SlackAdapter {
+    async fn schedule_message(
+        &self,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        send_at: i64,
+    ) -> Result<ScheduledMessage, AgentError> {
+        let mut body = serde_json::json!({
+            "channel": channel,
+            "text": text,
+            "post_at": send_at,
+        });
+        if let Some(thread_ts) = reply_to {
+            body["thread_ts"] = Value::String(thread_ts.to_string());
+        }
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("schedule for {send_at} (unix time) in {target}: {text:?}"))?;
+
+        let resp = self.api_post("chat.scheduleMessage", &body).await?;
+        Ok(ScheduledMessage {
+            id: resp["scheduled_message_id"].as_str().unwrap_or_default().to_string(),
+            platform: Platform::Slack,
+            channel_id: resp["channel"].as_str().unwrap_or(channel).to_string(),
+            text: text.to_string(),
+            send_at: resp["post_at"].as_i64().unwrap_or(send_at),
+            state: ScheduleState::Scheduled,
+            reply_to: reply_to.map(str::to_string),
+            queued: false,
+            message_id: None,
+            error: None,
+        })
+    }
+
+    async fn list_scheduled(&self, channel: Option<&str>) -> Result<Vec<ScheduledMessage>, AgentError> {
+        let mut scheduled = Vec::new();
+        let mut cursor = String::new();
+        // Ten pages of 100 is more than any workspace schedules ahead.
+        for _ in 0..10 {
+            let mut params = vec![("limit", "100")];
+            if let Some(ch) = channel {
+                params.push(("channel", ch));
+            }
+            if !cursor.is_empty() {
+                params.push(("cursor", &cursor));
+            }
+            let resp = self.api_get("chat.scheduledMessages.list", &params).await?;
+            if let Some(items) = resp["scheduled_messages"].as_array() {
+                scheduled.extend(items.iter().map(|m| ScheduledMessage {
+                    id: m["id"].as_str().unwrap_or_default().to_string(),
+                    platform: Platform::Slack,
+                    channel_id: m["channel_id"].as_str().unwrap_or_default().to_string(),
+                    text: m["text"].as_str().unwrap_or_default().to_string(),
+                    send_at: m["post_at"].as_i64().unwrap_or(0),
+                    state: ScheduleState::Scheduled,
+                    reply_to: None,
+                    queued: false,
+                    message_id: None,
+                    error: None,
+                }));
+            }
+            cursor = resp["response_metadata"]["next_cursor"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            if cursor.is_empty() {
+                break;
+            }
+        }
+        Ok(scheduled)
+    }
+
+    async fn cancel_scheduled(&self, channel: &str, id: &str) -> Result<(), AgentError> {
+        let body = serde_json::json!({
+            "channel": channel,
+            "scheduled_message_id": id,
+        });
+        let target = self.dry_run_target(channel).await?;
+        dry_run::intercept(|| format!("cancel scheduled message {id} in {target}"))?;
+        self.api_post("chat.deleteScheduledMessage", &body).await?;
+        Ok(())
+    }
+}
+
 async fn parse_slack_response(resp: reqwest::Response) -> Result<Value, AgentError> {
     let status = resp.status();
+    if status.as_u16() == 429 {
+        // Slack limits per method (and per channel for chat.postMessage).
+        let retry_after = resp
+            .headers()
+            .get("Retry-After")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(AgentError::rate_limited(
+            "slack: too many requests",
+            retry_after,
+            RateLimitScope::Method,
+        )
+        .with_code("ratelimited"));
+    }
     if !status.is_success() {
         let body = resp.text().await.unwrap_or_default();
         if status.as_u16() == 401 || status.as_u16() == 403 {
             return Err(AgentError::auth(format!(
                 "slack auth failed ({status}): {body}"
-            )));
+            ))
+            .with_code(status.as_str()));
         }
         return Err(AgentError::api(format!(
             "slack http error ({status}): {body}"
-        )));
+        ))
+        .with_code(status.as_str()));
     }
     let body: Value = resp
         .json()
@@ -426,14 +958,111 @@ async fn parse_slack_response(resp: reqwest::Response) -> Result<Value, AgentErr
 }
 
 fn check_slack_ok(resp: &Value) -> Result<(), AgentError> {
-    if resp["ok"].as_bool() != Some(true) {
-        let error = resp["error"].as_str().unwrap_or("unknown_error");
-        if error == "invalid_auth" || error == "not_authed" || error == "token_revoked" {
-            return Err(AgentError::auth(format!("slack auth error: {error}")));
+    if resp["ok"].as_bool() == Some(true) {
+        return Ok(());
+    }
+    let error = resp["error"].as_str().unwrap_or("unknown_error");
+    let err = match error {
+        "invalid_auth" | "not_authed" | "token_revoked" | "token_expired" | "account_inactive" => {
+            AgentError::auth(format!("slack auth error: {error}"))
         }
-        return Err(AgentError::api(format!("slack api error: {error}")));
+        "missing_scope" => missing_scope_error(resp),
+        "ratelimited" => AgentError::rate_limited(
+            "slack: too many requests",
+            None,
+            RateLimitScope::Method,
+        ),
+        "channel_not_found" => AgentError::not_found("slack channel not found")
+            .with_argument("channel")
+            .with_suggestion(
+                "Use list_channels for channel IDs (C..., G..., D...); private channels are only visible once the bot is invited",
+            ),
+        "not_in_channel" => AgentError::api("slack bot is not a member of this channel")
+            .with_argument("channel")
+            .with_suggestion("Invite the bot with /invite @<bot name> in that channel, or use slack_invite_to_channel"),
+        "is_archived" => AgentError::api("slack channel is archived").with_argument("channel"),
+        "user_not_found" | "users_not_found" => {
+            AgentError::not_found(format!("slack {error}")).with_argument("user")
+        }
+        "message_not_found" | "thread_not_found" => {
+            AgentError::not_found(format!("slack {error}")).with_argument("message_id")
+        }
+        _ => AgentError::api(format!("slack api error: {error}")),
+    };
+    Err(err.with_code(error))
+}
+
+// missing_scope responses list the scope the method needs and the scopes the
+// token has, e.g. needed="channels:history", provided="chat:write,users:read".
+fn missing_scope_error(resp: &Value) -> AgentError {
+    let needed = resp["needed"].as_str().unwrap_or("").trim();
+    let provided = resp["provided"].as_str().unwrap_or("").trim();
+    if needed.is_empty() {
+        return AgentError::auth("SLACK_BOT_TOKEN is missing a required scope");
+    }
+    let scopes: Vec<String> = needed.split(',').map(|s| format!("`{}`", s.trim())).collect();
+    let scopes = scopes.join(", ");
+    let mut msg = format!("SLACK_BOT_TOKEN lacks scope {scopes}");
+    if !provided.is_empty() {
+        msg.push_str(&format!(" (token has: {})", provided.replace(',', ", ")));
     }
-    Ok(())
+    AgentError::auth(msg).with_suggestion(format!(
+        "Add {scopes} under OAuth & Permissions > Bot Token Scopes, reinstall the app to the workspace, and update SLACK_BOT_TOKEN"
+    ))
+}
+
+// search.messages has no attachment modifier; has:attachment is applied to
+// the returned matches instead.
+fn slack_query(query: &SearchQuery) -> String {
+    let mut parts: Vec<String> = query.terms.clone();
+    parts.extend(query.phrases.iter().map(|p| format!("\"{}\"", p.replace('"', ""))));
+    if let Some(ref from) = query.from {
+        let from = from.trim_start_matches('@');
+        if is_slack_id(from, &['U', 'W']) {
+            parts.push(format!("from:<@{from}>"));
+        } else {
+            parts.push(format!("from:@{from}"));
+        }
+    }
+    if let Some(ref channel) = query.channel {
+        let channel = channel.trim_start_matches('#');
+        if is_slack_id(channel, &['C', 'G', 'D']) {
+            parts.push(format!("in:<#{channel}>"));
+        } else {
+            parts.push(format!("in:#{channel}"));
+        }
+    }
+    // Slack date modifiers are day-granular and exclusive, so widen by a
+    // day on each side and trim to the exact range afterwards.
+    if let Some(since) = query.range.since {
+        parts.push(format!("after:{}", slack_day(since - 86_400)));
+    }
+    if let Some(until) = query.range.until {
+        parts.push(format!("before:{}", slack_day(until + 86_400)));
+    }
+    parts.join(" ")
+}
+
+fn is_slack_id(s: &str, prefixes: &[char]) -> bool {
+    s.len() >= 9
+        && s.starts_with(prefixes)
+        && s.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+// Timestamps always carry six fractional digits, so a length-then-lexical
+// comparison orders them numerically without float rounding.
+fn slack_ts_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn slack_ts(unix: i64) -> String {
+    format!("{unix}.000000")
+}
+
+fn slack_day(unix: i64) -> String {
+    chrono::DateTime::from_timestamp(unix, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
 }
 
 fn parse_slack_channel(ch: &Value) -> Channel {
@@ -480,15 +1109,30 @@ fn parse_slack_message(m: &Value, channel: &str) -> Message {
 
     let has_files = m["files"].as_array().is_some_and(|a| !a.is_empty());
 
+    // History often embeds the author's profile; without it the sender is
+    // left as the ID for the user directory to name.
+    let sender_id = m["user"]
+        .as_str()
+        .or_else(|| m["bot_id"].as_str())
+        .unwrap_or("")
+        .to_string();
+    let profile = &m["user_profile"];
+    let sender = [&profile["display_name"], &profile["real_name"], &m["username"]]
+        .into_iter()
+        .filter_map(|v| v.as_str())
+        .find(|s| !s.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| match sender_id.as_str() {
+            "" => "unknown".to_string(),
+            id => id.to_string(),
+        });
+
     Message {
         id: ts_str.to_string(),
         platform: Platform::Slack,
         channel_id: channel.to_string(),
-        sender: m["user"]
-            .as_str()
-            .or_else(|| m["username"].as_str())
-            .unwrap_or("unknown")
-            .to_string(),
+        sender_id,
+        sender,
         text: m["text"].as_str().unwrap_or("").to_string(),
         timestamp,
         has_attachment: has_files,
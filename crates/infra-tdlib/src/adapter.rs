@@ -6,15 +6,34 @@ use tracing::debug;
 
 use crate::client::TdClient;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 
 const CHAT_CACHE_TTL: Duration = Duration::from_secs(300);
+const SEND_OUTCOME_CAP: usize = 4096;
+// How long schedule_message waits for Telegram to give a scheduled message
+// its lasting ID.
+const SCHEDULE_CONFIRM_WAIT: Duration = Duration::from_secs(10);
+// Chats checked for scheduled messages when no chat is given.
+const SCHEDULED_SCAN_CHATS: usize = 100;
+
+// Outgoing messages get a temporary id until the server confirms them;
+// outcomes are keyed by (chat_id, temporary id).
+#[derive(Debug, Clone)]
+enum SendOutcome {
+    Sent(i64),
+    Failed(String),
+}
 
+#[derive(Clone)]
 pub struct TdlibAdapter {
     client: Arc<TdClient>,
     chat_cache: Arc<RwLock<HashMap<String, (i64, Instant)>>>,
+    send_outcomes: Arc<RwLock<HashMap<(i64, i64), SendOutcome>>>,
+    events: EventHub,
+    events_started: Arc<AtomicBool>,
 }
 
 impl TdlibAdapter {
@@ -22,6 +41,9 @@ impl TdlibAdapter {
         Self {
             client,
             chat_cache: Arc::new(RwLock::new(HashMap::new())),
+            send_outcomes: Arc::new(RwLock::new(HashMap::new())),
+            events: EventHub::default(),
+            events_started: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -95,6 +117,212 @@ impl TdlibAdapter {
         Ok(id)
     }
 
+    // How a dry run names a chat: as given, plus the ID it resolved to.
+    fn chat_label(chat: &str, chat_id: i64) -> String {
+        if chat.parse::<i64>().is_ok() {
+            format!("chat {chat_id}")
+        } else {
+            format!("{chat} (chat {chat_id})")
+        }
+    }
+
+    async fn message_id_at(&self, chat_id: i64, date: i64) -> Result<Option<i64>, AgentError> {
+        let resp = self
+            .client
+            .send(json!({
+                "@type": "getChatMessageByDate",
+                "chat_id": chat_id,
+                "date": date,
+            }))
+            .await;
+
+        // TDLib answers with a 404 error when the chat has no message that old.
+        match resp {
+            Ok(msg) => Ok(msg.get("id").and_then(|v| v.as_i64())),
+            Err(e) if e.code() == Some("404") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // A negative offset makes getChatHistory start that many messages newer
+    // than from_message_id, so one call covers both sides of the anchor.
+    async fn read_anchored(
+        &self,
+        chat_id: i64,
+        anchor: &ReadAnchor,
+        limit: usize,
+        range: TimeRange,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let anchor_id = anchor.message_id().parse::<i64>().map_err(|_| {
+            AgentError::invalid_input(format!("invalid message id '{}'", anchor.message_id()))
+        })?;
+        let limit = limit.min(99);
+        let newer = match anchor {
+            ReadAnchor::After(_) => limit,
+            ReadAnchor::Around(_) => limit / 2,
+        };
+
+        let resp = self
+            .client
+            .send(json!({
+                "@type": "getChatHistory",
+                "chat_id": chat_id,
+                "from_message_id": anchor_id,
+                "offset": -(newer as i64),
+                "limit": limit + 1,
+                "only_local": false,
+            }))
+            .await?;
+
+        let raw_msgs = resp
+            .get("messages")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| AgentError::api("unexpected getChatHistory response"))?;
+
+        let msg_id = |m: &Message| m.id.parse::<i64>().unwrap_or(0);
+        let mut messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
+        if matches!(anchor, ReadAnchor::After(_)) {
+            messages.retain(|m| msg_id(m) > anchor_id);
+        }
+        messages.sort_by_key(|m| std::cmp::Reverse(msg_id(m)));
+        messages.truncate(limit);
+
+        let newer_count = messages.iter().filter(|m| msg_id(m) > anchor_id).count();
+        let older_count = messages.len() - newer_count;
+        let prev_cursor = if newer > 0 && newer_count >= newer {
+            messages.first().map(|m| format!("tg:after:{}", m.id))
+        } else {
+            None
+        };
+        let has_more = match anchor {
+            ReadAnchor::After(_) => !messages.is_empty(),
+            ReadAnchor::Around(_) => older_count >= limit - newer,
+        };
+        let next_cursor = if has_more {
+            messages.last().map(|m| format!("tg:{}", m.id))
+        } else {
+            None
+        };
+
+        messages.retain(|m| range.contains(m.timestamp));
+        self.mark_read_outbox(chat_id, &mut messages).await;
+
+        debug!(chat_id, anchor_id, count = messages.len(), has_more, "read telegram messages around anchor");
+        Ok(Paginated {
+            items: messages,
+            has_more,
+            next_cursor,
+            prev_cursor,
+        })
+    }
+
+    async fn resolve_sender(&self, who: &str) -> Result<Value, AgentError> {
+        if let Ok(id) = who.parse::<i64>() {
+            return Ok(if id < 0 {
+                json!({"@type": "messageSenderChat", "chat_id": id})
+            } else {
+                json!({"@type": "messageSenderUser", "user_id": id})
+            });
+        }
+
+        let chat_id = self.resolve_chat_id(who).await?;
+        let chat = self
+            .client
+            .send(json!({
+                "@type": "getChat",
+                "chat_id": chat_id,
+            }))
+            .await?;
+
+        let user_id = chat
+            .get("type")
+            .filter(|t| t.get("@type").and_then(|v| v.as_str()) == Some("chatTypePrivate"))
+            .and_then(|t| t.get("user_id"))
+            .and_then(|v| v.as_i64());
+        Ok(match user_id {
+            Some(user_id) => json!({"@type": "messageSenderUser", "user_id": user_id}),
+            None => json!({"@type": "messageSenderChat", "chat_id": chat_id}),
+        })
+    }
+
+    async fn search_in_chat(
+        &self,
+        chat: &str,
+        query: &SearchQuery,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let chat_id = self.resolve_chat_id(chat).await?;
+        let sender = match query.from.as_deref() {
+            Some(who) => self.resolve_sender(who).await?,
+            None => Value::Null,
+        };
+
+        let from_id = match cursor.and_then(|c| c.strip_prefix("tg:")) {
+            Some(c) => c
+                .parse::<i64>()
+                .map_err(|_| AgentError::invalid_input(format!("invalid cursor '{c}'")))?,
+            None => match query.range.until {
+                Some(until) => match self.message_id_at(chat_id, until).await? {
+                    Some(id) => id,
+                    None => {
+                        return Ok(Paginated {
+                            items: Vec::new(),
+                            has_more: false,
+                            next_cursor: None,
+                            prev_cursor: None,
+                        })
+                    }
+                },
+                None => 0,
+            },
+        };
+        let resp = self
+            .client
+            .send(json!({
+                "@type": "searchChatMessages",
+                "chat_id": chat_id,
+                "query": query.text(),
+                "sender_id": sender,
+                "from_message_id": from_id,
+                "offset": 0,
+                "limit": limit,
+                "filter": null,
+                "message_thread_id": 0,
+            }))
+            .await?;
+
+        let raw_msgs = resp
+            .get("messages")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| AgentError::api("unexpected searchChatMessages response"))?;
+        let raw_count = raw_msgs.len();
+        let next_from = resp
+            .get("next_from_message_id")
+            .and_then(|v| v.as_i64())
+            .filter(|id| *id != 0);
+
+        let mut messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
+        let reached_since = messages
+            .iter()
+            .any(|m| query.range.since.is_some_and(|s| m.timestamp < s));
+        messages
+            .retain(|m| query.range.contains(m.timestamp) && (!query.has_attachment || m.has_attachment));
+
+        let has_more = raw_count > 0 && next_from.is_some() && !reached_since;
+        debug!(chat_id, count = messages.len(), has_more, "searched telegram chat");
+        Ok(Paginated {
+            items: messages,
+            has_more,
+            next_cursor: if has_more {
+                next_from.map(|id| format!("tg:{id}"))
+            } else {
+                None
+            },
+            prev_cursor: None,
+        })
+    }
+
     fn parse_chat(val: &Value) -> Option<Channel> {
         let id = val.get("id")?.as_i64()?;
         let title = val
@@ -148,71 +376,43 @@ impl TdlibAdapter {
         })
     }
 
-    async fn resolve_senders(&self, messages: &mut [Message]) {
-        let mut user_ids: Vec<i64> = Vec::new();
-        for msg in messages.iter() {
-            if let Some(id_str) = msg.sender.strip_prefix("user:") {
-                if let Ok(id) = id_str.parse::<i64>() {
-                    if !user_ids.contains(&id) {
-                        user_ids.push(id);
-                    }
-                }
-            }
+    // Outgoing messages at or below the chat's read-outbox marker were read.
+    async fn mark_read_outbox(&self, chat_id: i64, messages: &mut [Message]) {
+        if !messages.iter().any(|m| m.meta.delivery == Some(DeliveryState::Sent)) {
+            return;
         }
-
-        let mut names: HashMap<i64, String> = HashMap::new();
-        for uid in user_ids {
-            if let Ok(user) = self
-                .client
-                .send(json!({"@type": "getUser", "user_id": uid}))
-                .await
-            {
-                let first = user
-                    .get("first_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let last = user
-                    .get("last_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let username = user
-                    .get("usernames")
-                    .and_then(|u| u.get("active_usernames"))
-                    .and_then(|a| a.as_array())
-                    .and_then(|a| a.first())
-                    .and_then(|u| u.as_str());
-
-                let display = if !last.is_empty() {
-                    format!("{first} {last}")
-                } else {
-                    first.to_string()
-                };
-
-                let name = match username {
-                    Some(u) => format!("{display} (@{u})"),
-                    None => display,
-                };
-                names.insert(uid, name);
+        let last_read = match self.last_read_outbox(chat_id).await {
+            Ok(id) => id,
+            Err(e) => {
+                debug!(chat_id, error = %e, "failed to fetch read-outbox marker");
+                return;
             }
-        }
-
+        };
         for msg in messages.iter_mut() {
-            if let Some(id_str) = msg.sender.strip_prefix("user:") {
-                if let Ok(id) = id_str.parse::<i64>() {
-                    if let Some(name) = names.get(&id) {
-                        msg.sender = name.clone();
-                    }
-                }
+            let read = msg.id.parse::<i64>().is_ok_and(|id| id <= last_read);
+            if read && msg.meta.delivery == Some(DeliveryState::Sent) {
+                msg.meta.delivery = Some(DeliveryState::Read);
             }
         }
     }
 
+    async fn last_read_outbox(&self, chat_id: i64) -> Result<i64, AgentError> {
+        let chat = self
+            .client
+            .send(json!({"@type": "getChat", "chat_id": chat_id}))
+            .await?;
+        Ok(chat
+            .get("last_read_outbox_message_id")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0))
+    }
+
     fn parse_message(val: &Value) -> Option<Message> {
         let id = val.get("id")?.as_i64()?;
         let chat_id = val.get("chat_id")?.as_i64()?;
         let date = val.get("date").and_then(|v| v.as_i64()).unwrap_or(0);
 
-        let sender = extract_sender(val);
+        let sender_id = extract_sender(val);
 
         let content = val.get("content")?;
         let content_type = content.get("@type").and_then(|v| v.as_str()).unwrap_or("");
@@ -312,37 +512,19 @@ impl TdlibAdapter {
 
         let reactions = val
             .get("interaction_info")
-            .and_then(|i| i.get("reactions"))
-            .and_then(|r| r.get("reactions"))
-            .and_then(|a| a.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|r| {
-                        let emoji = r
-                            .get("type")
-                            .and_then(|t| t.get("emoji"))
-                            .and_then(|e| e.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        let count = r
-                            .get("total_count")
-                            .and_then(|c| c.as_i64())
-                            .unwrap_or(0) as i32;
-                        if emoji.is_empty() {
-                            None
-                        } else {
-                            Some(Reaction { emoji, count })
-                        }
-                    })
-                    .collect::<Vec<_>>()
-            })
+            .map(parse_reactions)
             .filter(|r| !r.is_empty());
 
         Some(Message {
             id: id.to_string(),
             platform: Platform::Telegram,
             channel_id: chat_id.to_string(),
-            sender,
+            sender: if sender_id.is_empty() {
+                "Unknown".to_string()
+            } else {
+                sender_id.clone()
+            },
+            sender_id,
             text,
             timestamp: date,
             has_attachment,
@@ -354,34 +536,101 @@ impl TdlibAdapter {
                 views,
                 edit_date,
                 is_pinned,
+                delivery: delivery_state(val),
                 ..Default::default()
             },
         })
     }
 }
 
+fn delivery_state(msg: &Value) -> Option<DeliveryState> {
+    if !msg.get("is_outgoing").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return None;
+    }
+    let state = msg
+        .get("sending_state")
+        .and_then(|s| s.get("@type"))
+        .and_then(|t| t.as_str());
+    Some(match state {
+        Some("messageSendingStatePending") => DeliveryState::Pending,
+        Some("messageSendingStateFailed") => DeliveryState::Failed,
+        _ => DeliveryState::Sent,
+    })
+}
+
+// The sending user's ID, or the chat's (negative) ID for messages posted
+// as a channel or group. Names are filled in by the user directory.
 fn extract_sender(msg: &Value) -> String {
-    let sender = match msg.get("sender_id") {
-        Some(s) => s,
-        None => return "Unknown".to_string(),
+    let Some(sender) = msg.get("sender_id") else {
+        return String::new();
     };
+    let id = match sender.get("@type").and_then(|t| t.as_str()) {
+        Some("messageSenderUser") => sender.get("user_id"),
+        Some("messageSenderChat") => sender.get("chat_id"),
+        _ => None,
+    };
+    id.and_then(|v| v.as_i64())
+        .map(|id| id.to_string())
+        .unwrap_or_default()
+}
 
-    match sender.get("@type").and_then(|t| t.as_str()) {
-        Some("messageSenderUser") => {
-            let user_id = sender
-                .get("user_id")
-                .and_then(|v| v.as_i64())
-                .unwrap_or(0);
-            format!("user:{user_id}")
-        }
-        Some("messageSenderChat") => {
-            let chat_id = sender
-                .get("chat_id")
-                .and_then(|v| v.as_i64())
-                .unwrap_or(0);
-            format!("chat:{chat_id}")
-        }
-        _ => "Unknown".to_string(),
+// The chat, temporary message ID and outcome an update reports for a send.
+fn send_outcome(update: &Value) -> Option<(i64, i64, SendOutcome)> {
+    let outcome = match update.get("@type").and_then(|t| t.as_str()) {
+        Some("updateMessageSendSucceeded") => update
+            .get("message")
+            .and_then(|m| m.get("id"))
+            .and_then(|v| v.as_i64())
+            .map(SendOutcome::Sent),
+        Some("updateMessageSendFailed") => Some(SendOutcome::Failed(
+            update
+                .get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error")
+                .to_string(),
+        )),
+        _ => None,
+    }?;
+    let chat_id = update
+        .get("message")
+        .and_then(|m| m.get("chat_id"))
+        .and_then(|v| v.as_i64())?;
+    let old_id = update.get("old_message_id").and_then(|v| v.as_i64())?;
+    Some((chat_id, old_id, outcome))
+}
+
+fn parse_user(user: &Value) -> Profile {
+    let first = user
+        .get("first_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let last = user.get("last_name").and_then(|v| v.as_str());
+    let name = match last {
+        Some(l) if !l.is_empty() => format!("{first} {l}"),
+        _ => first.to_string(),
+    };
+    let username = user
+        .get("usernames")
+        .and_then(|u| u.get("active_usernames"))
+        .and_then(|a| a.as_array())
+        .and_then(|a| a.first())
+        .and_then(|u| u.as_str())
+        .map(String::from);
+    let phone = user
+        .get("phone_number")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(String::from);
+    let id = user.get("id").and_then(|v| v.as_i64()).unwrap_or(0);
+
+    Profile {
+        platform: Platform::Telegram,
+        id: id.to_string(),
+        name,
+        username,
+        email: None,
+        phone,
     }
 }
 
@@ -393,37 +642,7 @@ impl MessagingPort for TdlibAdapter {
 
     async fn get_profile(&self) -> Result<Profile, AgentError> {
         let resp = self.client.send(json!({"@type": "getMe"})).await?;
-        let first = resp
-            .get("first_name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-        let last = resp.get("last_name").and_then(|v| v.as_str());
-        let name = match last {
-            Some(l) if !l.is_empty() => format!("{first} {l}"),
-            _ => first.to_string(),
-        };
-        let username = resp
-            .get("usernames")
-            .and_then(|u| u.get("active_usernames"))
-            .and_then(|a| a.as_array())
-            .and_then(|a| a.first())
-            .and_then(|u| u.as_str())
-            .map(String::from);
-        let phone = resp
-            .get("phone_number")
-            .and_then(|v| v.as_str())
-            .filter(|s| !s.is_empty())
-            .map(String::from);
-        let id = resp.get("id").and_then(|v| v.as_i64()).unwrap_or(0);
-
-        Ok(Profile {
-            platform: Platform::Telegram,
-            id: id.to_string(),
-            name,
-            username,
-            email: None,
-            phone,
-        })
+        Ok(parse_user(&resp))
     }
 
     async fn list_channels(&self, limit: usize) -> Result<Vec<Channel>, AgentError> {
@@ -469,13 +688,38 @@ impl MessagingPort for TdlibAdapter {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
         let chat_id = self.resolve_chat_id(channel).await?;
 
-        let from_msg_id = cursor
+        let anchor = match cursor.and_then(|c| c.strip_prefix("tg:after:")) {
+            Some(id) => Some(ReadAnchor::After(id.to_string())),
+            None => anchor.cloned(),
+        };
+        if let Some(anchor) = anchor {
+            return self.read_anchored(chat_id, &anchor, limit, range).await;
+        }
+
+        let cursor_msg_id = cursor
             .and_then(|c| c.strip_prefix("tg:"))
-            .and_then(|s| s.parse::<i64>().ok())
-            .unwrap_or(0);
+            .and_then(|s| s.parse::<i64>().ok());
+
+        let from_msg_id = match (cursor_msg_id, range.until) {
+            (Some(id), _) => id,
+            (None, Some(until)) => match self.message_id_at(chat_id, until).await? {
+                Some(id) => id,
+                None => {
+                    return Ok(Paginated {
+                        items: vec![],
+                        has_more: false,
+                        next_cursor: None,
+                        prev_cursor: None,
+                    })
+                }
+            },
+            (None, None) => 0,
+        };
 
         let resp = self
             .client
@@ -496,18 +740,28 @@ impl MessagingPort for TdlibAdapter {
 
         let raw_count = raw_msgs.len();
         let mut messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
-        self.resolve_senders(&mut messages).await;
-
-        let has_more = raw_count == limit;
         let next_cursor = messages
             .last()
             .map(|m| format!("tg:{}", m.id));
 
+        let before_filter = messages.len();
+        messages.retain(|m| range.contains(m.timestamp));
+        let reached_since = messages.len() < before_filter;
+        self.mark_read_outbox(chat_id, &mut messages).await;
+
+        let has_more = raw_count == limit && !reached_since;
+        let prev_cursor = if cursor_msg_id.is_some() {
+            messages.first().map(|m| format!("tg:after:{}", m.id))
+        } else {
+            None
+        };
+
         debug!(chat_id, count = messages.len(), has_more, "read telegram messages");
         Ok(Paginated {
             items: messages,
             has_more,
             next_cursor: if has_more { next_cursor } else { None },
+            prev_cursor,
         })
     }
 
@@ -518,6 +772,10 @@ impl MessagingPort for TdlibAdapter {
         reply_to: Option<&str>,
     ) -> Result<Message, AgentError> {
         let chat_id = self.resolve_chat_id(channel).await?;
+        // Send confirmations arrive as updates, so make sure they are tracked.
+        if !dry_run::active() {
+            self.start_updates();
+        }
 
         let mut req = json!({
             "@type": "sendMessage",
@@ -540,6 +798,10 @@ impl MessagingPort for TdlibAdapter {
                 "message_id": reply_id,
             });
         }
+        dry_run::intercept(|| {
+            let reply = reply_to.map(|r| format!(" in reply to message {r}")).unwrap_or_default();
+            format!("send to {}{reply}: {text:?}", Self::chat_label(channel, chat_id))
+        })?;
 
         let resp = self.client.send(req).await?;
 
@@ -549,25 +811,30 @@ impl MessagingPort for TdlibAdapter {
 
     async fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         limit: usize,
         cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError> {
+        if let Some(chat) = query.channel.as_deref() {
+            return self.search_in_chat(chat, query, limit, cursor).await;
+        }
+
         let offset = cursor
             .and_then(|c| c.strip_prefix("tg:"))
             .unwrap_or("");
+        let text = query.text();
 
         let resp = self
             .client
             .send(json!({
                 "@type": "searchMessages",
                 "chat_list": {"@type": "chatListMain"},
-                "query": query,
+                "query": text,
                 "offset": offset,
                 "limit": limit,
                 "filter": null,
-                "min_date": 0,
-                "max_date": 0,
+                "min_date": query.range.since.unwrap_or(0),
+                "max_date": query.range.until.unwrap_or(0),
             }))
             .await?;
 
@@ -582,19 +849,534 @@ impl MessagingPort for TdlibAdapter {
             .filter(|s| !s.is_empty())
             .map(|s| format!("tg:{s}"));
 
-        let mut messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
-        self.resolve_senders(&mut messages).await;
+        // TDLib has no "any attachment" filter, so has:attachment is applied here.
+        let messages: Vec<Message> = raw_msgs
+            .iter()
+            .filter_map(Self::parse_message)
+            .filter(|m| !query.has_attachment || m.has_attachment)
+            .collect();
 
         let has_more = next_offset.is_some();
-        debug!(query, count = messages.len(), has_more, "searched telegram messages");
+        debug!(query = %text, count = messages.len(), has_more, "searched telegram messages");
         Ok(Paginated {
             items: messages,
             has_more,
             next_cursor: next_offset,
+            prev_cursor: None,
+        })
+    }
+
+    // TDLib matches words rather than exact phrases, has no unread search, and
+    // can only filter by sender within a single chat.
+    fn unsupported_clauses(&self, query: &SearchQuery) -> Vec<SearchClause> {
+        let mut unsupported = Vec::new();
+        if !query.phrases.is_empty() {
+            unsupported.push(SearchClause::Phrase);
+        }
+        if query.from.is_some() && query.channel.is_none() {
+            unsupported.push(SearchClause::From);
+        }
+        if query.is_unread {
+            unsupported.push(SearchClause::IsUnread);
+        }
+        unsupported
+    }
+
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        let chat_id = self.resolve_chat_id(channel).await?;
+        let mut msg_id: i64 = message_id
+            .parse()
+            .map_err(|_| AgentError::invalid_input(format!("invalid message id '{message_id}'")))?;
+        self.start_updates();
+
+        let status = |msg_id: i64, state, error| DeliveryStatus {
+            platform: Platform::Telegram,
+            channel_id: chat_id.to_string(),
+            message_id: msg_id.to_string(),
+            state,
+            error,
+            updated_at: None,
+        };
+
+        let outcome = self.send_outcomes.read().await.get(&(chat_id, msg_id)).cloned();
+        match outcome {
+            Some(SendOutcome::Failed(error)) => {
+                return Ok(status(msg_id, DeliveryState::Failed, Some(error)));
+            }
+            Some(SendOutcome::Sent(new_id)) => msg_id = new_id,
+            None => {}
+        }
+
+        let msg = self
+            .client
+            .send(json!({"@type": "getMessage", "chat_id": chat_id, "message_id": msg_id}))
+            .await?;
+        let state = delivery_state(&msg).ok_or_else(|| {
+            AgentError::invalid_input(format!("message {msg_id} was not sent by this account"))
+        })?;
+        if state == DeliveryState::Failed {
+            let error = msg
+                .get("sending_state")
+                .and_then(|s| s.get("error"))
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .map(String::from);
+            return Ok(status(msg_id, state, error));
+        }
+        if state == DeliveryState::Sent && msg_id <= self.last_read_outbox(chat_id).await? {
+            return Ok(status(msg_id, DeliveryState::Read, None));
+        }
+        Ok(status(msg_id, state, None))
+    }
+
+    async fn resolve_channel(&self, channel: &str) -> Result<ChatRef, AgentError> {
+        let chat_id = self.resolve_chat_id(channel).await?;
+        let chat = self
+            .client
+            .send(json!({"@type": "getChat", "chat_id": chat_id}))
+            .await?;
+        let mut chat_ref = ChatRef::new(chat_id.to_string());
+        if let Some(title) = chat.get("title").and_then(|v| v.as_str()) {
+            chat_ref = chat_ref.named(title);
+        }
+        let kind = &chat["type"];
+        let owner = match kind["@type"].as_str() {
+            Some("chatTypePrivate") => {
+                json!({"@type": "getUser", "user_id": kind["user_id"]})
+            }
+            Some("chatTypeSupergroup") => {
+                json!({"@type": "getSupergroup", "supergroup_id": kind["supergroup_id"]})
+            }
+            _ => return Ok(chat_ref),
+        };
+        let owner = self.client.send(owner).await?;
+        let usernames = owner["usernames"]["active_usernames"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|u| u.as_str());
+        for username in usernames {
+            chat_ref = chat_ref.named(format!("@{username}")).named(username);
+        }
+        Ok(chat_ref)
+    }
+}
+
+impl TdlibAdapter {
+    async fn forward_updates(self, mut updates: broadcast::Receiver<Value>) {
+        loop {
+            let update = match updates.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "telegram update stream lagged");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            self.track_send_outcome(&update).await;
+            if !self.events.has_subscribers() {
+                continue;
+            }
+            if let Some(event) = parse_update(&update) {
+                self.events.publish(event);
+            }
+        }
+        debug!("telegram update forwarding stopped");
+    }
+
+    fn start_updates(&self) {
+        if !self.events_started.swap(true, Ordering::SeqCst) {
+            tokio::spawn(self.clone().forward_updates(self.client.subscribe_updates()));
+        }
+    }
+
+    async fn track_send_outcome(&self, update: &Value) {
+        if let Some((chat_id, old_id, outcome)) = send_outcome(update) {
+            let mut outcomes = self.send_outcomes.write().await;
+            if outcomes.len() >= SEND_OUTCOME_CAP {
+                outcomes.clear();
+            }
+            outcomes.insert((chat_id, old_id), outcome);
+        }
+    }
+
+    // Waits for the update that confirms or refuses a message sent under a
+    // temporary ID. `updates` must be subscribed before the send.
+    async fn await_send(
+        &self,
+        updates: &mut broadcast::Receiver<Value>,
+        chat_id: i64,
+        temp_id: i64,
+    ) -> Option<SendOutcome> {
+        let wait = async {
+            loop {
+                match updates.recv().await {
+                    Ok(update) => match send_outcome(&update) {
+                        Some((c, old, outcome)) if c == chat_id && old == temp_id => return Some(outcome),
+                        _ => {}
+                    },
+                    // The tracker saw whatever this receiver missed.
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        let tracked = self.send_outcomes.read().await.get(&(chat_id, temp_id)).cloned();
+                        if tracked.is_some() {
+                            return tracked;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        };
+        tokio::time::timeout(SCHEDULE_CONFIRM_WAIT, wait).await.ok().flatten()
+    }
+
+    // A temporary message ID as Telegram has since renamed it.
+    async fn confirmed_id(&self, chat_id: i64, msg_id: i64) -> i64 {
+        match self.send_outcomes.read().await.get(&(chat_id, msg_id)) {
+            Some(SendOutcome::Sent(new_id)) => *new_id,
+            _ => msg_id,
+        }
+    }
+
+    async fn chat_scheduled(&self, chat_id: i64) -> Result<Vec<ScheduledMessage>, AgentError> {
+        let resp = self
+            .client
+            .send(json!({"@type": "getChatScheduledMessages", "chat_id": chat_id}))
+            .await?;
+        Ok(resp
+            .get("messages")
+            .and_then(|v| v.as_array())
+            .map(|msgs| msgs.iter().filter_map(parse_scheduled).collect())
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl EventPort for TdlibAdapter {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        let stream = self.events.subscribe();
+        self.start_updates();
+        Ok(stream)
+    }
+}
+
+fn parse_update(update: &Value) -> Option<Event> {
+    let chat_id = || update.get("chat_id").and_then(|v| v.as_i64()).map(|id| id.to_string());
+    let message_id = || {
+        update
+            .get("message_id")
+            .and_then(|v| v.as_i64())
+            .map(|id| id.to_string())
+    };
+
+    match update.get("@type")?.as_str()? {
+        "updateNewMessage" => {
+            let message = TdlibAdapter::parse_message(update.get("message")?)?;
+            Some(Event::MessageCreated {
+                message: Box::new(message),
+            })
+        }
+        "updateMessageContent" => {
+            // Reuse message parsing to render the new content as text.
+            let rendered = TdlibAdapter::parse_message(&json!({
+                "id": update.get("message_id")?,
+                "chat_id": update.get("chat_id")?,
+                "content": update.get("new_content")?,
+            }))?;
+            Some(Event::MessageEdited {
+                platform: Platform::Telegram,
+                channel_id: chat_id()?,
+                message_id: message_id()?,
+                text: rendered.text,
+                edited_at: None,
+            })
+        }
+        "updateDeleteMessages" => {
+            if !update.get("is_permanent").and_then(|v| v.as_bool()).unwrap_or(false) {
+                return None;
+            }
+            let message_ids = update
+                .get("message_ids")?
+                .as_array()?
+                .iter()
+                .filter_map(|v| v.as_i64())
+                .map(|id| id.to_string())
+                .collect();
+            Some(Event::MessageDeleted {
+                platform: Platform::Telegram,
+                channel_id: chat_id()?,
+                message_ids,
+            })
+        }
+        "updateMessageInteractionInfo" => Some(Event::ReactionChanged {
+            platform: Platform::Telegram,
+            channel_id: chat_id()?,
+            message_id: message_id()?,
+            reactions: update
+                .get("interaction_info")
+                .map(parse_reactions)
+                .unwrap_or_default(),
+        }),
+        "updateChatReadInbox" => Some(Event::ReadStateChanged {
+            platform: Platform::Telegram,
+            channel_id: chat_id()?,
+            last_read_id: update
+                .get("last_read_inbox_message_id")
+                .and_then(|v| v.as_i64())
+                .map(|id| id.to_string()),
+            unread_count: update
+                .get("unread_count")
+                .and_then(|v| v.as_i64())
+                .map(|n| n as i32),
+        }),
+        "updateMessageSendSucceeded" | "updateMessageSendFailed" => {
+            let message = update.get("message")?;
+            let state = if update.get("@type")?.as_str()? == "updateMessageSendSucceeded" {
+                DeliveryState::Sent
+            } else {
+                DeliveryState::Failed
+            };
+            Some(Event::DeliveryChanged {
+                platform: Platform::Telegram,
+                channel_id: message.get("chat_id")?.as_i64()?.to_string(),
+                message_id: message.get("id")?.as_i64()?.to_string(),
+                state,
+            })
+        }
+        // Everything up to last_read_outbox_message_id has been read.
+        "updateChatReadOutbox" => Some(Event::DeliveryChanged {
+            platform: Platform::Telegram,
+            channel_id: chat_id()?,
+            message_id: update
+                .get("last_read_outbox_message_id")?
+                .as_i64()?
+                .to_string(),
+            state: DeliveryState::Read,
+        }),
+        "updateChatTitle" => Some(Event::ChannelUpdated {
+            platform: Platform::Telegram,
+            channel_id: chat_id()?,
+            name: update.get("title").and_then(|v| v.as_str()).map(String::from),
+        }),
+        "updateNewChat" => {
+            let chat = update.get("chat")?;
+            Some(Event::ChannelUpdated {
+                platform: Platform::Telegram,
+                channel_id: chat.get("id")?.as_i64()?.to_string(),
+                name: chat.get("title").and_then(|v| v.as_str()).map(String::from),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn parse_reactions(interaction_info: &Value) -> Vec<Reaction> {
+    interaction_info
+        .get("reactions")
+        .and_then(|r| r.get("reactions"))
+        .and_then(|a| a.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|r| {
+                    let emoji = r
+                        .get("type")
+                        .and_then(|t| t.get("emoji"))
+                        .and_then(|e| e.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let count = r
+                        .get("total_count")
+                        .and_then(|c| c.as_i64())
+                        .unwrap_or(0) as i32;
+                    if emoji.is_empty() {
+                        None
+                    } else {
+                        Some(Reaction { emoji, count })
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_scheduled(val: &Value) -> Option<ScheduledMessage> {
+    let msg = TdlibAdapter::parse_message(val)?;
+    let send_at = val
+        .get("scheduling_state")
+        .and_then(|s| s.get("send_date"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(msg.timestamp);
+    Some(ScheduledMessage {
+        id: msg.id,
+        platform: Platform::Telegram,
+        channel_id: msg.channel_id,
+        text: msg.text,
+        send_at,
+        state: ScheduleState::Scheduled,
+        reply_to: msg.reply_to,
+        queued: false,
+        message_id: None,
+        error: None,
+    })
+}
+
+// Negative IDs are chats posting as themselves, named by their title.
+#[async_trait]
+impl UserPort for TdlibAdapter {
+    async fn get_user(&self, user_id: &str) -> Result<Profile, AgentError> {
+        let id: i64 = user_id.parse().map_err(|_| {
+            AgentError::invalid_input(format!("not a telegram user id: {user_id}"))
+                .with_argument("user_id")
+        })?;
+        if id > 0 {
+            let user = self
+                .client
+                .send(json!({"@type": "getUser", "user_id": id}))
+                .await?;
+            return Ok(parse_user(&user));
+        }
+        let chat = self
+            .client
+            .send(json!({"@type": "getChat", "chat_id": id}))
+            .await?;
+        Ok(Profile {
+            platform: Platform::Telegram,
+            id: user_id.to_string(),
+            name: chat
+                .get("title")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown")
+                .to_string(),
+            username: None,
+            email: None,
+            phone: None,
         })
     }
 }
 
+// TDLib keeps scheduled messages per chat, with their own IDs, until the
+// server sends them.
+#[async_trait]
+impl SchedulePort for TdlibAdapter {
+    async fn schedule_message(
+        &self,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        send_at: i64,
+    ) -> Result<ScheduledMessage, AgentError> {
+        let chat_id = self.resolve_chat_id(channel).await?;
+        let mut req = json!({
+            "@type": "sendMessage",
+            "chat_id": chat_id,
+            "options": {
+                "@type": "messageSendOptions",
+                "scheduling_state": {
+                    "@type": "messageSchedulingStateSendAtDate",
+                    "send_date": send_at,
+                }
+            },
+            "input_message_content": {
+                "@type": "inputMessageText",
+                "text": {
+                    "@type": "formattedText",
+                    "text": text,
+                }
+            }
+        });
+        if let Some(reply_str) = reply_to {
+            let reply_id: i64 = reply_str
+                .parse()
+                .map_err(|_| AgentError::invalid_input(format!("invalid reply_to: {reply_str} (expected numeric message ID)")))?;
+            req["reply_to"] = json!({
+                "@type": "inputMessageReplyToMessage",
+                "message_id": reply_id,
+            });
+        }
+        dry_run::intercept(|| {
+            format!(
+                "schedule for {send_at} (unix time) in {}: {text:?}",
+                Self::chat_label(channel, chat_id)
+            )
+        })?;
+
+        self.start_updates();
+        let mut updates = self.client.subscribe_updates();
+        let resp = self.client.send(req).await?;
+        let mut scheduled = parse_scheduled(&resp)
+            .ok_or_else(|| AgentError::internal("failed to parse scheduled message"))?;
+        // sendMessage answers with a temporary ID; the one the message keeps
+        // arrives in an update once Telegram has it. If that takes too long
+        // the temporary ID is returned, and cancel_scheduled maps it.
+        if let Ok(temp_id) = scheduled.id.parse::<i64>() {
+            match self.await_send(&mut updates, chat_id, temp_id).await {
+                Some(SendOutcome::Sent(id)) => scheduled.id = id.to_string(),
+                Some(SendOutcome::Failed(error)) => {
+                    return Err(AgentError::api(format!("telegram refused the scheduled message: {error}")))
+                }
+                None => debug!(chat_id, temp_id, "scheduled message not confirmed yet"),
+            }
+        }
+        Ok(scheduled)
+    }
+
+    // Telegram keeps scheduled messages per chat. Without a chat, the most
+    // recent chats are checked and only those flagged as having any are
+    // asked for them.
+    async fn list_scheduled(&self, channel: Option<&str>) -> Result<Vec<ScheduledMessage>, AgentError> {
+        if let Some(channel) = channel {
+            let chat_id = self.resolve_chat_id(channel).await?;
+            return self.chat_scheduled(chat_id).await;
+        }
+        let resp = self
+            .client
+            .send(json!({
+                "@type": "getChats",
+                "chat_list": {"@type": "chatListMain"},
+                "limit": SCHEDULED_SCAN_CHATS,
+            }))
+            .await?;
+        let chat_ids = resp
+            .get("chat_ids")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| AgentError::api("unexpected getChats response"))?;
+        let mut scheduled = Vec::new();
+        for chat_id in chat_ids.iter().filter_map(|v| v.as_i64()) {
+            let chat = self
+                .client
+                .send(json!({"@type": "getChat", "chat_id": chat_id}))
+                .await?;
+            if chat["has_scheduled_messages"].as_bool().unwrap_or(false) {
+                scheduled.extend(self.chat_scheduled(chat_id).await?);
+            }
+        }
+        Ok(scheduled)
+    }
+
+    async fn cancel_scheduled(&self, channel: &str, id: &str) -> Result<(), AgentError> {
+        let msg_id: i64 = id.parse().map_err(|_| {
+            AgentError::invalid_input(format!("not a telegram message id: {id}")).with_argument("id")
+        })?;
+        let chat_id = self.resolve_chat_id(channel).await?;
+        let msg_id = self.confirmed_id(chat_id, msg_id).await;
+        dry_run::intercept(|| {
+            format!("cancel scheduled message {msg_id} in {}", Self::chat_label(channel, chat_id))
+        })?;
+        self.client
+            .send(json!({
+                "@type": "deleteMessages",
+                "chat_id": chat_id,
+                "message_ids": [msg_id],
+                "revoke": true,
+            }))
+            .await?;
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl TelegramExt for TdlibAdapter {
     async fn download_media(
@@ -659,6 +1441,13 @@ impl TelegramExt for TdlibAdapter {
     ) -> Result<Message, AgentError> {
         let from_id = self.resolve_chat_id(from_chat).await?;
         let to_id = self.resolve_chat_id(to_chat).await?;
+        dry_run::intercept(|| {
+            format!(
+                "forward message {msg_id} from {} to {}",
+                Self::chat_label(from_chat, from_id),
+                Self::chat_label(to_chat, to_id)
+            )
+        })?;
 
         let resp = self
             .client
@@ -689,6 +1478,9 @@ impl TelegramExt for TdlibAdapter {
         text: &str,
     ) -> Result<Message, AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| {
+            format!("edit message {msg_id} in {} to {text:?}", Self::chat_label(chat, chat_id))
+        })?;
 
         let resp = self
             .client
@@ -716,6 +1508,12 @@ impl TelegramExt for TdlibAdapter {
         msg_ids: &[i64],
     ) -> Result<(), AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| {
+            format!(
+                "delete messages {msg_ids:?} in {} for everyone",
+                Self::chat_label(chat, chat_id)
+            )
+        })?;
 
         self.client
             .send(json!({
@@ -731,6 +1529,7 @@ impl TelegramExt for TdlibAdapter {
 
     async fn pin_message(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| format!("pin message {msg_id} in {}", Self::chat_label(chat, chat_id)))?;
 
         self.client
             .send(json!({
@@ -747,6 +1546,7 @@ impl TelegramExt for TdlibAdapter {
 
     async fn unpin_message(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| format!("unpin message {msg_id} in {}", Self::chat_label(chat, chat_id)))?;
 
         self.client
             .send(json!({
@@ -905,6 +1705,7 @@ impl TelegramExt for TdlibAdapter {
 
     async fn mark_read(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| format!("mark message {msg_id} in {} as read", Self::chat_label(chat, chat_id)))?;
 
         self.client
             .send(json!({
@@ -930,15 +1731,8 @@ impl TelegramExt for TdlibAdapter {
             }))
             .await?;
 
-        let msg = Self::parse_message(&resp)
-            .ok_or_else(|| AgentError::not_found(format!("message {msg_id} not found")))?;
-
-        let mut msgs = vec![msg];
-        self.resolve_senders(&mut msgs).await;
-
-        msgs.into_iter()
-            .next()
-            .ok_or_else(|| AgentError::internal("failed to resolve message sender"))
+        Self::parse_message(&resp)
+            .ok_or_else(|| AgentError::not_found(format!("message {msg_id} not found")))
     }
 
     async fn send_media(
@@ -998,6 +1792,14 @@ impl TelegramExt for TdlibAdapter {
             "@type": "inputFileLocal",
             "path": file_path,
         });
+        dry_run::intercept(|| {
+            let caption = caption.map(|c| format!(" with caption {c:?}")).unwrap_or_default();
+            format!(
+                "send {file_path} as a {file_key} to {}{caption}",
+                Self::chat_label(chat, chat_id)
+            )
+        })?;
+        self.start_updates();
 
         let resp = self
             .client
@@ -1019,6 +1821,9 @@ impl TelegramExt for TdlibAdapter {
         emoji: &str,
     ) -> Result<(), AgentError> {
         let chat_id = self.resolve_chat_id(chat).await?;
+        dry_run::intercept(|| {
+            format!("react {emoji} to message {msg_id} in {}", Self::chat_label(chat, chat_id))
+        })?;
 
         self.client
             .send(json!({
@@ -1065,8 +1870,7 @@ impl TelegramExt for TdlibAdapter {
             .and_then(|v| v.as_array())
             .ok_or_else(|| AgentError::api("unexpected searchChatMessages response"))?;
 
-        let mut messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
-        self.resolve_senders(&mut messages).await;
+        let messages: Vec<Message> = raw_msgs.iter().filter_map(Self::parse_message).collect();
 
         debug!(chat_id, query, count = messages.len(), "searched chat messages");
         Ok(messages)
@@ -4,9 +4,9 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use nexus_error::AgentError;
+use nexus_error::{AgentError, RateLimitScope};
 use serde_json::Value;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tracing::{debug, trace, warn};
 
 use crate::ffi;
@@ -19,6 +19,7 @@ pub struct TdClient {
     #[allow(dead_code)]
     auth_tx: mpsc::UnboundedSender<Value>,
     auth_rx: Mutex<Option<mpsc::UnboundedReceiver<Value>>>,
+    updates_tx: broadcast::Sender<Value>,
     next_id: AtomicU64,
     running: Arc<AtomicBool>,
 }
@@ -47,10 +48,12 @@ impl TdClient {
         let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
         let running = Arc::new(AtomicBool::new(true));
         let (auth_tx, auth_rx) = mpsc::unbounded_channel();
+        let (updates_tx, _) = broadcast::channel(1024);
 
         let pending_clone = pending.clone();
         let running_clone = running.clone();
         let auth_tx_clone = auth_tx.clone();
+        let updates_tx_clone = updates_tx.clone();
 
         // Dedicated OS thread for td_receive (blocking call)
         // Safety: td_receive is thread-safe. We run it on a dedicated thread
@@ -60,7 +63,7 @@ impl TdClient {
         std::thread::Builder::new()
             .name("tdlib-recv".into())
             .spawn(move || {
-                receive_loop(pending_clone, auth_tx_clone, running_clone);
+                receive_loop(pending_clone, auth_tx_clone, updates_tx_clone, running_clone);
             })
             .ok();
 
@@ -78,6 +81,7 @@ impl TdClient {
             pending,
             auth_tx,
             auth_rx: Mutex::new(Some(auth_rx)),
+            updates_tx,
             next_id: AtomicU64::new(1),
             running,
         }
@@ -90,7 +94,13 @@ impl TdClient {
         }
     }
 
+    // Updates are dropped when nobody is subscribed.
+    pub fn subscribe_updates(&self) -> broadcast::Receiver<Value> {
+        self.updates_tx.subscribe()
+    }
+
     pub async fn send(&self, mut request: Value) -> Result<Value, AgentError> {
+        let method = request["@type"].as_str().unwrap_or("request").to_string();
         let extra = format!("r{}", self.next_id.fetch_add(1, Ordering::Relaxed));
         request["@extra"] = Value::String(extra.clone());
 
@@ -122,10 +132,13 @@ impl TdClient {
             .map_err(|_| {
                 self.cleanup_pending(&extra);
                 AgentError::network("request timed out after 30s")
+                    .with_platform("telegram")
+                    .with_operation(method.as_str())
             })?
             .map_err(|_| AgentError::internal("response channel dropped"))?;
 
-        check_tdlib_error(&response)?;
+        check_tdlib_error(&response)
+            .map_err(|e| e.with_platform("telegram").with_operation(method))?;
         Ok(response)
     }
 
@@ -177,6 +190,7 @@ impl Drop for TdClient {
 fn receive_loop(
     pending: PendingMap,
     auth_tx: mpsc::UnboundedSender<Value>,
+    updates_tx: broadcast::Sender<Value>,
     running: Arc<AtomicBool>,
 ) {
     debug!("TDLib receive loop started");
@@ -231,8 +245,8 @@ fn receive_loop(
             continue;
         }
 
-        // Log other updates
         trace!(update_type = type_str, "received update");
+        let _ = updates_tx.send(value);
     }
 
     debug!("TDLib receive loop stopped");
@@ -245,7 +259,30 @@ fn check_tdlib_error(val: &Value) -> Result<(), AgentError> {
             .get("message")
             .and_then(|v| v.as_str())
             .unwrap_or("unknown error");
-        return Err(AgentError::api(format!("TDLib error {code}: {msg}")));
+        if code == 429 || msg.contains("FLOOD_WAIT_") {
+            return Err(AgentError::rate_limited(
+                format!("TDLib error {code}: {msg}"),
+                flood_wait_secs(msg).map(Duration::from_secs),
+                RateLimitScope::Method,
+            )
+            .with_code(code.to_string()));
+        }
+        let err = match code {
+            401 => AgentError::auth(format!("TDLib error {code}: {msg}")),
+            _ => AgentError::api(format!("TDLib error {code}: {msg}")),
+        };
+        return Err(err.with_code(code.to_string()));
     }
     Ok(())
 }
+
+// Flood waits arrive either raw ("FLOOD_WAIT_17") or as TDLib's rewording
+// ("Too Many Requests: retry after 17").
+fn flood_wait_secs(msg: &str) -> Option<u64> {
+    let rest = msg
+        .split_once("FLOOD_WAIT_")
+        .or_else(|| msg.split_once("retry after "))?
+        .1;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
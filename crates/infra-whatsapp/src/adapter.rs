@@ -1,17 +1,22 @@
 use std::path::Path;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use nexus_domain::*;
-use nexus_error::AgentError;
+use nexus_error::{AgentError, RateLimitScope};
 use reqwest::multipart;
 use serde::Deserialize;
+use tokio::sync::RwLock;
 use tracing::debug;
 
+use crate::webhook::{self, StatusStore, Statuses, WebhookConfig};
+
 const BASE_URL: &str = "https://graph.facebook.com/v21.0";
 
 pub struct WhatsAppConfig {
     pub access_token: String,
     pub phone_number_id: String,
+    pub webhook: Option<WebhookConfig>,
 }
 
 pub struct WhatsAppAdapter {
@@ -19,6 +24,8 @@ pub struct WhatsAppAdapter {
     auth: String,
     phone_number_id: String,
     http: reqwest::Client,
+    statuses: StatusStore,
+    webhook: Option<WebhookConfig>,
 }
 
 impl WhatsAppAdapter {
@@ -31,9 +38,31 @@ impl WhatsAppAdapter {
             auth,
             phone_number_id,
             http: reqwest::Client::new(),
+            statuses: Arc::new(RwLock::new(Statuses::default())),
+            webhook: cfg.webhook,
+        }
+    }
+
+    // Delivery receipts only arrive through the webhook, so without it sent
+    // messages stay pending.
+    pub fn start_webhook(&self) {
+        if let Some(cfg) = self.webhook.clone() {
+            tokio::spawn(webhook::serve(cfg, self.statuses.clone()));
         }
     }
 
+    async fn track_sent(&self, msg: &Message) {
+        let mut statuses = self.statuses.write().await;
+        statuses.insert_if_absent(DeliveryStatus {
+            platform: Platform::WhatsApp,
+            channel_id: msg.channel_id.clone(),
+            message_id: msg.id.clone(),
+            state: DeliveryState::Pending,
+            error: None,
+            updated_at: Some(msg.timestamp),
+        });
+    }
+
     fn url(&self, path: &str) -> String {
         format!("{}{path}", self.base_url)
     }
@@ -55,7 +84,7 @@ impl WhatsAppAdapter {
             .map_err(|e| AgentError::network(format!("whatsapp read body: {e}")))?;
 
         if !status.is_success() {
-            return Err(parse_api_error(&body, status.as_u16()));
+            return Err(parse_api_error(&body, status.as_u16()).with_operation(operation("GET", url)));
         }
 
         serde_json::from_str(&body)
@@ -84,7 +113,7 @@ impl WhatsAppAdapter {
             .map_err(|e| AgentError::network(format!("whatsapp read body: {e}")))?;
 
         if !status.is_success() {
-            return Err(parse_api_error(&body, status.as_u16()));
+            return Err(parse_api_error(&body, status.as_u16()).with_operation(operation("POST", url)));
         }
 
         serde_json::from_str(&body)
@@ -134,7 +163,7 @@ impl WhatsAppAdapter {
             .map_err(|e| AgentError::network(format!("whatsapp read body: {e}")))?;
 
         if !status.is_success() {
-            return Err(parse_api_error(&body, status.as_u16()));
+            return Err(parse_api_error(&body, status.as_u16()).with_operation(operation("POST", &url)));
         }
 
         let parsed: MediaUploadResponse = serde_json::from_str(&body)
@@ -144,11 +173,11 @@ impl WhatsAppAdapter {
     }
 }
 
-fn parse_api_error(body: &str, status: u16) -> AgentError {
-    if status == 401 || status == 403 {
-        return AgentError::auth(format!("whatsapp auth failed ({status}): {body}"));
-    }
+fn operation(verb: &str, url: &str) -> String {
+    format!("{verb} {}", url.strip_prefix(BASE_URL).unwrap_or(url))
+}
 
+fn parse_api_error(body: &str, status: u16) -> AgentError {
     #[derive(Deserialize)]
     struct WaErrorResp {
         error: Option<WaErrorDetail>,
@@ -159,15 +188,68 @@ fn parse_api_error(body: &str, status: u16) -> AgentError {
         code: Option<i64>,
     }
 
-    if let Ok(err) = serde_json::from_str::<WaErrorResp>(body) {
-        if let Some(detail) = err.error {
+    let detail = serde_json::from_str::<WaErrorResp>(body)
+        .ok()
+        .and_then(|e| e.error);
+    let err = match detail {
+        Some(detail) => {
             let msg = detail.message.unwrap_or_else(|| body.to_string());
             let code = detail.code.unwrap_or(0);
-            return AgentError::api(format!("whatsapp error {code}: {msg}"));
+            let text = format!("whatsapp error {code}: {msg}");
+            let err = match code {
+                _ if status == 401 || code == 190 => AgentError::auth(text),
+                131030 => AgentError::invalid_input(text)
+                    .with_argument("channel")
+                    .with_suggestion("Add the recipient to the allowed numbers list in the WhatsApp dashboard (test numbers only)"),
+                131047 => AgentError::api(text).with_suggestion(
+                    "More than 24 hours since the recipient last wrote; free-form messages need an approved template",
+                ),
+                _ => match throttle_scope(code) {
+                    Some(scope) => AgentError::rate_limited(text, None, scope),
+                    None if status == 429 => {
+                        AgentError::rate_limited(text, None, RateLimitScope::Global)
+                    }
+                    None if status == 403 => AgentError::auth(text),
+                    None => AgentError::api(text),
+                },
+            };
+            err.with_code(code.to_string())
         }
+        None => {
+            let text = format!("whatsapp error ({status}): {body}");
+            let err = match status {
+                401 | 403 => AgentError::auth(text),
+                429 => AgentError::rate_limited(text, None, RateLimitScope::Global),
+                _ => AgentError::api(text),
+            };
+            err.with_code(status.to_string())
+        }
+    };
+    err.with_platform("whatsapp")
+}
+
+// Meta reports throttling through error codes rather than Retry-After.
+fn throttle_scope(code: i64) -> Option<RateLimitScope> {
+    match code {
+        // App-level, business-account-level, and phone-number throughput limits.
+        4 | 80007 | 130429 => Some(RateLimitScope::Global),
+        613 => Some(RateLimitScope::Method),
+        // Too many messages to the same recipient.
+        131056 => Some(RateLimitScope::Resource),
+        _ => None,
     }
+}
 
-    AgentError::api(format!("whatsapp error ({status}): {body}"))
+// The Cloud API only sends to phone numbers in international format, so
+// anything else is refused here, before a dry run reports it as sendable.
+fn validate_recipient(to: &str) -> Result<(), AgentError> {
+    let digits = to.strip_prefix('+').unwrap_or(to);
+    if (7..=15).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(());
+    }
+    Err(AgentError::invalid_input(format!(
+        "invalid whatsapp recipient '{to}': expected a phone number in international format, e.g. 15551234567"
+    )))
 }
 
 fn detect_media_type(file_path: &str) -> (&'static str, &'static str) {
@@ -274,6 +356,8 @@ impl MessagingPort for WhatsAppAdapter {
         _channel: &str,
         _limit: usize,
         _cursor: Option<&str>,
+        _anchor: Option<&ReadAnchor>,
+        _range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
         Err(AgentError::not_implemented(
             "WhatsApp Cloud API does not support reading message history. Messages are delivered via webhooks.",
@@ -286,6 +370,7 @@ impl MessagingPort for WhatsAppAdapter {
         text: &str,
         reply_to: Option<&str>,
     ) -> Result<Message, AgentError> {
+        validate_recipient(channel)?;
         let url = self.url("/messages");
 
         let mut body = serde_json::json!({
@@ -299,6 +384,10 @@ impl MessagingPort for WhatsAppAdapter {
         if let Some(rt) = reply_to {
             body["context"] = serde_json::json!({ "message_id": rt });
         }
+        dry_run::intercept(|| {
+            let reply = reply_to.map(|r| format!(" in reply to {r}")).unwrap_or_default();
+            format!("send to {channel}{reply}: {text:?}")
+        })?;
 
         let resp = self.api_post_json(&url, &body).await?;
 
@@ -312,22 +401,28 @@ impl MessagingPort for WhatsAppAdapter {
             .map(|m| m.id)
             .ok_or_else(|| AgentError::api("whatsapp: no message id in response"))?;
 
-        Ok(Message {
+        let msg = Message {
             id: msg_id,
             platform: Platform::WhatsApp,
             channel_id: channel.to_string(),
+            sender_id: self.phone_number_id.clone(),
             sender: self.phone_number_id.clone(),
             text: text.to_string(),
             timestamp: now_ts(),
             has_attachment: false,
             reply_to: reply_to.map(|s| s.to_string()),
-            meta: MessageMeta::default(),
-        })
+            meta: MessageMeta {
+                delivery: Some(DeliveryState::Pending),
+                ..MessageMeta::default()
+            },
+        };
+        self.track_sent(&msg).await;
+        Ok(msg)
     }
 
     async fn search(
         &self,
-        _query: &str,
+        _query: &SearchQuery,
         _limit: usize,
         _cursor: Option<&str>,
     ) -> Result<Paginated<Message>, AgentError> {
@@ -335,6 +430,26 @@ impl MessagingPort for WhatsAppAdapter {
             "WhatsApp Cloud API does not support message search.",
         ))
     }
+
+    fn unsupported_clauses(&self, _query: &SearchQuery) -> Vec<SearchClause> {
+        Vec::new()
+    }
+
+    async fn get_delivery_status(
+        &self,
+        _channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        let statuses = self.statuses.read().await;
+        statuses.get(message_id).cloned().ok_or_else(|| {
+            let hint = if self.webhook.is_some() {
+                "no status received for it yet"
+            } else {
+                "set WHATSAPP_WEBHOOK_ADDR to receive status webhooks"
+            };
+            AgentError::not_found(format!("no delivery status for {message_id}: {hint}"))
+        })
+    }
 }
 
 #[async_trait]
@@ -345,6 +460,7 @@ impl WhatsAppExt for WhatsAppAdapter {
         file_path: &str,
         caption: &str,
     ) -> Result<Message, AgentError> {
+        validate_recipient(chat)?;
         for component in std::path::Path::new(file_path).components() {
             if matches!(component, std::path::Component::ParentDir) {
                 return Err(AgentError::invalid_input(
@@ -354,6 +470,16 @@ impl WhatsAppExt for WhatsAppAdapter {
         }
 
         let (media_type, mime) = detect_media_type(file_path);
+        if dry_run::active() {
+            let size = tokio::fs::metadata(file_path)
+                .await
+                .map_err(|e| AgentError::invalid_input(format!("cannot read file {file_path}: {e}")))?
+                .len();
+            dry_run::intercept(|| {
+                let caption = if caption.is_empty() { String::new() } else { format!(" with caption {caption:?}") };
+                format!("upload {file_path} ({size} bytes, {mime}) and send it as {media_type} to {chat}{caption}")
+            })?;
+        }
 
         let media_id = self.upload_media(file_path, mime).await?;
 
@@ -385,10 +511,11 @@ impl WhatsAppExt for WhatsAppAdapter {
             .map(|m| m.id)
             .ok_or_else(|| AgentError::api("whatsapp: no message id in response"))?;
 
-        Ok(Message {
+        let msg = Message {
             id: msg_id,
             platform: Platform::WhatsApp,
             channel_id: chat.to_string(),
+            sender_id: self.phone_number_id.clone(),
             sender: self.phone_number_id.clone(),
             text: caption.to_string(),
             timestamp: now_ts(),
@@ -396,8 +523,11 @@ impl WhatsAppExt for WhatsAppAdapter {
             reply_to: None,
             meta: MessageMeta {
                 media_type: Some(media_type.to_string()),
+                delivery: Some(DeliveryState::Pending),
                 ..MessageMeta::default()
             },
-        })
+        };
+        self.track_sent(&msg).await;
+        Ok(msg)
     }
 }
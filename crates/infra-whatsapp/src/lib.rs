@@ -1,3 +1,5 @@
 pub mod adapter;
+pub mod webhook;
 
 pub use adapter::{WhatsAppAdapter, WhatsAppConfig};
+pub use webhook::WebhookConfig;
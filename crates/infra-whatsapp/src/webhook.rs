@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use nexus_domain::{DeliveryState, DeliveryStatus, Platform};
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::net::TcpListener;
+use tokio::sync::{RwLock, Semaphore};
+use tracing::{debug, info, warn};
+
+const MAX_BODY: usize = 1 << 20;
+const MAX_HEADER_BUF: usize = 16 * 1024;
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_CONNECTIONS: usize = 64;
+const STATUS_CAP: usize = 10_000;
+
+pub type StatusStore = Arc<RwLock<Statuses>>;
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub addr: SocketAddr,
+    pub verify_token: String,
+    pub app_secret: String,
+}
+
+// Latest status per message, dropping the oldest tracked message once the
+// cap is reached.
+#[derive(Default)]
+pub struct Statuses {
+    map: HashMap<String, DeliveryStatus>,
+    order: VecDeque<String>,
+}
+
+impl Statuses {
+    pub fn get(&self, message_id: &str) -> Option<&DeliveryStatus> {
+        self.map.get(message_id)
+    }
+
+    pub fn insert(&mut self, status: DeliveryStatus) {
+        if !self.map.contains_key(&status.message_id) {
+            while self.map.len() >= STATUS_CAP {
+                let Some(oldest) = self.order.pop_front() else {
+                    break;
+                };
+                self.map.remove(&oldest);
+            }
+            self.order.push_back(status.message_id.clone());
+        }
+        self.map.insert(status.message_id.clone(), status);
+    }
+
+    pub fn insert_if_absent(&mut self, status: DeliveryStatus) {
+        if !self.map.contains_key(&status.message_id) {
+            self.insert(status);
+        }
+    }
+}
+
+pub async fn serve(cfg: WebhookConfig, statuses: StatusStore) {
+    let listener = match TcpListener::bind(cfg.addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            warn!(addr = %cfg.addr, error = %e, "whatsapp webhook bind failed");
+            return;
+        }
+    };
+    info!(addr = %cfg.addr, "whatsapp webhook listening");
+
+    let cfg = Arc::new(cfg);
+    let slots = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let Ok(permit) = slots.clone().acquire_owned().await else {
+            return;
+        };
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "whatsapp webhook accept failed");
+                continue;
+            }
+        };
+        let cfg = cfg.clone();
+        let statuses = statuses.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let service = service_fn(move |req| {
+                let cfg = cfg.clone();
+                let statuses = statuses.clone();
+                async move { Ok::<_, Infallible>(handle(req, &cfg, &statuses).await) }
+            });
+            let conn = http1::Builder::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(HEADER_TIMEOUT)
+                .max_buf_size(MAX_HEADER_BUF)
+                .keep_alive(false)
+                .serve_connection(TokioIo::new(stream), service);
+            match tokio::time::timeout(CONNECTION_TIMEOUT, conn).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => debug!(%peer, error = %e, "whatsapp webhook request failed"),
+                Err(_) => debug!(%peer, "whatsapp webhook connection timed out"),
+            }
+        });
+    }
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    cfg: &WebhookConfig,
+    statuses: &StatusStore,
+) -> Response<Full<Bytes>> {
+    match *req.method() {
+        Method::GET => match verify(req.uri().query().unwrap_or(""), &cfg.verify_token) {
+            Some(challenge) => respond(StatusCode::OK, challenge),
+            None => respond(StatusCode::FORBIDDEN, String::new()),
+        },
+        Method::POST => {
+            let signature = req
+                .headers()
+                .get("x-hub-signature-256")
+                .and_then(|v| v.to_str().ok())
+                .map(String::from);
+            let body = match Limited::new(req.into_body(), MAX_BODY).collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(e) if e.is::<LengthLimitError>() => {
+                    return respond(StatusCode::PAYLOAD_TOO_LARGE, String::new())
+                }
+                Err(_) => return respond(StatusCode::BAD_REQUEST, String::new()),
+            };
+            if !signature_valid(&cfg.app_secret, signature.as_deref(), &body) {
+                warn!("whatsapp webhook rejected a request with a missing or invalid signature");
+                return respond(StatusCode::UNAUTHORIZED, String::new());
+            }
+            match serde_json::from_slice::<Value>(&body) {
+                Ok(payload) => {
+                    apply_statuses(&payload, statuses).await;
+                    respond(StatusCode::OK, String::new())
+                }
+                Err(_) => respond(StatusCode::BAD_REQUEST, String::new()),
+            }
+        }
+        _ => respond(StatusCode::METHOD_NOT_ALLOWED, String::new()),
+    }
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+}
+
+fn verify(query: &str, verify_token: &str) -> Option<String> {
+    let params: HashMap<&str, &str> = query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .collect();
+    let subscribe = params.get("hub.mode") == Some(&"subscribe");
+    let token_ok = params.get("hub.verify_token") == Some(&verify_token);
+    match params.get("hub.challenge") {
+        Some(challenge) if subscribe && token_ok => Some(challenge.to_string()),
+        _ => None,
+    }
+}
+
+// Meta signs every POST with HMAC-SHA256 of the raw body under the app
+// secret, sent as `X-Hub-Signature-256: sha256=<hex>`.
+fn signature_valid(app_secret: &str, header: Option<&str>, body: &[u8]) -> bool {
+    let Some(expected) = header
+        .and_then(|h| h.strip_prefix("sha256="))
+        .and_then(decode_hex)
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(app_secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+async fn apply_statuses(payload: &Value, statuses: &StatusStore) {
+    let updates: Vec<DeliveryStatus> = payload["entry"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|entry| entry["changes"].as_array().into_iter().flatten())
+        .flat_map(|change| change["value"]["statuses"].as_array().into_iter().flatten())
+        .filter_map(parse_status)
+        .collect();
+    if updates.is_empty() {
+        return;
+    }
+
+    let mut store = statuses.write().await;
+    for update in updates {
+        debug!(id = update.message_id, state = %update.state, "whatsapp status update");
+        // Webhooks can arrive out of order; never move a message backwards.
+        let stale = store
+            .get(&update.message_id)
+            .is_some_and(|cur| update.state != DeliveryState::Failed && cur.state >= update.state);
+        if !stale {
+            store.insert(update);
+        }
+    }
+}
+
+fn parse_status(status: &Value) -> Option<DeliveryStatus> {
+    let state = match status["status"].as_str()? {
+        "sent" => DeliveryState::Sent,
+        "delivered" => DeliveryState::Delivered,
+        "read" => DeliveryState::Read,
+        "failed" => DeliveryState::Failed,
+        _ => return None,
+    };
+    let error = status["errors"][0]
+        .get("title")
+        .and_then(|t| t.as_str())
+        .map(String::from);
+    Some(DeliveryStatus {
+        platform: Platform::WhatsApp,
+        channel_id: status["recipient_id"].as_str().unwrap_or("").to_string(),
+        message_id: status["id"].as_str()?.to_string(),
+        state,
+        error,
+        updated_at: status["timestamp"].as_str().and_then(|t| t.parse().ok()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            unreachable!("hmac accepts any key length")
+        };
+        mac.update(body);
+        let hex: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        format!("sha256={hex}")
+    }
+
+    fn status(id: &str) -> DeliveryStatus {
+        DeliveryStatus {
+            platform: Platform::WhatsApp,
+            channel_id: "1555".into(),
+            message_id: id.into(),
+            state: DeliveryState::Sent,
+            error: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn accepts_only_matching_signature() {
+        let body = br#"{"entry":[]}"#;
+        let header = sign("secret", body);
+        assert!(signature_valid("secret", Some(&header), body));
+        assert!(!signature_valid("other", Some(&header), body));
+        assert!(!signature_valid("secret", Some(&header), b"{}"));
+        assert!(!signature_valid("secret", None, body));
+        assert!(!signature_valid("secret", Some("sha256=zz"), body));
+        assert!(!signature_valid("secret", Some(header.trim_start_matches("sha256=")), body));
+    }
+
+    #[test]
+    fn verify_requires_subscribe_and_token() {
+        let ok = "hub.mode=subscribe&hub.verify_token=t&hub.challenge=42";
+        assert_eq!(verify(ok, "t").as_deref(), Some("42"));
+        assert_eq!(verify(ok, "x"), None);
+        assert_eq!(verify("hub.verify_token=t&hub.challenge=42", "t"), None);
+    }
+
+    #[test]
+    fn evicts_oldest_status_at_cap() {
+        let mut store = Statuses::default();
+        for i in 0..STATUS_CAP {
+            store.insert(status(&i.to_string()));
+        }
+        store.insert(status("0"));
+        assert_eq!(store.map.len(), STATUS_CAP);
+        store.insert(status("new"));
+        assert_eq!(store.map.len(), STATUS_CAP);
+        assert!(store.get("0").is_none());
+        assert!(store.get("1").is_some());
+        assert!(store.get("new").is_some());
+    }
+}
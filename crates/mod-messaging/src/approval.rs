@@ -0,0 +1,373 @@
+// Human approval for outbound messages. On platforms that need it, sends
+// are held in a queue instead of going out: one JSON file per message in
+// the data dir, so `nexus approve` and the server can both work on it. A
+// reviewer approves, rejects or edits each message, and the running server
+// sends approved ones through the usual adapter. The agent polls the
+// outcome by ID.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use nexus_domain::{dry_run, Platform};
+use nexus_error::AgentError;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::service::{validate_not_empty, AgentService};
+use crate::time::now_ts;
+
+// Decided messages stay visible this long so the agent can see what happened.
+const KEEP_DECIDED: i64 = 7 * 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalState {
+    Pending,
+    // Approved and waiting for the server to send it.
+    Approved,
+    // Handed to the platform; still in this state after a restart, it may
+    // or may not have gone out.
+    Sending,
+    Sent,
+    Rejected,
+    Failed,
+}
+
+impl std::fmt::Display for ApprovalState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Sending => "sending",
+            Self::Sent => "sent",
+            Self::Rejected => "rejected",
+            Self::Failed => "failed",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMessage {
+    pub id: String,
+    pub platform: Platform,
+    pub channel: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    pub state: ApprovalState,
+    pub created_at: i64,
+    // The agent's text, when a reviewer changed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct ApprovalQueue {
+    dir: PathBuf,
+    platforms: HashSet<Platform>,
+    // Serializes this process's changes; the reviewer only touches pending
+    // messages and the server only sends approved ones, so the two
+    // processes never rewrite the same state.
+    lock: Mutex<()>,
+}
+
+impl ApprovalQueue {
+    pub fn open(dir: &Path) -> Result<Self, AgentError> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            AgentError::internal(format!("failed to create approval queue {}: {e}", dir.display()))
+        })?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            platforms: HashSet::new(),
+            lock: Mutex::new(()),
+        })
+    }
+
+    // Messages that were being sent when the last server stopped may or may
+    // not have gone out, so they are marked failed. Only for the server: to a
+    // reviewer, a message is sending while the server sends it.
+    pub fn recover(&self) -> Result<(), AgentError> {
+        let _guard = self.lock()?;
+        for mut item in self.list()?.into_iter().filter(|i| i.state == ApprovalState::Sending) {
+            item.state = ApprovalState::Failed;
+            item.error = Some("interrupted while sending; check the chat before sending again".into());
+            self.save(&item)?;
+        }
+        Ok(())
+    }
+
+    // A queue opened only to review messages holds no platforms.
+    pub fn for_platforms(mut self, platforms: impl IntoIterator<Item = Platform>) -> Self {
+        self.platforms.extend(platforms);
+        self
+    }
+
+    pub fn covers(&self, platform: Platform) -> bool {
+        self.platforms.contains(&platform)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, ()>, AgentError> {
+        self.lock
+            .lock()
+            .map_err(|_| AgentError::internal("approval queue lock poisoned"))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn save(&self, item: &PendingMessage) -> Result<(), AgentError> {
+        let path = self.path(&item.id);
+        let fail = |e: std::io::Error| {
+            AgentError::internal(format!("failed to write {}: {e}", path.display()))
+        };
+        let json = serde_json::to_vec_pretty(item)
+            .map_err(|e| AgentError::internal(format!("failed to encode pending message: {e}")))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(fail)?;
+        std::fs::rename(&tmp, &path).map_err(fail)
+    }
+
+    pub fn add(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+    ) -> Result<PendingMessage, AgentError> {
+        dry_run::intercept(|| format!("hold for approval: send to {platform} {channel}: {text:?}"))?;
+        let _guard = self.lock()?;
+        let mut item = PendingMessage {
+            id: String::new(),
+            platform,
+            channel: channel.to_string(),
+            text: text.to_string(),
+            reply_to: reply_to.map(str::to_string),
+            state: ApprovalState::Pending,
+            created_at: now_ts(),
+            original_text: None,
+            decided_at: None,
+            reason: None,
+            message_id: None,
+            error: None,
+        };
+        // The file is created empty first to claim the ID.
+        loop {
+            item.id = format!("apr-{:08x}", fastrand::u32(..));
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(self.path(&item.id)) {
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(AgentError::internal(format!(
+                        "failed to write to approval queue {}: {e}",
+                        self.dir.display()
+                    )))
+                }
+            }
+        }
+        self.save(&item)?;
+        Ok(item)
+    }
+
+    pub fn get(&self, id: &str) -> Result<PendingMessage, AgentError> {
+        let not_found = || {
+            AgentError::not_found(format!("no pending message {id}"))
+                .with_argument("id")
+                .with_suggestion("Use get_approval_status without an id to list pending messages")
+        };
+        let valid = id.strip_prefix("apr-").is_some_and(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()));
+        if !valid {
+            return Err(not_found());
+        }
+        let bytes = match std::fs::read(self.path(id)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(not_found()),
+            Err(e) => {
+                return Err(AgentError::internal(format!("failed to read pending message {id}: {e}")))
+            }
+        };
+        serde_json::from_slice(&bytes)
+            .map_err(|e| AgentError::internal(format!("corrupt pending message {id}: {e}")))
+    }
+
+    pub fn list(&self) -> Result<Vec<PendingMessage>, AgentError> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| {
+            AgentError::internal(format!("failed to read approval queue {}: {e}", self.dir.display()))
+        })?;
+        let mut items = Vec::new();
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            // Skips files still being claimed by `add`, as well as damaged ones.
+            if let Ok(Ok(item)) = std::fs::read(&path).map(|b| serde_json::from_slice::<PendingMessage>(&b)) {
+                items.push(item);
+            }
+        }
+        items.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(items)
+    }
+
+    // Applies a reviewer's decision to a message still pending.
+    fn decide(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut PendingMessage),
+    ) -> Result<PendingMessage, AgentError> {
+        let _guard = self.lock()?;
+        let mut item = self.get(id)?;
+        if item.state != ApprovalState::Pending {
+            return Err(AgentError::invalid_input(format!("{id} is already {}", item.state)));
+        }
+        change(&mut item);
+        self.save(&item)?;
+        Ok(item)
+    }
+
+    // The running server sends it on its next pass.
+    pub fn approve(&self, id: &str) -> Result<PendingMessage, AgentError> {
+        self.decide(id, |item| {
+            item.state = ApprovalState::Approved;
+            item.decided_at = Some(now_ts());
+        })
+    }
+
+    pub fn reject(&self, id: &str, reason: Option<&str>) -> Result<PendingMessage, AgentError> {
+        self.decide(id, |item| {
+            item.state = ApprovalState::Rejected;
+            item.decided_at = Some(now_ts());
+            item.reason = reason.map(str::to_string);
+        })
+    }
+
+    // The agent's text is kept as `original_text`.
+    pub fn edit(&self, id: &str, text: &str) -> Result<PendingMessage, AgentError> {
+        validate_not_empty(text, "text")?;
+        self.decide(id, |item| {
+            if item.original_text.is_none() {
+                item.original_text = Some(item.text.clone());
+            }
+            item.text = text.to_string();
+        })
+    }
+
+    // Claims the approved messages, marking them sending, and forgets
+    // decided ones past keeping.
+    fn take_approved(&self, now: i64) -> Result<Vec<PendingMessage>, AgentError> {
+        let _guard = self.lock()?;
+        let mut approved = Vec::new();
+        for mut item in self.list()? {
+            match item.state {
+                ApprovalState::Approved => {
+                    item.state = ApprovalState::Sending;
+                    self.save(&item)?;
+                    approved.push(item);
+                }
+                ApprovalState::Sent | ApprovalState::Rejected | ApprovalState::Failed
+                    if item.decided_at.unwrap_or(item.created_at) < now - KEEP_DECIDED =>
+                {
+                    if let Err(e) = std::fs::remove_file(self.path(&item.id)) {
+                        warn!(id = %item.id, error = %e, "could not remove old pending message");
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(approved)
+    }
+
+    fn finish(&self, id: &str, result: Result<String, String>) -> Result<(), AgentError> {
+        let _guard = self.lock()?;
+        let mut item = self.get(id)?;
+        match result {
+            Ok(message_id) => {
+                item.state = ApprovalState::Sent;
+                item.message_id = Some(message_id);
+            }
+            Err(error) => {
+                item.state = ApprovalState::Failed;
+                item.error = Some(error);
+            }
+        }
+        self.save(&item)
+    }
+}
+
+impl AgentService {
+    pub fn needs_approval(&self, platform: Platform) -> bool {
+        self.approval_queue().is_some_and(|q| q.covers(platform))
+    }
+
+    pub async fn submit_message(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+    ) -> Result<PendingMessage, AgentError> {
+        validate_not_empty(channel, "channel")?;
+        validate_not_empty(text, "text")?;
+        self.get(platform)?;
+        let queue = self.approval_queue().ok_or_else(|| {
+            AgentError::not_implemented("the approval queue is not running")
+        })?;
+        let item = queue.add(platform, channel, text, reply_to)?;
+        info!(%platform, channel, id = %item.id, "message held for approval");
+        Ok(item)
+    }
+
+    // Sends the approved messages one at a time.
+    async fn send_approved(&self, queue: &ApprovalQueue) -> Result<(), AgentError> {
+        for item in queue.take_approved(now_ts())? {
+            let send = async {
+                let sent = self
+                    .send_message(item.platform, &item.channel, &item.text, item.reply_to.as_deref())
+                    .await;
+                match sent {
+                    Ok(msg) => {
+                        info!(platform = %item.platform, id = %item.id, msg_id = %msg.id, "sent approved message");
+                        Ok(msg.id)
+                    }
+                    Err(e) => {
+                        warn!(platform = %item.platform, id = %item.id, error = %e, "approved message failed");
+                        Err(e.to_string())
+                    }
+                }
+            };
+            let arguments = json!({
+                "id": item.id,
+                "channel": item.channel,
+                "text": item.text,
+                "reply_to": item.reply_to,
+            });
+            let result = self.record_send("approved_send", item.platform, arguments, send).await;
+            queue.finish(&item.id, result)?;
+        }
+        Ok(())
+    }
+}
+
+// Sends approved messages every `every` until the process exits.
+pub async fn run_approvals(agent: Arc<AgentService>, every: Duration) {
+    let Some(queue) = agent.approval_queue().cloned() else {
+        return;
+    };
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        if let Err(e) = agent.send_approved(&queue).await {
+            warn!(error = %e, "approval queue run failed");
+        }
+    }
+}
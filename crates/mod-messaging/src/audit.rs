@@ -0,0 +1,892 @@
+// Audit log. Every write tool call is appended as JSON lines to a file
+// under NEXUS_DATA_DIR/audit: a `started` line with the client that made
+// it, the tool and its arguments before it runs, then a line with the same
+// call ID saying what the adapters were asked to change and how it ended. A
+// call with no second line was cut short, and may or may not have happened.
+// Files roll over each UTC day and, past an optional size cap, within the
+// day. With chaining on, each record carries the SHA-256 of its own line
+// and of the record before it, so editing or removing a line shows up
+// when the log is verified.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use nexus_domain::{Channel, Message, Platform, ScheduledMessage, TimeRange};
+use nexus_error::{AgentError, ErrorResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{error, warn};
+
+use crate::middleware::{Call, Reply};
+use crate::service::AgentService;
+use crate::policy::glob;
+
+// Longest result text kept in a record, in characters.
+const RESULT_CHARS: usize = 2000;
+
+// Argument names whose values never reach the log, alone or as the last
+// `_`-separated part of a name like `access_token`. Cursors named like
+// tokens are kept.
+const SECRET_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "credentials",
+];
+const CURSOR_KEYS: &[&str] = &["page_token", "next_page_token"];
+
+// How long an append waits for another process's lock, and when a lock
+// left behind by a process that died is taken over.
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+const LOCK_STALE: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    static ACTIONS: Arc<Mutex<Vec<Action>>>;
+}
+
+// The MCP client, as it named itself in `initialize`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Client {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+}
+
+// One write an adapter was asked to make while serving a tool call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    pub platform: Platform,
+    pub operation: String,
+    // The chat, channel or recipient as the call named it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    // The chat or channel ID it resolved to, when the reply says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub message_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Action {
+    fn new(call: &Call, result: &Result<Reply, AgentError>) -> Self {
+        let mut action = Self {
+            platform: call.platform,
+            operation: call.operation.to_string(),
+            target: call.target.clone(),
+            resolved: None,
+            message_ids: Vec::new(),
+            error: None,
+        };
+        match result {
+            Ok(reply) => {
+                if let Some(msg) = reply.downcast_ref::<Message>() {
+                    action.resolved = Some(msg.channel_id.clone());
+                    action.message_ids.push(msg.id.clone());
+                } else if let Some(msgs) = reply.downcast_ref::<Vec<Message>>() {
+                    action.resolved = msgs.first().map(|m| m.channel_id.clone());
+                    action.message_ids = msgs.iter().map(|m| m.id.clone()).collect();
+                } else if let Some(job) = reply.downcast_ref::<ScheduledMessage>() {
+                    action.resolved = Some(job.channel_id.clone());
+                    action.message_ids.push(job.id.clone());
+                } else if let Some(channel) = reply.downcast_ref::<Channel>() {
+                    action.resolved = Some(channel.id.clone());
+                }
+            }
+            Err(e) => action.error = Some(ErrorResponse::from(e).code.to_string()),
+        }
+        action
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    // The call is about to run; another record says how it ended.
+    Started,
+    Ok,
+    Error,
+    // Refused by the access policy before anything ran.
+    Denied,
+    DryRun,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Started => "started",
+            Self::Ok => "ok",
+            Self::Error => "error",
+            Self::Denied => "denied",
+            Self::DryRun => "dry_run",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    // When the call arrived, in Unix seconds.
+    pub ts: i64,
+    // The same moment in UTC, to the millisecond.
+    pub time: String,
+    // Shared by a call's started and outcome records.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub call: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<Client>,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    pub arguments: Value,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<Action>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    // Must stay last: it is appended to the line it hashes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip)]
+    started_ms: i64,
+}
+
+impl Record {
+    // Starts a record for a call arriving now. Secret-looking arguments
+    // are masked.
+    pub fn new(tool: &str, arguments: &Value) -> Self {
+        let now_ms = now_ms();
+        let mut arguments = arguments.clone();
+        scrub(&mut arguments);
+        Self {
+            ts: now_ms / 1000,
+            time: format_time(now_ms),
+            call: format!("{:016x}", fastrand::u64(..)),
+            client: None,
+            tool: tool.to_string(),
+            platform: None,
+            arguments,
+            actions: Vec::new(),
+            outcome: Outcome::Ok,
+            result: None,
+            error: None,
+            duration_ms: 0,
+            prev: None,
+            hash: None,
+            started_ms: now_ms,
+        }
+    }
+
+    pub fn finish(&mut self, outcome: Outcome, result: &Result<String, String>) {
+        self.outcome = outcome;
+        match result {
+            Ok(text) => self.result = Some(text.chars().take(RESULT_CHARS).collect()),
+            Err(e) => self.error = Some(e.clone()),
+        }
+        self.duration_ms = (now_ms() - self.started_ms).max(0) as u64;
+    }
+
+    // The record written before the call runs.
+    fn started(&self) -> Self {
+        Self {
+            outcome: Outcome::Started,
+            ..self.clone()
+        }
+    }
+
+    // Whether the call touched `platform`, directly or through a fan-out.
+    pub fn touches(&self, platform: Platform) -> bool {
+        self.platform == Some(platform) || self.actions.iter().any(|a| a.platform == platform)
+    }
+}
+
+// Runs `fut` and returns its output with the writes the adapters were
+// asked to make along the way, as recorded by `AuditLayer`.
+pub async fn scope<F: Future>(fut: F) -> (F::Output, Vec<Action>) {
+    let actions = Arc::new(Mutex::new(Vec::new()));
+    let output = ACTIONS.scope(actions.clone(), fut).await;
+    let actions = actions.lock().map(|mut a| std::mem::take(&mut *a)).unwrap_or_default();
+    (output, actions)
+}
+
+pub(crate) fn observe(call: &Call, result: &Result<Reply, AgentError>) {
+    let _ = ACTIONS.try_with(|actions| {
+        if let Ok(mut actions) = actions.lock() {
+            actions.push(Action::new(call, result));
+        }
+    });
+}
+
+// Masks the values of secret-looking keys, at any depth.
+fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret(key) {
+                    *value = Value::String("[REDACTED]".to_string());
+                } else {
+                    scrub(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(scrub),
+        _ => {}
+    }
+}
+
+// Compared in snake case, so `accessToken` and `Access-Token` read as
+// `access_token`.
+fn is_secret(key: &str) -> bool {
+    let mut snake = String::with_capacity(key.len() + 4);
+    let mut prev_lower = false;
+    for c in key.chars() {
+        if c.is_uppercase() && prev_lower {
+            snake.push('_');
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        snake.extend(c.to_lowercase());
+    }
+    let snake = snake.replace(['-', ' ', '.'], "_");
+    !CURSOR_KEYS.contains(&snake.as_str())
+        && SECRET_KEYS.iter().any(|s| {
+            snake == *s || snake.strip_suffix(s).is_some_and(|head| head.ends_with('_'))
+        })
+}
+
+#[derive(Default)]
+struct State {
+    // The day and part of the file last written, and its size.
+    file: Option<(String, u32)>,
+    size: u64,
+    last_hash: Option<String>,
+}
+
+pub struct AuditLog {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    chain: bool,
+    state: Mutex<State>,
+}
+
+impl AuditLog {
+    // Picks up where the newest file ends, so a chain carries on across
+    // restarts.
+    pub fn open(dir: &Path) -> Result<Self, AgentError> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            AgentError::internal(format!("failed to create audit dir {}: {e}", dir.display()))
+        })?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes: None,
+            chain: false,
+            state: Mutex::new(tail(dir)?),
+        })
+    }
+
+    // Starts a new file within the day once the current one would pass
+    // `bytes`.
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    pub fn chained(mut self, chain: bool) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, State>, AgentError> {
+        self.state
+            .lock()
+            .map_err(|_| AgentError::internal("audit log lock poisoned"))
+    }
+
+    pub fn append(&self, mut record: Record) -> Result<(), AgentError> {
+        let mut state = self.lock()?;
+        // `nexus approve` writes to the same log as the server, so another
+        // process may have added records since this one last did. The file
+        // lock keeps both from chaining onto the same tail.
+        let _held = FileLock::acquire(&self.dir.join("audit.lock"))?;
+        if let Some(newest) = files(&self.dir)?.pop() {
+            let moved = state.file.as_ref() != Some(&(newest.day.clone(), newest.part))
+                || state.size != file_size(&newest.path);
+            if moved {
+                *state = tail(&self.dir)?;
+            }
+        }
+        record.prev = if self.chain { state.last_hash.clone() } else { None };
+        record.hash = None;
+        let mut line = serde_json::to_string(&record)
+            .map_err(|e| AgentError::internal(format!("failed to encode audit record: {e}")))?;
+        let hash = self.chain.then(|| sha256_hex(&line));
+        if let Some(ref hash) = hash {
+            line.pop();
+            line.push_str(&format!(",\"hash\":\"{hash}\"}}"));
+        }
+        line.push('\n');
+
+        let day = record.time.get(..10).unwrap_or_default().to_string();
+        let (mut file_day, mut part) = state.file.clone().unwrap_or_else(|| (day.clone(), 0));
+        if file_day != day {
+            (file_day, part) = (day, 0);
+            state.size = file_size(&self.dir.join(file_name(&file_day, part)));
+        }
+        if let Some(max) = self.max_bytes {
+            while state.size > 0 && state.size + line.len() as u64 > max {
+                part += 1;
+                state.size = file_size(&self.dir.join(file_name(&file_day, part)));
+            }
+        }
+        let path = self.dir.join(file_name(&file_day, part));
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| {
+                f.write_all(line.as_bytes())?;
+                f.flush()
+            });
+        if let Err(e) = written {
+            return Err(AgentError::internal(format!(
+                "failed to write audit log {}: {e}",
+                path.display()
+            )));
+        }
+        state.size += line.len() as u64;
+        state.file = Some((file_day, part));
+        state.last_hash = hash;
+        Ok(())
+    }
+}
+
+impl AuditLog {
+    // Writes a started record before `call` runs and an outcome record after,
+    // with the writes it asked the adapters to make. A failure is recorded as
+    // an error whatever `outcome` says, and a record that cannot be written
+    // does not hold back the result.
+    pub async fn record<F>(&self, mut record: Record, outcome: Outcome, call: F) -> Result<String, String>
+    where
+        F: Future<Output = Result<String, String>>,
+    {
+        if let Err(e) = self.append(record.started()) {
+            error!(tool = %record.tool, error = %e, "audit record not written");
+        }
+        let (result, actions) = scope(call).await;
+        record.actions = actions;
+        let outcome = match (&result, outcome) {
+            (Err(_), Outcome::Ok | Outcome::DryRun) => Outcome::Error,
+            (_, outcome) => outcome,
+        };
+        record.finish(outcome, &result);
+        let tool = record.tool.clone();
+        if let Err(e) = self.append(record) {
+            error!(tool, error = %e, "audit record not written");
+        }
+        result
+    }
+}
+
+impl AgentService {
+    // Records a send the service makes on its own, outside any tool call,
+    // the way the server records tool calls.
+    pub(crate) async fn record_send<F>(
+        &self,
+        tool: &str,
+        platform: Platform,
+        arguments: Value,
+        send: F,
+    ) -> Result<String, String>
+    where
+        F: Future<Output = Result<String, String>>,
+    {
+        let Some(log) = self.audit_log() else {
+            return send.await;
+        };
+        let mut record = Record::new(tool, &arguments);
+        record.platform = Some(platform);
+        log.record(record, Outcome::Ok, send).await
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Query {
+    pub range: TimeRange,
+    // A tool name, or a glob like `gmail_*`.
+    pub tool: Option<String>,
+    pub platform: Option<Platform>,
+    // Only calls that failed or were denied.
+    pub errors: bool,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    fn matches(&self, record: &Record) -> bool {
+        self.range.contains(record.ts)
+            && self.tool.as_deref().map_or(true, |t| glob(t, &record.tool))
+            && self.platform.map_or(true, |p| record.touches(p))
+            && (!self.errors || matches!(record.outcome, Outcome::Error | Outcome::Denied))
+    }
+}
+
+// Oldest first. A call that ended shows only as its outcome record; lines
+// that do not parse are skipped with a warning.
+pub fn read(dir: &Path, query: &Query) -> Result<Vec<Record>, AgentError> {
+    let mut records = Vec::new();
+    // Calls that ended; their started records are left out.
+    let mut ended = HashSet::new();
+    for file in files(dir)? {
+        let text = std::fs::read_to_string(&file.path).map_err(|e| read_error(&file.path, e))?;
+        for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            match serde_json::from_str::<Record>(line) {
+                Ok(record) => {
+                    if record.outcome != Outcome::Started && !record.call.is_empty() {
+                        ended.insert(record.call.clone());
+                    }
+                    if query.matches(&record) {
+                        records.push(record);
+                    }
+                }
+                Err(e) => warn!(file = %file.path.display(), line = n + 1, error = %e, "skipping unreadable audit record"),
+            }
+        }
+    }
+    records.retain(|r| r.outcome != Outcome::Started || !ended.contains(&r.call));
+    if let Some(limit) = query.limit {
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+    }
+    Ok(records)
+}
+
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub records: usize,
+    pub chained: usize,
+    // Where the log does not check out, as `file:line: what`.
+    pub problems: Vec<String>,
+}
+
+// A log with no problems can still have lost records from its end.
+pub fn verify(dir: &Path) -> Result<Verification, AgentError> {
+    let mut report = Verification::default();
+    // The previous record's hash; None after an unchained record.
+    let mut last: Option<String> = None;
+    for file in files(dir)? {
+        let text = std::fs::read_to_string(&file.path).map_err(|e| read_error(&file.path, e))?;
+        let name = file.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let mut problem = |what: &str| report.problems.push(format!("{name}:{}: {what}", n + 1));
+            let record: Record = match serde_json::from_str(line) {
+                Ok(r) => r,
+                Err(e) => {
+                    problem(&format!("not a valid record ({e})"));
+                    last = None;
+                    continue;
+                }
+            };
+            let Some(hash) = record.hash else {
+                if last.is_some() {
+                    problem("unchained record after chained ones");
+                }
+                report.records += 1;
+                last = None;
+                continue;
+            };
+            let suffix = format!(",\"hash\":\"{hash}\"}}");
+            match line.strip_suffix(&suffix) {
+                Some(body) if sha256_hex(&format!("{body}}}")) == hash => {}
+                _ => problem("hash does not match the record; it was changed after it was written"),
+            }
+            match (&record.prev, &last) {
+                (Some(prev), Some(last)) if prev == last => {}
+                (None, None) => {}
+                (None, Some(_)) => problem("chain restarts here; records before it may have been removed"),
+                _ => problem("prev does not match the record before; records were removed or reordered"),
+            }
+            report.records += 1;
+            report.chained += 1;
+            last = Some(hash);
+        }
+    }
+    Ok(report)
+}
+
+// A lock shared with other processes writing the log: the file exists
+// while it is held.
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<Self, AgentError> {
+        let deadline = Instant::now() + LOCK_WAIT;
+        loop {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(_) => return Ok(Self(path.to_path_buf())),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(AgentError::internal(format!(
+                        "failed to lock audit log {}: {e}",
+                        path.display()
+                    )))
+                }
+            }
+            let age = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok());
+            if age.is_some_and(|a| a > LOCK_STALE) {
+                warn!(path = %path.display(), "removing stale audit log lock");
+                let _ = std::fs::remove_file(path);
+                continue;
+            }
+            if Instant::now() >= deadline {
+                return Err(AgentError::internal(format!(
+                    "audit log {} is locked by another process",
+                    path.display()
+                ))
+                .with_suggestion("Remove the lock file if no other nexus process is running"));
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+struct LogFile {
+    day: String,
+    part: u32,
+    path: PathBuf,
+}
+
+// audit-2026-10-18.jsonl, then audit-2026-10-18.1.jsonl and so on.
+fn file_name(day: &str, part: u32) -> String {
+    match part {
+        0 => format!("audit-{day}.jsonl"),
+        n => format!("audit-{day}.{n}.jsonl"),
+    }
+}
+
+// The log's files in the order they were written.
+fn files(dir: &Path) -> Result<Vec<LogFile>, AgentError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(read_error(dir, e)),
+    };
+    let mut files: Vec<LogFile> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let stem = name.strip_prefix("audit-")?.strip_suffix(".jsonl")?;
+            let (day, part) = match stem.split_once('.') {
+                Some((day, part)) => (day, part.parse().ok()?),
+                None => (stem, 0),
+            };
+            Some(LogFile {
+                day: day.to_string(),
+                part,
+                path,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| (&a.day, a.part).cmp(&(&b.day, b.part)));
+    Ok(files)
+}
+
+// Where the newest file ends, so a chain carries on from its last record.
+fn tail(dir: &Path) -> Result<State, AgentError> {
+    let mut state = State::default();
+    if let Some(last) = files(dir)?.pop() {
+        let text = std::fs::read_to_string(&last.path).map_err(|e| read_error(&last.path, e))?;
+        state.size = text.len() as u64;
+        state.last_hash = text
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .and_then(|l| serde_json::from_str::<Value>(l).ok())
+            .and_then(|v| v.get("hash")?.as_str().map(str::to_string));
+        state.file = Some((last.day, last.part));
+    }
+    Ok(state)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> AgentError {
+    AgentError::internal(format!("failed to read audit log {}: {e}", path.display()))
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn format_time(ms: i64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ms) {
+        Some(dt) => dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        None => ms.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("nexus-audit-{:016x}", fastrand::u64(..)));
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn chained_log(dir: &Path) -> AuditLog {
+        match AuditLog::open(dir) {
+            Ok(log) => log.chained(true),
+            Err(e) => unreachable!("temp dir is writable: {e}"),
+        }
+    }
+
+    fn write(log: &AuditLog, tool: &str) {
+        let mut record = Record::new(tool, &serde_json::json!({ "channel": "#general" }));
+        record.finish(Outcome::Ok, &Ok("sent".to_string()));
+        assert!(log.append(record).is_ok());
+    }
+
+    fn check(dir: &Path) -> Verification {
+        match verify(dir) {
+            Ok(report) => report,
+            Err(e) => unreachable!("log is readable: {e}"),
+        }
+    }
+
+    fn lines(dir: &Path) -> Vec<(PathBuf, String)> {
+        let Ok(files) = files(dir) else {
+            unreachable!("log is readable")
+        };
+        files
+            .into_iter()
+            .flat_map(|f| {
+                let text = std::fs::read_to_string(&f.path).unwrap_or_default();
+                let lines: Vec<String> = text.lines().map(str::to_string).collect();
+                lines.into_iter().map(move |l| (f.path.clone(), l))
+            })
+            .collect()
+    }
+
+    fn rewrite(dir: &Path, edit: impl Fn(Vec<String>) -> Vec<String>) {
+        let all = lines(dir);
+        let Some((path, _)) = all.first() else {
+            unreachable!("log has records")
+        };
+        let edited = edit(all.iter().map(|(_, l)| l.clone()).collect());
+        assert!(std::fs::write(path, edited.join("\n") + "\n").is_ok());
+    }
+
+    #[test]
+    fn intact_chain_verifies() {
+        let dir = TempDir::new();
+        let log = chained_log(&dir.0);
+        for tool in ["send_message", "edit_message", "delete_message"] {
+            write(&log, tool);
+        }
+        let report = check(&dir.0);
+        assert_eq!((report.records, report.chained), (3, 3));
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn edited_record_fails_its_hash() {
+        let dir = TempDir::new();
+        let log = chained_log(&dir.0);
+        write(&log, "send_message");
+        write(&log, "send_message");
+        rewrite(&dir.0, |mut lines| {
+            lines[1] = lines[1].replace("#general", "#random");
+            lines
+        });
+        let report = check(&dir.0);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0]
+            .ends_with(":2: hash does not match the record; it was changed after it was written"));
+    }
+
+    #[test]
+    fn removed_record_breaks_the_chain() {
+        let dir = TempDir::new();
+        let log = chained_log(&dir.0);
+        for _ in 0..3 {
+            write(&log, "send_message");
+        }
+        rewrite(&dir.0, |mut lines| {
+            lines.remove(1);
+            lines
+        });
+        let report = check(&dir.0);
+        assert_eq!(report.records, 2);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains(":2: prev does not match"));
+
+        // The oldest record gone too: the first one left still points back.
+        rewrite(&dir.0, |mut lines| {
+            lines.remove(0);
+            lines
+        });
+        let report = check(&dir.0);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains(":1: prev does not match"));
+    }
+
+    #[test]
+    fn chain_carries_across_files_and_writers() {
+        let dir = TempDir::new();
+        let log = chained_log(&dir.0).max_bytes(1);
+        write(&log, "send_message");
+        write(&log, "send_message");
+        // Another process, such as `nexus approve`, writing to the same log.
+        let other = chained_log(&dir.0);
+        write(&other, "approve_message");
+        write(&log, "send_message");
+
+        let files = lines(&dir.0);
+        assert!(files.iter().map(|(p, _)| p).collect::<HashSet<_>>().len() >= 3);
+        let report = check(&dir.0);
+        assert_eq!(report.chained, 4);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn scrubs_whole_secret_names_only() {
+        for key in [
+            "access_token",
+            "token",
+            "accessToken",
+            "X-Api-Key",
+            "client_secret",
+            "Password",
+            "authorization",
+        ] {
+            assert!(is_secret(key), "{key}");
+        }
+        for key in [
+            "max_tokens",
+            "token_count",
+            "page_token",
+            "pageToken",
+            "next_page_token",
+            "tokenizer",
+            "secretary",
+            "auth",
+        ] {
+            assert!(!is_secret(key), "{key}");
+        }
+        let record = Record::new(
+            "send_message",
+            &serde_json::json!({ "max_tokens": 5, "access_token": "t" }),
+        );
+        assert_eq!(record.arguments["max_tokens"], 5);
+        assert_eq!(record.arguments["access_token"], "[REDACTED]");
+    }
+
+    #[test]
+    fn concurrent_writers_keep_one_chain() {
+        let dir = TempDir::new();
+        let logs = [Arc::new(chained_log(&dir.0)), Arc::new(chained_log(&dir.0))];
+        let writers: Vec<_> = logs
+            .iter()
+            .map(|log| {
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        write(&log, "send_message");
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            assert!(writer.join().is_ok());
+        }
+        let report = check(&dir.0);
+        assert_eq!(report.chained, 100);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert!(!dir.0.join("audit.lock").exists());
+    }
+
+    #[test]
+    fn unchained_records_verify_without_hashes() {
+        let dir = TempDir::new();
+        let Ok(log) = AuditLog::open(&dir.0) else {
+            unreachable!("temp dir is writable")
+        };
+        write(&log, "send_message");
+        let report = check(&dir.0);
+        assert_eq!((report.records, report.chained), (1, 0));
+        assert!(report.problems.is_empty());
+    }
+
+    #[test]
+    fn secrets_are_masked_and_ended_calls_read_once() {
+        let dir = TempDir::new();
+        let log = chained_log(&dir.0);
+        let args = serde_json::json!({ "to": "ann", "api_key": "k", "auth": { "Password": "p" } });
+        let mut record = Record::new("gmail_send_email", &args);
+        assert_eq!(record.arguments["api_key"], "[REDACTED]");
+        assert_eq!(record.arguments["auth"]["Password"], "[REDACTED]");
+        assert_eq!(record.arguments["to"], "ann");
+
+        assert!(log.append(record.started()).is_ok());
+        record.finish(Outcome::Error, &Err("timed out".to_string()));
+        assert!(log.append(record).is_ok());
+        let unfinished = Record::new("send_message", &serde_json::json!({}));
+        assert!(log.append(unfinished.started()).is_ok());
+
+        let Ok(records) = read(&dir.0, &Query::default()) else {
+            unreachable!("log is readable")
+        };
+        let outcomes: Vec<Outcome> = records.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, [Outcome::Error, Outcome::Started]);
+        assert_eq!(check(&dir.0).chained, 3);
+    }
+}
@@ -0,0 +1,376 @@
+// One announcement to many chats. The text is written once in a small
+// Markdown subset and rendered for each platform: Slack and WhatsApp get
+// their own markup, Discord takes Markdown as is, and Telegram and Gmail,
+// which send_message posts as plain text, get the markers stripped.
+
+use std::collections::HashSet;
+
+use futures::{stream, StreamExt};
+use nexus_domain::Platform;
+use nexus_error::{AgentError, ErrorResponse};
+use serde::Serialize;
+use tracing::info;
+
+use crate::outbox::fingerprint;
+use crate::service::{validate_not_empty, AgentService};
+
+const MAX_TARGETS: usize = 50;
+const SEND_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct BroadcastTarget {
+    pub platform: Platform,
+    pub channel: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BroadcastResult {
+    pub platform: Platform,
+    pub channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+    // In a dry run, what sending to this target would do.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    // Where the platform needs approval, the held message's ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BroadcastReport {
+    pub sent: usize,
+    pub failed: usize,
+    // Targets a dry run stopped short of sending to.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub planned: usize,
+    // Targets held for a reviewer's approval.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pending: usize,
+    pub results: Vec<BroadcastResult>,
+}
+
+impl AgentService {
+    // A failed target does not stop the others, and repeated targets are sent
+    // to once. With an idempotency key each target is sent to at most once,
+    // so repeating a broadcast only retries the targets that failed.
+    pub async fn broadcast(
+        &self,
+        targets: &[BroadcastTarget],
+        text: &str,
+        key: Option<&str>,
+    ) -> Result<BroadcastReport, AgentError> {
+        validate_not_empty(text, "text")?;
+        if targets.is_empty() {
+            return Err(AgentError::invalid_input("no targets given").with_argument("targets"));
+        }
+        let mut seen = HashSet::new();
+        let targets: Vec<&BroadcastTarget> = targets.iter().filter(|t| seen.insert(*t)).collect();
+        if targets.len() > MAX_TARGETS {
+            return Err(AgentError::invalid_input(format!(
+                "{} targets is more than the {MAX_TARGETS} a broadcast can reach",
+                targets.len()
+            ))
+            .with_argument("targets"));
+        }
+
+        let sends = targets.into_iter().map(|t| async move {
+            let body = render_text(t.platform, text);
+            let target_key = key.map(|key| format!("{key}/{}:{}", t.platform, t.channel));
+            // The sent message's ID, or the held message's.
+            let sent = if self.needs_approval(t.platform) {
+                let submit = || self.submit_message(t.platform, &t.channel, &body, None);
+                match target_key {
+                    Some(ref key) => {
+                        let platform = t.platform.to_string();
+                        let request = fingerprint(&["submit_message", &platform, &t.channel, &body]);
+                        self.once(key, &request, submit).await
+                    }
+                    None => submit().await,
+                }
+                .map(|held| (None, Some(held.id)))
+            } else {
+                match target_key {
+                    Some(ref key) => {
+                        self.send_message_once(t.platform, &t.channel, &body, None, key)
+                            .await
+                    }
+                    None => self.send_message(t.platform, &t.channel, &body, None).await,
+                }
+                .map(|msg| (Some(msg.id), None))
+            };
+            let (plan, error) = match sent.as_ref().err() {
+                Some(AgentError::DryRun { message, .. }) => (Some(message.clone()), None),
+                Some(e) => (None, Some(ErrorResponse::from(e))),
+                None => (None, None),
+            };
+            let (message_id, pending_id) = sent.unwrap_or_default();
+            BroadcastResult {
+                platform: t.platform,
+                channel: t.channel.clone(),
+                message_id,
+                error,
+                plan,
+                pending_id,
+            }
+        });
+        let results: Vec<BroadcastResult> =
+            stream::iter(sends).buffered(SEND_CONCURRENCY).collect().await;
+
+        let failed = results.iter().filter(|r| r.error.is_some()).count();
+        let planned = results.iter().filter(|r| r.plan.is_some()).count();
+        let pending = results.iter().filter(|r| r.pending_id.is_some()).count();
+        let sent = results.len() - failed - planned - pending;
+        info!(sent, failed, planned, pending, "broadcast finished");
+        Ok(BroadcastReport {
+            sent,
+            failed,
+            planned,
+            pending,
+            results,
+        })
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+struct Markup {
+    bold: &'static str,
+    italic: &'static str,
+    strike: &'static str,
+    code: &'static str,
+    block: &'static str,
+    // Slack wraps links in angle brackets, so its text must escape them.
+    slack: bool,
+}
+
+const PLAIN: Markup = Markup {
+    bold: "",
+    italic: "",
+    strike: "",
+    code: "",
+    block: "",
+    slack: false,
+};
+
+const SLACK: Markup = Markup {
+    bold: "*",
+    italic: "_",
+    strike: "~",
+    code: "`",
+    block: "```",
+    slack: true,
+};
+
+const WHATSAPP: Markup = Markup {
+    bold: "*",
+    italic: "_",
+    strike: "~",
+    code: "`",
+    block: "```",
+    slack: false,
+};
+
+// Markdown (`**bold**`, `*italic*` or `_italic_`, `~~strike~~`, `` `code` ``,
+// fenced blocks and `[label](url)` links) in each platform's own markup.
+pub fn render_text(platform: Platform, text: &str) -> String {
+    let markup = match platform {
+        Platform::Discord => return text.to_string(),
+        Platform::Slack => &SLACK,
+        Platform::WhatsApp => &WHATSAPP,
+        Platform::Telegram | Platform::Gmail => &PLAIN,
+    };
+    let mut out = String::with_capacity(text.len());
+    render_inline(text, markup, &mut out);
+    out
+}
+
+fn render_inline(s: &str, m: &Markup, out: &mut String) {
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if let Some(consumed) = render_span(rest, m, out) {
+            rest = &rest[consumed..];
+            continue;
+        }
+        push_text(c, m, out);
+        // A word character before `_` makes it part of a name like
+        // snake_case rather than an italic marker.
+        if c.is_alphanumeric() {
+            let word = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            for ch in rest[c.len_utf8()..word].chars() {
+                push_text(ch, m, out);
+            }
+            rest = &rest[word..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+}
+
+// Renders the span at the start of `s`, if there is one, and returns how
+// many bytes it took. Unclosed markers are left as text.
+fn render_span(s: &str, m: &Markup, out: &mut String) -> Option<usize> {
+    if let Some(body) = s.strip_prefix("```") {
+        let end = body.find("```")?;
+        let mut code = &body[..end];
+        // Without fences, the newlines that set them apart go too.
+        if m.block.is_empty() {
+            code = code.strip_prefix('\n').unwrap_or(code);
+            code = code.strip_suffix('\n').unwrap_or(code);
+        }
+        out.push_str(m.block);
+        push_verbatim(code, m, out);
+        out.push_str(m.block);
+        return Some(3 + end + 3);
+    }
+    if let Some(body) = s.strip_prefix('`') {
+        let end = body.find('`').filter(|&e| e > 0)?;
+        out.push_str(m.code);
+        push_verbatim(&body[..end], m, out);
+        out.push_str(m.code);
+        return Some(1 + end + 1);
+    }
+    if s.starts_with('[') {
+        let close = s.find("](")?;
+        let url_end = s[close + 2..].find(')')? + close + 2;
+        let (label, url) = (&s[1..close], &s[close + 2..url_end]);
+        if label.contains('\n') || url.contains(char::is_whitespace) || url.is_empty() {
+            return None;
+        }
+        push_link(label, url, m, out);
+        return Some(url_end + 1);
+    }
+    for (marker, wrap) in [
+        ("**", m.bold),
+        ("__", m.bold),
+        ("~~", m.strike),
+        ("*", m.italic),
+        ("_", m.italic),
+    ] {
+        let Some(body) = s.strip_prefix(marker) else {
+            continue;
+        };
+        if body.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let end = body.find(marker).filter(|&e| e > 0)?;
+        if body[..end].ends_with(char::is_whitespace) || body[..end].contains('\n') {
+            return None;
+        }
+        out.push_str(wrap);
+        render_inline(&body[..end], m, out);
+        out.push_str(wrap);
+        return Some(marker.len() * 2 + end);
+    }
+    None
+}
+
+fn push_link(label: &str, url: &str, m: &Markup, out: &mut String) {
+    if m.slack {
+        out.push('<');
+        out.push_str(url);
+        if label != url {
+            out.push('|');
+            push_verbatim(label, m, out);
+        }
+        out.push('>');
+    } else if label == url || label.is_empty() {
+        out.push_str(url);
+    } else {
+        render_inline(label, m, out);
+        out.push_str(" (");
+        out.push_str(url);
+        out.push(')');
+    }
+}
+
+fn push_verbatim(s: &str, m: &Markup, out: &mut String) {
+    for c in s.chars() {
+        push_text(c, m, out);
+    }
+}
+
+fn push_text(c: char, m: &Markup, out: &mut String) {
+    match c {
+        '&' if m.slack => out.push_str("&amp;"),
+        '<' if m.slack => out.push_str("&lt;"),
+        '>' if m.slack => out.push_str("&gt;"),
+        _ => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discord_keeps_markdown() {
+        let text = "**bold** and [docs](https://example.com)";
+        assert_eq!(render_text(Platform::Discord, text), text);
+    }
+
+    #[test]
+    fn slack_uses_mrkdwn() {
+        assert_eq!(
+            render_text(Platform::Slack, "**bold**, *it* _it_ ~~gone~~ `a<b`"),
+            "*bold*, _it_ _it_ ~gone~ `a&lt;b`"
+        );
+        assert_eq!(
+            render_text(
+                Platform::Slack,
+                "see [the docs](https://example.com/a?b=1&c=2) & more"
+            ),
+            "see <https://example.com/a?b=1&c=2|the docs> &amp; more"
+        );
+        assert_eq!(
+            render_text(Platform::Slack, "[https://x.io](https://x.io)"),
+            "<https://x.io>"
+        );
+    }
+
+    #[test]
+    fn whatsapp_uses_its_markers() {
+        assert_eq!(
+            render_text(Platform::WhatsApp, "**bold** ~~old~~ [site](https://x.io)"),
+            "*bold* ~old~ site (https://x.io)"
+        );
+    }
+
+    #[test]
+    fn plain_platforms_drop_markers() {
+        let text = "**Release** of `v2`:\n```\nmake\n```\n[notes](https://x.io)";
+        let plain = "Release of v2:\nmake\nnotes (https://x.io)";
+        assert_eq!(render_text(Platform::Telegram, text), plain);
+        assert_eq!(render_text(Platform::Gmail, text), plain);
+    }
+
+    #[test]
+    fn code_is_left_verbatim() {
+        assert_eq!(
+            render_text(Platform::Slack, "```\n**not bold** <tag>\n```"),
+            "```\n**not bold** &lt;tag&gt;\n```"
+        );
+        assert_eq!(render_text(Platform::WhatsApp, "`*x*`"), "`*x*`");
+    }
+
+    #[test]
+    fn stray_markers_stay_text() {
+        for text in [
+            "snake_case_name and file_name.rs",
+            "2 * 3 * 4",
+            "a ** b",
+            "unclosed **bold",
+            "_ spaced _",
+            "[label] (not a link)",
+            "[bad](has space)",
+        ] {
+            assert_eq!(render_text(Platform::WhatsApp, text), text, "{text}");
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::cursor;
+
+const CHARS_PER_TOKEN: usize = 4;
+const MIN_CHARS: usize = 200;
+// Message body widths tried, widest first, before items are dropped.
+const WIDTHS: [usize; 3] = [120, 80, 40];
+
+// A cap on a listing's text. Formatters fit within it by shrinking message
+// bodies, then collapsing runs from the same sender, then dropping items from
+// the end behind a summary and a cursor.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    max_chars: usize,
+    cursor: Option<String>,
+    skipped: usize,
+}
+
+// How much of a listing to render: the body width (0 for the full text),
+// whether to collapse same-sender runs, and how many items to show.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Shape {
+    pub width: usize,
+    pub collapse: bool,
+    pub shown: usize,
+}
+
+impl Budget {
+    pub fn chars(max_chars: usize) -> Self {
+        Self {
+            max_chars: max_chars.max(MIN_CHARS),
+            cursor: None,
+            skipped: 0,
+        }
+    }
+
+    pub fn tokens(max_tokens: usize) -> Self {
+        Self::chars(max_tokens.saturating_mul(CHARS_PER_TOKEN))
+    }
+
+    // The cursor the listing was fetched with, so the cursor left for omitted
+    // items resumes the same page.
+    pub fn resuming(mut self, cursor: Option<&str>) -> Self {
+        if let Ok((cursor, skipped)) = cursor::resume_point(cursor) {
+            self.cursor = cursor;
+            self.skipped = skipped;
+        }
+        self
+    }
+
+    pub(crate) fn resume_cursor(&self, shown: usize) -> String {
+        cursor::resume(self.cursor.as_deref(), self.skipped + shown)
+    }
+
+    // `render` draws the listing in a shape, with the size line if given.
+    // The size line is reserved at the budget's own size while fitting, so
+    // the final one, which counts itself, never pushes the output over.
+    pub(crate) fn fit(
+        &self,
+        total: usize,
+        natural: usize,
+        render: impl Fn(Shape, Option<&str>) -> String,
+    ) -> String {
+        let whole = Shape {
+            width: natural,
+            collapse: false,
+            shown: total,
+        };
+        let untrimmed = char_len(&render(whole, None));
+        let reserve = self.size_line(self.max_chars, untrimmed);
+        let fits = |shape: Shape| char_len(&render(shape, Some(&reserve))) <= self.max_chars;
+
+        let mut shapes = vec![whole];
+        shapes.extend(
+            WIDTHS
+                .iter()
+                .filter(|&&w| natural == 0 || w < natural)
+                .map(|&width| Shape { width, ..whole }),
+        );
+        shapes.push(Shape {
+            collapse: true,
+            ..shapes[shapes.len() - 1]
+        });
+        let narrowest = shapes[shapes.len() - 1];
+        let shape = match shapes.into_iter().find(|&s| fits(s)) {
+            Some(shape) => shape,
+            None => {
+                let (mut lo, mut hi) = (0, total.saturating_sub(1));
+                while lo < hi {
+                    let mid = (lo + hi).div_ceil(2);
+                    if fits(Shape { shown: mid, ..narrowest }) {
+                        lo = mid;
+                    } else {
+                        hi = mid - 1;
+                    }
+                }
+                Shape { shown: lo, ..narrowest }
+            }
+        };
+
+        let mut out = render(shape, Some(&reserve));
+        for _ in 0..3 {
+            let size = char_len(&out);
+            let next = render(shape, Some(&self.size_line(size, untrimmed)));
+            let settled = char_len(&next) == size;
+            out = next;
+            if settled {
+                break;
+            }
+        }
+        out
+    }
+
+    fn size_line(&self, size: usize, untrimmed: usize) -> String {
+        let mut line = format!(
+            "{size} chars (~{} tokens) of {} budget",
+            size.div_ceil(CHARS_PER_TOKEN),
+            self.max_chars
+        );
+        if untrimmed > size {
+            line.push_str(&format!(", {untrimmed} untrimmed"));
+        }
+        line
+    }
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use nexus_domain::Platform;
+use nexus_error::AgentError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const RESUME_PREFIX: &str = "resume:";
+
+// Cursors for fan-out calls hold one position per platform. They are passed
+// back verbatim by clients, so the JSON is hex-encoded to keep them opaque
+// and free of quoting issues.
+pub fn encode<V: Serialize>(positions: &BTreeMap<Platform, V>) -> String {
+    let keyed: BTreeMap<String, &V> = positions
+        .iter()
+        .map(|(p, v)| (p.to_string(), v))
+        .collect();
+    to_hex(&serde_json::to_string(&keyed).unwrap_or_default())
+}
+
+pub fn decode<V: DeserializeOwned>(cursor: &str) -> Result<BTreeMap<Platform, V>, AgentError> {
+    let keyed: BTreeMap<String, V> = from_hex(cursor)?;
+    keyed
+        .into_iter()
+        .map(|(name, v)| Ok((name.parse::<Platform>().map_err(|_| invalid())?, v)))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+struct Resume {
+    cursor: Option<String>,
+    skip: usize,
+}
+
+// A cursor into the middle of a page that an output budget cut short: the
+// cursor that produced the page and how many of its items were shown.
+pub fn resume(cursor: Option<&str>, skip: usize) -> String {
+    let resume = Resume {
+        cursor: cursor.map(str::to_string),
+        skip,
+    };
+    let json = serde_json::to_string(&resume).unwrap_or_default();
+    format!("{RESUME_PREFIX}{}", to_hex(&json))
+}
+
+// Splits a cursor into the cursor to fetch with and the number of leading
+// items to drop. Cursors that are not resume cursors pass through as-is.
+pub fn resume_point(cursor: Option<&str>) -> Result<(Option<String>, usize), AgentError> {
+    match cursor.map(str::trim) {
+        Some(c) => match c.strip_prefix(RESUME_PREFIX) {
+            Some(hex) => {
+                let resume: Resume = from_hex(hex)?;
+                Ok((resume.cursor, resume.skip))
+            }
+            None => Ok((Some(c.to_string()), 0)),
+        },
+        None => Ok((None, 0)),
+    }
+}
+
+fn to_hex(json: &str) -> String {
+    json.bytes().fold(String::with_capacity(json.len() * 2), |mut out, b| {
+        let _ = write!(out, "{b:02x}");
+        out
+    })
+}
+
+fn from_hex<T: DeserializeOwned>(cursor: &str) -> Result<T, AgentError> {
+    let cursor = cursor.trim();
+    if cursor.len() % 2 != 0 || !cursor.is_ascii() {
+        return Err(invalid());
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| invalid())?;
+    serde_json::from_slice(&bytes).map_err(|_| invalid())
+}
+
+fn invalid() -> AgentError {
+    AgentError::invalid_input("invalid cursor").with_argument("cursor")
+}
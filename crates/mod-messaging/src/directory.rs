@@ -0,0 +1,212 @@
+// Names message senders on every platform the same way. Adapters put the
+// sender's platform ID in `sender_id` and a name in `sender` when the
+// platform hands one over; when it does not (Slack history, Telegram), the
+// sender is left as the ID and the directory looks the user up through the
+// platform's `UserPort`, caching the answer.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use futures::{stream, StreamExt};
+use nexus_domain::{Channel, ChatRef, Message, MessagingPort, Platform, Profile, UserPort};
+use nexus_error::AgentError;
+use tracing::debug;
+
+const CAPACITY: usize = 4096;
+const LOOKUP_CONCURRENCY: usize = 4;
+
+type Key = (Platform, String);
+
+pub struct UserDirectory {
+    ttl: Duration,
+    resolvers: RwLock<HashMap<Platform, Arc<dyn UserPort>>>,
+    // Misses are cached too, so bots and deleted users are not looked up on
+    // every read.
+    cache: Mutex<HashMap<Key, (Instant, Option<String>)>>,
+}
+
+impl UserDirectory {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            resolvers: RwLock::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(&self, resolver: Arc<dyn UserPort>) {
+        if let Ok(mut resolvers) = self.resolvers.write() {
+            resolvers.insert(resolver.platform(), resolver);
+        }
+    }
+
+    fn resolver(&self, platform: Platform) -> Option<Arc<dyn UserPort>> {
+        self.resolvers.read().ok()?.get(&platform).cloned()
+    }
+
+    fn cached(&self, key: &Key) -> Option<Option<String>> {
+        let cache = self.cache.lock().ok()?;
+        let (stored, name) = cache.get(key)?;
+        (stored.elapsed() < self.ttl).then(|| name.clone())
+    }
+
+    fn remember(&self, key: Key, name: Option<String>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if cache.len() >= CAPACITY {
+                cache.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+                if cache.len() >= CAPACITY {
+                    cache.clear();
+                }
+            }
+            cache.insert(key, (Instant::now(), name));
+        }
+    }
+
+    // `None` if the platform has no resolver or does not know the ID.
+    pub async fn display_name(&self, platform: Platform, user_id: &str) -> Option<String> {
+        let key = (platform, user_id.to_string());
+        if let Some(hit) = self.cached(&key) {
+            return hit;
+        }
+        let resolver = self.resolver(platform)?;
+        let name = match resolver.get_user(user_id).await {
+            Ok(profile) => Some(display_name(&profile)),
+            // Worth asking again next time rather than caching the miss.
+            Err(e @ (AgentError::Network { .. } | AgentError::RateLimited { .. })) => {
+                debug!(%platform, user_id, error = %e, "user lookup failed");
+                return None;
+            }
+            Err(e) => {
+                debug!(%platform, user_id, error = %e, "user not found");
+                None
+            }
+        };
+        self.remember(key, name.clone());
+        name
+    }
+
+    pub fn needs_names(&self, messages: &[Message]) -> bool {
+        messages.iter().any(unnamed)
+    }
+
+    // Looks each distinct bare-ID sender up once.
+    pub async fn name_senders(&self, messages: &mut [Message]) {
+        let wanted: HashSet<Key> = messages
+            .iter()
+            .filter(|m| unnamed(m))
+            .map(|m| (m.platform, m.sender_id.clone()))
+            .collect();
+        if wanted.is_empty() {
+            return;
+        }
+        let lookups = wanted.into_iter().map(|key| async move {
+            let name = self.display_name(key.0, &key.1).await;
+            (key, name)
+        });
+        let names: HashMap<Key, String> = stream::iter(lookups)
+            .buffer_unordered(LOOKUP_CONCURRENCY)
+            .filter_map(|(key, name)| async move { name.map(|n| (key, n)) })
+            .collect()
+            .await;
+        for msg in messages.iter_mut().filter(|m| unnamed(m)) {
+            if let Some(name) = names.get(&(msg.platform, msg.sender_id.clone())) {
+                msg.sender = name.clone();
+            }
+        }
+    }
+}
+
+fn unnamed(msg: &Message) -> bool {
+    !msg.sender_id.is_empty() && (msg.sender.is_empty() || msg.sender == msg.sender_id)
+}
+
+// "Name (@username)", or just the name when there is no distinct username.
+pub fn display_name(profile: &Profile) -> String {
+    match profile.username {
+        Some(ref u) if !u.is_empty() && *u != profile.name => {
+            if profile.name.is_empty() {
+                format!("@{u}")
+            } else {
+                format!("{} (@{u})", profile.name)
+            }
+        }
+        _ => profile.name.clone(),
+    }
+}
+
+// Resolves channel arguments and message channel IDs to every name the chat
+// answers to, so policy rules match a chat however a call names it. Lookups
+// that fail leave the chat known only by what was given.
+pub struct ChatDirectory {
+    ttl: Duration,
+    ports: RwLock<HashMap<Platform, Arc<dyn MessagingPort>>>,
+    cache: Mutex<HashMap<Key, (Instant, ChatRef)>>,
+}
+
+impl ChatDirectory {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            ports: RwLock::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(&self, port: Arc<dyn MessagingPort>) {
+        if let Ok(mut ports) = self.ports.write() {
+            ports.insert(port.platform(), port);
+        }
+    }
+
+    fn port(&self, platform: Platform) -> Option<Arc<dyn MessagingPort>> {
+        self.ports.read().ok()?.get(&platform).cloned()
+    }
+
+    fn cached(&self, key: &Key) -> Option<ChatRef> {
+        let cache = self.cache.lock().ok()?;
+        let (stored, chat) = cache.get(key)?;
+        (stored.elapsed() < self.ttl).then(|| chat.clone())
+    }
+
+    fn remember(&self, key: Key, chat: ChatRef) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if cache.len() >= CAPACITY {
+                cache.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+                if cache.len() >= CAPACITY {
+                    cache.clear();
+                }
+            }
+            cache.insert(key, (Instant::now(), chat));
+        }
+    }
+
+    // Listings name their channels, which saves a lookup per channel later.
+    pub fn learn(&self, channels: &[Channel]) {
+        for ch in channels {
+            let chat = ChatRef::new(ch.id.clone()).named(ch.name.clone());
+            self.remember((ch.platform, ch.id.clone()), chat);
+        }
+    }
+
+    pub async fn resolve(&self, platform: Platform, channel: &str) -> ChatRef {
+        let key = (platform, channel.to_string());
+        if let Some(hit) = self.cached(&key) {
+            return hit;
+        }
+        let Some(port) = self.port(platform) else {
+            return ChatRef::new(channel);
+        };
+        match port.resolve_channel(channel).await {
+            Ok(chat) => {
+                let chat = chat.named(channel);
+                self.remember(key, chat.clone());
+                chat
+            }
+            Err(e) => {
+                debug!(%platform, channel, error = %e, "channel lookup failed");
+                ChatRef::new(channel)
+            }
+        }
+    }
+}
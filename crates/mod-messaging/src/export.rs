@@ -0,0 +1,487 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use nexus_domain::{Message, Platform, TimeRange};
+use nexus_error::AgentError;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use crate::format;
+use crate::service::{validate_not_empty, AgentService};
+use crate::time;
+
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jsonl,
+    Mbox,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<Self, AgentError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "jsonl" | "ndjson" | "json" => Ok(Self::Jsonl),
+            "mbox" => Ok(Self::Mbox),
+            "html" | "htm" => Ok(Self::Html),
+            other => Err(AgentError::invalid_input(format!(
+                "unknown export format '{other}', use jsonl, mbox or html"
+            ))
+            .with_argument("format")),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| Self::parse(e).ok())
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Mbox => "mbox",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub platform: Platform,
+    pub channel: String,
+    pub format: ExportFormat,
+    pub output: PathBuf,
+    pub range: TimeRange,
+    pub attachments: bool,
+    // Stop after roughly this many messages, leaving the checkpoint so a
+    // later call continues. Checked between pages.
+    pub max_messages: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub output: PathBuf,
+    pub messages: usize,
+    pub attachments: usize,
+    pub attachments_skipped: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest: Option<i64>,
+    pub resumed: bool,
+    pub complete: bool,
+}
+
+// Platforms that cannot download by message return `NotImplemented`.
+#[async_trait]
+pub trait AttachmentFetcher: Send + Sync {
+    async fn fetch(&self, msg: &Message, dir: &Path) -> Result<Vec<PathBuf>, AgentError>;
+}
+
+// Written next to the output after every page. `written` is the length of
+// the data file once that page was flushed, so a resumed export first cuts
+// off anything a crash left half-written.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    platform: Platform,
+    channel: String,
+    format: ExportFormat,
+    cursor: Option<String>,
+    written: u64,
+    summary: ExportSummary,
+}
+
+// JSONL and mbox are appended to the output as pages arrive. HTML pages are
+// spooled as JSONL and rendered oldest first once the export completes.
+struct ExportPaths {
+    output: PathBuf,
+    data: PathBuf,
+    checkpoint: PathBuf,
+    files: PathBuf,
+}
+
+impl ExportPaths {
+    fn new(output: &Path, format: ExportFormat) -> Self {
+        let with_suffix = |suffix: &str| {
+            let mut name = output.file_name().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            output.with_file_name(name)
+        };
+        let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+        Self {
+            output: output.to_path_buf(),
+            data: match format {
+                ExportFormat::Html => with_suffix(".part"),
+                _ => output.to_path_buf(),
+            },
+            checkpoint: with_suffix(".checkpoint"),
+            files: output.with_file_name(format!("{stem}_files")),
+        }
+    }
+}
+
+impl AgentService {
+    // Pages back through a channel with `read_messages` until the history
+    // runs out or passes `range.since`, writing each page before fetching
+    // the next. An interrupted export resumes from its checkpoint when run
+    // again with the same output path.
+    pub async fn export_channel(
+        &self,
+        req: &ExportRequest,
+        fetcher: Option<&dyn AttachmentFetcher>,
+    ) -> Result<ExportSummary, AgentError> {
+        validate_not_empty(&req.channel, "channel")?;
+        if req.format == ExportFormat::Mbox && req.platform != Platform::Gmail {
+            return Err(AgentError::invalid_input(format!(
+                "mbox export is for gmail; use jsonl or html for {}",
+                req.platform
+            ))
+            .with_argument("format"));
+        }
+        self.get(req.platform)?;
+
+        let paths = ExportPaths::new(&req.output, req.format);
+        let (mut cursor, mut summary) = match read_checkpoint(&paths.checkpoint).await? {
+            Some(cp) => {
+                if cp.platform != req.platform || cp.channel != req.channel || cp.format != req.format {
+                    return Err(AgentError::invalid_input(format!(
+                        "{} belongs to an export of {} {} as {}; finish it or delete the checkpoint",
+                        paths.checkpoint.display(),
+                        cp.platform,
+                        cp.channel,
+                        cp.format
+                    ))
+                    .with_argument("output"));
+                }
+                truncate(&paths.data, cp.written).await?;
+                info!(output = %paths.output.display(), messages = cp.summary.messages, "resuming export");
+                let mut summary = cp.summary;
+                summary.resumed = true;
+                (cp.cursor, summary)
+            }
+            None => {
+                if tokio::fs::try_exists(&paths.output).await.unwrap_or(false) {
+                    return Err(AgentError::invalid_input(format!(
+                        "{} already exists",
+                        paths.output.display()
+                    ))
+                    .with_argument("output"));
+                }
+                if let Some(parent) = paths.output.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| io_error("mkdir", parent, e))?;
+                }
+                truncate(&paths.data, 0).await?;
+                let summary = ExportSummary {
+                    output: paths.output.clone(),
+                    ..Default::default()
+                };
+                // Checkpointed before the first fetch, so a run that fails
+                // or stops before any page is written still resumes.
+                write_checkpoint(
+                    &paths.checkpoint,
+                    &Checkpoint {
+                        platform: req.platform,
+                        channel: req.channel.clone(),
+                        format: req.format,
+                        cursor: None,
+                        written: 0,
+                        summary: summary.clone(),
+                    },
+                )
+                .await?;
+                (None, summary)
+            }
+        };
+
+        let mut this_run = 0;
+        summary.complete = loop {
+            if req.max_messages.is_some_and(|max| this_run >= max) {
+                break false;
+            }
+            let page = self
+                .read_messages(req.platform, &req.channel, PAGE_SIZE, cursor.as_deref(), None, req.range)
+                .await?;
+            // Adapters that ignore the range still stop the export at `since`.
+            let past_since = req
+                .range
+                .since
+                .is_some_and(|since| page.items.iter().any(|m| m.timestamp < since));
+
+            let mut chunk = String::new();
+            for msg in page.items.iter().filter(|m| req.range.contains(m.timestamp)) {
+                let saved = if req.attachments && msg.has_attachment {
+                    self.save_attachments(msg, fetcher, &paths, &mut summary).await
+                } else {
+                    Vec::new()
+                };
+                chunk.push_str(&entry(req.format, msg, &saved));
+                summary.messages += 1;
+                summary.oldest = Some(summary.oldest.map_or(msg.timestamp, |t| t.min(msg.timestamp)));
+                summary.newest = Some(summary.newest.map_or(msg.timestamp, |t| t.max(msg.timestamp)));
+                this_run += 1;
+            }
+            let written = append(&paths.data, &chunk).await?;
+
+            let next = page.next_cursor.filter(|_| page.has_more);
+            let stalled = next.is_some() && next == cursor;
+            cursor = next;
+            write_checkpoint(
+                &paths.checkpoint,
+                &Checkpoint {
+                    platform: req.platform,
+                    channel: req.channel.clone(),
+                    format: req.format,
+                    cursor: cursor.clone(),
+                    written,
+                    summary: summary.clone(),
+                },
+            )
+            .await?;
+            info!(platform = %req.platform, channel = %req.channel, messages = summary.messages, "exported page");
+
+            if stalled {
+                warn!(platform = %req.platform, channel = %req.channel, "cursor did not advance, ending export");
+            }
+            if cursor.is_none() || past_since || page.items.is_empty() || stalled {
+                break true;
+            }
+        };
+
+        if summary.complete {
+            if req.format == ExportFormat::Html {
+                render_html(req, &paths, &summary).await?;
+            }
+            remove(&paths.checkpoint).await?;
+        }
+        info!(
+            platform = %req.platform,
+            channel = %req.channel,
+            output = %paths.output.display(),
+            messages = summary.messages,
+            complete = summary.complete,
+            "export finished"
+        );
+        Ok(summary)
+    }
+
+    // Attachment failures are counted, not fatal: one missing file should
+    // not stop an archive of thousands of messages.
+    async fn save_attachments(
+        &self,
+        msg: &Message,
+        fetcher: Option<&dyn AttachmentFetcher>,
+        paths: &ExportPaths,
+        summary: &mut ExportSummary,
+    ) -> Vec<String> {
+        let Some(fetcher) = fetcher else {
+            summary.attachments_skipped += 1;
+            return Vec::new();
+        };
+        if let Err(e) = tokio::fs::create_dir_all(&paths.files).await {
+            warn!(dir = %paths.files.display(), error = %e, "cannot create attachment directory");
+            summary.attachments_skipped += 1;
+            return Vec::new();
+        }
+        match fetcher.fetch(msg, &paths.files).await {
+            Ok(files) => {
+                summary.attachments += files.len();
+                let base = paths.output.parent().unwrap_or(Path::new(""));
+                files
+                    .iter()
+                    .map(|f| f.strip_prefix(base).unwrap_or(f).display().to_string())
+                    .collect()
+            }
+            Err(e) => {
+                if !matches!(e, AgentError::NotImplemented { .. }) {
+                    warn!(msg_id = %msg.id, error = %e, "attachment download failed");
+                }
+                summary.attachments_skipped += 1;
+                Vec::new()
+            }
+        }
+    }
+}
+
+fn entry(format: ExportFormat, msg: &Message, saved: &[String]) -> String {
+    match format {
+        ExportFormat::Jsonl | ExportFormat::Html => {
+            let mut value = serde_json::to_value(msg).unwrap_or_default();
+            if let (false, Some(obj)) = (saved.is_empty(), value.as_object_mut()) {
+                obj.insert("attachments".to_string(), serde_json::json!(saved));
+            }
+            format!("{value}\n")
+        }
+        ExportFormat::Mbox => mbox_entry(msg),
+    }
+}
+
+// mboxrd: a "From " separator line, headers rebuilt from the message, then
+// the body with any line starting with (">"*)"From " quoted once more.
+fn mbox_entry(msg: &Message) -> String {
+    let date = chrono::DateTime::from_timestamp(msg.timestamp, 0).unwrap_or_default();
+    let address = mail_address(&msg.sender);
+    let mut out = format!("From {address} {}\n", date.format("%a %b %e %H:%M:%S %Y"));
+    out.push_str(&format!("From: {}\n", one_line(&msg.sender)));
+    out.push_str(&format!("Date: {}\n", date.to_rfc2822()));
+    if let Some(ref subject) = msg.meta.subject {
+        out.push_str(&format!("Subject: {}\n", one_line(subject)));
+    }
+    if let Some(ref cc) = msg.meta.cc {
+        out.push_str(&format!("Cc: {}\n", one_line(&cc.join(", "))));
+    }
+    out.push_str(&format!("Message-ID: {}\n", angle(&msg.id)));
+    if let Some(ref reply_to) = msg.reply_to {
+        out.push_str(&format!("In-Reply-To: {}\n", angle(reply_to)));
+    }
+    out.push_str(&format!("X-Nexus-Folder: {}\n", one_line(&msg.channel_id)));
+    out.push_str("Content-Type: text/plain; charset=utf-8\n\n");
+    for line in msg.text.replace("\r\n", "\n").lines() {
+        if line.trim_start_matches('>').starts_with("From ") {
+            out.push('>');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+fn mail_address(sender: &str) -> String {
+    let addr = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender,
+    };
+    let addr = addr.trim();
+    if addr.is_empty() || addr.contains(char::is_whitespace) {
+        "MAILER-DAEMON".to_string()
+    } else {
+        addr.to_string()
+    }
+}
+
+fn angle(id: &str) -> String {
+    let id = one_line(id);
+    if id.starts_with('<') {
+        id
+    } else {
+        format!("<{id}>")
+    }
+}
+
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+async fn render_html(
+    req: &ExportRequest,
+    paths: &ExportPaths,
+    summary: &ExportSummary,
+) -> Result<(), AgentError> {
+    let spool = tokio::fs::read_to_string(&paths.data)
+        .await
+        .map_err(|e| io_error("read", &paths.data, e))?;
+    let mut messages: Vec<Message> = spool
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| match serde_json::from_str(l) {
+            Ok(msg) => Some(msg),
+            Err(e) => {
+                warn!(error = %e, "skipping unreadable spooled message");
+                None
+            }
+        })
+        .collect();
+    messages.sort_by_key(|m| m.timestamp);
+
+    let mut details = vec![
+        ("Platform", req.platform.to_string()),
+        ("Channel", req.channel.clone()),
+        ("Messages", summary.messages.to_string()),
+        ("Exported", format::format_timestamp(time::now_ts())),
+    ];
+    if let (Some(oldest), Some(newest)) = (summary.oldest, summary.newest) {
+        details.push((
+            "Period",
+            format!("{} to {}", format::format_timestamp(oldest), format::format_timestamp(newest)),
+        ));
+    }
+    let title = format!("{} · {}", req.channel, req.platform);
+    let html = format::html_document(&title, details, &messages);
+    tokio::fs::write(&paths.output, html)
+        .await
+        .map_err(|e| io_error("write", &paths.output, e))?;
+    remove(&paths.data).await
+}
+
+async fn read_checkpoint(path: &Path) -> Result<Option<Checkpoint>, AgentError> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+            AgentError::internal(format!("corrupt checkpoint {}: {e}", path.display()))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error("read", path, e)),
+    }
+}
+
+async fn write_checkpoint(path: &Path, cp: &Checkpoint) -> Result<(), AgentError> {
+    let json = serde_json::to_vec(cp).map_err(|e| AgentError::internal(format!("checkpoint: {e}")))?;
+    let tmp = path.with_extension("checkpoint.tmp");
+    tokio::fs::write(&tmp, json)
+        .await
+        .map_err(|e| io_error("write", &tmp, e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| io_error("rename", path, e))
+}
+
+// Appends and flushes, returning the file's new length.
+async fn append(path: &Path, chunk: &str) -> Result<u64, AgentError> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| io_error("open", path, e))?;
+    file.write_all(chunk.as_bytes())
+        .await
+        .map_err(|e| io_error("write", path, e))?;
+    file.sync_data().await.map_err(|e| io_error("sync", path, e))?;
+    let meta = file.metadata().await.map_err(|e| io_error("stat", path, e))?;
+    Ok(meta.len())
+}
+
+async fn truncate(path: &Path, len: u64) -> Result<(), AgentError> {
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(len == 0)
+        .open(path)
+        .await
+        .map_err(|e| io_error("open", path, e))?;
+    file.set_len(len).await.map_err(|e| io_error("truncate", path, e))
+}
+
+async fn remove(path: &Path) -> Result<(), AgentError> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(io_error("remove", path, e)),
+    }
+}
+
+fn io_error(op: &str, path: &Path, e: std::io::Error) -> AgentError {
+    AgentError::internal(format!("{op} {}: {e}", path.display()))
+}
@@ -1,10 +1,27 @@
-use nexus_domain::{Channel, ChannelType, ChatInfo, ChatMember, Message, Paginated, Platform, Profile};
+use chrono::Datelike;
+use nexus_domain::{
+    Channel, ChannelType, ChatInfo, ChatMember, DeliveryStatus, Message, Paginated, Platform,
+    Profile, ScheduledMessage,
+};
+
+use crate::approval::{ApprovalState, PendingMessage};
+use crate::audit::{Record, Verification};
+use crate::broadcast::BroadcastReport;
+use crate::budget::{Budget, Shape};
+use crate::export::ExportSummary;
+use crate::schedule::ScheduledList;
+use crate::render::{escape, render, MessageView, Node, Quote};
+use crate::service::{InboxPage, MergedSearchResults, PlatformFailure, SearchResults};
+use crate::store::{LocalResults, ReadPage, SyncReport};
+use crate::time;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Format {
     Compact,
     Expanded,
     Full,
+    Markdown,
+    Html,
 }
 
 impl Format {
@@ -12,6 +29,8 @@ impl Format {
         match s {
             Some("full") => Self::Full,
             Some("expanded") => Self::Expanded,
+            Some("markdown") | Some("md") => Self::Markdown,
+            Some("html") => Self::Html,
             _ => Self::Compact,
         }
     }
@@ -35,63 +54,610 @@ pub fn format_profile(profile: &Profile, fmt: Format) -> String {
             parts.join(" ")
         }
         Format::Full => to_json(profile),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![("Platform", profile.platform.to_string()), ("ID", profile.id.clone())];
+            if let Some(ref u) = profile.username {
+                fields.push(("Username", format!("@{u}")));
+            }
+            if let Some(ref e) = profile.email {
+                fields.push(("Email", e.clone()));
+            }
+            if let Some(ref p) = profile.phone {
+                fields.push(("Phone", format!("+{p}")));
+            }
+            render(fmt, &[Node::Heading(2, profile.name.clone()), Node::Fields(fields)])
+        }
     }
 }
 
-pub fn format_channels(channels: &[Channel], fmt: Format) -> String {
+pub fn format_channels(channels: &[Channel], fmt: Format, budget: Option<&Budget>) -> String {
     match fmt {
-        Format::Compact | Format::Expanded => {
-            let mut lines: Vec<String> = Vec::with_capacity(channels.len() + 1);
+        Format::Compact | Format::Expanded => laid_out(fmt, budget, channels.len(), |shape, size| {
+            let mut lines: Vec<String> = Vec::with_capacity(shape.shown + 3);
             lines.push(format!("{} channels:", channels.len()));
-            for ch in channels {
+            for ch in &channels[..shape.shown] {
                 lines.push(format_channel_line(ch));
             }
+            push_trailer(&mut lines, omitted_rows(channels.len() - shape.shown, "channels"), size);
             lines.join("\n")
-        }
+        }),
         Format::Full => to_json(channels),
+        Format::Markdown | Format::Html => laid_out(fmt, budget, channels.len(), |shape, size| {
+            let rows = channels[..shape.shown]
+                .iter()
+                .map(|ch| {
+                    vec![
+                        ch.name.clone(),
+                        format_channel_type(&ch.channel_type).to_string(),
+                        ch.unread_count.to_string(),
+                        ch.member_count.map(|n| n.to_string()).unwrap_or_default(),
+                        ch.id.clone(),
+                    ]
+                })
+                .collect();
+            let mut nodes = vec![
+                Node::Heading(2, format!("{} channels", channels.len())),
+                Node::Table(vec!["Name", "Type", "Unread", "Members", "ID"], rows),
+            ];
+            push_trailer_notes(&mut nodes, omitted_rows(channels.len() - shape.shown, "channels"), size);
+            render(fmt, &nodes)
+        }),
     }
 }
 
-pub fn format_messages(messages: &[Message], fmt: Format) -> String {
+pub fn format_messages(messages: &[Message], fmt: Format, budget: Option<&Budget>) -> String {
     match fmt {
-        Format::Compact | Format::Expanded => {
-            let max_text = if fmt == Format::Expanded { 0 } else { 200 };
-            let mut lines: Vec<String> = Vec::with_capacity(messages.len() + 1);
-            lines.push(format!("{} messages:", messages.len()));
-            for msg in messages {
-                lines.push(format_message_line(msg, max_text));
+        Format::Full => to_json(messages),
+        _ => MessageList {
+            heading: format!("{} messages", messages.len()),
+            items: messages.iter().map(|m| (m, m.channel_id.as_str())).collect(),
+            tagged: false,
+            next_cursor: None,
+            resumable: false,
+            notes: Vec::new(),
+        }
+        .format(fmt, budget),
+    }
+}
+
+pub fn format_paginated(result: &Paginated<Message>, fmt: Format, budget: Option<&Budget>) -> String {
+    match fmt {
+        Format::Full => to_json(result),
+        _ => paginated_list(result).format(fmt, budget),
+    }
+}
+
+pub fn format_read(read: &ReadPage, fmt: Format, budget: Option<&Budget>) -> String {
+    match fmt {
+        Format::Full => to_json(read),
+        _ => {
+            let mut list = paginated_list(&read.page);
+            if let Some(ref reason) = read.offline {
+                list.notes.push((
+                    format!("  offline: from the local store ({reason})"),
+                    format!("Offline: from the local store ({reason})"),
+                ));
             }
-            lines.join("\n")
+            list.format(fmt, budget)
         }
-        Format::Full => to_json(messages),
     }
 }
 
-pub fn format_paginated(result: &Paginated<Message>, fmt: Format) -> String {
+pub fn format_search(results: &SearchResults, fmt: Format, budget: Option<&Budget>) -> String {
     match fmt {
-        Format::Compact | Format::Expanded => {
-            let max_text = if fmt == Format::Expanded { 0 } else { 200 };
-            let mut lines: Vec<String> = Vec::with_capacity(result.items.len() + 2);
-            lines.push(format!("{} messages:", result.items.len()));
-            for msg in &result.items {
-                lines.push(format_message_line(msg, max_text));
-            }
-            if result.has_more {
-                if let Some(ref cursor) = result.next_cursor {
-                    lines.push(format!("  ... more available (cursor: {cursor})"));
+        Format::Full => to_json(results),
+        _ => {
+            let mut list = paginated_list(&results.page);
+            if !results.unsupported.is_empty() {
+                let clauses: Vec<String> =
+                    results.unsupported.iter().map(|c| c.to_string()).collect();
+                list.notes.push(ignored_note(results.platform, &clauses));
+            }
+            list.format(fmt, budget)
+        }
+    }
+}
+
+pub fn format_inbox(page: &InboxPage, fmt: Format, budget: Option<&Budget>) -> String {
+    match fmt {
+        Format::Full => to_json(page),
+        _ => MessageList {
+            heading: format!("{} messages", page.items.len()),
+            items: page.items.iter().map(|i| (&i.message, i.channel.as_str())).collect(),
+            tagged: true,
+            next_cursor: page.next_cursor.as_deref(),
+            resumable: true,
+            notes: partial_notes(&page.failed, &page.unsupported),
+        }
+        .format(fmt, budget),
+    }
+}
+
+pub fn format_search_all(
+    results: &MergedSearchResults,
+    fmt: Format,
+    budget: Option<&Budget>,
+) -> String {
+    match fmt {
+        Format::Full => to_json(results),
+        _ => {
+            let mut notes: Vec<(String, String)> = results
+                .ignored
+                .iter()
+                .map(|ignored| {
+                    let clauses: Vec<String> =
+                        ignored.clauses.iter().map(|c| c.to_string()).collect();
+                    ignored_note(ignored.platform, &clauses)
+                })
+                .collect();
+            notes.extend(partial_notes(&results.failed, &results.unsupported));
+            MessageList {
+                heading: format!("{} messages ({})", results.items.len(), results.order),
+                items: results.items.iter().map(|m| (m, m.channel_id.as_str())).collect(),
+                tagged: true,
+                next_cursor: results.next_cursor.as_deref(),
+                resumable: true,
+                notes,
+            }
+            .format(fmt, budget)
+        }
+    }
+}
+
+pub fn format_local_search(
+    results: &LocalResults,
+    fmt: Format,
+    budget: Option<&Budget>,
+) -> String {
+    match fmt {
+        Format::Full => to_json(results),
+        _ => {
+            let mut notes = Vec::new();
+            if !results.ignored.is_empty() {
+                let clauses: Vec<String> = results.ignored.iter().map(|c| c.to_string()).collect();
+                notes.push((
+                    format!("  note: not supported locally, ignored: {}", clauses.join(", ")),
+                    format!("Not supported locally, ignored: {}", clauses.join(", ")),
+                ));
+            }
+            MessageList {
+                heading: format!("{} stored messages ({})", results.items.len(), results.order),
+                items: results.items.iter().map(|i| (&i.message, i.channel.as_str())).collect(),
+                tagged: true,
+                next_cursor: results.next_cursor.as_deref(),
+                resumable: true,
+                notes,
+            }
+            .format(fmt, budget)
+        }
+    }
+}
+
+pub fn format_sync(report: &SyncReport) -> String {
+    let mut out = format!(
+        "synced {} messages from {} channels",
+        report.messages, report.channels
+    );
+    for f in &report.failed {
+        out.push_str(&format!("\n  failed: {} {}", f.platform, f.error.to_compact()));
+    }
+    out
+}
+
+pub fn format_audit(records: &[Record]) -> String {
+    if records.is_empty() {
+        return "no audit records".to_string();
+    }
+    let mut lines = Vec::new();
+    for r in records {
+        let mut line = format!("{} {} {}", format_timestamp(r.ts), r.outcome, r.tool);
+        if let Some(platform) = r.platform {
+            line.push_str(&format!(" on {platform}"));
+        }
+        if let Some(ref client) = r.client {
+            line.push_str(&format!(" by {}", client.name));
+            if !client.version.is_empty() {
+                line.push_str(&format!(" {}", client.version));
+            }
+        }
+        lines.push(line);
+        for a in &r.actions {
+            let mut line = format!("  {} {}", a.platform, a.operation);
+            match (&a.target, &a.resolved) {
+                (Some(target), Some(id)) if target != id => line.push_str(&format!(" {target} -> {id}")),
+                (Some(target), _) => line.push_str(&format!(" {target}")),
+                (None, Some(id)) => line.push_str(&format!(" {id}")),
+                (None, None) => {}
+            }
+            if !a.message_ids.is_empty() {
+                line.push_str(&format!(" id:{}", a.message_ids.join(",")));
+            }
+            match a.error.as_deref() {
+                Some("DRY_RUN") => line.push_str(" (dry run)"),
+                Some(code) => line.push_str(&format!(" failed: {code}")),
+                None => {}
+            }
+            lines.push(line);
+        }
+        if let Some(ref e) = r.error {
+            lines.push(format!("  error: {}", truncate(e, 200)));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn format_verification(report: &Verification) -> String {
+    let mut out = format!(
+        "checked {} audit records, {} of them chained",
+        report.records, report.chained
+    );
+    if report.problems.is_empty() {
+        out.push_str(": no problems found");
+    }
+    for p in &report.problems {
+        out.push_str(&format!("\n  {p}"));
+    }
+    out
+}
+
+// A list of messages as every text format shows it: a heading, the messages,
+// then the cursor and notes. Notes are kept as (compact line, document note).
+struct MessageList<'a> {
+    heading: String,
+    items: Vec<(&'a Message, &'a str)>,
+    tagged: bool,
+    next_cursor: Option<&'a str>,
+    resumable: bool,
+    notes: Vec<(String, String)>,
+}
+
+impl MessageList<'_> {
+    fn format(&self, fmt: Format, budget: Option<&Budget>) -> String {
+        laid_out(fmt, budget, self.items.len(), |shape, size| {
+            let shown = &self.items[..shape.shown];
+            let omitted = self.omitted(shape.shown, budget);
+            match fmt {
+                Format::Compact | Format::Expanded => {
+                    let mut lines: Vec<String> = Vec::with_capacity(shown.len() + 4);
+                    lines.push(format!("{}:", self.heading));
+                    lines.extend(compact_lines(shown, self.tagged, shape.width, shape.collapse));
+                    match (omitted, self.next_cursor) {
+                        (Some(o), _) => lines.push(format!("  ... {o}")),
+                        (None, Some(cursor)) => {
+                            lines.push(format!("  ... more available (cursor: {cursor})"))
+                        }
+                        (None, None) => {}
+                    }
+                    lines.extend(self.notes.iter().map(|(line, _)| line.clone()));
+                    push_trailer(&mut lines, None, size);
+                    lines.join("\n")
+                }
+                _ => {
+                    let mut nodes = vec![Node::Heading(2, self.heading.clone())];
+                    nodes.extend(transcript(shown, shape.width, shape.collapse));
+                    match (omitted, self.next_cursor) {
+                        (Some(o), _) => nodes.push(Node::Note(o)),
+                        (None, Some(cursor)) => {
+                            nodes.push(Node::Note(format!("More available (cursor: {cursor})")))
+                        }
+                        (None, None) => {}
+                    }
+                    nodes.extend(self.notes.iter().map(|(_, note)| Node::Note(note.clone())));
+                    push_trailer_notes(&mut nodes, None, size);
+                    render(fmt, &nodes)
                 }
             }
+        })
+    }
+
+    // Summarizes the messages a budget left out. Listings fetched with a
+    // cursor get a resume cursor that starts at the first omitted message.
+    fn omitted(&self, shown: usize, budget: Option<&Budget>) -> Option<String> {
+        let rest = &self.items[shown..];
+        let (first, _) = rest.first()?;
+        let mut senders: Vec<&str> = Vec::new();
+        for (m, _) in rest {
+            if !senders.contains(&m.sender.as_str()) {
+                senders.push(&m.sender);
+            }
+        }
+        let from = match senders.len() {
+            0..=3 => senders.join(", "),
+            n => format!("{} and {} others", senders[..2].join(", "), n - 2),
+        };
+        let when = if rest.len() == 1 {
+            format!("at {}", format_timestamp(first.timestamp))
+        } else {
+            format!("between {}", time_span(rest.iter().map(|(m, _)| m.timestamp)))
+        };
+        let noun = if rest.len() == 1 { "message" } else { "messages" };
+        let summary = format!(
+            "{} more {noun} omitted to fit the budget, from {from} {when}",
+            rest.len()
+        );
+        Some(match budget {
+            Some(b) if self.resumable => {
+                format!("{summary} (cursor: {})", b.resume_cursor(shown))
+            }
+            _ => format!("{summary}; raise max_chars to see them"),
+        })
+    }
+}
+
+fn paginated_list(result: &Paginated<Message>) -> MessageList<'_> {
+    let mut notes = Vec::new();
+    if let Some(ref cursor) = result.prev_cursor {
+        notes.push((
+            format!("  ... newer messages (cursor: {cursor})"),
+            format!("Newer messages (cursor: {cursor})"),
+        ));
+    }
+    MessageList {
+        heading: format!("{} messages", result.items.len()),
+        items: result.items.iter().map(|m| (m, m.channel_id.as_str())).collect(),
+        tagged: false,
+        next_cursor: if result.has_more { result.next_cursor.as_deref() } else { None },
+        resumable: true,
+        notes,
+    }
+}
+
+fn ignored_note(platform: Platform, clauses: &[String]) -> (String, String) {
+    (
+        format!("  note: not supported on {platform}, ignored: {}", clauses.join(", ")),
+        format!("Not supported on {platform}, ignored: {}", clauses.join(", ")),
+    )
+}
+
+fn partial_notes(failed: &[PlatformFailure], unsupported: &[Platform]) -> Vec<(String, String)> {
+    let mut notes: Vec<(String, String)> = failed
+        .iter()
+        .map(|f| {
+            (
+                format!("  failed: {} {}", f.platform, f.error.to_compact()),
+                format!("Failed on {}: {}", f.platform, f.error.to_compact()),
+            )
+        })
+        .collect();
+    if !unsupported.is_empty() {
+        let names: Vec<String> = unsupported.iter().map(|p| p.to_string()).collect();
+        notes.push((
+            format!("  not supported on: {}", names.join(", ")),
+            format!("Not supported on: {}", names.join(", ")),
+        ));
+    }
+    notes
+}
+
+// Renders a listing whole, or fitted to the budget when there is one.
+fn laid_out(
+    fmt: Format,
+    budget: Option<&Budget>,
+    total: usize,
+    render: impl Fn(Shape, Option<&str>) -> String,
+) -> String {
+    let natural = if fmt == Format::Compact { 200 } else { 0 };
+    match budget {
+        Some(b) => b.fit(total, natural, render),
+        None => render(
+            Shape {
+                width: natural,
+                collapse: false,
+                shown: total,
+            },
+            None,
+        ),
+    }
+}
+
+fn omitted_rows(count: usize, noun: &str) -> Option<String> {
+    (count > 0).then(|| format!("{count} more {noun} omitted to fit the budget; raise max_chars to see them"))
+}
+
+fn push_trailer(lines: &mut Vec<String>, omitted: Option<String>, size: Option<&str>) {
+    if let Some(o) = omitted {
+        lines.push(format!("  ... {o}"));
+    }
+    if let Some(s) = size {
+        lines.push(format!("  size: {s}"));
+    }
+}
+
+fn push_trailer_notes(nodes: &mut Vec<Node>, omitted: Option<String>, size: Option<&str>) {
+    if let Some(o) = omitted {
+        nodes.push(Node::Note(o));
+    }
+    if let Some(s) = size {
+        nodes.push(Node::Note(format!("Size: {s}")));
+    }
+}
+
+fn compact_lines(
+    items: &[(&Message, &str)],
+    tagged: bool,
+    width: usize,
+    collapse: bool,
+) -> Vec<String> {
+    let line = |msg: &Message, label: &str| {
+        if tagged {
+            format_tagged_line(msg, label, width)
+        } else {
+            format_message_line(msg, width)
+        }
+    };
+    if !collapse {
+        return items.iter().map(|(m, label)| line(m, label)).collect();
+    }
+    runs(items)
+        .into_iter()
+        .map(|run| match run {
+            [(m, label)] => line(m, label),
+            _ => collapsed_line(run, tagged, width),
+        })
+        .collect()
+}
+
+// One line for a run of messages from one sender: the time span, the sender
+// and count once, then each message's text and id.
+fn collapsed_line(run: &[(&Message, &str)], tagged: bool, width: usize) -> String {
+    let (first, label) = run[0];
+    let parts: Vec<String> = run
+        .iter()
+        .map(|(m, _)| {
+            let text = if width > 0 { truncate(&m.text, width) } else { clean_text(&m.text) };
+            let attach = if m.has_attachment { " +attach" } else { "" };
+            format!("{text}{attach} (id:{})", m.id)
+        })
+        .collect();
+    let place = if tagged {
+        format!("{} {} | ", first.platform, label)
+    } else {
+        String::new()
+    };
+    format!(
+        "  [{}] {place}{} ({}): {}",
+        time_span(run.iter().map(|(m, _)| m.timestamp)),
+        first.sender,
+        run.len(),
+        parts.join(" / ")
+    )
+}
+
+// Splits messages into runs of consecutive messages from the same sender in
+// the same channel.
+fn runs<'a, 'b>(items: &'b [(&'a Message, &'a str)]) -> Vec<&'b [(&'a Message, &'a str)]> {
+    let same = |(a, la): &(&Message, &str), (b, lb): &(&Message, &str)| {
+        a.platform == b.platform && la == lb && a.sender == b.sender
+    };
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=items.len() {
+        if i == items.len() || !same(&items[i - 1], &items[i]) {
+            runs.push(&items[start..i]);
+            start = i;
+        }
+    }
+    runs
+}
+
+fn time_span(timestamps: impl Iterator<Item = i64>) -> String {
+    let (lo, hi) = timestamps.fold((i64::MAX, i64::MIN), |(lo, hi), ts| (lo.min(ts), hi.max(ts)));
+    let (from, to) = (format_timestamp(lo), format_timestamp(hi));
+    match (from.rsplit_once(' '), to.rsplit_once(' ')) {
+        _ if from == to => from,
+        (Some((day, _)), Some((to_day, to_time))) if day == to_day => format!("{from}-{to_time}"),
+        _ => format!("{from} - {to}"),
+    }
+}
+
+fn format_tagged_line(msg: &Message, channel: &str, max_text: usize) -> String {
+    format!(
+        "  [{}] {} {} | {}",
+        format_timestamp(msg.timestamp),
+        msg.platform,
+        channel,
+        format_message_body(msg, max_text)
+    )
+}
+
+pub fn format_export(summary: &ExportSummary, fmt: Format) -> String {
+    let period = match (summary.oldest, summary.newest) {
+        (Some(oldest), Some(newest)) => Some(format!(
+            "{} to {}",
+            format_timestamp(oldest),
+            format_timestamp(newest)
+        )),
+        _ => None,
+    };
+    let attachments = if summary.attachments + summary.attachments_skipped > 0 {
+        Some(format!(
+            "{} saved, {} skipped",
+            summary.attachments, summary.attachments_skipped
+        ))
+    } else {
+        None
+    };
+    let unfinished = "stopped at max_messages; run the same export again to continue";
+    match fmt {
+        Format::Compact | Format::Expanded => {
+            let mut lines = vec![format!(
+                "{} {} messages to {}",
+                if summary.complete { "exported" } else { "partially exported" },
+                summary.messages,
+                summary.output.display()
+            )];
+            if let Some(p) = period {
+                lines.push(format!("  period: {p}"));
+            }
+            if let Some(a) = attachments {
+                lines.push(format!("  attachments: {a}"));
+            }
+            if summary.resumed {
+                lines.push("  resumed from checkpoint".to_string());
+            }
+            if !summary.complete {
+                lines.push(format!("  ... {unfinished}"));
+            }
             lines.join("\n")
         }
-        Format::Full => to_json(result),
+        Format::Full => to_json(summary),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![
+                ("Output", summary.output.display().to_string()),
+                ("Messages", summary.messages.to_string()),
+                ("Complete", if summary.complete { "yes" } else { "no" }.to_string()),
+            ];
+            if let Some(p) = period {
+                fields.push(("Period", p));
+            }
+            if let Some(a) = attachments {
+                fields.push(("Attachments", a));
+            }
+            if summary.resumed {
+                fields.push(("Resumed", "yes".to_string()));
+            }
+            let mut nodes = vec![Node::Heading(2, "Export".to_string()), Node::Fields(fields)];
+            if !summary.complete {
+                nodes.push(Node::Note(unfinished.to_string()));
+            }
+            render(fmt, &nodes)
+        }
     }
 }
 
+const DOCUMENT_STYLE: &str = "body{font:15px/1.5 system-ui,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;color:#1d1d1f}\
+dl{display:grid;grid-template-columns:max-content auto;gap:.2rem 1rem}dt{font-weight:600}dd{margin:0}\
+h3{border-bottom:1px solid #ddd;padding-bottom:.3rem;margin-top:2rem}\
+article.message{margin:1rem 0}article.message header{color:#555;font-size:.9em}\
+.sender{color:#1d1d1f}.tag{background:#eee;border-radius:3px;padding:0 .3rem;margin-left:.3rem}\
+p.text{margin:.2rem 0;white-space:pre-wrap}blockquote.reply{margin:.3rem 0;padding-left:.7rem;border-left:3px solid #ccc;color:#555}\
+ul.attachments,p.reactions{font-size:.9em;color:#555}";
+
+// A standalone page for an exported conversation. Styles are inline so the
+// file opens anywhere without the network.
+pub(crate) fn html_document(
+    title: &str,
+    details: Vec<(&'static str, String)>,
+    messages: &[Message],
+) -> String {
+    let items: Vec<(&Message, &str)> = messages.iter().map(|m| (m, m.channel_id.as_str())).collect();
+    let mut nodes = vec![Node::Heading(1, title.to_string()), Node::Fields(details)];
+    nodes.extend(transcript(&items, 0, false));
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{DOCUMENT_STYLE}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape(title),
+        render(Format::Html, &nodes)
+    )
+}
+
 pub fn format_message(msg: &Message, fmt: Format) -> String {
     match fmt {
         Format::Compact => format_message_line(msg, 200),
         Format::Expanded => format_message_line(msg, 0),
         Format::Full => to_json(msg),
+        Format::Markdown | Format::Html => {
+            render(fmt, &[Node::Message(Box::new(message_view(msg, &[])))])
+        }
     }
 }
 
@@ -123,29 +689,64 @@ pub fn format_chat_info(info: &ChatInfo, fmt: Format) -> String {
             parts.join(" | ")
         }
         Format::Full => to_json(info),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![
+                ("Type", format_channel_type(&info.channel_type).to_string()),
+                ("Members", info.member_count.to_string()),
+            ];
+            if info.unread_count > 0 {
+                fields.push(("Unread", info.unread_count.to_string()));
+            }
+            if let Some(ref desc) = info.description {
+                if !desc.is_empty() {
+                    fields.push(("Description", desc.clone()));
+                }
+            }
+            if info.is_verified {
+                fields.push(("Verified", "yes".to_string()));
+            }
+            if info.is_scam {
+                fields.push(("Scam", "flagged by the platform".to_string()));
+            }
+            if let Some(ref link) = info.invite_link {
+                fields.push(("Invite link", link.clone()));
+            }
+            fields.push(("ID", info.id.clone()));
+            fields.push(("Platform", info.platform.to_string()));
+            render(fmt, &[Node::Heading(2, info.name.clone()), Node::Fields(fields)])
+        }
     }
 }
 
-pub fn format_labels(labels: &[String], fmt: Format) -> String {
+pub fn format_labels(labels: &[String], fmt: Format, budget: Option<&Budget>) -> String {
     match fmt {
-        Format::Compact | Format::Expanded => {
-            let mut lines: Vec<String> = Vec::with_capacity(labels.len() + 1);
+        Format::Compact | Format::Expanded => laid_out(fmt, budget, labels.len(), |shape, size| {
+            let mut lines: Vec<String> = Vec::with_capacity(shape.shown + 3);
             lines.push(format!("{} labels:", labels.len()));
-            for l in labels {
+            for l in &labels[..shape.shown] {
                 lines.push(format!("  {l}"));
             }
+            push_trailer(&mut lines, omitted_rows(labels.len() - shape.shown, "labels"), size);
             lines.join("\n")
-        }
+        }),
         Format::Full => to_json(labels),
+        Format::Markdown | Format::Html => laid_out(fmt, budget, labels.len(), |shape, size| {
+            let mut nodes = vec![
+                Node::Heading(2, format!("{} labels", labels.len())),
+                Node::List(labels[..shape.shown].to_vec()),
+            ];
+            push_trailer_notes(&mut nodes, omitted_rows(labels.len() - shape.shown, "labels"), size);
+            render(fmt, &nodes)
+        }),
     }
 }
 
-pub fn format_members(members: &[ChatMember], fmt: Format) -> String {
+pub fn format_members(members: &[ChatMember], fmt: Format, budget: Option<&Budget>) -> String {
     match fmt {
-        Format::Compact | Format::Expanded => {
-            let mut lines: Vec<String> = Vec::with_capacity(members.len() + 1);
+        Format::Compact | Format::Expanded => laid_out(fmt, budget, members.len(), |shape, size| {
+            let mut lines: Vec<String> = Vec::with_capacity(shape.shown + 3);
             lines.push(format!("{} members:", members.len()));
-            for m in members {
+            for m in &members[..shape.shown] {
                 let username = m
                     .username
                     .as_ref()
@@ -156,9 +757,434 @@ pub fn format_members(members: &[ChatMember], fmt: Format) -> String {
                     m.name, username, m.role, m.user_id
                 ));
             }
+            push_trailer(&mut lines, omitted_rows(members.len() - shape.shown, "members"), size);
             lines.join("\n")
-        }
+        }),
         Format::Full => to_json(members),
+        Format::Markdown | Format::Html => laid_out(fmt, budget, members.len(), |shape, size| {
+            let rows = members[..shape.shown]
+                .iter()
+                .map(|m| {
+                    vec![
+                        m.name.clone(),
+                        m.username.as_ref().map(|u| format!("@{u}")).unwrap_or_default(),
+                        m.role.to_string(),
+                        m.user_id.clone(),
+                    ]
+                })
+                .collect();
+            let mut nodes = vec![
+                Node::Heading(2, format!("{} members", members.len())),
+                Node::Table(vec!["Name", "Username", "Role", "ID"], rows),
+            ];
+            push_trailer_notes(&mut nodes, omitted_rows(members.len() - shape.shown, "members"), size);
+            render(fmt, &nodes)
+        }),
+    }
+}
+
+pub fn format_delivery_status(status: &DeliveryStatus, fmt: Format) -> String {
+    match fmt {
+        Format::Compact | Format::Expanded => {
+            let mut parts = vec![
+                format!("{} [{}]", status.state, status.platform),
+                format!("id:{}", status.message_id),
+            ];
+            if !status.channel_id.is_empty() {
+                parts.push(format!("chat:{}", status.channel_id));
+            }
+            if let Some(ts) = status.updated_at {
+                parts.push(format!("at:{}", format_timestamp(ts)));
+            }
+            if let Some(ref e) = status.error {
+                parts.push(format!("error:{e}"));
+            }
+            parts.join(" | ")
+        }
+        Format::Full => to_json(status),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![
+                ("State", status.state.to_string()),
+                ("Platform", status.platform.to_string()),
+                ("Message", status.message_id.clone()),
+            ];
+            if !status.channel_id.is_empty() {
+                fields.push(("Chat", status.channel_id.clone()));
+            }
+            if let Some(ts) = status.updated_at {
+                fields.push(("Updated", format_timestamp(ts)));
+            }
+            if let Some(ref e) = status.error {
+                fields.push(("Error", e.clone()));
+            }
+            render(fmt, &[Node::Heading(2, "Delivery status".to_string()), Node::Fields(fields)])
+        }
+    }
+}
+
+pub fn format_broadcast(report: &BroadcastReport, fmt: Format) -> String {
+    let mut heading = if report.planned > 0 {
+        format!("broadcast would send to {} of {} targets", report.planned, report.results.len())
+    } else {
+        format!("broadcast sent to {} of {} targets", report.sent, report.results.len())
+    };
+    if report.pending > 0 {
+        heading.push_str(&format!(", {} held for approval", report.pending));
+    }
+    match fmt {
+        Format::Compact | Format::Expanded => {
+            let mut lines = vec![format!("{heading}:")];
+            for r in &report.results {
+                let outcome = match (&r.message_id, &r.error, &r.plan, &r.pending_id) {
+                    (_, Some(e), _, _) => format!("failed: {}", e.to_compact()),
+                    (_, None, Some(plan), _) => format!("would {plan}"),
+                    (_, None, None, Some(id)) => format!("held for approval id:{id}"),
+                    (Some(id), None, None, None) => format!("sent id:{id}"),
+                    (None, None, None, None) => "sent".to_string(),
+                };
+                lines.push(format!("  {} {} | {outcome}", r.platform, r.channel));
+            }
+            lines.join("\n")
+        }
+        Format::Full => to_json(report),
+        Format::Markdown | Format::Html => {
+            let rows = report
+                .results
+                .iter()
+                .map(|r| {
+                    let (result, detail) = match (&r.message_id, &r.error, &r.plan, &r.pending_id) {
+                        (_, Some(e), _, _) => ("failed", e.to_compact()),
+                        (_, None, Some(plan), _) => ("dry run", format!("would {plan}")),
+                        (_, None, None, Some(id)) => ("held for approval", id.clone()),
+                        (id, None, None, None) => ("sent", id.clone().unwrap_or_default()),
+                    };
+                    vec![r.platform.to_string(), r.channel.clone(), result.to_string(), detail]
+                })
+                .collect();
+            render(
+                fmt,
+                &[
+                    Node::Heading(2, heading),
+                    Node::Table(vec!["Platform", "Channel", "Result", "Message ID / error"], rows),
+                ],
+            )
+        }
+    }
+}
+
+pub fn format_scheduled(job: &ScheduledMessage, fmt: Format) -> String {
+    match fmt {
+        Format::Compact | Format::Expanded => {
+            let width = if fmt == Format::Compact { 200 } else { 0 };
+            format!("{}:\n{}", job.state, scheduled_line(job, width))
+        }
+        Format::Full => to_json(job),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![
+                ("State", job.state.to_string()),
+                ("Send at", format_timestamp(job.send_at)),
+                ("Platform", job.platform.to_string()),
+                ("Chat", job.channel_id.clone()),
+                ("ID", job.id.clone()),
+                ("Held by", scheduled_holder(job).to_string()),
+            ];
+            if let Some(ref id) = job.message_id {
+                fields.push(("Message", id.clone()));
+            }
+            if let Some(ref e) = job.error {
+                fields.push(("Error", e.clone()));
+            }
+            fields.push(("Text", job.text.clone()));
+            render(fmt, &[Node::Heading(2, "Scheduled message".to_string()), Node::Fields(fields)])
+        }
+    }
+}
+
+pub fn format_scheduled_list(list: &ScheduledList, fmt: Format, budget: Option<&Budget>) -> String {
+    let items = &list.items;
+    let heading = format!("{} scheduled messages", items.len());
+    let notes = partial_notes(&list.failures, &[]);
+    match fmt {
+        Format::Compact | Format::Expanded => laid_out(fmt, budget, items.len(), |shape, size| {
+            let width = if fmt == Format::Compact { 200 } else { 0 };
+            let mut lines: Vec<String> = Vec::with_capacity(shape.shown + notes.len() + 3);
+            lines.push(format!("{heading}:"));
+            for job in &items[..shape.shown] {
+                lines.push(scheduled_line(job, width));
+            }
+            lines.extend(notes.iter().map(|(line, _)| line.clone()));
+            push_trailer(&mut lines, omitted_rows(items.len() - shape.shown, "jobs"), size);
+            lines.join("\n")
+        }),
+        Format::Full => to_json(list),
+        Format::Markdown | Format::Html => laid_out(fmt, budget, items.len(), |shape, size| {
+            let rows = items[..shape.shown]
+                .iter()
+                .map(|j| {
+                    vec![
+                        format_timestamp(j.send_at),
+                        j.state.to_string(),
+                        j.platform.to_string(),
+                        j.channel_id.clone(),
+                        j.id.clone(),
+                        truncate(&j.text, 200),
+                    ]
+                })
+                .collect();
+            let mut nodes = vec![
+                Node::Heading(2, heading.clone()),
+                Node::Table(vec!["Send at", "State", "Platform", "Chat", "ID", "Text"], rows),
+            ];
+            nodes.extend(notes.iter().map(|(_, note)| Node::Note(note.clone())));
+            push_trailer_notes(&mut nodes, omitted_rows(items.len() - shape.shown, "jobs"), size);
+            render(fmt, &nodes)
+        }),
+    }
+}
+
+pub fn format_pending(item: &PendingMessage, fmt: Format) -> String {
+    match fmt {
+        Format::Compact | Format::Expanded => {
+            let width = if fmt == Format::Compact { 200 } else { 0 };
+            format!("{}:\n{}", pending_heading(item), pending_line(item, width))
+        }
+        Format::Full => to_json(item),
+        Format::Markdown | Format::Html => {
+            let mut fields = vec![
+                ("State", item.state.to_string()),
+                ("Platform", item.platform.to_string()),
+                ("Chat", item.channel.clone()),
+                ("ID", item.id.clone()),
+                ("Submitted", format_timestamp(item.created_at)),
+            ];
+            if let Some(at) = item.decided_at {
+                fields.push(("Decided", format_timestamp(at)));
+            }
+            if let Some(ref reason) = item.reason {
+                fields.push(("Reason", reason.clone()));
+            }
+            if let Some(ref id) = item.message_id {
+                fields.push(("Message", id.clone()));
+            }
+            if let Some(ref e) = item.error {
+                fields.push(("Error", e.clone()));
+            }
+            fields.push(("Text", item.text.clone()));
+            if let Some(ref original) = item.original_text {
+                fields.push(("Submitted text", original.clone()));
+            }
+            render(fmt, &[Node::Heading(2, pending_heading(item)), Node::Fields(fields)])
+        }
+    }
+}
+
+pub fn format_pending_list(items: &[PendingMessage], fmt: Format, budget: Option<&Budget>) -> String {
+    let heading = format!("{} messages held for approval", items.len());
+    match fmt {
+        Format::Compact | Format::Expanded => laid_out(fmt, budget, items.len(), |shape, size| {
+            let width = if fmt == Format::Compact { 200 } else { 0 };
+            let mut lines: Vec<String> = Vec::with_capacity(shape.shown + 2);
+            lines.push(format!("{heading}:"));
+            for item in &items[..shape.shown] {
+                lines.push(pending_line(item, width));
+            }
+            push_trailer(&mut lines, omitted_rows(items.len() - shape.shown, "messages"), size);
+            lines.join("\n")
+        }),
+        Format::Full => to_json(items),
+        Format::Markdown | Format::Html => laid_out(fmt, budget, items.len(), |shape, size| {
+            let rows = items[..shape.shown]
+                .iter()
+                .map(|i| {
+                    vec![
+                        format_timestamp(i.created_at),
+                        i.state.to_string(),
+                        i.platform.to_string(),
+                        i.channel.clone(),
+                        i.id.clone(),
+                        truncate(&i.text, 200),
+                    ]
+                })
+                .collect();
+            let mut nodes = vec![
+                Node::Heading(2, heading.clone()),
+                Node::Table(vec!["Submitted", "State", "Platform", "Chat", "ID", "Text"], rows),
+            ];
+            push_trailer_notes(&mut nodes, omitted_rows(items.len() - shape.shown, "messages"), size);
+            render(fmt, &nodes)
+        }),
+    }
+}
+
+fn pending_heading(item: &PendingMessage) -> String {
+    match item.state {
+        ApprovalState::Pending => "held for approval".to_string(),
+        state => state.to_string(),
+    }
+}
+
+fn pending_line(item: &PendingMessage, width: usize) -> String {
+    let text = if width > 0 { truncate(&item.text, width) } else { clean_text(&item.text) };
+    let mut parts = vec![
+        format!("  [{}] {} {}", format_timestamp(item.created_at), item.state, item.platform),
+        format!("chat:{}", item.channel),
+        format!("id:{}", item.id),
+    ];
+    if item.original_text.is_some() {
+        parts.push("edited by reviewer".to_string());
+    }
+    if let Some(ref reason) = item.reason {
+        parts.push(format!("reason:{reason}"));
+    }
+    if let Some(ref id) = item.message_id {
+        parts.push(format!("msg:{id}"));
+    }
+    if let Some(ref e) = item.error {
+        parts.push(format!("error:{e}"));
+    }
+    format!("{}: {text}", parts.join(" | "))
+}
+
+fn scheduled_holder(job: &ScheduledMessage) -> &'static str {
+    if job.queued {
+        "nexus queue"
+    } else {
+        "platform"
+    }
+}
+
+fn scheduled_line(job: &ScheduledMessage, width: usize) -> String {
+    let text = if width > 0 { truncate(&job.text, width) } else { clean_text(&job.text) };
+    let mut parts = vec![
+        format!("  [{}] {} {}", format_timestamp(job.send_at), job.state, job.platform),
+        format!("chat:{}", job.channel_id),
+        format!("id:{}", job.id),
+        format!("via:{}", scheduled_holder(job)),
+    ];
+    if let Some(ref id) = job.message_id {
+        parts.push(format!("msg:{id}"));
+    }
+    if let Some(ref e) = job.error {
+        parts.push(format!("error:{e}"));
+    }
+    format!("{}: {text}", parts.join(" | "))
+}
+
+// Groups messages under a heading per channel, in order of first
+// appearance, keeping their order within each channel. Replies quote the
+// message they answer when it is in the same set. Bodies are cut to `width`
+// when it is not 0, and `collapse` merges same-sender runs into one entry.
+fn transcript(messages: &[(&Message, &str)], width: usize, collapse: bool) -> Vec<Node> {
+    let all: Vec<&Message> = messages.iter().map(|(m, _)| *m).collect();
+    let mut groups: Vec<((Platform, &str), Vec<&Message>)> = Vec::new();
+    for (msg, label) in messages {
+        let key = (msg.platform, *label);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(msg),
+            None => groups.push((key, vec![msg])),
+        }
+    }
+    let view = |m: &Message| {
+        let mut view = message_view(m, &all);
+        if width > 0 {
+            view.text = truncate(&view.text, width);
+            if let Some(ref mut quote) = view.quote {
+                if quote.sender.is_some() {
+                    quote.text = truncate(&quote.text, width);
+                }
+            }
+        }
+        view
+    };
+    let mut nodes = Vec::new();
+    for ((platform, label), group) in groups {
+        nodes.push(Node::Heading(3, format!("{label} · {platform}")));
+        if !collapse {
+            nodes.extend(group.into_iter().map(|m| Node::Message(Box::new(view(m)))));
+            continue;
+        }
+        let mut rest = group.as_slice();
+        while let Some(first) = rest.first() {
+            let len = rest.iter().take_while(|m| m.sender == first.sender).count();
+            let (run, tail) = rest.split_at(len);
+            let mut merged = view(first);
+            if run.len() > 1 {
+                merged.id = run.iter().map(|m| m.id.as_str()).collect::<Vec<_>>().join(", ");
+                merged.time = time_span(run.iter().map(|m| m.timestamp));
+                merged.tags.clear();
+                for m in &run[1..] {
+                    let next = view(m);
+                    merged.text = format!("{}\n{}", merged.text, next.text);
+                    merged.attachments.extend(next.attachments);
+                    merged.reactions.extend(next.reactions);
+                }
+            }
+            nodes.push(Node::Message(Box::new(merged)));
+            rest = tail;
+        }
+    }
+    nodes
+}
+
+fn message_view(msg: &Message, context: &[&Message]) -> MessageView {
+    let mut tags = Vec::new();
+    if let Some(ref from) = msg.meta.forward_from {
+        tags.push(format!("forwarded from {from}"));
+    }
+    if let Some(true) = msg.meta.is_pinned {
+        tags.push("pinned".to_string());
+    }
+    if msg.meta.edit_date.is_some() {
+        tags.push("edited".to_string());
+    }
+    if let Some(views) = msg.meta.views {
+        tags.push(format!("{views} views"));
+    }
+    if let Some(state) = msg.meta.delivery {
+        tags.push(state.to_string());
+    }
+    let quote = msg.reply_to.as_ref().map(|id| {
+        match context.iter().find(|m| &m.id == id) {
+            Some(original) => Quote {
+                sender: Some(original.sender.clone()),
+                text: truncate(&original.text, 200),
+            },
+            None => Quote {
+                sender: None,
+                text: format!("In reply to {id}"),
+            },
+        }
+    });
+    let attachments = if msg.has_attachment {
+        vec![msg.meta.media_type.clone().unwrap_or_else(|| "file".to_string())]
+    } else {
+        Vec::new()
+    };
+    let reactions = msg
+        .meta
+        .reactions
+        .iter()
+        .flatten()
+        .map(|r| {
+            if r.count > 1 {
+                format!("{}×{}", r.emoji, r.count)
+            } else {
+                r.emoji.clone()
+            }
+        })
+        .collect();
+    MessageView {
+        id: msg.id.clone(),
+        sender: msg.sender.clone(),
+        time: format_timestamp(msg.timestamp),
+        datetime: chrono::DateTime::from_timestamp(msg.timestamp, 0)
+            .map(|dt| dt.with_timezone(&time::timezone()).to_rfc3339()),
+        tags,
+        subject: msg.meta.subject.clone(),
+        quote,
+        text: msg.text.clone(),
+        attachments,
+        reactions,
     }
 }
 
@@ -187,7 +1213,14 @@ fn format_channel_line(ch: &Channel) -> String {
 }
 
 fn format_message_line(msg: &Message, max_text: usize) -> String {
-    let ts = format_timestamp(msg.timestamp);
+    format!(
+        "  [{}] {}",
+        format_timestamp(msg.timestamp),
+        format_message_body(msg, max_text)
+    )
+}
+
+fn format_message_body(msg: &Message, max_text: usize) -> String {
     let text = if max_text > 0 { truncate(&msg.text, max_text) } else { clean_text(&msg.text) };
     let attach = if msg.has_attachment { " +attach" } else { "" };
     let reply = msg
@@ -232,20 +1265,30 @@ fn format_message_line(msg: &Message, max_text: usize) -> String {
             }
         }
     }
+    if let Some(state) = msg.meta.delivery {
+        extras.push_str(&format!(" {state}"));
+    }
 
     format!(
-        "  [{ts}] {sender}: {text}{attach}{reply}{extras} (id:{id})",
+        "{sender}: {text}{attach}{reply}{extras} (id:{id})",
         sender = msg.sender,
         id = msg.id,
     )
 }
 
 pub fn format_timestamp(ts: i64) -> String {
+    let tz = time::timezone();
     let dt = chrono::DateTime::from_timestamp(ts, 0);
     match dt {
         Some(dt) => {
-            let utc = dt.with_timezone(&chrono::Utc);
-            utc.format("%b %d %H:%M").to_string()
+            let local = dt.with_timezone(&tz);
+            let this_year = chrono::DateTime::from_timestamp(time::now_ts(), 0)
+                .map(|now| now.with_timezone(&tz).year());
+            if this_year == Some(local.year()) {
+                local.format("%b %d %H:%M").to_string()
+            } else {
+                local.format("%b %d %Y %H:%M").to_string()
+            }
         }
         None => format!("{ts}"),
     }
@@ -1,5 +1,38 @@
+pub mod approval;
+pub mod audit;
+pub mod broadcast;
+pub mod budget;
+pub mod cursor;
+pub mod directory;
+pub mod export;
 pub mod format;
+pub mod middleware;
+pub mod outbox;
+pub mod policy;
+pub mod query;
+pub mod redact;
+mod render;
+pub mod retry;
+pub mod schedule;
 pub mod service;
+pub mod store;
+pub mod time;
 
+pub use approval::{ApprovalQueue, ApprovalState, PendingMessage};
+pub use audit::AuditLog;
+pub use broadcast::{BroadcastReport, BroadcastResult, BroadcastTarget};
+pub use budget::Budget;
+pub use directory::{ChatDirectory, UserDirectory};
+pub use export::{AttachmentFetcher, ExportFormat, ExportRequest, ExportSummary};
 pub use format::Format;
-pub use service::AgentService;
+pub use middleware::{Layer, Layered, Stack};
+pub use outbox::Outbox;
+pub use policy::{Policy, PolicyRequest};
+pub use redact::Redactor;
+pub use retry::{Idempotency, RetryPolicy};
+pub use schedule::{ScheduleQueue, ScheduledList};
+pub use service::{
+    AgentService, IgnoredClauses, InboxItem, InboxPage, MergedSearchResults, PlatformFailure,
+    SearchOrder, SearchResults,
+};
+pub use store::{LocalResults, MessageStore, ReadPage, SyncReport};
@@ -0,0 +1,19 @@
+use super::{Access, Call, Layer, Next};
+use crate::audit;
+
+// Notes each write and what it resolved to for the audit record of the
+// tool call it serves. Does nothing outside `audit::scope`.
+pub struct AuditLayer;
+
+impl Layer for AuditLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        if call.access != Access::Write {
+            return next;
+        }
+        Box::pin(async move {
+            let result = next.await;
+            audit::observe(call, &result);
+            result
+        })
+    }
+}
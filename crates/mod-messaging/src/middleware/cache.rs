@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use nexus_domain::Platform;
+use tracing::debug;
+
+use super::{Access, Call, Layer, Next, Reply};
+
+const CAPACITY: usize = 1024;
+
+type CacheKey = (Platform, &'static str, String);
+
+// Reuses read results for `ttl`. Any write on a platform drops that
+// platform's entries, so an agent reading back what it just sent sees it.
+pub struct CacheLayer {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, Reply)>>,
+}
+
+impl CacheLayer {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<Reply> {
+        let entries = self.entries.lock().ok()?;
+        let (stored, reply) = entries.get(key)?;
+        (stored.elapsed() < self.ttl).then(|| reply.clone())
+    }
+
+    fn store(&self, key: CacheKey, reply: Reply) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= CAPACITY {
+                entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+                if entries.len() >= CAPACITY {
+                    entries.clear();
+                }
+            }
+            entries.insert(key, (Instant::now(), reply));
+        }
+    }
+
+    fn invalidate(&self, platform: Platform) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(p, _, _), _| *p != platform);
+        }
+    }
+}
+
+impl Layer for CacheLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        if call.access == Access::Write {
+            return Box::pin(async move {
+                let result = next.await;
+                self.invalidate(call.platform);
+                result
+            });
+        }
+        let Some(ref args) = call.key else {
+            return next;
+        };
+        Box::pin(async move {
+            let key = (call.platform, call.operation, args.clone());
+            if let Some(hit) = self.lookup(&key) {
+                debug!(platform = %call.platform, operation = call.operation, "cache hit");
+                return Ok(hit);
+            }
+            let reply = next.await?;
+            self.store(key, reply.clone());
+            Ok(reply)
+        })
+    }
+}
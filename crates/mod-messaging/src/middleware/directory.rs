@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use nexus_domain::{Message, Paginated};
+
+use super::{Call, Layer, Next, Reply};
+use crate::directory::UserDirectory;
+
+// Names senders that adapters could only report by ID. Replies that are
+// already named pass through untouched; others are copied and filled in.
+pub struct DirectoryLayer {
+    directory: Arc<UserDirectory>,
+}
+
+impl DirectoryLayer {
+    pub fn new(directory: Arc<UserDirectory>) -> Self {
+        Self { directory }
+    }
+
+    async fn name(&self, reply: Reply) -> Reply {
+        let dir = &self.directory;
+        if let Some(page) = reply.downcast_ref::<Paginated<Message>>() {
+            if dir.needs_names(&page.items) {
+                let mut page = page.clone();
+                dir.name_senders(&mut page.items).await;
+                return Arc::new(page);
+            }
+        } else if let Some(msg) = reply.downcast_ref::<Message>() {
+            if dir.needs_names(std::slice::from_ref(msg)) {
+                let mut msg = msg.clone();
+                dir.name_senders(std::slice::from_mut(&mut msg)).await;
+                return Arc::new(msg);
+            }
+        } else if let Some(msgs) = reply.downcast_ref::<Vec<Message>>() {
+            if dir.needs_names(msgs) {
+                let mut msgs = msgs.clone();
+                dir.name_senders(&mut msgs).await;
+                return Arc::new(msgs);
+            }
+        }
+        reply
+    }
+}
+
+impl Layer for DirectoryLayer {
+    fn call<'a>(&'a self, _call: &'a Call, next: Next<'a>) -> Next<'a> {
+        Box::pin(async move {
+            let reply = next.await?;
+            Ok(self.name(reply).await)
+        })
+    }
+}
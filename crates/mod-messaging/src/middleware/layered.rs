@@ -0,0 +1,560 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nexus_domain::{
+    Channel, ChatInfo, ChatMember, ChatRef, DeliveryStatus, DiscordExt, EventPort, EventStream, GmailExt,
+    Message, MessagingPort, Paginated, Platform, Profile, ReadAnchor, SchedulePort,
+    ScheduledMessage, SearchClause, SearchQuery, SlackExt, TelegramExt, TimeRange, UserPort,
+    WhatsAppExt,
+};
+use nexus_error::AgentError;
+
+use super::{Call, Stack};
+
+// Implements whichever of the port and extension traits the wrapped adapter
+// implements.
+pub struct Layered<P: ?Sized> {
+    inner: Arc<P>,
+    stack: Stack,
+    platform: Platform,
+}
+
+impl<P: MessagingPort + ?Sized> Layered<P> {
+    pub fn new(inner: Arc<P>, stack: Stack) -> Self {
+        let platform = inner.platform();
+        Self {
+            inner,
+            stack,
+            platform,
+        }
+    }
+}
+
+impl<P: ?Sized> Layered<P> {
+    pub fn inner(&self) -> &Arc<P> {
+        &self.inner
+    }
+
+    fn read(&self, operation: &'static str) -> Call {
+        Call::read(self.platform, operation)
+    }
+
+    fn write(&self, operation: &'static str) -> Call {
+        Call::write(self.platform, operation)
+    }
+}
+
+#[async_trait]
+impl<P: MessagingPort + ?Sized> MessagingPort for Layered<P> {
+    fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    async fn get_profile(&self) -> Result<Profile, AgentError> {
+        self.stack
+            .run(self.read("get_profile"), self.inner.get_profile())
+            .await
+    }
+
+    async fn list_channels(&self, limit: usize) -> Result<Vec<Channel>, AgentError> {
+        let call = self.read("list_channels").key(limit.to_string());
+        self.stack.run(call, self.inner.list_channels(limit)).await
+    }
+
+    async fn read_messages(
+        &self,
+        channel: &str,
+        limit: usize,
+        cursor: Option<&str>,
+        anchor: Option<&ReadAnchor>,
+        range: TimeRange,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let call = self
+            .read("read_messages")
+            .target(channel)
+            .key(format!("{channel}|{limit}|{cursor:?}|{anchor:?}|{range:?}"));
+        self.stack
+            .run(call, self.inner.read_messages(channel, limit, cursor, anchor, range))
+            .await
+    }
+
+    async fn send_message(
+        &self,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("send_message").target(channel);
+        self.stack
+            .run(call, self.inner.send_message(channel, text, reply_to))
+            .await
+    }
+
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let mut call = self
+            .read("search")
+            .key(format!("{query:?}|{limit}|{cursor:?}"));
+        if let Some(ref channel) = query.channel {
+            call = call.target(channel.clone());
+        }
+        self.stack
+            .run(call, self.inner.search(query, limit, cursor))
+            .await
+    }
+
+    fn unsupported_clauses(&self, query: &SearchQuery) -> Vec<SearchClause> {
+        self.inner.unsupported_clauses(query)
+    }
+
+    async fn get_delivery_status(
+        &self,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        let call = self.read("get_delivery_status").target(channel).uncached();
+        self.stack
+            .run(call, self.inner.get_delivery_status(channel, message_id))
+            .await
+    }
+
+    // Resolution is what the policy layer itself relies on, so it goes
+    // straight to the adapter.
+    async fn resolve_channel(&self, channel: &str) -> Result<ChatRef, AgentError> {
+        self.inner.resolve_channel(channel).await
+    }
+}
+
+// Streams are long-lived rather than request/response, so subscribing
+// bypasses the layers.
+#[async_trait]
+impl<P: EventPort + ?Sized> EventPort for Layered<P> {
+    async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        self.inner.subscribe_events().await
+    }
+}
+
+#[async_trait]
+impl<P: UserPort + ?Sized> UserPort for Layered<P> {
+    async fn get_user(&self, user_id: &str) -> Result<Profile, AgentError> {
+        let call = self.read("get_user").target(user_id).key(user_id);
+        self.stack.run(call, self.inner.get_user(user_id)).await
+    }
+}
+
+#[async_trait]
+impl<P: SchedulePort + ?Sized> SchedulePort for Layered<P> {
+    async fn schedule_message(
+        &self,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        send_at: i64,
+    ) -> Result<ScheduledMessage, AgentError> {
+        let call = self.write("schedule_message").target(channel);
+        self.stack
+            .run(call, self.inner.schedule_message(channel, text, reply_to, send_at))
+            .await
+    }
+
+    // The platform sends these on its own, so a cached list goes stale.
+    async fn list_scheduled(&self, channel: Option<&str>) -> Result<Vec<ScheduledMessage>, AgentError> {
+        let mut call = self.read("list_scheduled").uncached();
+        if let Some(ch) = channel {
+            call = call.target(ch);
+        }
+        self.stack.run(call, self.inner.list_scheduled(channel)).await
+    }
+
+    async fn cancel_scheduled(&self, channel: &str, id: &str) -> Result<(), AgentError> {
+        let call = self.write("cancel_scheduled").target(channel);
+        self.stack.run(call, self.inner.cancel_scheduled(channel, id)).await
+    }
+}
+
+#[async_trait]
+impl<P: TelegramExt + ?Sized> TelegramExt for Layered<P> {
+    async fn download_media(
+        &self,
+        chat: &str,
+        msg_id: i64,
+        path: &str,
+    ) -> Result<String, AgentError> {
+        let call = self.read("download_media").target(chat).uncached();
+        self.stack
+            .run(call, self.inner.download_media(chat, msg_id, path))
+            .await
+    }
+
+    async fn forward_message(
+        &self,
+        from_chat: &str,
+        to_chat: &str,
+        msg_id: i64,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("forward_message").target(to_chat);
+        self.stack
+            .run(call, self.inner.forward_message(from_chat, to_chat, msg_id))
+            .await
+    }
+
+    async fn edit_message(
+        &self,
+        chat: &str,
+        msg_id: i64,
+        text: &str,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("edit_message").target(chat);
+        self.stack
+            .run(call, self.inner.edit_message(chat, msg_id, text))
+            .await
+    }
+
+    async fn delete_messages(&self, chat: &str, msg_ids: &[i64]) -> Result<(), AgentError> {
+        let call = self.write("delete_messages").target(chat);
+        self.stack
+            .run(call, self.inner.delete_messages(chat, msg_ids))
+            .await
+    }
+
+    async fn pin_message(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
+        let call = self.write("pin_message").target(chat);
+        self.stack
+            .run(call, TelegramExt::pin_message(&*self.inner, chat, msg_id))
+            .await
+    }
+
+    async fn unpin_message(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
+        let call = self.write("unpin_message").target(chat);
+        self.stack
+            .run(call, self.inner.unpin_message(chat, msg_id))
+            .await
+    }
+
+    async fn get_chat_info(&self, chat: &str) -> Result<ChatInfo, AgentError> {
+        let call = self.read("get_chat_info").target(chat).key(chat);
+        self.stack.run(call, self.inner.get_chat_info(chat)).await
+    }
+
+    async fn mark_read(&self, chat: &str, msg_id: i64) -> Result<(), AgentError> {
+        let call = self.write("mark_read").target(chat);
+        self.stack
+            .run(call, TelegramExt::mark_read(&*self.inner, chat, msg_id))
+            .await
+    }
+
+    async fn get_message(&self, chat: &str, msg_id: i64) -> Result<Message, AgentError> {
+        let call = self
+            .read("get_message")
+            .target(chat)
+            .key(format!("{chat}|{msg_id}"));
+        self.stack
+            .run(call, self.inner.get_message(chat, msg_id))
+            .await
+    }
+
+    async fn send_media(
+        &self,
+        chat: &str,
+        file_path: &str,
+        caption: Option<&str>,
+        media_type: Option<&str>,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("send_media").target(chat);
+        self.stack
+            .run(
+                call,
+                TelegramExt::send_media(&*self.inner, chat, file_path, caption, media_type),
+            )
+            .await
+    }
+
+    async fn react_message(&self, chat: &str, msg_id: i64, emoji: &str) -> Result<(), AgentError> {
+        let call = self.write("react_message").target(chat);
+        self.stack
+            .run(call, self.inner.react_message(chat, msg_id, emoji))
+            .await
+    }
+
+    async fn search_chat(
+        &self,
+        chat: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Message>, AgentError> {
+        let call = self
+            .read("search_chat")
+            .target(chat)
+            .key(format!("{chat}|{query}|{limit}"));
+        self.stack
+            .run(call, self.inner.search_chat(chat, query, limit))
+            .await
+    }
+
+    async fn get_chat_members(
+        &self,
+        chat: &str,
+        limit: usize,
+    ) -> Result<Vec<ChatMember>, AgentError> {
+        let call = self
+            .read("get_chat_members")
+            .target(chat)
+            .key(format!("{chat}|{limit}"));
+        self.stack
+            .run(call, self.inner.get_chat_members(chat, limit))
+            .await
+    }
+}
+
+#[async_trait]
+impl<P: GmailExt + ?Sized> GmailExt for Layered<P> {
+    async fn send_email(
+        &self,
+        to: &[String],
+        cc: &[String],
+        bcc: &[String],
+        subject: &str,
+        body: &str,
+        reply_to: Option<&str>,
+        attachments: &[String],
+    ) -> Result<Message, AgentError> {
+        let call = self.write("send_email").target(to.join(","));
+        self.stack
+            .run(
+                call,
+                self.inner
+                    .send_email(to, cc, bcc, subject, body, reply_to, attachments),
+            )
+            .await
+    }
+
+    async fn archive(&self, thread_id: &str) -> Result<(), AgentError> {
+        let call = self.write("archive").target(thread_id);
+        self.stack.run(call, self.inner.archive(thread_id)).await
+    }
+
+    async fn list_labels(&self) -> Result<Vec<String>, AgentError> {
+        self.stack
+            .run(self.read("list_labels"), self.inner.list_labels())
+            .await
+    }
+
+    async fn add_label(&self, thread_id: &str, label: &str) -> Result<(), AgentError> {
+        let call = self.write("add_label").target(thread_id);
+        self.stack
+            .run(call, self.inner.add_label(thread_id, label))
+            .await
+    }
+
+    async fn mark_read(&self, message_id: &str) -> Result<(), AgentError> {
+        let call = self.write("mark_read").target(message_id);
+        self.stack
+            .run(call, GmailExt::mark_read(&*self.inner, message_id))
+            .await
+    }
+
+    async fn mark_unread(&self, message_id: &str) -> Result<(), AgentError> {
+        let call = self.write("mark_unread").target(message_id);
+        self.stack.run(call, self.inner.mark_unread(message_id)).await
+    }
+
+    async fn star(&self, message_id: &str) -> Result<(), AgentError> {
+        let call = self.write("star").target(message_id);
+        self.stack.run(call, self.inner.star(message_id)).await
+    }
+
+    async fn unstar(&self, message_id: &str) -> Result<(), AgentError> {
+        let call = self.write("unstar").target(message_id);
+        self.stack.run(call, self.inner.unstar(message_id)).await
+    }
+
+    async fn move_to(&self, message_id: &str, folder: &str) -> Result<(), AgentError> {
+        let call = self.write("move_to").target(message_id);
+        self.stack
+            .run(call, self.inner.move_to(message_id, folder))
+            .await
+    }
+
+    async fn trash(&self, message_id: &str) -> Result<(), AgentError> {
+        let call = self.write("trash").target(message_id);
+        self.stack.run(call, self.inner.trash(message_id)).await
+    }
+
+    async fn remove_label(&self, message_id: &str, label: &str) -> Result<(), AgentError> {
+        let call = self.write("remove_label").target(message_id);
+        self.stack
+            .run(call, self.inner.remove_label(message_id, label))
+            .await
+    }
+
+    async fn get_attachment(
+        &self,
+        message_id: &str,
+        filename: &str,
+        save_path: &str,
+    ) -> Result<String, AgentError> {
+        let call = self.read("get_attachment").target(message_id).uncached();
+        self.stack
+            .run(call, self.inner.get_attachment(message_id, filename, save_path))
+            .await
+    }
+
+    async fn create_draft(
+        &self,
+        to: &[String],
+        subject: &str,
+        body: &str,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("create_draft").target(to.join(","));
+        self.stack
+            .run(call, self.inner.create_draft(to, subject, body))
+            .await
+    }
+}
+
+#[async_trait]
+impl<P: WhatsAppExt + ?Sized> WhatsAppExt for Layered<P> {
+    async fn send_media(
+        &self,
+        chat: &str,
+        file_path: &str,
+        caption: &str,
+    ) -> Result<Message, AgentError> {
+        let call = self.write("send_media").target(chat);
+        self.stack
+            .run(call, WhatsAppExt::send_media(&*self.inner, chat, file_path, caption))
+            .await
+    }
+}
+
+#[async_trait]
+impl<P: SlackExt + ?Sized> SlackExt for Layered<P> {
+    async fn set_status(&self, text: &str, emoji: &str) -> Result<(), AgentError> {
+        self.stack
+            .run(self.write("set_status"), self.inner.set_status(text, emoji))
+            .await
+    }
+
+    async fn create_channel(&self, name: &str, is_private: bool) -> Result<Channel, AgentError> {
+        let call = self.write("create_channel").target(name);
+        self.stack
+            .run(call, self.inner.create_channel(name, is_private))
+            .await
+    }
+
+    async fn invite_to_channel(&self, channel: &str, user_id: &str) -> Result<(), AgentError> {
+        let call = self.write("invite_to_channel").target(channel);
+        self.stack
+            .run(call, self.inner.invite_to_channel(channel, user_id))
+            .await
+    }
+
+    async fn set_topic(&self, channel: &str, topic: &str) -> Result<(), AgentError> {
+        let call = self.write("set_topic").target(channel);
+        self.stack
+            .run(call, self.inner.set_topic(channel, topic))
+            .await
+    }
+
+    async fn add_reaction(&self, channel: &str, msg_ts: &str, emoji: &str) -> Result<(), AgentError> {
+        let call = self.write("add_reaction").target(channel);
+        self.stack
+            .run(call, SlackExt::add_reaction(&*self.inner, channel, msg_ts, emoji))
+            .await
+    }
+
+    async fn remove_reaction(
+        &self,
+        channel: &str,
+        msg_ts: &str,
+        emoji: &str,
+    ) -> Result<(), AgentError> {
+        let call = self.write("remove_reaction").target(channel);
+        self.stack
+            .run(call, SlackExt::remove_reaction(&*self.inner, channel, msg_ts, emoji))
+            .await
+    }
+
+    async fn upload_file(
+        &self,
+        channels: &[String],
+        file_path: &str,
+        title: Option<&str>,
+    ) -> Result<String, AgentError> {
+        let call = self.write("upload_file").target(channels.join(","));
+        self.stack
+            .run(call, self.inner.upload_file(channels, file_path, title))
+            .await
+    }
+
+    async fn list_users(&self, limit: usize) -> Result<Vec<ChatMember>, AgentError> {
+        let call = self.read("list_users").key(limit.to_string());
+        self.stack.run(call, self.inner.list_users(limit)).await
+    }
+
+    async fn get_user_info(&self, user: &str) -> Result<Profile, AgentError> {
+        let call = self.read("get_user_info").target(user).key(user);
+        self.stack.run(call, self.inner.get_user_info(user)).await
+    }
+}
+
+#[async_trait]
+impl<P: DiscordExt + ?Sized> DiscordExt for Layered<P> {
+    async fn list_guilds(&self) -> Result<Vec<Channel>, AgentError> {
+        self.stack
+            .run(self.read("list_guilds"), self.inner.list_guilds())
+            .await
+    }
+
+    async fn list_guild_channels(&self, guild_id: &str) -> Result<Vec<Channel>, AgentError> {
+        let call = self
+            .read("list_guild_channels")
+            .target(guild_id)
+            .key(guild_id);
+        self.stack
+            .run(call, self.inner.list_guild_channels(guild_id))
+            .await
+    }
+
+    async fn create_thread(
+        &self,
+        channel: &str,
+        name: &str,
+        msg_id: Option<&str>,
+    ) -> Result<Channel, AgentError> {
+        let call = self.write("create_thread").target(channel);
+        self.stack
+            .run(call, self.inner.create_thread(channel, name, msg_id))
+            .await
+    }
+
+    async fn add_reaction(&self, channel: &str, msg_id: &str, emoji: &str) -> Result<(), AgentError> {
+        let call = self.write("add_reaction").target(channel);
+        self.stack
+            .run(call, DiscordExt::add_reaction(&*self.inner, channel, msg_id, emoji))
+            .await
+    }
+
+    async fn remove_reaction(
+        &self,
+        channel: &str,
+        msg_id: &str,
+        emoji: &str,
+    ) -> Result<(), AgentError> {
+        let call = self.write("remove_reaction").target(channel);
+        self.stack
+            .run(call, DiscordExt::remove_reaction(&*self.inner, channel, msg_id, emoji))
+            .await
+    }
+
+    async fn pin_message(&self, channel: &str, msg_id: &str) -> Result<(), AgentError> {
+        let call = self.write("pin_message").target(channel);
+        self.stack
+            .run(call, DiscordExt::pin_message(&*self.inner, channel, msg_id))
+            .await
+    }
+}
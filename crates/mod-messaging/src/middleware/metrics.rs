@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nexus_domain::Platform;
+use nexus_error::AgentError;
+use serde::Serialize;
+use tracing::debug;
+
+use super::{Call, Layer, Next};
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct OperationStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+impl OperationStats {
+    pub fn mean_ms(&self) -> u64 {
+        self.total_ms.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+// Call counts and latencies per platform and operation, shared between a
+// `TimingLayer` and whoever reports them.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    stats: Arc<Mutex<BTreeMap<(Platform, &'static str), OperationStats>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, call: &Call, elapsed: Duration, ok: bool) {
+        let ms = elapsed.as_millis() as u64;
+        if let Ok(mut stats) = self.stats.lock() {
+            let entry = stats.entry((call.platform, call.operation)).or_default();
+            entry.calls += 1;
+            entry.errors += u64::from(!ok);
+            entry.total_ms += ms;
+            entry.max_ms = entry.max_ms.max(ms);
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<(Platform, &'static str, OperationStats)> {
+        match self.stats.lock() {
+            Ok(stats) => stats.iter().map(|(&(p, op), s)| (p, op, *s)).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+pub struct TimingLayer {
+    metrics: Metrics,
+}
+
+impl TimingLayer {
+    pub fn new(metrics: Metrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl Layer for TimingLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = next.await;
+            let elapsed = start.elapsed();
+            // A dry run stopping a call is not the platform failing it.
+            let ok = matches!(result, Ok(_) | Err(AgentError::DryRun { .. }));
+            self.metrics.record(call, elapsed, ok);
+            debug!(
+                platform = %call.platform,
+                operation = call.operation,
+                elapsed_ms = elapsed.as_millis() as u64,
+                ok,
+                "adapter call"
+            );
+            result
+        })
+    }
+}
@@ -0,0 +1,153 @@
+// Composable middleware around adapters, in the spirit of tower's `Layer`.
+// A `Stack` wraps an adapter in `Layered`, which implements `MessagingPort`
+// and every extension trait the adapter does, and sends each call through
+// the layers before it reaches the adapter.
+
+mod audit;
+mod cache;
+mod directory;
+mod layered;
+mod metrics;
+mod policy;
+mod rate_limit;
+mod store;
+mod trace;
+
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use nexus_domain::{MessagingPort, Platform};
+use nexus_error::AgentError;
+
+pub use audit::AuditLayer;
+pub use cache::CacheLayer;
+pub use directory::DirectoryLayer;
+pub use layered::Layered;
+pub use metrics::{Metrics, OperationStats, TimingLayer};
+pub use policy::{Guard, PolicyLayer};
+pub use rate_limit::{Limits, Rate, RateLimitLayer};
+pub use store::StoreLayer;
+pub use trace::TraceLayer;
+
+// Type-erased so one layer can handle every operation, and shared so the
+// cache can hand out the same reply twice.
+pub type Reply = Arc<dyn Any + Send + Sync>;
+
+pub type Next<'a> = Pin<Box<dyn Future<Output = Result<Reply, AgentError>> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub platform: Platform,
+    pub operation: &'static str,
+    pub access: Access,
+    // The chat, channel or recipient the call acts on, if any.
+    pub target: Option<String>,
+    // The arguments that tell this call apart from others of the same
+    // operation. Only reads whose result can be reused have one.
+    pub key: Option<String>,
+}
+
+impl Call {
+    pub fn read(platform: Platform, operation: &'static str) -> Self {
+        Self {
+            platform,
+            operation,
+            access: Access::Read,
+            target: None,
+            key: Some(String::new()),
+        }
+    }
+
+    pub fn write(platform: Platform, operation: &'static str) -> Self {
+        Self {
+            platform,
+            operation,
+            access: Access::Write,
+            target: None,
+            key: None,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        if self.key.is_some() {
+            self.key = Some(key.into());
+        }
+        self
+    }
+
+    // For reads whose answer changes on its own, like delivery status.
+    pub fn uncached(mut self) -> Self {
+        self.key = None;
+        self
+    }
+}
+
+pub trait Layer: Send + Sync {
+    // `next` is the rest of the stack down to the adapter; a layer may answer
+    // without polling it.
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a>;
+}
+
+// Clones share the layers and their state, so the same stack can wrap an
+// adapter for the service and for its extension tools.
+#[derive(Clone, Default)]
+pub struct Stack {
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The first layer added is the outermost.
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn wrap<P: MessagingPort + ?Sized>(&self, inner: Arc<P>) -> Arc<Layered<P>> {
+        Arc::new(Layered::new(inner, self.clone()))
+    }
+
+    pub(crate) async fn run<T, Fut>(&self, call: Call, fut: Fut) -> Result<T, AgentError>
+    where
+        T: Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, AgentError>> + Send,
+    {
+        if self.layers.is_empty() {
+            return fut.await;
+        }
+        let inner: Next<'_> = Box::pin(async move { fut.await.map(|v| Arc::new(v) as Reply) });
+        let next = self
+            .layers
+            .iter()
+            .rev()
+            .fold(inner, |next, layer| layer.call(&call, next));
+        let reply = next.await?;
+        reply
+            .downcast::<T>()
+            .map(|v| Arc::try_unwrap(v).unwrap_or_else(|v| (*v).clone()))
+            .map_err(|_| {
+                AgentError::internal(format!("middleware returned the wrong type for {}", call.operation))
+                    .in_context(call.platform, call.operation)
+            })
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use nexus_domain::{Channel, Message, Paginated, Platform};
+use nexus_error::AgentError;
+
+use super::{Access, Call, Layer, Next, Reply};
+use crate::directory::ChatDirectory;
+use crate::policy::{current_tool, Policy, PolicyRequest};
+
+type Check = dyn Fn(&Call) -> Result<(), String> + Send + Sync;
+
+// Operations whose target is a user, a new name or a server rather than a
+// chat, so there is nothing to resolve.
+const NOT_CHATS: &[&str] = &["get_user", "get_user_info", "create_channel", "list_guild_channels"];
+
+// The access policy applied to adapter calls. Targets are resolved to their
+// ID and names before the rules are checked, so a chat is matched however a
+// call names it, and chats the policy denies are dropped from read results.
+#[derive(Clone)]
+pub struct Guard {
+    policy: Arc<Policy>,
+    chats: Arc<ChatDirectory>,
+}
+
+impl Guard {
+    pub fn new(policy: Arc<Policy>, chats: Arc<ChatDirectory>) -> Self {
+        Self { policy, chats }
+    }
+
+    pub async fn check(
+        &self,
+        platform: Platform,
+        operation: &str,
+        access: Access,
+        target: Option<&str>,
+    ) -> Result<(), AgentError> {
+        let tool = current_tool();
+        let targets: Vec<&str> = match target {
+            Some(t) if !NOT_CHATS.contains(&operation) => {
+                t.split(',').map(str::trim).filter(|t| !t.is_empty()).collect()
+            }
+            Some(t) => vec![t],
+            None => Vec::new(),
+        };
+        if targets.is_empty() {
+            return self.decide(tool.as_deref(), platform, operation, access, None, &[]);
+        }
+        for target in targets {
+            let aliases: Vec<String> = if self.policy.names_chats() && !NOT_CHATS.contains(&operation) {
+                let chat = self.chats.resolve(platform, target).await;
+                chat.all().map(String::from).collect()
+            } else {
+                Vec::new()
+            };
+            self.decide(tool.as_deref(), platform, operation, access, Some(target), &aliases)?;
+        }
+        Ok(())
+    }
+
+    fn decide(
+        &self,
+        tool: Option<&str>,
+        platform: Platform,
+        operation: &str,
+        access: Access,
+        target: Option<&str>,
+        aliases: &[String],
+    ) -> Result<(), AgentError> {
+        let req = PolicyRequest {
+            tool: tool.unwrap_or(operation),
+            platform: Some(platform),
+            access,
+            target,
+            aliases,
+        };
+        match tool {
+            Some(_) => self.policy.check(&req),
+            None => self.policy.check_chat(&req),
+        }
+    }
+
+    async fn readable(&self, platform: Platform, channel: &str) -> bool {
+        self.check(platform, "read_messages", Access::Read, Some(channel))
+            .await
+            .is_ok()
+    }
+
+    // Drops items from chats the policy does not let the current tool read,
+    // checking each distinct chat once.
+    pub async fn retain_by<T>(&self, items: &mut Vec<T>, message: impl Fn(&T) -> &Message) {
+        if !self.policy.names_chats() {
+            return;
+        }
+        let chats: HashSet<(Platform, String)> = items
+            .iter()
+            .map(|i| (message(i).platform, message(i).channel_id.clone()))
+            .collect();
+        let mut allowed = HashMap::with_capacity(chats.len());
+        for (platform, channel) in chats {
+            let ok = self.readable(platform, &channel).await;
+            allowed.insert((platform, channel), ok);
+        }
+        items.retain(|i| {
+            let m = message(i);
+            allowed
+                .get(&(m.platform, m.channel_id.clone()))
+                .copied()
+                .unwrap_or(false)
+        });
+    }
+
+    pub async fn retain_messages(&self, messages: &mut Vec<Message>) {
+        self.retain_by(messages, |m| m).await;
+    }
+
+    pub async fn retain_channels(&self, channels: &mut Vec<Channel>) {
+        if !self.policy.names_chats() {
+            return;
+        }
+        self.chats.learn(channels);
+        let mut kept = Vec::with_capacity(channels.len());
+        for ch in channels.drain(..) {
+            if self.readable(ch.platform, &ch.id).await {
+                kept.push(ch);
+            }
+        }
+        *channels = kept;
+    }
+
+    async fn filter(&self, reply: Reply) -> Reply {
+        if !self.policy.names_chats() {
+            return reply;
+        }
+        if let Some(page) = reply.downcast_ref::<Paginated<Message>>() {
+            let mut page = page.clone();
+            self.retain_messages(&mut page.items).await;
+            return Arc::new(page);
+        }
+        if let Some(msgs) = reply.downcast_ref::<Vec<Message>>() {
+            let mut msgs = msgs.clone();
+            self.retain_messages(&mut msgs).await;
+            return Arc::new(msgs);
+        }
+        if let Some(channels) = reply.downcast_ref::<Vec<Channel>>() {
+            let mut channels = channels.clone();
+            self.retain_channels(&mut channels).await;
+            return Arc::new(channels);
+        }
+        reply
+    }
+}
+
+enum Rules {
+    Check(Arc<Check>),
+    Policy(Guard),
+}
+
+// Refuses calls the check rejects with `POLICY_DENIED`, before they reach
+// the adapter or any layer inside this one.
+pub struct PolicyLayer {
+    rules: Rules,
+}
+
+impl PolicyLayer {
+    pub fn new(check: impl Fn(&Call) -> Result<(), String> + Send + Sync + 'static) -> Self {
+        Self {
+            rules: Rules::Check(Arc::new(check)),
+        }
+    }
+
+    pub fn read_only() -> Self {
+        Self::new(|call| match call.access {
+            Access::Read => Ok(()),
+            Access::Write => Err(format!("{} changes data and the server is read-only", call.operation)),
+        })
+    }
+
+    pub fn access(guard: Guard) -> Self {
+        Self {
+            rules: Rules::Policy(guard),
+        }
+    }
+}
+
+impl Layer for PolicyLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        Box::pin(async move {
+            match self.rules {
+                Rules::Check(ref check) => {
+                    if let Err(reason) = check(call) {
+                        return Err(AgentError::policy_denied(reason)
+                            .in_context(call.platform, call.operation));
+                    }
+                    next.await
+                }
+                Rules::Policy(ref guard) => {
+                    guard
+                        .check(call.platform, call.operation, call.access, call.target.as_deref())
+                        .await
+                        .map_err(|e| e.in_context(call.platform, call.operation))?;
+                    let reply = next.await?;
+                    match call.access {
+                        Access::Read => Ok(guard.filter(reply).await),
+                        Access::Write => Ok(reply),
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,429 @@
+// Outbound rate limiting. Each platform has a read budget and a write
+// budget, and writes also have a budget per chat, so an agent stuck in a
+// loop cannot get the account flood-banned or the app throttled. The
+// defaults follow the limits each platform publishes. Work the server does
+// on its own, like store sync, shares the budgets but yields to tool calls.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use nexus_domain::{dry_run, Platform};
+use nexus_error::{AgentError, RateLimitScope};
+use tracing::debug;
+
+use super::{Access, Call, Layer, Next};
+use crate::policy::current_tool;
+
+// Below the fan-out timeout, so a queued call in a fan-out is refused with
+// a retry time rather than cut off.
+const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(15);
+// The share of each bucket's burst that calls outside any tool call leave
+// for tool calls.
+const BACKGROUND_RESERVE: f64 = 0.5;
+// Per-chat buckets that have filled up again are dropped past this many.
+const PRUNE_AT: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl Rate {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            per_second: per_second.max(f64::MIN_POSITIVE),
+            burst: burst.max(1),
+        }
+    }
+
+    pub fn per_minute(calls: u32) -> Self {
+        Self::new(f64::from(calls) / 60.0, (calls / 6).max(1))
+    }
+
+    // `N/s`, `N/m` or `N/h`, optionally with a burst: `20/m:3`. Without one
+    // the burst is a sixth of N.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (rate, burst) = match s.split_once(':') {
+            Some((rate, burst)) => (rate, Some(burst.trim().parse::<u32>().ok()?)),
+            None => (s, None),
+        };
+        let (calls, unit) = rate.split_once('/')?;
+        let calls: f64 = calls.trim().parse().ok().filter(|n: &f64| n.is_finite() && *n > 0.0)?;
+        let secs = match unit.trim() {
+            "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" | "hour" => 3600.0,
+            _ => return None,
+        };
+        let burst = burst.unwrap_or((calls / 6.0) as u32);
+        Some(Self::new(calls / secs, burst))
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.per_second >= 1.0 {
+            write!(f, "{}/s", self.per_second.round())
+        } else {
+            write!(f, "{}/min", (self.per_second * 60.0).round())
+        }
+    }
+}
+
+// One platform's budgets. `None` leaves that kind of call unlimited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub read: Option<Rate>,
+    pub write: Option<Rate>,
+    // Writes to any one chat, channel or recipient.
+    pub per_channel: Option<Rate>,
+}
+
+impl Limits {
+    pub fn uniform(rate: Rate) -> Self {
+        Self {
+            read: Some(rate),
+            write: Some(rate),
+            per_channel: None,
+        }
+    }
+
+    pub fn defaults(platform: Platform) -> Self {
+        match platform {
+            // Telegram documents limits for bots: about 30 messages a second
+            // overall, one a second per chat and 20 a minute per group. User
+            // accounts, which TDLib signs in as, hit FLOOD_WAIT well before.
+            Platform::Telegram => Self {
+                read: Some(Rate::per_minute(300)),
+                write: Some(Rate::per_minute(60)),
+                per_channel: Some(Rate::per_minute(20)),
+            },
+            // Most Web API methods are Tier 3 (50+ a minute); chat.postMessage
+            // allows about one message a second per channel.
+            Platform::Slack => Self {
+                read: Some(Rate::per_minute(50)),
+                write: Some(Rate::per_minute(50)),
+                per_channel: Some(Rate::new(1.0, 3)),
+            },
+            // 50 requests a second per bot, split here between reads and
+            // writes, and 5 messages per 5 seconds per channel.
+            Platform::Discord => Self {
+                read: Some(Rate::new(25.0, 25)),
+                write: Some(Rate::new(25.0, 25)),
+                per_channel: Some(Rate::new(1.0, 5)),
+            },
+            // The Cloud API sends up to 80 messages a second per number, and
+            // one every 6 seconds to the same user.
+            Platform::WhatsApp => Self {
+                read: Some(Rate::new(10.0, 20)),
+                write: Some(Rate::new(80.0, 80)),
+                per_channel: Some(Rate::new(1.0 / 6.0, 3)),
+            },
+            // The Gmail API allows 250 quota units a second per user and a
+            // send costs 100; accounts may send 500 to 2,000 mails a day.
+            Platform::Gmail => Self {
+                read: Some(Rate::new(25.0, 25)),
+                write: Some(Rate::per_minute(20)),
+                per_channel: Some(Rate::per_minute(10)),
+            },
+        }
+    }
+
+    // `spec` is comma-separated `read=`, `write=` and `channel=` rates, where
+    // `off` lifts that limit: `write=30/m,channel=1/s:3`.
+    pub fn with_overrides(mut self, spec: &str) -> Result<Self, String> {
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=rate, got {part}"))?;
+            let rate = match value.trim() {
+                "off" => None,
+                v => Some(Rate::parse(v).ok_or_else(|| format!("invalid rate {v}; use e.g. 20/m or 1/s:3"))?),
+            };
+            match name.trim() {
+                "read" => self.read = rate,
+                "write" => self.write = rate,
+                "channel" => self.per_channel = rate,
+                other => return Err(format!("unknown budget {other}; use read, write or channel")),
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BucketKey {
+    platform: Platform,
+    access: Access,
+    channel: Option<String>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+    rate: Rate,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let refill = now.duration_since(self.last).as_secs_f64() * self.rate.per_second;
+        self.tokens = (self.tokens + refill).min(f64::from(self.rate.burst));
+        self.last = now;
+    }
+
+    // How long until this bucket has a token for one more call and `keep`
+    // tokens to spare.
+    fn wait(&self, keep: f64) -> Duration {
+        let needed = (1.0 + keep).min(f64::from(self.rate.burst));
+        if self.tokens >= needed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) / self.rate.per_second)
+        }
+    }
+}
+
+enum Reservation {
+    // Taken; go after this wait.
+    Taken(Duration),
+    // Nothing taken; ask again after this wait.
+    Later(Duration),
+}
+
+// Token buckets per platform for reads and writes, and per chat for writes.
+// Tokens may go negative, which queues later callers behind earlier ones.
+// Calls made outside any tool call never queue: they wait until a bucket has
+// tokens to spare beyond a reserve left for tool calls.
+pub struct RateLimitLayer {
+    uniform: Option<Limits>,
+    overrides: HashMap<Platform, Limits>,
+    max_wait: Duration,
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl RateLimitLayer {
+    pub fn defaults() -> Self {
+        Self {
+            uniform: None,
+            overrides: HashMap::new(),
+            max_wait: DEFAULT_MAX_WAIT,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn per_minute(calls: u32) -> Self {
+        Self {
+            uniform: Some(Limits::uniform(Rate::per_minute(calls))),
+            ..Self::defaults()
+        }
+    }
+
+    pub fn with_limits(mut self, platform: Platform, limits: Limits) -> Self {
+        self.overrides.insert(platform, limits);
+        self
+    }
+
+    // A zero wait refuses every call made while its budget is empty.
+    pub fn max_wait(mut self, wait: Duration) -> Self {
+        self.max_wait = wait;
+        self
+    }
+
+    pub fn limits(&self, platform: Platform) -> Limits {
+        self.overrides
+            .get(&platform)
+            .copied()
+            .or(self.uniform)
+            .unwrap_or_else(|| Limits::defaults(platform))
+    }
+
+    // Takes a token from every bucket the call draws on and returns how
+    // long to wait for the slowest. A call that would wait past max_wait
+    // takes nothing and is refused. A background call takes nothing until
+    // it can go at once.
+    fn reserve(&self, call: &Call, background: bool) -> Result<Reservation, AgentError> {
+        let limits = self.limits(call.platform);
+        let mut draws: Vec<(Option<&String>, Rate)> = Vec::with_capacity(2);
+        match call.access {
+            Access::Read => draws.extend(limits.read.map(|r| (None, r))),
+            Access::Write => {
+                draws.extend(limits.write.map(|r| (None, r)));
+                if let (Some(rate), Some(target)) = (limits.per_channel, call.target.as_ref()) {
+                    draws.push((Some(target), rate));
+                }
+            }
+        }
+        if draws.is_empty() {
+            return Ok(Reservation::Taken(Duration::ZERO));
+        }
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(Reservation::Taken(Duration::ZERO));
+        };
+        let now = Instant::now();
+        if buckets.len() >= PRUNE_AT {
+            buckets.retain(|key, bucket| {
+                bucket.refill(now);
+                key.channel.is_none() || bucket.tokens < f64::from(bucket.rate.burst)
+            });
+        }
+
+        let key = |channel: Option<&String>| BucketKey {
+            platform: call.platform,
+            access: call.access,
+            channel: channel.cloned(),
+        };
+        let mut wait = Duration::ZERO;
+        let mut slowest = None;
+        for &(channel, rate) in &draws {
+            let bucket = buckets.entry(key(channel)).or_insert(Bucket {
+                tokens: f64::from(rate.burst),
+                last: now,
+                rate,
+            });
+            bucket.rate = rate;
+            bucket.refill(now);
+            let keep = if background { f64::from(rate.burst) * BACKGROUND_RESERVE } else { 0.0 };
+            if bucket.wait(keep) > wait {
+                wait = bucket.wait(keep);
+                slowest = Some((channel, rate));
+            }
+        }
+        if background && !wait.is_zero() {
+            return Ok(Reservation::Later(wait));
+        }
+        if let Some((channel, rate)) = slowest.filter(|_| wait > self.max_wait) {
+            return Err(self.refusal(call, channel, rate, wait));
+        }
+        for &(channel, _) in &draws {
+            if let Some(bucket) = buckets.get_mut(&key(channel)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(Reservation::Taken(wait))
+    }
+
+    fn refusal(&self, call: &Call, channel: Option<&String>, rate: Rate, wait: Duration) -> AgentError {
+        let kind = match call.access {
+            Access::Read => "read",
+            Access::Write => "write",
+        };
+        let (message, scope) = match channel {
+            Some(channel) => (
+                format!("{} {kind} budget for {channel} ({rate}) is used up", call.platform),
+                RateLimitScope::Resource,
+            ),
+            None => (
+                format!("{} {kind} budget ({rate}) is used up", call.platform),
+                RateLimitScope::Global,
+            ),
+        };
+        AgentError::rate_limited(message, Some(wait), scope)
+            .in_context(call.platform, call.operation)
+            .with_suggestion(format!(
+                "Slow down and retry after the wait; NEXUS_RATE_LIMIT_{} sets this budget",
+                call.platform.to_string().to_uppercase()
+            ))
+    }
+}
+
+impl Layer for RateLimitLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        // Reserve when polled, not when the stack is built, so calls an
+        // outer layer answers itself (cache hits, denials) cost nothing.
+        // Dry-run writes send nothing, so they draw on no write budget.
+        Box::pin(async move {
+            if call.access == Access::Write && dry_run::active() {
+                return next.await;
+            }
+            let background = current_tool().is_none();
+            loop {
+                let (wait, taken) = match self.reserve(call, background)? {
+                    Reservation::Taken(wait) => (wait, true),
+                    Reservation::Later(wait) => (wait, false),
+                };
+                if !wait.is_zero() {
+                    debug!(
+                        platform = %call.platform,
+                        operation = call.operation,
+                        target = call.target.as_deref(),
+                        wait_ms = wait.as_millis() as u64,
+                        background,
+                        "rate limit wait"
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                if taken {
+                    break;
+                }
+            }
+            next.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rates_and_bursts() {
+        assert_eq!(Rate::parse("20/m"), Some(Rate::new(20.0 / 60.0, 3)));
+        assert_eq!(Rate::parse("1/s:3"), Some(Rate::new(1.0, 3)));
+        assert_eq!(Rate::parse(" 120 / min : 10 "), Some(Rate::new(2.0, 10)));
+        assert_eq!(Rate::parse("3600/h"), Some(Rate::new(1.0, 600)));
+        assert_eq!(Rate::parse("0.5/s"), Some(Rate::new(0.5, 1)));
+    }
+
+    #[test]
+    fn rejects_malformed_rates() {
+        for spec in [
+            "", "20", "20/d", "/m", "0/m", "-1/s", "inf/s", "NaN/s", "20/m:", "20/m:x", "20/m:-1",
+        ] {
+            assert_eq!(Rate::parse(spec), None, "{spec}");
+        }
+    }
+
+    #[test]
+    fn displays_the_rate_it_parsed() {
+        assert_eq!(
+            Rate::parse("5/s").map(|r| r.to_string()).as_deref(),
+            Some("5/s")
+        );
+        assert_eq!(
+            Rate::parse("30/m").map(|r| r.to_string()).as_deref(),
+            Some("30/min")
+        );
+    }
+
+    #[test]
+    fn overrides_replace_or_lift_budgets() {
+        let base = Limits::defaults(Platform::Slack);
+        let Ok(limits) = base.with_overrides("write=30/m, channel=off") else {
+            unreachable!("overrides are valid")
+        };
+        assert_eq!(limits.read, base.read);
+        assert_eq!(limits.write, Rate::parse("30/m"));
+        assert_eq!(limits.per_channel, None);
+        assert!(base.with_overrides("writes=30/m").is_err());
+        assert!(base.with_overrides("write=fast").is_err());
+        assert!(base.with_overrides("write").is_err());
+    }
+
+    #[test]
+    fn bucket_waits_for_the_missing_tokens() {
+        let mut bucket = Bucket {
+            tokens: 0.0,
+            last: Instant::now(),
+            rate: Rate::new(2.0, 4),
+        };
+        assert_eq!(bucket.wait(0.0), Duration::from_millis(500));
+        assert_eq!(bucket.wait(1.0), Duration::from_secs(1));
+        // Keeping more than the burst would never be satisfied.
+        assert_eq!(bucket.wait(10.0), Duration::from_secs(2));
+        bucket.tokens = 4.0;
+        assert_eq!(bucket.wait(2.0), Duration::ZERO);
+    }
+}
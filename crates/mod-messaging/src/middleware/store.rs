@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use nexus_domain::{Channel, Message, Paginated};
+use tracing::warn;
+
+use super::{Call, Layer, Next, Reply};
+use crate::store::MessageStore;
+
+// Storing is best effort: a failure is logged and the reply passes through
+// untouched.
+pub struct StoreLayer {
+    store: Arc<MessageStore>,
+}
+
+impl StoreLayer {
+    pub fn new(store: Arc<MessageStore>) -> Self {
+        Self { store }
+    }
+}
+
+fn record(store: &MessageStore, reply: &Reply) -> Result<(), nexus_error::AgentError> {
+    if let Some(page) = reply.downcast_ref::<Paginated<Message>>() {
+        store.record(&page.items)?;
+    } else if let Some(msg) = reply.downcast_ref::<Message>() {
+        store.record(std::slice::from_ref(msg))?;
+    } else if let Some(msgs) = reply.downcast_ref::<Vec<Message>>() {
+        store.record(msgs)?;
+    } else if let Some(channels) = reply.downcast_ref::<Vec<Channel>>() {
+        store.record_channels(channels)?;
+    }
+    Ok(())
+}
+
+impl Layer for StoreLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        Box::pin(async move {
+            let reply = next.await?;
+            let stored = reply.clone();
+            if let Err(e) = self.store.run(move |s| record(s, &stored)).await {
+                warn!(platform = %call.platform, operation = call.operation, error = %e, "storing messages failed");
+            }
+            Ok(reply)
+        })
+    }
+}
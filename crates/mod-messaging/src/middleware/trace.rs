@@ -0,0 +1,26 @@
+use tracing::{debug, debug_span, Instrument};
+
+use super::{Call, Layer, Next};
+
+pub struct TraceLayer;
+
+impl Layer for TraceLayer {
+    fn call<'a>(&'a self, call: &'a Call, next: Next<'a>) -> Next<'a> {
+        let span = debug_span!(
+            "adapter",
+            platform = %call.platform,
+            operation = call.operation,
+            target = call.target.as_deref(),
+        );
+        Box::pin(
+            async move {
+                let result = next.await;
+                if let Err(ref e) = result {
+                    debug!(error = %e, "adapter call failed");
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
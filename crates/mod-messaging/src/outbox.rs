@@ -0,0 +1,561 @@
+// Idempotent sends. A send made with an idempotency key is recorded in an
+// outbox file in the data dir: first as pending, then with its result. A
+// retried call with the same key gets the recorded result back instead of
+// sending again. A send that never confirmed (a timeout, a crash) leaves
+// its entry pending; the retry then looks for the message before deciding.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use nexus_domain::{dry_run, Message, Platform, TimeRange};
+use nexus_error::AgentError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::retry::Idempotency;
+use crate::service::{validate_not_empty, AgentService};
+use crate::time::now_ts;
+
+// How far back to look for a send that may have gone through.
+const RECONCILE_WINDOW: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    request: String,
+    started_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    // A call in this process is sending under the key right now.
+    #[serde(skip)]
+    in_flight: bool,
+}
+
+pub enum Claim {
+    // Not seen before; the caller sends and then completes or releases.
+    Fresh,
+    Replay(Value),
+    // Sent before without a confirmed outcome. The caller now holds the
+    // key and must complete, release or unclaim it.
+    InDoubt { started_at: i64 },
+}
+
+pub struct Outbox {
+    path: PathBuf,
+    ttl: i64,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Outbox {
+    pub fn open(path: &Path, ttl: Duration) -> Result<Self, AgentError> {
+        let entries: HashMap<String, Entry> = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                AgentError::internal(format!("corrupt outbox {}: {e}", path.display()))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(AgentError::internal(format!(
+                    "failed to read outbox {}: {e}",
+                    path.display()
+                )))
+            }
+        };
+        let outbox = Self {
+            path: path.to_path_buf(),
+            ttl: ttl.as_secs() as i64,
+            entries: Mutex::new(entries),
+        };
+        let mut entries = outbox.lock()?;
+        outbox.expire(&mut entries);
+        outbox.save(&entries)?;
+        drop(entries);
+        Ok(outbox)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, Entry>>, AgentError> {
+        self.entries
+            .lock()
+            .map_err(|_| AgentError::internal("outbox lock poisoned"))
+    }
+
+    fn expire(&self, entries: &mut HashMap<String, Entry>) {
+        let cutoff = now_ts() - self.ttl;
+        entries.retain(|_, e| e.started_at > cutoff);
+    }
+
+    fn save(&self, entries: &HashMap<String, Entry>) -> Result<(), AgentError> {
+        let fail = |e: std::io::Error| {
+            AgentError::internal(format!("failed to write outbox {}: {e}", self.path.display()))
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(fail)?;
+        }
+        let json = serde_json::to_vec(entries)
+            .map_err(|e| AgentError::internal(format!("failed to encode outbox: {e}")))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(fail)?;
+        std::fs::rename(&tmp, &self.path).map_err(fail)
+    }
+
+    // `request` is a fingerprint of what is being sent. A key reused for a
+    // different request is an error rather than a silent replay.
+    pub fn claim(&self, key: &str, request: &str) -> Result<Claim, AgentError> {
+        let mut entries = self.lock()?;
+        self.expire(&mut entries);
+        if let Some(entry) = entries.get_mut(key) {
+            if entry.request != request {
+                return Err(AgentError::invalid_input(
+                    "idempotency_key was already used for a different request",
+                )
+                .with_argument("idempotency_key")
+                .with_suggestion("Use a new key for each distinct message"));
+            }
+            if let Some(ref result) = entry.result {
+                return Ok(Claim::Replay(result.clone()));
+            }
+            if entry.in_flight {
+                return Err(AgentError::invalid_input(
+                    "a send with this idempotency_key is still in progress",
+                )
+                .with_argument("idempotency_key")
+                .with_suggestion("Wait a moment and retry with the same key"));
+            }
+            entry.in_flight = true;
+            return Ok(Claim::InDoubt {
+                started_at: entry.started_at,
+            });
+        }
+        entries.insert(
+            key.to_string(),
+            Entry {
+                request: request.to_string(),
+                started_at: now_ts(),
+                result: None,
+                in_flight: true,
+            },
+        );
+        self.save(&entries)?;
+        Ok(Claim::Fresh)
+    }
+
+    pub fn complete(&self, key: &str, result: Value) -> Result<(), AgentError> {
+        let mut entries = self.lock()?;
+        if let Some(entry) = entries.get_mut(key) {
+            entry.result = Some(result);
+            entry.in_flight = false;
+        }
+        self.save(&entries)
+    }
+
+    pub fn unclaim(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(key) {
+                entry.in_flight = false;
+            }
+        }
+    }
+
+    // Forgets a send that definitely did not happen, so the key can be
+    // retried.
+    pub fn release(&self, key: &str) -> Result<(), AgentError> {
+        let mut entries = self.lock()?;
+        entries.remove(key);
+        self.save(&entries)
+    }
+}
+
+// FNV-1a, which unlike std's hasher gives the same answer in every build.
+pub fn fingerprint(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+// Errors after which the platform may or may not have acted.
+fn in_doubt(err: &AgentError) -> bool {
+    matches!(err, AgentError::Network { .. } | AgentError::Internal { .. })
+}
+
+fn in_doubt_error(started_at: i64) -> AgentError {
+    AgentError::invalid_input(format!(
+        "an earlier send with this idempotency_key (at {started_at}) never confirmed and may have been delivered"
+    ))
+    .with_argument("idempotency_key")
+    .with_suggestion("Check the chat; to send anyway, use a new idempotency_key")
+}
+
+impl AgentService {
+    // A repeat returns the recorded result, and a repeat of a send that never
+    // confirmed is refused, since it may have gone out. Dry runs leave the
+    // outbox alone.
+    pub async fn once<T, F, Fut>(&self, key: &str, request: &str, send: F) -> Result<T, AgentError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, AgentError>>,
+    {
+        let outbox = self.outbox_for(key)?;
+        if dry_run::active() {
+            return send().await;
+        }
+        match outbox.claim(key, request)? {
+            Claim::Replay(result) => replay(key, result),
+            Claim::InDoubt { started_at } => {
+                outbox.unclaim(key);
+                Err(in_doubt_error(started_at))
+            }
+            Claim::Fresh => record(outbox, key, send().await),
+        }
+    }
+
+    // A repeat of a send that never confirmed, such as a TDLib send that
+    // timed out after the message went out, looks for the message in the
+    // channel before sending again.
+    pub async fn send_message_once(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        key: &str,
+    ) -> Result<Message, AgentError> {
+        let outbox = self.outbox_for(key)?;
+        if dry_run::active() {
+            return self.send_message(platform, channel, text, reply_to).await;
+        }
+        let request = fingerprint(&[
+            "send_message",
+            &platform.to_string(),
+            channel,
+            text,
+            reply_to.unwrap_or(""),
+        ]);
+        match outbox.claim(key, &request)? {
+            Claim::Replay(result) => replay(key, result),
+            Claim::Fresh => {
+                let sent = self.send_message_keyed(platform, channel, text, reply_to, now_ts()).await;
+                record(outbox, key, sent)
+            }
+            Claim::InDoubt { started_at } => {
+                match self.find_sent(platform, channel, text, started_at).await {
+                    Ok(Some(msg)) => {
+                        info!(%platform, key, msg_id = %msg.id, "earlier send found; not sending again");
+                        let value = serde_json::to_value(&msg)
+                            .map_err(|e| AgentError::internal(format!("failed to encode result: {e}")))?;
+                        outbox.complete(key, value)?;
+                        Ok(msg)
+                    }
+                    Ok(None) => {
+                        info!(%platform, key, "earlier send not found; sending again");
+                        let sent = self
+                            .send_message_keyed(platform, channel, text, reply_to, started_at)
+                            .await;
+                        record(outbox, key, sent)
+                    }
+                    Err(e) => {
+                        warn!(%platform, key, error = %e, "could not check for an earlier send");
+                        outbox.unclaim(key);
+                        Err(in_doubt_error(started_at))
+                    }
+                }
+            }
+        }
+    }
+
+    // Retries like a read, except that an attempt after one that may have
+    // gone out first looks for it, as a repeat of the whole call would.
+    async fn send_message_keyed(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        since: i64,
+    ) -> Result<Message, AgentError> {
+        validate_not_empty(channel, "channel")?;
+        validate_not_empty(text, "text")?;
+        let adapter = self.get(platform)?;
+        let doubt = AtomicBool::new(false);
+        let msg = self
+            .call(platform, "send_message", Idempotency::Keyed, || {
+                let doubt = &doubt;
+                async move {
+                    if doubt.load(Ordering::SeqCst) {
+                        if let Some(msg) = self.find_sent(platform, channel, text, since).await? {
+                            info!(%platform, msg_id = %msg.id, "earlier attempt found; not sending again");
+                            return Ok(msg);
+                        }
+                    }
+                    let sent = adapter.send_message(channel, text, reply_to).await;
+                    doubt.store(matches!(&sent, Err(e) if in_doubt(e)), Ordering::SeqCst);
+                    sent
+                }
+            })
+            .await?;
+        info!(%platform, channel, msg_id = %msg.id, "sent message");
+        Ok(msg)
+    }
+
+    fn outbox_for(&self, key: &str) -> Result<&Outbox, AgentError> {
+        if key.trim().is_empty() {
+            return Err(AgentError::invalid_input("idempotency_key is empty").with_argument("idempotency_key"));
+        }
+        self.outbox()
+            .ok_or_else(|| AgentError::not_implemented("the outbox is not available, so idempotency_key cannot be honoured"))
+    }
+
+    // The newest messages since the first attempt, checked for one of our
+    // own with the same text. Messages posted as the chat itself, like a
+    // Telegram channel post, count as ours too.
+    async fn find_sent(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        since: i64,
+    ) -> Result<Option<Message>, AgentError> {
+        let adapter = self.get(platform)?;
+        let me = self
+            .call(platform, "get_profile", Idempotency::Idempotent, || adapter.get_profile())
+            .await?;
+        // A little slack for clocks that disagree with the platform's.
+        let range = TimeRange::new(Some(since - 60), None);
+        let page = self
+            .call(platform, "read_messages", Idempotency::Idempotent, || {
+                adapter.read_messages(channel, RECONCILE_WINDOW, None, None, range)
+            })
+            .await?;
+        let own = |m: &Message| {
+            !m.sender_id.is_empty()
+                && (m.sender_id.eq_ignore_ascii_case(&me.id) || m.sender_id == m.channel_id)
+        };
+        Ok(page.items.into_iter().find(|m| own(m) && m.text.trim() == text.trim()))
+    }
+}
+
+fn replay<T: DeserializeOwned>(key: &str, result: Value) -> Result<T, AgentError> {
+    info!(key, "idempotency_key seen before; returning the recorded result");
+    serde_json::from_value(result)
+        .map_err(|e| AgentError::internal(format!("recorded result for {key} is unreadable: {e}")))
+}
+
+fn record<T: Serialize>(outbox: &Outbox, key: &str, sent: Result<T, AgentError>) -> Result<T, AgentError> {
+    match sent {
+        Ok(result) => {
+            let value = serde_json::to_value(&result)
+                .map_err(|e| AgentError::internal(format!("failed to encode result: {e}")))?;
+            outbox.complete(key, value)?;
+            Ok(result)
+        }
+        Err(e) => {
+            if in_doubt(&e) {
+                outbox.unclaim(key);
+            } else {
+                outbox.release(key)?;
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use nexus_domain::{
+        Channel, DeliveryStatus, MessageMeta, MessagingPort, Paginated, Profile, ReadAnchor,
+        SearchClause, SearchQuery,
+    };
+
+    use super::*;
+    use crate::middleware::Stack;
+    use crate::retry::RetryPolicy;
+
+    // A chat whose first send fails with a network error, after or before
+    // the message went out.
+    struct FlakyChat {
+        delivers_first: bool,
+        sends: AtomicU32,
+        delivered: Mutex<Vec<Message>>,
+    }
+
+    impl FlakyChat {
+        fn new(delivers_first: bool) -> Arc<Self> {
+            Arc::new(Self {
+                delivers_first,
+                sends: AtomicU32::new(0),
+                delivered: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn delivered(&self) -> Vec<Message> {
+            self.delivered.lock().map(|d| d.clone()).unwrap_or_default()
+        }
+    }
+
+    #[async_trait]
+    impl MessagingPort for FlakyChat {
+        fn platform(&self) -> Platform {
+            Platform::Slack
+        }
+
+        async fn get_profile(&self) -> Result<Profile, AgentError> {
+            Ok(Profile {
+                platform: Platform::Slack,
+                id: "U0BOT".into(),
+                name: "bot".into(),
+                username: None,
+                email: None,
+                phone: None,
+            })
+        }
+
+        async fn list_channels(&self, _limit: usize) -> Result<Vec<Channel>, AgentError> {
+            Ok(Vec::new())
+        }
+
+        async fn read_messages(
+            &self,
+            _channel: &str,
+            _limit: usize,
+            _cursor: Option<&str>,
+            _anchor: Option<&ReadAnchor>,
+            _range: TimeRange,
+        ) -> Result<Paginated<Message>, AgentError> {
+            Ok(Paginated {
+                items: self.delivered(),
+                has_more: false,
+                next_cursor: None,
+                prev_cursor: None,
+            })
+        }
+
+        async fn send_message(
+            &self,
+            channel: &str,
+            text: &str,
+            _reply_to: Option<&str>,
+        ) -> Result<Message, AgentError> {
+            let n = self.sends.fetch_add(1, Ordering::SeqCst);
+            let msg = Message {
+                id: format!("m{n}"),
+                platform: Platform::Slack,
+                channel_id: channel.into(),
+                sender_id: "U0BOT".into(),
+                sender: "bot".into(),
+                text: text.into(),
+                timestamp: now_ts(),
+                has_attachment: false,
+                reply_to: None,
+                meta: MessageMeta::default(),
+            };
+            if n > 0 || self.delivers_first {
+                if let Ok(mut delivered) = self.delivered.lock() {
+                    delivered.push(msg.clone());
+                }
+            }
+            if n == 0 {
+                return Err(AgentError::network("timed out"));
+            }
+            Ok(msg)
+        }
+
+        async fn search(
+            &self,
+            _query: &SearchQuery,
+            _limit: usize,
+            _cursor: Option<&str>,
+        ) -> Result<Paginated<Message>, AgentError> {
+            Err(AgentError::not_implemented("search"))
+        }
+
+        fn unsupported_clauses(&self, _query: &SearchQuery) -> Vec<SearchClause> {
+            Vec::new()
+        }
+
+        async fn get_delivery_status(
+            &self,
+            _channel: &str,
+            _message_id: &str,
+        ) -> Result<DeliveryStatus, AgentError> {
+            Err(AgentError::not_implemented("get_delivery_status"))
+        }
+    }
+
+    struct Setup {
+        agent: AgentService,
+        chat: Arc<FlakyChat>,
+        path: PathBuf,
+    }
+
+    impl Drop for Setup {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn setup(delivers_first: bool) -> Setup {
+        let chat = FlakyChat::new(delivers_first);
+        let path =
+            std::env::temp_dir().join(format!("nexus-outbox-{:016x}.json", fastrand::u64(..)));
+        let mut agent = AgentService::new();
+        agent.register(chat.clone(), &Stack::new());
+        agent.set_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+        match Outbox::open(&path, Duration::from_secs(3600)) {
+            Ok(outbox) => agent.set_outbox(Arc::new(outbox)),
+            Err(e) => unreachable!("temp outbox opens: {e}"),
+        }
+        Setup { agent, chat, path }
+    }
+
+    #[tokio::test]
+    async fn keyed_send_finds_an_attempt_that_went_out() {
+        let s = setup(true);
+        let sent = s
+            .agent
+            .send_message_once(Platform::Slack, "C1", "hello", None, "k1")
+            .await;
+        assert_eq!(sent.ok().map(|m| m.id).as_deref(), Some("m0"));
+        assert_eq!(s.chat.sends.load(Ordering::SeqCst), 1);
+        assert_eq!(s.chat.delivered().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn keyed_send_retries_an_attempt_that_did_not() {
+        let s = setup(false);
+        let sent = s
+            .agent
+            .send_message_once(Platform::Slack, "C1", "hello", None, "k1")
+            .await;
+        assert_eq!(sent.ok().map(|m| m.id).as_deref(), Some("m1"));
+        assert_eq!(s.chat.sends.load(Ordering::SeqCst), 2);
+        assert_eq!(s.chat.delivered().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unkeyed_send_is_not_retried() {
+        let s = setup(false);
+        let sent = s
+            .agent
+            .send_message(Platform::Slack, "C1", "hello", None)
+            .await;
+        assert!(matches!(sent, Err(AgentError::Network { .. })));
+        assert_eq!(s.chat.sends.load(Ordering::SeqCst), 1);
+    }
+}
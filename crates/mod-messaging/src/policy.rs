@@ -0,0 +1,341 @@
+// Access policy. A JSON file of allow and deny rules over tools, platforms,
+// read/write access and the chats, channels or recipients a call acts on,
+// with `*` and `?` globs. The first rule that matches decides; calls no
+// rule matches get the default, which is allow unless the file says deny.
+
+use std::future::Future;
+use std::path::Path;
+
+use nexus_domain::Platform;
+use nexus_error::AgentError;
+use serde::Deserialize;
+
+use crate::middleware::Access;
+
+tokio::task_local! {
+    static TOOL: String;
+}
+
+// Runs a tool call with its name visible to the policy layer, which only
+// sees adapter operations.
+pub async fn scope<F: Future>(tool: &str, fut: F) -> F::Output {
+    TOOL.scope(tool.to_string(), fut).await
+}
+
+pub(crate) fn current_tool() -> Option<String> {
+    TOOL.try_with(Clone::clone).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    #[default]
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Globs {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Globs {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::One(p) => glob(p, value),
+            Self::Many(ps) => ps.iter().any(|p| glob(p, value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    effect: Effect,
+    #[serde(default)]
+    tool: Option<Globs>,
+    #[serde(default)]
+    platform: Option<Globs>,
+    #[serde(default)]
+    access: Option<AccessKind>,
+    // The chat, channel or recipient, under whichever name reads best.
+    #[serde(default, alias = "chat", alias = "recipient", alias = "target")]
+    channel: Option<Globs>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+impl Rule {
+    // Everything but the target, which callers check separately.
+    fn covers(&self, tool: &str, platform: Option<Platform>, access: Access) -> bool {
+        self.tool.as_ref().map_or(true, |g| g.matches(tool))
+            && self.platform.as_ref().map_or(true, |g| {
+                platform.is_some_and(|p| g.matches(&p.to_string()))
+            })
+            && self.access.map_or(true, |a| match a {
+                AccessKind::Read => access == Access::Read,
+                AccessKind::Write => access == Access::Write,
+            })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    default: Effect,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+// One thing a tool call does: the tool, the platform and the chat,
+// channel or recipient, when it has them.
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyRequest<'a> {
+    pub tool: &'a str,
+    pub platform: Option<Platform>,
+    pub access: Access,
+    pub target: Option<&'a str>,
+    // Other names the target resolved to: its ID, title or handles.
+    pub aliases: &'a [String],
+}
+
+impl PolicyRequest<'_> {
+    fn target_matches(&self, globs: &Globs) -> bool {
+        self.target
+            .into_iter()
+            .chain(self.aliases.iter().map(String::as_str))
+            .any(|t| globs.matches(t))
+    }
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        let bytes = std::fs::read(path).map_err(|e| {
+            AgentError::internal(format!("failed to read policy {}: {e}", path.display()))
+        })?;
+        serde_json::from_slice(&bytes).map_err(|e| {
+            AgentError::invalid_input(format!("invalid policy {}: {e}", path.display()))
+        })
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn check(&self, req: &PolicyRequest<'_>) -> Result<(), AgentError> {
+        let decided = self.rules.iter().enumerate().find(|(_, r)| {
+            r.covers(req.tool, req.platform, req.access)
+                && r.channel.as_ref().map_or(true, |g| req.target_matches(g))
+        });
+        let (effect, reason) = match decided {
+            Some((i, rule)) => (rule.effect, rule_reason(i, rule)),
+            None => (self.default, "the policy's default".to_string()),
+        };
+        deny_unless(effect, req, reason)
+    }
+
+    // For adapter calls made outside any tool call, such as background sync
+    // or queued sends: the tool was decided when the work was queued, so only
+    // rules about chats that name no tool apply, and nothing else is denied.
+    pub fn check_chat(&self, req: &PolicyRequest<'_>) -> Result<(), AgentError> {
+        let decided = self.rules.iter().enumerate().find(|(_, r)| {
+            r.tool.is_none()
+                && r.covers(req.tool, req.platform, req.access)
+                && r.channel.as_ref().is_some_and(|g| req.target_matches(g))
+        });
+        match decided {
+            Some((i, rule)) => deny_unless(rule.effect, req, rule_reason(i, rule)),
+            None => Ok(()),
+        }
+    }
+
+    // Whether any rule is about particular chats, which is when calls need
+    // their targets resolved and results need filtering.
+    pub fn names_chats(&self) -> bool {
+        self.rules.iter().any(|r| r.channel.is_some())
+    }
+
+    // False only when a rule denies `tool` whatever the target; used to hide
+    // tools from listings.
+    pub fn may_use(&self, tool: &str, platform: Option<Platform>, access: Access) -> bool {
+        for rule in self.rules.iter().filter(|r| r.covers(tool, platform, access)) {
+            match (rule.channel.is_some(), rule.effect) {
+                (false, effect) => return effect == Effect::Allow,
+                (true, Effect::Allow) => return true,
+                (true, Effect::Deny) => {}
+            }
+        }
+        self.default == Effect::Allow
+    }
+}
+
+fn rule_reason(index: usize, rule: &Rule) -> String {
+    rule.reason
+        .clone()
+        .unwrap_or_else(|| format!("policy rule {}", index + 1))
+}
+
+fn deny_unless(effect: Effect, req: &PolicyRequest<'_>, reason: String) -> Result<(), AgentError> {
+    if effect == Effect::Allow {
+        return Ok(());
+    }
+    let mut what = req.tool.to_string();
+    if let Some(p) = req.platform {
+        what.push_str(&format!(" on {p}"));
+    }
+    if let Some(t) = req.target {
+        what.push_str(&format!(" in {t}"));
+    }
+    let mut err = AgentError::policy_denied(format!("{what} is denied: {reason}"))
+        .with_operation(req.tool);
+    if let Some(p) = req.platform {
+        err = err.with_platform(p.to_string());
+    }
+    Err(err)
+}
+
+// Case-insensitive; `*` matches any run of characters, `?` any one.
+pub(crate) fn glob(pattern: &str, value: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let v: Vec<char> = value.to_lowercase().chars().collect();
+    let (mut pi, mut vi) = (0, 0);
+    // Where the last `*` was and how much of the value it has taken.
+    let mut star: Option<(usize, usize)> = None;
+    while vi < v.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, vi));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == v[vi] => {
+                pi += 1;
+                vi += 1;
+            }
+            _ => match star {
+                Some((sp, sv)) => {
+                    pi = sp + 1;
+                    vi = sv + 1;
+                    star = Some((sp, sv + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(json: &str) -> Policy {
+        match serde_json::from_str(json) {
+            Ok(policy) => policy,
+            Err(e) => unreachable!("test policy does not parse: {e}"),
+        }
+    }
+
+    fn request<'a>(
+        tool: &'a str,
+        target: Option<&'a str>,
+        aliases: &'a [String],
+    ) -> PolicyRequest<'a> {
+        PolicyRequest {
+            tool,
+            platform: Some(Platform::Slack),
+            access: Access::Write,
+            target,
+            aliases,
+        }
+    }
+
+    fn allows(p: &Policy, tool: &str, target: Option<&str>, aliases: &[String]) -> bool {
+        p.check(&request(tool, target, aliases)).is_ok()
+    }
+
+    #[test]
+    fn glob_matches_literals_case_insensitively() {
+        assert!(glob("send_message", "send_message"));
+        assert!(glob("#General", "#general"));
+        assert!(!glob("send_message", "send_messages"));
+        assert!(!glob("send_messages", "send_message"));
+        assert!(glob("", ""));
+        assert!(!glob("", "x"));
+    }
+
+    #[test]
+    fn glob_star_and_question_mark() {
+        assert!(glob("*", ""));
+        assert!(glob("*", "anything"));
+        assert!(glob("slack_*", "slack_set_topic"));
+        assert!(glob("*_message", "telegram_edit_message"));
+        assert!(glob("#team-*-ops", "#team-eu-west-ops"));
+        assert!(glob("a*b*c", "aXXbYYbZc"));
+        assert!(!glob("a*b*c", "aXXbYY"));
+        assert!(glob("C0?BOTS01", "C04BOTS01"));
+        assert!(!glob("C0?BOTS01", "C0BOTS01"));
+        assert!(glob("**", "x"));
+        assert!(glob("x*", "x"));
+    }
+
+    #[test]
+    fn glob_handles_non_ascii() {
+        assert!(glob("café-?", "CAFÉ-1"));
+        assert!(glob("*ü*", "grüße"));
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let p = policy(
+            r##"{"default": "deny", "rules": [
+                {"effect": "allow", "tool": "send_message", "channel": ["#bots", "#ops-*"]},
+                {"effect": "deny", "tool": "send_*", "reason": "sends go to #bots"},
+                {"effect": "allow", "tool": "send_*"}
+            ]}"##,
+        );
+        assert!(allows(&p, "send_message", Some("#bots"), &[]));
+        assert!(allows(&p, "send_message", Some("#ops-eu"), &[]));
+        assert!(!allows(&p, "send_message", Some("#random"), &[]));
+        assert!(!allows(&p, "send_file", Some("#bots"), &[]));
+        assert!(!allows(&p, "read_messages", Some("#bots"), &[]));
+    }
+
+    #[test]
+    fn target_rules_match_resolved_aliases() {
+        let p = policy(r##"{"rules": [{"effect": "deny", "chat": "#payroll"}]}"##);
+        let aliases = vec!["C0PAYROLL1".to_string(), "#payroll".to_string()];
+        assert!(!allows(&p, "send_message", Some("C0PAYROLL1"), &aliases));
+        assert!(allows(&p, "send_message", Some("C0PAYROLL1"), &[]));
+        let background = request("sync", Some("C0PAYROLL1"), &aliases);
+        assert!(p.check_chat(&background).is_err());
+    }
+
+    #[test]
+    fn may_use_hides_only_tools_denied_everywhere() {
+        let p = policy(
+            r##"{"rules": [
+                {"effect": "deny", "tool": "slack_set_topic"},
+                {"effect": "deny", "tool": "send_message", "channel": "#payroll"}
+            ]}"##,
+        );
+        assert!(!p.may_use("slack_set_topic", Some(Platform::Slack), Access::Write));
+        assert!(p.may_use("send_message", Some(Platform::Slack), Access::Write));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let parsed: Result<Policy, _> =
+            serde_json::from_str(r#"{"rules": [{"effect": "deny", "tools": "x"}]}"#);
+        assert!(parsed.is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use nexus_domain::SearchQuery;
+use nexus_error::AgentError;
+
+use crate::time;
+
+enum Token {
+    Word(String),
+    Phrase(String),
+}
+
+// Unknown `key:value` tokens are kept as plain search terms so URLs and
+// times like 10:30 still work.
+pub fn parse_query(input: &str) -> Result<SearchQuery, AgentError> {
+    let mut q = SearchQuery::default();
+
+    for token in tokenize(input)? {
+        let word = match token {
+            Token::Phrase(p) => {
+                q.phrases.push(p);
+                continue;
+            }
+            Token::Word(w) => w,
+        };
+
+        let Some((key, value)) = word.split_once(':') else {
+            q.terms.push(word);
+            continue;
+        };
+        if value.is_empty() {
+            q.terms.push(word);
+            continue;
+        }
+
+        match key.to_lowercase().as_str() {
+            "from" => set_once(&mut q.from, value, "from")?,
+            "in" => set_once(&mut q.channel, value, "in")?,
+            "has" => match value.to_lowercase().as_str() {
+                "attachment" | "attachments" | "file" | "files" => q.has_attachment = true,
+                other => {
+                    return Err(AgentError::invalid_input(format!(
+                        "unsupported 'has:{other}' (only has:attachment is supported)"
+                    )))
+                }
+            },
+            "is" => match value.to_lowercase().as_str() {
+                "unread" => q.is_unread = true,
+                other => {
+                    return Err(AgentError::invalid_input(format!(
+                        "unsupported 'is:{other}' (only is:unread is supported)"
+                    )))
+                }
+            },
+            "after" => q.range.since = Some(clause_time("after", value)?),
+            "before" => q.range.until = Some(clause_time("before", value)? - 1),
+            _ => q.terms.push(word),
+        }
+    }
+
+    if let (Some(s), Some(u)) = (q.range.since, q.range.until) {
+        if s > u {
+            return Err(AgentError::invalid_input("'after:' must be earlier than 'before:'"));
+        }
+    }
+    if q.is_empty() {
+        return Err(AgentError::invalid_input("query has no search terms or filters"));
+    }
+    Ok(q)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, AgentError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == '"' {
+            chars.next();
+            let phrase = read_quoted(&mut chars)?;
+            if !phrase.trim().is_empty() {
+                tokens.push(Token::Phrase(phrase.trim().to_string()));
+            }
+            continue;
+        }
+
+        // A quote inside a word (from:"Alice Smith") quotes the rest of the value.
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if c == '"' {
+                word.push_str(&read_quoted(&mut chars)?);
+            } else {
+                word.push(c);
+            }
+        }
+        tokens.push(Token::Word(word));
+    }
+
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<String, AgentError> {
+    let mut out = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(out);
+        }
+        out.push(c);
+    }
+    Err(AgentError::invalid_input("unterminated quote in query"))
+}
+
+fn set_once(slot: &mut Option<String>, value: &str, key: &str) -> Result<(), AgentError> {
+    if slot.is_some() {
+        return Err(AgentError::invalid_input(format!("'{key}:' may only appear once")));
+    }
+    *slot = Some(value.trim().to_string());
+    Ok(())
+}
+
+fn clause_time(key: &str, value: &str) -> Result<i64, AgentError> {
+    time::parse_instant(value).map_err(|e| match e {
+        AgentError::InvalidInput { message, ctx } => AgentError::InvalidInput {
+            message: format!("{key}: {message}"),
+            ctx,
+        },
+        other => other,
+    })
+}
@@ -0,0 +1,464 @@
+// PII redaction for tool output. Phone numbers, email addresses, card
+// numbers, IBANs and configured patterns are replaced with placeholders
+// like [EMAIL_1] before text leaves the server. The same value gets the same
+// placeholder for the life of the process, so the agent can still tell
+// people apart; in reversible mode placeholders in tool arguments are
+// swapped back, so it can reply to an address it never saw. The mapping is
+// in memory and capped, so a placeholder it no longer holds is refused
+// rather than sent as written.
+
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use nexus_error::AgentError;
+use regex::Regex;
+use serde_json::Value;
+
+const MAX_MAPPED: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Phone,
+    Email,
+    Card,
+    Iban,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Email, Kind::Iban, Kind::Card, Kind::Phone];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Phone => "PHONE",
+            Self::Email => "EMAIL",
+            Self::Card => "CARD",
+            Self::Iban => "IBAN",
+        }
+    }
+
+    // Bare digit runs are left alone: chat, message and user IDs are digit
+    // runs too. Phones need a leading + or the usual separators.
+    fn pattern(self) -> &'static str {
+        match self {
+            Self::Phone => r"\+\d{1,3}(?:[ .-]?\(?\d{1,4}\)?){2,5}|\(?\d{3}\)?[ .-]\d{3}[ .-]\d{4}",
+            Self::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+            Self::Card => r"\d(?:[ -]?\d){12,18}",
+            Self::Iban => r"[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?",
+        }
+    }
+
+    fn accepts(self, found: &str) -> bool {
+        let digits: String = found.chars().filter(char::is_ascii_digit).collect();
+        match self {
+            Self::Phone => (7..=15).contains(&digits.len()),
+            Self::Email => true,
+            // Unseparated runs must be the usual 15 or 16 digits, which
+            // keeps most numeric IDs out even when they pass Luhn.
+            Self::Card => {
+                let separated = digits.len() != found.len();
+                (13..=19).contains(&digits.len())
+                    && (separated || matches!(digits.len(), 15 | 16))
+                    && matches!(digits.as_bytes()[0], b'2'..=b'6')
+                    && luhn(&digits)
+            }
+            Self::Iban => iban_valid(found),
+        }
+    }
+
+    // The form two mentions of the same value share.
+    fn normalize(self, found: &str) -> String {
+        match self {
+            Self::Phone | Self::Card => found.chars().filter(char::is_ascii_digit).collect(),
+            Self::Email => found.to_lowercase(),
+            Self::Iban => found.chars().filter(|c| !c.is_whitespace()).collect(),
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "phone" | "phones" => Ok(Self::Phone),
+            "email" | "emails" => Ok(Self::Email),
+            "card" | "cards" => Ok(Self::Card),
+            "iban" | "ibans" => Ok(Self::Iban),
+            other => Err(format!("unknown redaction kind: {other} (use phone, email, card or iban)")),
+        }
+    }
+}
+
+struct Rule {
+    label: String,
+    regex: Regex,
+    // None for configured patterns, which are taken as written.
+    kind: Option<Kind>,
+}
+
+#[derive(Default)]
+struct Mapping {
+    placeholders: HashMap<(String, String), String>,
+    originals: HashMap<String, String>,
+    counts: HashMap<String, usize>,
+    // Oldest first, for eviction past MAX_MAPPED.
+    order: VecDeque<(String, String)>,
+}
+
+pub struct Redactor {
+    rules: Vec<Rule>,
+    reversible: bool,
+    placeholder: Regex,
+    mapping: Mutex<Mapping>,
+}
+
+impl Redactor {
+    pub fn new(kinds: &[Kind]) -> Result<Self, AgentError> {
+        let mut redactor = Self {
+            rules: Vec::new(),
+            reversible: false,
+            placeholder: compile(r"\[[A-Z][A-Z0-9_]*_\d+\]")?,
+            mapping: Mutex::new(Mapping::default()),
+        };
+        // Emails first, since their digits would otherwise read as phones.
+        for kind in Kind::ALL.into_iter().filter(|k| kinds.contains(k)) {
+            redactor.rules.push(Rule {
+                label: kind.label().to_string(),
+                regex: compile(kind.pattern())?,
+                kind: Some(kind),
+            });
+        }
+        Ok(redactor)
+    }
+
+    // A configured pattern, masked as `[NAME_n]`.
+    pub fn pattern(mut self, name: &str, pattern: &str) -> Result<Self, AgentError> {
+        let label: String = name
+            .trim()
+            .to_uppercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !label.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err(AgentError::invalid_input(format!(
+                "redaction pattern name {name:?} must start with a letter"
+            )));
+        }
+        self.rules.push(Rule {
+            label,
+            regex: compile(pattern)?,
+            kind: None,
+        });
+        Ok(self)
+    }
+
+    pub fn reversible(mut self, reversible: bool) -> Self {
+        self.reversible = reversible;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let Ok(mut mapping) = self.mapping.lock() else {
+            // Better to withhold the text than to let it out unmasked.
+            return "[output withheld: redaction unavailable]".to_string();
+        };
+        let mut out = text.to_string();
+        for rule in &self.rules {
+            let mut masked = String::with_capacity(out.len());
+            let mut last = 0;
+            for m in rule.regex.find_iter(&out) {
+                let found = m.as_str();
+                let accepted = match rule.kind {
+                    Some(kind) => standalone(&out, m.start(), m.end()) && kind.accepts(found),
+                    None => !found.is_empty(),
+                };
+                if !accepted {
+                    continue;
+                }
+                masked.push_str(&out[last..m.start()]);
+                masked.push_str(&self.placeholder_for(&mut mapping, rule, found));
+                last = m.end();
+            }
+            masked.push_str(&out[last..]);
+            out = masked;
+        }
+        out
+    }
+
+    fn placeholder_for(&self, mapping: &mut Mapping, rule: &Rule, found: &str) -> String {
+        let normalized = rule.kind.map_or_else(|| found.to_string(), |k| k.normalize(found));
+        let key = (rule.label.clone(), normalized);
+        if let Some(placeholder) = mapping.placeholders.get(&key) {
+            return placeholder.clone();
+        }
+        let count = mapping.counts.entry(rule.label.clone()).or_insert(0);
+        *count += 1;
+        let placeholder = format!("[{}_{count}]", rule.label);
+        if self.reversible {
+            mapping.originals.insert(placeholder.clone(), found.to_string());
+        }
+        // An evicted value gets a new number if it comes back, so an old
+        // placeholder never silently points at something else.
+        if mapping.order.len() >= MAX_MAPPED {
+            if let Some(old) = mapping.order.pop_front() {
+                if let Some(gone) = mapping.placeholders.remove(&old) {
+                    mapping.originals.remove(&gone);
+                }
+            }
+        }
+        mapping.order.push_back(key.clone());
+        mapping.placeholders.insert(key, placeholder.clone());
+        placeholder
+    }
+
+    // A placeholder of one of our labels that this process no longer maps
+    // was handed out before a restart or evicted since.
+    fn ours(&self, placeholder: &str) -> bool {
+        let inner = placeholder.trim_start_matches('[').trim_end_matches(']');
+        inner
+            .rsplit_once('_')
+            .is_some_and(|(label, _)| self.rules.iter().any(|r| r.label == label))
+    }
+
+    // Text holding a placeholder this process cannot restore is refused.
+    pub fn restore(&self, text: &str) -> Result<String, AgentError> {
+        if !self.reversible {
+            return Ok(text.to_string());
+        }
+        let mapping = self
+            .mapping
+            .lock()
+            .map_err(|_| AgentError::internal("redaction mapping unavailable"))?;
+        let mut unknown = Vec::new();
+        let restored = self
+            .placeholder
+            .replace_all(text, |caps: &regex::Captures<'_>| {
+                let placeholder = &caps[0];
+                match mapping.originals.get(placeholder) {
+                    Some(original) => original.clone(),
+                    None => {
+                        if self.ours(placeholder) && !unknown.iter().any(|u| u == placeholder) {
+                            unknown.push(placeholder.to_string());
+                        }
+                        placeholder.to_string()
+                    }
+                }
+            })
+            .into_owned();
+        if !unknown.is_empty() {
+            return Err(AgentError::invalid_input(format!(
+                "unknown placeholder {}: it was handed out before a restart or has expired",
+                unknown.join(", ")
+            ))
+            .with_suggestion("Read the messages again to get current placeholders."));
+        }
+        Ok(restored)
+    }
+
+    pub fn restore_args(&self, args: &mut Value) -> Result<(), AgentError> {
+        if !self.reversible {
+            return Ok(());
+        }
+        match args {
+            Value::String(s) => *s = self.restore(s)?,
+            Value::Array(items) => {
+                for item in items {
+                    self.restore_args(item)?;
+                }
+            }
+            Value::Object(fields) => {
+                for value in fields.values_mut() {
+                    self.restore_args(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, AgentError> {
+    Regex::new(pattern)
+        .map_err(|e| AgentError::invalid_input(format!("invalid redaction pattern {pattern:?}: {e}")))
+}
+
+// Not part of a longer word or number.
+fn standalone(text: &str, start: usize, end: usize) -> bool {
+    let joins = |c: char| c.is_alphanumeric() || c == '_';
+    !text[..start].chars().next_back().is_some_and(|c| joins(c) || c == '-' || c == '+')
+        && !text[end..].chars().next().is_some_and(joins)
+}
+
+fn luhn(digits: &str) -> bool {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let d = u32::from(b - b'0');
+            match (i % 2 == 1, d * 2) {
+                (true, doubled) if doubled > 9 => doubled - 9,
+                (true, doubled) => doubled,
+                (false, _) => d,
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+// ISO 13616: move the country code and check digits to the end, read
+// letters as 10-35, and the number mod 97 must be 1.
+fn iban_valid(found: &str) -> bool {
+    let compact: String = found.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    let mut rem: u32 = 0;
+    for c in tail.chars().chain(head.chars()) {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        rem = if value < 10 {
+            (rem * 10 + value) % 97
+        } else {
+            (rem * 100 + value) % 97
+        };
+    }
+    rem == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(reversible: bool) -> Redactor {
+        match Redactor::new(&Kind::ALL) {
+            Ok(r) => r.reversible(reversible),
+            Err(e) => unreachable!("built-in patterns compile: {e}"),
+        }
+    }
+
+    #[test]
+    fn luhn_checks_card_numbers() {
+        assert!(luhn("4111111111111111"));
+        assert!(luhn("5500005555555559"));
+        assert!(luhn("378282246310005"));
+        assert!(!luhn("4111111111111112"));
+        assert!(!luhn("1234567812345678"));
+    }
+
+    #[test]
+    fn iban_needs_valid_check_digits() {
+        assert!(iban_valid("GB82WEST12345698765432"));
+        assert!(iban_valid("GB82 WEST 1234 5698 7654 32"));
+        assert!(iban_valid("DE89370400440532013000"));
+        assert!(!iban_valid("GB82WEST12345698765433"));
+        assert!(!iban_valid("GB00WEST12345698765432"));
+        assert!(!iban_valid("GB82WEST1234"));
+    }
+
+    #[test]
+    fn masks_each_kind() {
+        let r = redactor(false);
+        assert_eq!(r.redact("mail Ann@Example.com now"), "mail [EMAIL_1] now");
+        assert_eq!(r.redact("call +44 20 7946 0958"), "call [PHONE_1]");
+        assert_eq!(r.redact("or (555) 123-4567."), "or [PHONE_2].");
+        assert_eq!(r.redact("card 4111 1111 1111 1111"), "card [CARD_1]");
+        assert_eq!(r.redact("pay GB82 WEST 1234 5698 7654 32"), "pay [IBAN_1]");
+    }
+
+    #[test]
+    fn leaves_ids_and_near_misses_alone() {
+        let r = redactor(false);
+        for text in [
+            "chat 15551234567",
+            "message 1234567890123456789",
+            "card 4111 1111 1111 1112",
+            "iban GB82 WEST 1234 5698 7654 33",
+            "id -1001234567890",
+            "ref ABC4111111111111111",
+        ] {
+            assert_eq!(r.redact(text), text);
+        }
+    }
+
+    #[test]
+    fn same_value_gets_same_placeholder() {
+        let r = redactor(false);
+        assert_eq!(r.redact("ann@example.com"), "[EMAIL_1]");
+        assert_eq!(r.redact("bob@example.com"), "[EMAIL_2]");
+        assert_eq!(r.redact("ANN@example.com"), "[EMAIL_1]");
+        assert_eq!(r.redact("+1 555 123 4567"), r.redact("+1-555-123-4567"));
+    }
+
+    #[test]
+    fn configured_patterns_use_their_name() {
+        let r = Redactor::new(&[]).and_then(|r| r.pattern("employee id", r"EMP-\d{6}"));
+        let Ok(r) = r else {
+            unreachable!("pattern compiles")
+        };
+        assert_eq!(
+            r.redact("EMP-123456 and EMP-654321"),
+            "[EMPLOYEE_ID_1] and [EMPLOYEE_ID_2]"
+        );
+        assert!(Redactor::new(&[])
+            .and_then(|r| r.pattern("1st", "x"))
+            .is_err());
+        assert!(Redactor::new(&[])
+            .and_then(|r| r.pattern("bad", "("))
+            .is_err());
+    }
+
+    #[test]
+    fn restores_only_when_reversible() {
+        let r = redactor(true);
+        r.redact("from ann@example.com");
+        let mut args =
+            serde_json::json!({ "to": ["[EMAIL_1]"], "body": "hi [EMAIL_1], see [TODO_1]" });
+        assert!(r.restore_args(&mut args).is_ok());
+        assert_eq!(
+            args,
+            serde_json::json!({
+                "to": ["ann@example.com"],
+                "body": "hi ann@example.com, see [TODO_1]",
+            })
+        );
+
+        let r = redactor(false);
+        r.redact("from ann@example.com");
+        assert_eq!(r.restore("[EMAIL_1]").ok().as_deref(), Some("[EMAIL_1]"));
+    }
+
+    #[test]
+    fn refuses_placeholders_it_no_longer_maps() {
+        let r = redactor(true);
+        r.redact("ann@example.com");
+        assert!(r.restore("to [EMAIL_1]").is_ok());
+        assert!(r.restore("to [EMAIL_2]").is_err());
+        let mut args = serde_json::json!({ "text": ["call [PHONE_1]"] });
+        assert!(r.restore_args(&mut args).is_err());
+    }
+
+    #[test]
+    fn evicts_the_oldest_value_past_the_cap() {
+        let r = redactor(true);
+        for i in 0..=MAX_MAPPED {
+            r.redact(&format!("user{i}@example.com"));
+        }
+        assert!(r.restore("[EMAIL_1]").is_err());
+        assert_eq!(
+            r.restore("[EMAIL_2]").ok().as_deref(),
+            Some("user1@example.com")
+        );
+        assert_eq!(
+            r.redact("user0@example.com"),
+            format!("[EMAIL_{}]", MAX_MAPPED + 2)
+        );
+    }
+}
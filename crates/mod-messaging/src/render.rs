@@ -0,0 +1,237 @@
+// A small document model shared by the markdown and html formats, so each
+// `format_*` function describes its output once and both renderers agree.
+
+use crate::format::Format;
+
+pub(crate) enum Node {
+    Heading(u8, String),
+    Fields(Vec<(&'static str, String)>),
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    List(Vec<String>),
+    Message(Box<MessageView>),
+    Note(String),
+}
+
+pub(crate) struct MessageView {
+    pub id: String,
+    pub sender: String,
+    pub time: String,
+    pub datetime: Option<String>,
+    pub tags: Vec<String>,
+    pub subject: Option<String>,
+    pub quote: Option<Quote>,
+    pub text: String,
+    pub attachments: Vec<String>,
+    pub reactions: Vec<String>,
+}
+
+pub(crate) struct Quote {
+    pub sender: Option<String>,
+    pub text: String,
+}
+
+pub(crate) fn render(fmt: Format, nodes: &[Node]) -> String {
+    match fmt {
+        Format::Html => html(nodes),
+        _ => markdown(nodes),
+    }
+}
+
+fn markdown(nodes: &[Node]) -> String {
+    let blocks: Vec<String> = nodes.iter().map(markdown_node).collect();
+    blocks.join("\n\n")
+}
+
+fn markdown_node(node: &Node) -> String {
+    match node {
+        Node::Heading(level, text) => {
+            format!("{} {}", "#".repeat(usize::from(*level)), one_line(text))
+        }
+        Node::Fields(fields) => fields
+            .iter()
+            .map(|(k, v)| format!("- **{k}:** {}", one_line(v)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Node::Table(header, rows) => {
+            let mut lines = vec![
+                format!("| {} |", header.join(" | ")),
+                format!("|{}|", vec!["---"; header.len()].join("|")),
+            ];
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|c| table_cell(c)).collect();
+                lines.push(format!("| {} |", cells.join(" | ")));
+            }
+            lines.join("\n")
+        }
+        Node::List(items) => items
+            .iter()
+            .map(|i| format!("- {}", one_line(i)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Node::Message(m) => markdown_message(m),
+        Node::Note(text) => format!("_{}_", one_line(text)),
+    }
+}
+
+fn markdown_message(m: &MessageView) -> String {
+    let mut meta = vec![m.time.clone(), format!("id: {}", m.id)];
+    meta.extend(m.tags.iter().cloned());
+    let mut parts = vec![format!("**{}** — {}", one_line(&m.sender), meta.join(", "))];
+    if let Some(ref subject) = m.subject {
+        parts.push(format!("**Subject:** {}", one_line(subject)));
+    }
+    if let Some(ref q) = m.quote {
+        let quoted = match q.sender {
+            Some(ref s) => format!("**{}:** {}", one_line(s), q.text),
+            None => q.text.clone(),
+        };
+        parts.push(
+            quoted
+                .lines()
+                .map(|l| format!("> {l}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+    let text = m.text.replace('\r', "");
+    if !text.trim().is_empty() {
+        parts.push(text);
+    }
+    let mut trailer = Vec::new();
+    if !m.attachments.is_empty() {
+        trailer.push(format!("Attachments: {}", m.attachments.join(", ")));
+    }
+    if !m.reactions.is_empty() {
+        trailer.push(format!("Reactions: {}", m.reactions.join(" ")));
+    }
+    if !trailer.is_empty() {
+        parts.push(trailer.join("  \n"));
+    }
+    parts.join("\n\n")
+}
+
+fn html(nodes: &[Node]) -> String {
+    let mut out = vec!["<div class=\"nexus\">".to_string()];
+    out.extend(nodes.iter().map(html_node));
+    out.push("</div>".to_string());
+    out.join("\n")
+}
+
+fn html_node(node: &Node) -> String {
+    match node {
+        Node::Heading(level, text) => format!("<h{level}>{}</h{level}>", escape(text)),
+        Node::Fields(fields) => {
+            let rows: String = fields
+                .iter()
+                .map(|(k, v)| format!("<dt>{}</dt><dd>{}</dd>", escape(k), escape(v)))
+                .collect();
+            format!("<dl>{rows}</dl>")
+        }
+        Node::Table(header, rows) => {
+            let head: String = header
+                .iter()
+                .map(|h| format!("<th>{}</th>", escape(h)))
+                .collect();
+            let body: String = rows
+                .iter()
+                .map(|row| {
+                    let cells: String = row
+                        .iter()
+                        .map(|c| format!("<td>{}</td>", escape(c)))
+                        .collect();
+                    format!("<tr>{cells}</tr>")
+                })
+                .collect();
+            format!("<table><thead><tr>{head}</tr></thead><tbody>{body}</tbody></table>")
+        }
+        Node::List(items) => {
+            let lis: String = items
+                .iter()
+                .map(|i| format!("<li>{}</li>", escape(i)))
+                .collect();
+            format!("<ul>{lis}</ul>")
+        }
+        Node::Message(m) => html_message(m),
+        Node::Note(text) => format!("<p class=\"note\"><em>{}</em></p>", escape(text)),
+    }
+}
+
+fn html_message(m: &MessageView) -> String {
+    let time = match m.datetime {
+        Some(ref dt) => format!("<time datetime=\"{}\">{}</time>", escape(dt), escape(&m.time)),
+        None => escape(&m.time),
+    };
+    let tags: String = m
+        .tags
+        .iter()
+        .map(|t| format!(" <span class=\"tag\">{}</span>", escape(t)))
+        .collect();
+    let mut out = vec![
+        format!("<article class=\"message\" data-id=\"{}\">", escape(&m.id)),
+        format!(
+            "<header><strong class=\"sender\">{}</strong> {time} <span class=\"id\">id: {}</span>{tags}</header>",
+            escape(&m.sender),
+            escape(&m.id)
+        ),
+    ];
+    if let Some(ref subject) = m.subject {
+        out.push(format!("<p class=\"subject\"><strong>{}</strong></p>", escape(subject)));
+    }
+    if let Some(ref q) = m.quote {
+        let sender = q
+            .sender
+            .as_ref()
+            .map(|s| format!("<strong>{}:</strong> ", escape(s)))
+            .unwrap_or_default();
+        out.push(format!(
+            "<blockquote class=\"reply\">{sender}{}</blockquote>",
+            multiline(&q.text)
+        ));
+    }
+    if !m.text.trim().is_empty() {
+        out.push(format!("<p class=\"text\">{}</p>", multiline(&m.text)));
+    }
+    if !m.attachments.is_empty() {
+        let lis: String = m
+            .attachments
+            .iter()
+            .map(|a| format!("<li>{}</li>", escape(a)))
+            .collect();
+        out.push(format!("<ul class=\"attachments\">{lis}</ul>"));
+    }
+    if !m.reactions.is_empty() {
+        out.push(format!(
+            "<p class=\"reactions\">{}</p>",
+            escape(&m.reactions.join(" "))
+        ));
+    }
+    out.push("</article>".to_string());
+    out.join("\n")
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn multiline(s: &str) -> String {
+    escape(&s.replace('\r', "")).replace('\n', "<br>")
+}
+
+fn one_line(s: &str) -> String {
+    s.replace('\r', "").replace('\n', " ")
+}
+
+fn table_cell(s: &str) -> String {
+    one_line(s).replace('|', "\\|")
+}
@@ -0,0 +1,207 @@
+use std::future::Future;
+use std::time::Duration;
+
+use nexus_domain::Platform;
+use nexus_error::AgentError;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // Total attempts including the first; 1 disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // Longest server-requested wait to sit out. Longer rate limits are
+    // returned to the caller with their retry_after instead.
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    // Full jitter: a random wait up to the exponential cap, so concurrent
+    // callers spread out instead of retrying in lockstep.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let millis = u64::try_from(exp.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(fastrand::u64(0..=millis))
+    }
+
+    fn delay_for(&self, err: &AgentError, attempt: u32, idempotency: Idempotency) -> Option<Duration> {
+        // A rate-limited call did nothing, so even a send can repeat it.
+        let repeatable = idempotency != Idempotency::NonIdempotent;
+        match err {
+            AgentError::RateLimited { retry_after: Some(wait), .. } => {
+                // A little jitter on top of the hint avoids a thundering herd
+                // when the window reopens.
+                let jitter = Duration::from_millis(fastrand::u64(0..=250));
+                (*wait <= self.max_retry_after).then(|| *wait + jitter)
+            }
+            AgentError::RateLimited { .. } => Some(self.backoff(attempt)),
+            AgentError::Network { .. } if repeatable => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+}
+
+// Whether repeating a call is safe. Sends may already have been delivered
+// when an error comes back, so they are only retried after a network error
+// when an idempotency key guards them: the keyed call checks for the
+// earlier attempt before sending again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    Idempotent,
+    Keyed,
+    NonIdempotent,
+}
+
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    platform: Platform,
+    operation: &str,
+    idempotency: Idempotency,
+    mut call: F,
+) -> Result<T, AgentError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AgentError>>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let err = match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        if attempt >= max_attempts {
+            return Err(err);
+        }
+        let Some(delay) = policy.delay_for(&err, attempt, idempotency) else {
+            return Err(err);
+        };
+        warn!(
+            %platform,
+            operation,
+            attempt,
+            max_attempts,
+            delay_ms = delay.as_millis() as u64,
+            error = %err,
+            "retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use nexus_error::RateLimitScope;
+
+    use super::*;
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Attempts made when every one fails with `err`.
+    async fn attempts(idempotency: Idempotency, err: fn() -> AgentError) -> u32 {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), AgentError> = with_retry(
+            &fast(),
+            Platform::Slack,
+            "send_message",
+            idempotency,
+            || {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move { Err(err()) }
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        calls.load(Ordering::SeqCst)
+    }
+
+    fn network() -> AgentError {
+        AgentError::network("connection reset")
+    }
+
+    fn throttled() -> AgentError {
+        AgentError::rate_limited(
+            "slow down",
+            Some(Duration::from_millis(1)),
+            RateLimitScope::Global,
+        )
+    }
+
+    #[tokio::test]
+    async fn keyed_sends_retry_network_errors() {
+        assert_eq!(attempts(Idempotency::Keyed, network).await, 3);
+        assert_eq!(attempts(Idempotency::Idempotent, network).await, 3);
+    }
+
+    #[tokio::test]
+    async fn unkeyed_sends_do_not_retry_network_errors() {
+        assert_eq!(attempts(Idempotency::NonIdempotent, network).await, 1);
+    }
+
+    #[tokio::test]
+    async fn every_send_retries_rate_limits() {
+        assert_eq!(attempts(Idempotency::NonIdempotent, throttled).await, 3);
+        assert_eq!(attempts(Idempotency::Keyed, throttled).await, 3);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_returned_at_once() {
+        let invalid = || AgentError::invalid_input("bad channel");
+        assert_eq!(attempts(Idempotency::Idempotent, invalid).await, 1);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_success() {
+        let calls = AtomicU32::new(0);
+        let result = with_retry(
+            &fast(),
+            Platform::Slack,
+            "send_message",
+            Idempotency::Keyed,
+            || {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if n == 0 {
+                        Err(network())
+                    } else {
+                        Ok(n)
+                    }
+                }
+            },
+        )
+        .await;
+        assert_eq!(result.ok(), Some(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,458 @@
+// Scheduled sends. Telegram and Slack hold scheduled messages themselves and
+// send them whether or not Nexus is running. Other platforms go through a
+// job queue kept as JSON in the data dir, which the running server works
+// through; jobs that came due while it was down are marked missed instead
+// of going out late.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use nexus_domain::{dry_run, Platform, ScheduleState, ScheduledMessage};
+use nexus_error::{AgentError, ErrorResponse};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::format::format_timestamp;
+use crate::retry::Idempotency;
+use crate::service::{validate_not_empty, AgentService, PlatformFailure};
+use crate::time::now_ts;
+
+// A job this late when the queue first sees it is missed rather than sent.
+const MISSED_AFTER: i64 = 15 * 60;
+// Finished jobs stay listed this long so the agent can see what happened.
+const KEEP_FINISHED: i64 = 7 * 86_400;
+
+pub struct ScheduleQueue {
+    path: PathBuf,
+    jobs: Mutex<Vec<ScheduledMessage>>,
+}
+
+impl ScheduleQueue {
+    // Jobs that were being sent when the last run stopped are marked failed,
+    // since they may or may not have gone out.
+    pub fn open(path: &Path) -> Result<Self, AgentError> {
+        let mut jobs: Vec<ScheduledMessage> = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                AgentError::internal(format!("corrupt schedule queue {}: {e}", path.display()))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(AgentError::internal(format!(
+                    "failed to read schedule queue {}: {e}",
+                    path.display()
+                )))
+            }
+        };
+        for job in jobs.iter_mut().filter(|j| j.state == ScheduleState::Sending) {
+            job.state = ScheduleState::Failed;
+            job.error = Some("interrupted while sending; check the channel before rescheduling".into());
+        }
+        let queue = Self {
+            path: path.to_path_buf(),
+            jobs: Mutex::new(Vec::new()),
+        };
+        queue.save(&jobs)?;
+        if let Ok(mut guard) = queue.jobs.lock() {
+            *guard = jobs;
+        }
+        Ok(queue)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<ScheduledMessage>>, AgentError> {
+        self.jobs
+            .lock()
+            .map_err(|_| AgentError::internal("schedule queue lock poisoned"))
+    }
+
+    // Written to a temporary file and renamed so a crash never leaves a
+    // half-written queue.
+    fn save(&self, jobs: &[ScheduledMessage]) -> Result<(), AgentError> {
+        let fail = |e: std::io::Error| {
+            AgentError::internal(format!("failed to write schedule queue {}: {e}", self.path.display()))
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(fail)?;
+        }
+        let json = serde_json::to_vec_pretty(jobs)
+            .map_err(|e| AgentError::internal(format!("failed to encode schedule queue: {e}")))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(fail)?;
+        std::fs::rename(&tmp, &self.path).map_err(fail)
+    }
+
+    pub fn add(&self, mut job: ScheduledMessage) -> Result<ScheduledMessage, AgentError> {
+        dry_run::intercept(|| {
+            format!(
+                "queue for {} in {} {}: {:?}",
+                format_timestamp(job.send_at),
+                job.platform,
+                job.channel_id,
+                job.text
+            )
+        })?;
+        let mut jobs = self.lock()?;
+        let mut n = now_ts() as u64;
+        while jobs.iter().any(|j| j.id == format!("job-{n:x}")) {
+            n += 1;
+        }
+        job.id = format!("job-{n:x}");
+        job.queued = true;
+        job.state = ScheduleState::Scheduled;
+        jobs.push(job.clone());
+        self.save(&jobs)?;
+        Ok(job)
+    }
+
+    pub fn list(&self) -> Vec<ScheduledMessage> {
+        self.jobs.lock().map(|jobs| jobs.clone()).unwrap_or_default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<ScheduledMessage> {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.iter().find(|j| j.id == id).cloned())
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<ScheduledMessage, AgentError> {
+        let mut jobs = self.lock()?;
+        let job = jobs
+            .iter_mut()
+            .find(|j| j.id == id)
+            .ok_or_else(|| AgentError::not_found(format!("no scheduled job {id}")))?;
+        if job.state != ScheduleState::Scheduled {
+            return Err(AgentError::invalid_input(format!(
+                "job {id} is already {}",
+                job.state
+            )));
+        }
+        dry_run::intercept(|| {
+            format!("cancel queued job {id}, due in {} {}", job.platform, job.channel_id)
+        })?;
+        job.state = ScheduleState::Cancelled;
+        let job = job.clone();
+        self.save(&jobs)?;
+        Ok(job)
+    }
+
+    // Claims the jobs due now, marking them sending, and marks jobs too far
+    // past due as missed. Returns (due, missed).
+    fn take_due(&self, now: i64) -> Result<(Vec<ScheduledMessage>, Vec<ScheduledMessage>), AgentError> {
+        let mut jobs = self.lock()?;
+        jobs.retain(|j| j.state == ScheduleState::Scheduled || j.send_at > now - KEEP_FINISHED);
+        let mut due = Vec::new();
+        let mut missed = Vec::new();
+        for job in jobs.iter_mut() {
+            if job.state != ScheduleState::Scheduled || job.send_at > now {
+                continue;
+            }
+            if job.send_at < now - MISSED_AFTER {
+                job.state = ScheduleState::Missed;
+                missed.push(job.clone());
+            } else {
+                job.state = ScheduleState::Sending;
+                due.push(job.clone());
+            }
+        }
+        self.save(&jobs)?;
+        Ok((due, missed))
+    }
+
+    fn finish(&self, id: &str, result: Result<String, String>) -> Result<(), AgentError> {
+        let mut jobs = self.lock()?;
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+            match result {
+                Ok(message_id) => {
+                    job.state = ScheduleState::Sent;
+                    job.message_id = Some(message_id);
+                }
+                Err(error) => {
+                    job.state = ScheduleState::Failed;
+                    job.error = Some(error);
+                }
+            }
+        }
+        self.save(&jobs)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledList {
+    pub items: Vec<ScheduledMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<PlatformFailure>,
+}
+
+impl AgentService {
+    // Schedules natively where the platform supports it, in the job queue
+    // otherwise.
+    pub async fn schedule_message(
+        &self,
+        platform: Platform,
+        channel: &str,
+        text: &str,
+        reply_to: Option<&str>,
+        send_at: i64,
+    ) -> Result<ScheduledMessage, AgentError> {
+        validate_not_empty(channel, "channel")?;
+        validate_not_empty(text, "text")?;
+        if send_at <= now_ts() {
+            return Err(AgentError::invalid_input("send_at is not in the future")
+                .with_argument("send_at")
+                .with_suggestion("Use send_message to send now"));
+        }
+        self.get(platform)?;
+
+        let scheduled = if let Some(scheduler) = self.scheduler(platform) {
+            self.call(platform, "schedule_message", Idempotency::NonIdempotent, || {
+                scheduler.schedule_message(channel, text, reply_to, send_at)
+            })
+            .await?
+        } else {
+            let queue = self.schedule_queue().ok_or_else(|| {
+                AgentError::not_implemented(format!(
+                    "{platform} has no native scheduling and the schedule queue is not running"
+                ))
+            })?;
+            queue.add(ScheduledMessage {
+                id: String::new(),
+                platform,
+                channel_id: channel.to_string(),
+                text: text.to_string(),
+                send_at,
+                state: ScheduleState::Scheduled,
+                reply_to: reply_to.map(str::to_string),
+                queued: true,
+                message_id: None,
+                error: None,
+            })?
+        };
+        info!(%platform, channel, id = %scheduled.id, send_at, "scheduled message");
+        Ok(scheduled)
+    }
+
+    // Soonest first: messages the platforms hold plus the queue's jobs,
+    // including ones recently sent, failed or missed.
+    pub async fn list_scheduled(
+        &self,
+        platform: Option<Platform>,
+        channel: Option<&str>,
+    ) -> Result<ScheduledList, AgentError> {
+        if let Some(p) = platform {
+            self.get(p)?;
+        }
+        let mut items: Vec<ScheduledMessage> = self
+            .schedule_queue()
+            .map(|q| q.list())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|j| platform.map_or(true, |p| j.platform == p))
+            .filter(|j| channel.map_or(true, |c| j.channel_id == c))
+            .collect();
+
+        let mut failures = Vec::new();
+        for (p, scheduler) in self.schedulers() {
+            if platform.is_some_and(|want| want != p) {
+                continue;
+            }
+            let listed = self
+                .call(p, "list_scheduled", Idempotency::Idempotent, || {
+                    scheduler.list_scheduled(channel)
+                })
+                .await;
+            match listed {
+                Ok(found) => items.extend(found),
+                // Asked for this platform specifically, so its error is the answer.
+                Err(e) if platform.is_some() => return Err(e),
+                Err(e) => failures.push(PlatformFailure {
+                    platform: p,
+                    error: ErrorResponse::from(&e),
+                }),
+            }
+        }
+        items.sort_by_key(|j| j.send_at);
+        Ok(ScheduledList { items, failures })
+    }
+
+    // Takes a queued job's ID, or a platform-held message's ID in `channel`.
+    // A queued job must be on `platform`, and in `channel` when one is given.
+    pub async fn cancel_scheduled(
+        &self,
+        platform: Platform,
+        channel: Option<&str>,
+        id: &str,
+    ) -> Result<(), AgentError> {
+        validate_not_empty(id, "id")?;
+        let channel = channel.filter(|c| !c.is_empty());
+        if let Some(queue) = self.schedule_queue() {
+            if let Some(job) = queue.get(id) {
+                if job.platform != platform {
+                    return Err(AgentError::not_found(format!("no scheduled {platform} job {id}"))
+                        .with_suggestion("Use list_scheduled to see job IDs"));
+                }
+                if let Some(channel) = channel.filter(|c| *c != job.channel_id) {
+                    return Err(AgentError::invalid_input(format!(
+                        "job {id} is for {}, not {channel}",
+                        job.channel_id
+                    ))
+                    .with_argument("channel"));
+                }
+                queue.cancel(id)?;
+                info!(%platform, id, "cancelled scheduled job");
+                return Ok(());
+            }
+        }
+        let scheduler = self.scheduler(platform).ok_or_else(|| {
+            AgentError::not_found(format!("no scheduled job {id}")).with_suggestion("Use list_scheduled to see job IDs")
+        })?;
+        let channel = channel.ok_or_else(|| {
+            AgentError::invalid_input(format!("cancelling a {platform} scheduled message needs its channel"))
+                .with_argument("channel")
+        })?;
+        self.call(platform, "cancel_scheduled", Idempotency::Idempotent, || {
+            scheduler.cancel_scheduled(channel, id)
+        })
+        .await?;
+        info!(%platform, channel, id, "cancelled scheduled message");
+        Ok(())
+    }
+
+    // Sends the queue's due jobs one at a time and reports missed ones.
+    async fn send_due(&self, queue: &ScheduleQueue) -> Result<(), AgentError> {
+        let (due, missed) = queue.take_due(now_ts())?;
+        for job in &missed {
+            warn!(platform = %job.platform, channel = %job.channel_id, id = %job.id, send_at = job.send_at, "scheduled message missed while the server was not running");
+        }
+        for job in due {
+            let send = async {
+                let sent = self
+                    .send_message(job.platform, &job.channel_id, &job.text, job.reply_to.as_deref())
+                    .await;
+                match sent {
+                    Ok(msg) => {
+                        info!(platform = %job.platform, id = %job.id, msg_id = %msg.id, "sent scheduled message");
+                        Ok(msg.id)
+                    }
+                    Err(e) => {
+                        warn!(platform = %job.platform, id = %job.id, error = %e, "scheduled message failed");
+                        Err(e.to_string())
+                    }
+                }
+            };
+            let arguments = json!({
+                "id": job.id,
+                "channel": job.channel_id,
+                "text": job.text,
+                "reply_to": job.reply_to,
+            });
+            let result = self.record_send("scheduled_send", job.platform, arguments, send).await;
+            queue.finish(&job.id, result)?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run_schedule(agent: Arc<AgentService>, every: Duration) {
+    let Some(queue) = agent.schedule_queue().cloned() else {
+        return;
+    };
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        if let Err(e) = agent.send_due(&queue).await {
+            warn!(error = %e, "schedule queue run failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Setup {
+        agent: AgentService,
+        queue: Arc<ScheduleQueue>,
+        path: PathBuf,
+    }
+
+    impl Drop for Setup {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    // One queued WhatsApp job for chat-a.
+    fn setup() -> (Setup, String) {
+        let path =
+            std::env::temp_dir().join(format!("nexus-schedule-{:016x}.json", fastrand::u64(..)));
+        let queue = match ScheduleQueue::open(&path) {
+            Ok(queue) => Arc::new(queue),
+            Err(e) => unreachable!("temp queue opens: {e}"),
+        };
+        let job = queue.add(ScheduledMessage {
+            id: String::new(),
+            platform: Platform::WhatsApp,
+            channel_id: "chat-a".to_string(),
+            text: "later".to_string(),
+            send_at: now_ts() + 3600,
+            state: ScheduleState::Scheduled,
+            reply_to: None,
+            queued: true,
+            message_id: None,
+            error: None,
+        });
+        let Ok(job) = job else {
+            unreachable!("job is queued")
+        };
+        let mut agent = AgentService::new();
+        agent.set_schedule_queue(queue.clone());
+        (Setup { agent, queue, path }, job.id)
+    }
+
+    fn state(setup: &Setup, id: &str) -> Option<ScheduleState> {
+        setup.queue.get(id).map(|j| j.state)
+    }
+
+    #[tokio::test]
+    async fn queued_job_is_cancelled_only_where_it_is_going() {
+        let (setup, id) = setup();
+        let other = setup
+            .agent
+            .cancel_scheduled(Platform::Discord, Some("chat-a"), &id)
+            .await;
+        assert!(
+            matches!(other, Err(AgentError::NotFound { .. })),
+            "{other:?}"
+        );
+        let wrong = setup
+            .agent
+            .cancel_scheduled(Platform::WhatsApp, Some("chat-b"), &id)
+            .await;
+        assert!(
+            matches!(wrong, Err(AgentError::InvalidInput { .. })),
+            "{wrong:?}"
+        );
+        assert_eq!(state(&setup, &id), Some(ScheduleState::Scheduled));
+
+        let done = setup
+            .agent
+            .cancel_scheduled(Platform::WhatsApp, Some("chat-a"), &id)
+            .await;
+        assert!(done.is_ok(), "{done:?}");
+        assert_eq!(state(&setup, &id), Some(ScheduleState::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn queued_job_needs_no_channel() {
+        let (setup, id) = setup();
+        let done = setup
+            .agent
+            .cancel_scheduled(Platform::WhatsApp, None, &id)
+            .await;
+        assert!(done.is_ok(), "{done:?}");
+        assert_eq!(state(&setup, &id), Some(ScheduleState::Cancelled));
+    }
+}
@@ -1,12 +1,218 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
-use nexus_domain::{Channel, Message, MessagingPort, Paginated, Platform, Profile};
-use nexus_error::AgentError;
-use tracing::info;
+use futures::{future, stream, StreamExt};
+use nexus_domain::{
+    Channel, DeliveryStatus, EventPort, EventStream, Message, MessagingPort, Paginated, Platform,
+    Event, Profile, ReadAnchor, SchedulePort, SearchClause, SearchQuery, TimeRange, UserPort,
+};
+use nexus_error::{AgentError, ErrorResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::approval::ApprovalQueue;
+use crate::audit::AuditLog;
+use crate::cursor;
+use crate::directory::{ChatDirectory, UserDirectory};
+use crate::middleware::{Guard, Stack};
+use crate::query;
+use crate::outbox::Outbox;
+use crate::redact::Redactor;
+use crate::retry::{with_retry, Idempotency, RetryPolicy};
+use crate::schedule::ScheduleQueue;
+use crate::store::MessageStore;
+
+const FANOUT_TIMEOUT: Duration = Duration::from_secs(20);
+const INBOX_CHANNELS: usize = 10;
+const CHANNEL_CONCURRENCY: usize = 4;
+const DIRECTORY_TTL: Duration = Duration::from_secs(3600);
+const CHAT_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub platform: Platform,
+    #[serde(flatten)]
+    pub page: Paginated<Message>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<SearchClause>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    #[default]
+    Recent,
+    Relevance,
+}
+
+impl std::fmt::Display for SearchOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Recent => write!(f, "recent"),
+            Self::Relevance => write!(f, "relevance"),
+        }
+    }
+}
+
+impl SearchOrder {
+    pub fn parse(s: Option<&str>) -> Result<Self, AgentError> {
+        match s {
+            None | Some("recent") => Ok(Self::Recent),
+            Some("relevance") => Ok(Self::Relevance),
+            Some(other) => Err(AgentError::invalid_input(format!(
+                "unknown order '{other}', expected recent or relevance"
+            ))
+            .with_argument("order")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IgnoredClauses {
+    pub platform: Platform,
+    pub clauses: Vec<SearchClause>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergedSearchResults {
+    pub items: Vec<Message>,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    pub order: SearchOrder,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored: Vec<IgnoredClauses>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<PlatformFailure>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<Platform>,
+}
+
+// Where one platform's search left off: the page at `cursor`, fetched with
+// `size` so page boundaries stay stable, minus the `skip` results already
+// returned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SearchPosition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+    #[serde(default)]
+    skip: usize,
+    size: usize,
+}
+
+struct SearchLane {
+    platform: Platform,
+    pos: SearchPosition,
+    items: std::vec::IntoIter<Message>,
+    taken: usize,
+    next_cursor: Option<String>,
+}
+
+impl SearchLane {
+    fn next_position(&self) -> Option<SearchPosition> {
+        if self.items.len() > 0 {
+            return Some(SearchPosition {
+                skip: self.pos.skip + self.taken,
+                ..self.pos.clone()
+            });
+        }
+        self.next_cursor.as_ref().map(|c| SearchPosition {
+            cursor: Some(c.clone()),
+            skip: 0,
+            size: self.pos.size,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformFailure {
+    pub platform: Platform,
+    pub error: ErrorResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboxItem {
+    pub channel: String,
+    #[serde(flatten)]
+    pub message: Message,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboxPage {
+    pub items: Vec<InboxItem>,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<PlatformFailure>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<Platform>,
+}
+
+#[derive(Default)]
+struct InboxBatch {
+    items: Vec<InboxItem>,
+    has_more: bool,
+}
+
+// Where one platform's inbox left off: messages up to `until`, minus those
+// at exactly `until` that were already returned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct InboxPosition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    seen: Vec<String>,
+}
+
+impl InboxPosition {
+    fn admits(&self, msg: &Message) -> bool {
+        match self.until {
+            Some(until) => {
+                msg.timestamp < until
+                    || (msg.timestamp == until && !self.seen.contains(&seen_key(msg)))
+            }
+            None => true,
+        }
+    }
+
+    fn advance<'a>(mut self, consumed: impl Iterator<Item = &'a Message>) -> Self {
+        for msg in consumed {
+            match self.until {
+                Some(until) if msg.timestamp == until => self.seen.push(seen_key(msg)),
+                Some(until) if msg.timestamp > until => {}
+                _ => {
+                    self.until = Some(msg.timestamp);
+                    self.seen = vec![seen_key(msg)];
+                }
+            }
+        }
+        self
+    }
+}
+
+fn seen_key(msg: &Message) -> String {
+    format!("{}/{}", msg.channel_id, msg.id)
+}
 
 pub struct AgentService {
     adapters: HashMap<Platform, Arc<dyn MessagingPort>>,
+    event_sources: HashMap<Platform, Arc<dyn EventPort>>,
+    retry: RetryPolicy,
+    platform_retry: HashMap<Platform, RetryPolicy>,
+    store: Option<Arc<MessageStore>>,
+    directory: Arc<UserDirectory>,
+    chats: Arc<ChatDirectory>,
+    guard: Option<Guard>,
+    schedulers: HashMap<Platform, Arc<dyn SchedulePort>>,
+    schedule_queue: Option<Arc<ScheduleQueue>>,
+    approval_queue: Option<Arc<ApprovalQueue>>,
+    outbox: Option<Arc<Outbox>>,
+    redactor: Option<Arc<Redactor>>,
+    audit: Option<Arc<AuditLog>>,
 }
 
 impl Default for AgentService {
@@ -19,19 +225,219 @@ impl AgentService {
     pub fn new() -> Self {
         Self {
             adapters: HashMap::new(),
+            event_sources: HashMap::new(),
+            retry: RetryPolicy::default(),
+            platform_retry: HashMap::new(),
+            store: None,
+            directory: Arc::new(UserDirectory::new(DIRECTORY_TTL)),
+            chats: Arc::new(ChatDirectory::new(CHAT_TTL)),
+            guard: None,
+            schedulers: HashMap::new(),
+            schedule_queue: None,
+            approval_queue: None,
+            outbox: None,
+            redactor: None,
+            audit: None,
         }
     }
 
-    pub fn register(&mut self, adapter: Arc<dyn MessagingPort>) {
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    pub fn set_platform_retry_policy(&mut self, platform: Platform, policy: RetryPolicy) {
+        self.platform_retry.insert(platform, policy);
+    }
+
+    // The store only answers local_search and offline reads here; recording
+    // is StoreLayer's job.
+    pub fn set_store(&mut self, store: Arc<MessageStore>) {
+        self.store = Some(store);
+    }
+
+    pub fn store(&self) -> Option<&Arc<MessageStore>> {
+        self.store.as_ref()
+    }
+
+    // Shared with the DirectoryLayer, which names senders in replies; the
+    // agent uses it for events, which do not pass through the layers.
+    pub fn directory(&self) -> &Arc<UserDirectory> {
+        &self.directory
+    }
+
+    // Resolves chats for the access policy, through the bare adapters.
+    pub fn chats(&self) -> &Arc<ChatDirectory> {
+        &self.chats
+    }
+
+    // The policy layer guards adapter calls; the service applies the same
+    // guard to what it reads from the store.
+    pub fn set_guard(&mut self, guard: Guard) {
+        self.guard = Some(guard);
+    }
+
+    pub(crate) fn guard(&self) -> Option<&Guard> {
+        self.guard.as_ref()
+    }
+
+    pub fn register_users(&self, resolver: Arc<dyn UserPort>) {
+        self.directory.register(resolver);
+    }
+
+    // Platforms that schedule natively; the rest use the queue.
+    pub fn register_scheduler(&mut self, scheduler: Arc<dyn SchedulePort>) {
+        self.schedulers.insert(scheduler.platform(), scheduler);
+    }
+
+    pub(crate) fn scheduler(&self, platform: Platform) -> Option<&Arc<dyn SchedulePort>> {
+        self.schedulers.get(&platform)
+    }
+
+    pub(crate) fn schedulers(&self) -> impl Iterator<Item = (Platform, &Arc<dyn SchedulePort>)> {
+        self.schedulers.iter().map(|(p, s)| (*p, s))
+    }
+
+    pub fn set_schedule_queue(&mut self, queue: Arc<ScheduleQueue>) {
+        self.schedule_queue = Some(queue);
+    }
+
+    pub fn schedule_queue(&self) -> Option<&Arc<ScheduleQueue>> {
+        self.schedule_queue.as_ref()
+    }
+
+    pub fn set_approval_queue(&mut self, queue: Arc<ApprovalQueue>) {
+        self.approval_queue = Some(queue);
+    }
+
+    pub fn approval_queue(&self) -> Option<&Arc<ApprovalQueue>> {
+        self.approval_queue.as_ref()
+    }
+
+    pub fn set_outbox(&mut self, outbox: Arc<Outbox>) {
+        self.outbox = Some(outbox);
+    }
+
+    pub fn outbox(&self) -> Option<&Outbox> {
+        self.outbox.as_deref()
+    }
+
+    // Applied by the server to tool output; the service itself returns
+    // unmasked data.
+    pub fn set_redactor(&mut self, redactor: Arc<Redactor>) {
+        self.redactor = Some(redactor);
+    }
+
+    pub fn redactor(&self) -> Option<&Redactor> {
+        self.redactor.as_deref()
+    }
+
+    // The server records tool calls; the service records the sends it makes
+    // on its own, from the schedule and approval queues.
+    pub fn set_audit_log(&mut self, log: Arc<AuditLog>) {
+        self.audit = Some(log);
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_deref()
+    }
+
+    pub fn retry_policy(&self, platform: Platform) -> &RetryPolicy {
+        self.platform_retry.get(&platform).unwrap_or(&self.retry)
+    }
+
+    // Runs one adapter call under the platform's retry policy and tags any
+    // error with the platform and operation.
+    pub(crate) async fn call<T, F, Fut>(
+        &self,
+        platform: Platform,
+        operation: &str,
+        idempotency: Idempotency,
+        call: F,
+    ) -> Result<T, AgentError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, AgentError>>,
+    {
+        with_retry(self.retry_policy(platform), platform, operation, idempotency, call)
+            .await
+            .map_err(|e| e.in_context(platform, operation))
+    }
+
+    // Bounds one platform's share of a fan-out call so a slow platform cannot
+    // hold up the others.
+    async fn within<T>(
+        platform: Platform,
+        operation: &str,
+        timeout: Duration,
+        fut: impl Future<Output = Result<T, AgentError>>,
+    ) -> Result<T, AgentError> {
+        tokio::time::timeout(timeout, fut)
+            .await
+            .unwrap_or_else(|_| {
+                Err(AgentError::network(format!(
+                    "{platform} did not respond within {}s",
+                    timeout.as_secs()
+                )))
+            })
+            .map_err(|e| e.in_context(platform, operation))
+    }
+
+    // The adapter is wrapped in `stack`; pass `Stack::new()` for none.
+    pub fn register(&mut self, adapter: Arc<dyn MessagingPort>, stack: &Stack) {
         let platform = adapter.platform();
         info!(%platform, "registered adapter");
+        self.chats.register(adapter.clone());
+        let adapter: Arc<dyn MessagingPort> = if stack.is_empty() {
+            adapter
+        } else {
+            stack.wrap(adapter)
+        };
         self.adapters.insert(platform, adapter);
     }
 
+    pub fn register_events(&mut self, source: Arc<dyn EventPort>) {
+        let platform = source.platform();
+        info!(%platform, "registered event source");
+        self.event_sources.insert(platform, source);
+    }
+
+    // Merges every platform's events into one stream. A platform that fails
+    // to subscribe is logged and left out rather than failing the rest.
+    pub async fn subscribe_events(&self) -> Result<EventStream, AgentError> {
+        if self.event_sources.is_empty() {
+            return Err(AgentError::platform_not_available(
+                "no connected platform provides events",
+            ));
+        }
+        let mut streams = Vec::with_capacity(self.event_sources.len());
+        for (platform, source) in &self.event_sources {
+            match source.subscribe_events().await {
+                Ok(events) => streams.push(events),
+                Err(e) => warn!(%platform, error = %e, "event subscription failed"),
+            }
+        }
+        let directory = self.directory.clone();
+        let events = stream::select_all(streams).then(move |event| {
+            let directory = directory.clone();
+            async move {
+                match event {
+                    Event::MessageCreated { mut message } => {
+                        directory.name_senders(std::slice::from_mut(&mut *message)).await;
+                        Event::MessageCreated { message }
+                    }
+                    other => other,
+                }
+            }
+        });
+        Ok(Box::pin(events))
+    }
+
     pub fn get(&self, platform: Platform) -> Result<&Arc<dyn MessagingPort>, AgentError> {
         self.adapters
             .get(&platform)
-            .ok_or_else(|| AgentError::platform_not_available(format!("{platform}")))
+            .ok_or_else(|| {
+                AgentError::platform_not_available(format!("{platform}")).with_platform(platform.to_string())
+            })
     }
 
     pub fn available_platforms(&self) -> Vec<Platform> {
@@ -39,7 +445,12 @@ impl AgentService {
     }
 
     pub async fn get_profile(&self, platform: Platform) -> Result<Profile, AgentError> {
-        self.get(platform)?.get_profile().await
+        let adapter = self.get(platform)?;
+        self
+            .call(platform, "get_profile", Idempotency::Idempotent, || {
+                adapter.get_profile()
+            })
+            .await
     }
 
     pub async fn list_channels(
@@ -48,7 +459,12 @@ impl AgentService {
         limit: usize,
     ) -> Result<Vec<Channel>, AgentError> {
         let limit = clamp(limit, 1, 100, 20);
-        let channels = self.get(platform)?.list_channels(limit).await?;
+        let adapter = self.get(platform)?;
+        let channels = self
+            .call(platform, "list_channels", Idempotency::Idempotent, || {
+                adapter.list_channels(limit)
+            })
+            .await?;
         info!(%platform, count = channels.len(), "listed channels");
         Ok(channels)
     }
@@ -59,11 +475,29 @@ impl AgentService {
         channel: &str,
         limit: usize,
         cursor: Option<&str>,
+        anchor: Option<ReadAnchor>,
+        range: TimeRange,
     ) -> Result<Paginated<Message>, AgentError> {
         validate_not_empty(channel, "channel")?;
+        let (cursor, skip) = cursor::resume_point(cursor)?;
+        if let Some(ref anchor) = anchor {
+            validate_not_empty(anchor.message_id(), "after/around")?;
+            if cursor.is_some() {
+                return Err(AgentError::invalid_input(
+                    "use either a cursor or an after/around anchor, not both",
+                )
+                .with_argument("cursor"));
+            }
+        }
         let limit = clamp(limit, 1, 200, 20);
-        let result = self.get(platform)?.read_messages(channel, limit, cursor).await?;
-        info!(%platform, channel, count = result.items.len(), has_more = result.has_more, "read messages");
+        let adapter = self.get(platform)?;
+        let mut result = self
+            .call(platform, "read_messages", Idempotency::Idempotent, || {
+                adapter.read_messages(channel, limit, cursor.as_deref(), anchor.as_ref(), range)
+            })
+            .await?;
+        skip_shown(&mut result.items, skip);
+        info!(%platform, channel, ?anchor, ?range, count = result.items.len(), has_more = result.has_more, "read messages");
         Ok(result)
     }
 
@@ -76,9 +510,11 @@ impl AgentService {
     ) -> Result<Message, AgentError> {
         validate_not_empty(channel, "channel")?;
         validate_not_empty(text, "text")?;
+        let adapter = self.get(platform)?;
         let msg = self
-            .get(platform)?
-            .send_message(channel, text, reply_to)
+            .call(platform, "send_message", Idempotency::NonIdempotent, || {
+                adapter.send_message(channel, text, reply_to)
+            })
             .await?;
         info!(%platform, channel, msg_id = %msg.id, "sent message");
         Ok(msg)
@@ -90,22 +526,396 @@ impl AgentService {
         query: &str,
         limit: usize,
         cursor: Option<&str>,
-    ) -> Result<Paginated<Message>, AgentError> {
-        validate_not_empty(query, "query")?;
+        range: TimeRange,
+    ) -> Result<SearchResults, AgentError> {
         let limit = clamp(limit, 1, 100, 20);
-        let result = self.get(platform)?.search(query, limit, cursor).await?;
-        info!(%platform, query, count = result.items.len(), has_more = result.has_more, "searched");
-        Ok(result)
+        let parsed = parse_search(query, range)?;
+        let (cursor, skip) = cursor::resume_point(cursor)?;
+
+        let adapter = self.get(platform)?;
+        let unsupported = adapter.unsupported_clauses(&parsed);
+        if !unsupported.is_empty() {
+            warn!(%platform, ?unsupported, "ignoring unsupported search clauses");
+        }
+        let parsed = parsed.without(&unsupported);
+        if parsed.is_empty() {
+            return Err(AgentError::invalid_input(format!(
+                "nothing left to search on {platform} after dropping unsupported clauses"
+            ))
+            .with_argument("query"));
+        }
+
+        let mut page = self
+            .call(platform, "search", Idempotency::Idempotent, || {
+                adapter.search(&parsed, limit, cursor.as_deref())
+            })
+            .await?;
+        skip_shown(&mut page.items, skip);
+        info!(%platform, query, range = ?parsed.range, count = page.items.len(), has_more = page.has_more, "searched");
+        Ok(SearchResults {
+            platform,
+            page,
+            unsupported,
+        })
+    }
+
+    // Collects recent (or only unread) messages from every connected platform
+    // concurrently and merges them newest first. Platforms that fail are
+    // listed in `failed` rather than failing the whole call.
+    pub async fn unified_inbox(
+        &self,
+        limit: usize,
+        unread_only: bool,
+        cursor: Option<&str>,
+        range: TimeRange,
+    ) -> Result<InboxPage, AgentError> {
+        if self.adapters.is_empty() {
+            return Err(AgentError::platform_not_available("no platforms connected"));
+        }
+        let limit = clamp(limit, 1, 200, 30);
+        let (cursor, skip) = cursor::resume_point(cursor)?;
+        let positions: BTreeMap<Platform, InboxPosition> = match cursor.as_deref() {
+            Some(c) => cursor::decode(c)?,
+            None => self.adapters.keys().map(|&p| (p, InboxPosition::default())).collect(),
+        };
+
+        let fetches = positions
+            .iter()
+            .filter(|(p, _)| self.adapters.contains_key(p))
+            .map(|(&platform, pos)| async move {
+                let batch = self.platform_inbox(platform, limit, unread_only, pos, range);
+                (platform, Self::within(platform, "unified_inbox", FANOUT_TIMEOUT, batch).await)
+            });
+        let results = future::join_all(fetches).await;
+
+        let mut failed = Vec::new();
+        let mut unsupported = Vec::new();
+        let mut batches = Vec::new();
+        let mut retry = BTreeMap::new();
+        for (platform, result) in results {
+            match result {
+                Ok(batch) => batches.push((platform, batch)),
+                Err(AgentError::NotImplemented { .. }) => unsupported.push(platform),
+                Err(e) => {
+                    warn!(%platform, error = %e, "inbox fetch failed");
+                    failed.push(PlatformFailure {
+                        platform,
+                        error: ErrorResponse::from(&e),
+                    });
+                    retry.insert(platform, positions.get(&platform).cloned().unwrap_or_default());
+                }
+            }
+        }
+
+        let mut items: Vec<InboxItem> = batches
+            .iter_mut()
+            .flat_map(|(_, batch)| std::mem::take(&mut batch.items))
+            .collect();
+        items.sort_by_key(|i| Reverse(i.message.timestamp));
+        let leftover = items.split_off(items.len().min(limit));
+
+        let mut next = BTreeMap::new();
+        for (platform, batch) in &batches {
+            let platform = *platform;
+            let more = batch.has_more || leftover.iter().any(|i| i.message.platform == platform);
+            if more {
+                let consumed = items
+                    .iter()
+                    .map(|i| &i.message)
+                    .filter(|m| m.platform == platform);
+                let pos = positions.get(&platform).cloned().unwrap_or_default();
+                next.insert(platform, pos.advance(consumed));
+            }
+        }
+        let has_more = !next.is_empty();
+        // Failed platforms are retried from where they were on the next page.
+        let next_cursor = has_more.then(|| {
+            next.extend(retry);
+            cursor::encode(&next)
+        });
+        skip_shown(&mut items, skip);
+
+        info!(count = items.len(), has_more, failed = failed.len(), "unified inbox");
+        Ok(InboxPage {
+            items,
+            has_more,
+            next_cursor,
+            failed,
+            unsupported,
+        })
+    }
+
+    async fn platform_inbox(
+        &self,
+        platform: Platform,
+        limit: usize,
+        unread_only: bool,
+        pos: &InboxPosition,
+        range: TimeRange,
+    ) -> Result<InboxBatch, AgentError> {
+        let adapter = self.get(platform)?;
+        let mut channels = self
+            .call(platform, "list_channels", Idempotency::Idempotent, || {
+                adapter.list_channels(100)
+            })
+            .await?;
+        // Platforms that report unread counts or activity dates only
+        // contribute those channels; the rest contribute their first few.
+        let active = |c: &Channel| c.unread_count > 0 || c.last_message_date.is_some();
+        if unread_only {
+            channels.retain(|c| c.unread_count > 0);
+        } else if channels.iter().any(active) {
+            channels.retain(active);
+        }
+        channels.sort_by_key(|c| Reverse((c.last_message_date, c.unread_count)));
+        channels.truncate(INBOX_CHANNELS);
+
+        let reads = channels.into_iter().map(|ch| async move {
+            // The unread messages are the newest `unread_count` ones, so they
+            // are always read from the top and filtered by position after.
+            let (want, bound) = if unread_only {
+                ((ch.unread_count.max(0) as usize).min(200), range)
+            } else {
+                (limit, range.intersect(TimeRange::new(None, pos.until)))
+            };
+            let result = self
+                .call(platform, "read_messages", Idempotency::Idempotent, || {
+                    adapter.read_messages(&ch.id, want, None, None, bound)
+                })
+                .await;
+            (ch, want, result)
+        });
+        let results: Vec<_> = stream::iter(reads)
+            .buffer_unordered(CHANNEL_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut batch = InboxBatch::default();
+        let mut first_err = None;
+        let mut any_ok = false;
+        for (ch, want, result) in results {
+            match result {
+                Ok(page) => {
+                    any_ok = true;
+                    batch.has_more |= page.has_more && (!unread_only || ch.unread_count as usize > want);
+                    batch.items.extend(
+                        page.items
+                            .into_iter()
+                            .filter(|m| pos.admits(m))
+                            .map(|message| InboxItem {
+                                channel: ch.name.clone(),
+                                message,
+                            }),
+                    );
+                }
+                Err(e) => {
+                    warn!(%platform, channel = %ch.id, error = %e, "inbox read failed");
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        match first_err {
+            Some(e) if !any_ok => Err(e),
+            _ => Ok(batch),
+        }
+    }
+
+    // Runs one query on every platform that can search, in parallel, and
+    // interleaves the results. Platforms whose part of the query is entirely
+    // unsupported, or that cannot search at all, are listed as unsupported.
+    pub async fn search_all(
+        &self,
+        query: &str,
+        limit: usize,
+        order: SearchOrder,
+        cursor: Option<&str>,
+        range: TimeRange,
+        timeout: Option<Duration>,
+    ) -> Result<MergedSearchResults, AgentError> {
+        if self.adapters.is_empty() {
+            return Err(AgentError::platform_not_available("no platforms connected"));
+        }
+        let limit = clamp(limit, 1, 100, 20);
+        let timeout = timeout.unwrap_or(FANOUT_TIMEOUT);
+        let mut parsed = parse_search(query, range)?;
+        parsed.by_relevance = order == SearchOrder::Relevance;
+        let (cursor, skip) = cursor::resume_point(cursor)?;
+        let positions: BTreeMap<Platform, SearchPosition> = match cursor.as_deref() {
+            Some(c) => cursor::decode(c)?,
+            None => self
+                .adapters
+                .keys()
+                .map(|&p| (p, SearchPosition { size: limit, ..Default::default() }))
+                .collect(),
+        };
+
+        let mut unsupported = Vec::new();
+        let mut ignored = Vec::new();
+        let mut jobs = Vec::new();
+        for (&platform, pos) in &positions {
+            let Some(adapter) = self.adapters.get(&platform) else {
+                continue;
+            };
+            let dropped = adapter.unsupported_clauses(&parsed);
+            let platform_query = parsed.without(&dropped);
+            if platform_query.is_empty() {
+                unsupported.push(platform);
+                continue;
+            }
+            if !dropped.is_empty() {
+                ignored.push(IgnoredClauses {
+                    platform,
+                    clauses: dropped,
+                });
+            }
+            jobs.push((platform, adapter, pos, platform_query));
+        }
+
+        let fetches = jobs.iter().map(|(platform, adapter, pos, q)| async move {
+            let platform = *platform;
+            let page = self.call(platform, "search", Idempotency::Idempotent, || {
+                adapter.search(q, pos.size.clamp(1, 100), pos.cursor.as_deref())
+            });
+            (platform, *pos, Self::within(platform, "search_all", timeout, page).await)
+        });
+        let results = future::join_all(fetches).await;
+
+        let mut failed = Vec::new();
+        let mut lanes = Vec::new();
+        let mut retry = BTreeMap::new();
+        for (platform, pos, result) in results {
+            match result {
+                Ok(page) => {
+                    let next_cursor = page.next_cursor.filter(|_| page.has_more);
+                    let mut items = page.items;
+                    items.drain(..pos.skip.min(items.len()));
+                    lanes.push(SearchLane {
+                        platform,
+                        pos: pos.clone(),
+                        items: items.into_iter(),
+                        taken: 0,
+                        next_cursor,
+                    });
+                }
+                Err(AgentError::NotImplemented { .. }) => unsupported.push(platform),
+                Err(e) => {
+                    warn!(%platform, error = %e, "search failed");
+                    failed.push(PlatformFailure {
+                        platform,
+                        error: ErrorResponse::from(&e),
+                    });
+                    retry.insert(platform, pos.clone());
+                }
+            }
+        }
+
+        let mut items = merge_lanes(&mut lanes, limit, order);
+        skip_shown(&mut items, skip);
+        let mut next: BTreeMap<Platform, SearchPosition> = lanes
+            .iter()
+            .filter_map(|lane| lane.next_position().map(|pos| (lane.platform, pos)))
+            .collect();
+        let has_more = !next.is_empty();
+        let next_cursor = has_more.then(|| {
+            next.extend(retry);
+            cursor::encode(&next)
+        });
+
+        info!(query, count = items.len(), has_more, failed = failed.len(), "searched all platforms");
+        Ok(MergedSearchResults {
+            items,
+            has_more,
+            next_cursor,
+            order,
+            ignored,
+            failed,
+            unsupported,
+        })
+    }
+
+    pub async fn get_delivery_status(
+        &self,
+        platform: Platform,
+        channel: &str,
+        message_id: &str,
+    ) -> Result<DeliveryStatus, AgentError> {
+        validate_not_empty(message_id, "message_id")?;
+        let adapter = self.get(platform)?;
+        let status = self
+            .call(platform, "get_delivery_status", Idempotency::Idempotent, || {
+                adapter.get_delivery_status(channel, message_id)
+            })
+            .await?;
+        info!(%platform, channel, message_id, state = %status.state, "delivery status");
+        Ok(status)
     }
 }
 
-fn validate_not_empty(val: &str, name: &str) -> Result<(), AgentError> {
+pub(crate) fn parse_search(query: &str, range: TimeRange) -> Result<SearchQuery, AgentError> {
+    validate_not_empty(query, "query")?;
+    let mut parsed = query::parse_query(query).map_err(|e| e.with_argument("query"))?;
+    parsed.range = parsed.range.intersect(range);
+    if let (Some(s), Some(u)) = (parsed.range.since, parsed.range.until) {
+        if s > u {
+            return Err(
+                AgentError::invalid_input("query dates and since/until do not overlap")
+                    .with_argument("since/until"),
+            );
+        }
+    }
+    Ok(parsed)
+}
+
+// Interleaves the lanes without reordering any one platform's results, so
+// each lane only ever gives up a prefix and can resume exactly. By recency,
+// merging stops once a lane with more pages runs dry, since its next result
+// could be newer than anything left in the others.
+fn merge_lanes(lanes: &mut [SearchLane], limit: usize, order: SearchOrder) -> Vec<Message> {
+    let mut merged = Vec::with_capacity(limit);
+    let mut turn = 0;
+    while merged.len() < limit {
+        let pick = match order {
+            SearchOrder::Recent => {
+                if lanes
+                    .iter()
+                    .any(|l| l.items.len() == 0 && l.next_cursor.is_some())
+                {
+                    break;
+                }
+                lanes
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, l)| l.items.as_slice().first().map(|m| (i, m.timestamp)))
+                    .max_by_key(|&(_, ts)| ts)
+                    .map(|(i, _)| i)
+            }
+            SearchOrder::Relevance => (0..lanes.len())
+                .map(|k| (turn + k) % lanes.len())
+                .find(|&i| lanes[i].items.len() > 0),
+        };
+        let Some(i) = pick else { break };
+        if let Some(msg) = lanes[i].items.next() {
+            lanes[i].taken += 1;
+            merged.push(msg);
+        }
+        turn = i + 1;
+    }
+    merged
+}
+
+pub(crate) fn validate_not_empty(val: &str, name: &str) -> Result<(), AgentError> {
     if val.trim().is_empty() {
-        return Err(AgentError::invalid_input(format!("{name} cannot be empty")));
+        return Err(AgentError::invalid_input(format!("{name} cannot be empty")).with_argument(name));
     }
     Ok(())
 }
 
+// Drops the items a budgeted response already showed when a page is
+// fetched again through a resume cursor.
+pub(crate) fn skip_shown<T>(items: &mut Vec<T>, skip: usize) {
+    items.drain(..skip.min(items.len()));
+}
+
 fn clamp(val: usize, min: usize, max: usize, default: usize) -> usize {
     if val == 0 {
         default
@@ -0,0 +1,734 @@
+// An optional SQLite copy of every message Nexus has seen, with an FTS5
+// index over sender, subject and text. It answers `local_search` across all
+// platforms, serves reads while a platform is down, and is kept current by
+// `StoreLayer` and a periodic `sync_store`.
+
+use std::cmp::Reverse;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use nexus_domain::{
+    Channel, Message, Paginated, Platform, ReadAnchor, SearchClause, SearchQuery, TimeRange,
+};
+use nexus_error::{AgentError, ErrorResponse};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::cursor;
+use crate::middleware::Access;
+use crate::service::{
+    parse_search, skip_shown, validate_not_empty, AgentService, InboxItem, PlatformFailure,
+    SearchOrder,
+};
+use crate::time::now_ts;
+
+const STORED_PREFIX: &str = "stored:";
+const SYNC_CHANNELS: usize = 20;
+const SYNC_PAGES: usize = 5;
+const SYNC_PAGE_SIZE: usize = 100;
+const SYNC_CONCURRENCY: usize = 4;
+
+// Column weights for bm25: a match in the subject counts most, then the
+// sender, then the body.
+const RANK: &str = "bm25(messages_fts, 2.0, 3.0, 1.0)";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS messages (
+    platform TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    id TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    subject TEXT,
+    text TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    has_attachment INTEGER NOT NULL,
+    json TEXT NOT NULL,
+    stored_at INTEGER NOT NULL,
+    PRIMARY KEY (platform, channel_id, id)
+);
+CREATE INDEX IF NOT EXISTS messages_by_channel ON messages (platform, channel_id, timestamp);
+CREATE INDEX IF NOT EXISTS messages_by_time ON messages (timestamp);
+
+CREATE TABLE IF NOT EXISTS channels (
+    platform TEXT NOT NULL,
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (platform, id)
+);
+
+-- How far back each channel is known to be complete.
+CREATE TABLE IF NOT EXISTS sync_marks (
+    platform TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    synced_to INTEGER NOT NULL,
+    PRIMARY KEY (platform, channel_id)
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+    sender, subject, text,
+    content = 'messages', content_rowid = 'rowid',
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
+CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
+    INSERT INTO messages_fts (rowid, sender, subject, text)
+    VALUES (new.rowid, new.sender, new.subject, new.text);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, sender, subject, text)
+    VALUES ('delete', old.rowid, old.sender, old.subject, old.text);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, sender, subject, text)
+    VALUES ('delete', old.rowid, old.sender, old.subject, old.text);
+    INSERT INTO messages_fts (rowid, sender, subject, text)
+    VALUES (new.rowid, new.sender, new.subject, new.text);
+END;
+";
+
+// The store's calls block on SQLite, so async code runs them through `run`.
+pub struct MessageStore {
+    conn: Mutex<Connection>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalQuery {
+    pub query: SearchQuery,
+    pub platform: Option<Platform>,
+    pub order: SearchOrder,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalResults {
+    pub items: Vec<InboxItem>,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    pub order: SearchOrder,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored: Vec<SearchClause>,
+}
+
+// A `read_messages` page, from the platform or, when it could not be reached,
+// from the store.
+#[derive(Debug, Serialize)]
+pub struct ReadPage {
+    #[serde(flatten)]
+    pub page: Paginated<Message>,
+    // Why the page came from the store, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub channels: usize,
+    pub messages: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<PlatformFailure>,
+}
+
+impl MessageStore {
+    pub fn open(path: &Path) -> Result<Self, AgentError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                AgentError::internal(format!("cannot create {}: {e}", dir.display()))
+            })?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        info!(path = %path.display(), "opened message store");
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, AgentError> {
+        self.conn
+            .lock()
+            .map_err(|_| AgentError::internal("message store lock poisoned"))
+    }
+
+    // Runs `f` on the blocking thread pool, off the async workers.
+    pub async fn run<T, F>(self: &Arc<Self>, f: F) -> Result<T, AgentError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&MessageStore) -> Result<T, AgentError> + Send + 'static,
+    {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || f(&store))
+            .await
+            .map_err(|e| AgentError::internal(format!("message store task failed: {e}")))?
+    }
+
+    pub fn record(&self, messages: &[Message]) -> Result<usize, AgentError> {
+        if messages.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(db_error)?;
+        {
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT INTO messages
+                        (platform, channel_id, id, sender, subject, text, timestamp, has_attachment, json, stored_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT (platform, channel_id, id) DO UPDATE SET
+                        sender = excluded.sender, subject = excluded.subject, text = excluded.text,
+                        timestamp = excluded.timestamp, has_attachment = excluded.has_attachment,
+                        json = excluded.json, stored_at = excluded.stored_at",
+                )
+                .map_err(db_error)?;
+            let now = now_ts();
+            for msg in messages {
+                let json = serde_json::to_string(msg)
+                    .map_err(|e| AgentError::internal(format!("cannot store message: {e}")))?;
+                stmt.execute(params![
+                    msg.platform.to_string(),
+                    msg.channel_id,
+                    msg.id,
+                    msg.sender,
+                    msg.meta.subject,
+                    msg.text,
+                    msg.timestamp,
+                    msg.has_attachment,
+                    json,
+                    now,
+                ])
+                .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(messages.len())
+    }
+
+    // Remembers channel names so `in:` and results can use them.
+    pub fn record_channels(&self, channels: &[Channel]) -> Result<(), AgentError> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(db_error)?;
+        {
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT INTO channels (platform, id, name) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (platform, id) DO UPDATE SET name = excluded.name",
+                )
+                .map_err(db_error)?;
+            for ch in channels {
+                stmt.execute(params![ch.platform.to_string(), ch.id, ch.name])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    pub fn newest(&self, platform: Platform, channel: &str) -> Result<Option<i64>, AgentError> {
+        self.conn()?
+            .query_row(
+                "SELECT MAX(timestamp) FROM messages WHERE platform = ?1 AND channel_id = ?2",
+                params![platform.to_string(), channel],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+    }
+
+    // Where the last complete sync of a channel reached.
+    fn sync_mark(&self, platform: Platform, channel: &str) -> Result<Option<i64>, AgentError> {
+        self.conn()?
+            .query_row(
+                "SELECT MAX(synced_to) FROM sync_marks WHERE platform = ?1 AND channel_id = ?2",
+                params![platform.to_string(), channel],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+    }
+
+    fn set_sync_mark(&self, platform: Platform, channel: &str, ts: i64) -> Result<(), AgentError> {
+        self.conn()?
+            .execute(
+                "INSERT INTO sync_marks (platform, channel_id, synced_to) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (platform, channel_id) DO UPDATE SET synced_to = excluded.synced_to",
+                params![platform.to_string(), channel, ts],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    // A channel's messages newest first, strictly older than `before`, the
+    // (timestamp, rowid) of the last message on the previous page.
+    fn channel_page(
+        &self,
+        platform: Platform,
+        channel: &str,
+        limit: usize,
+        before: Option<(i64, i64)>,
+        range: TimeRange,
+    ) -> Result<Paginated<Message>, AgentError> {
+        let (ts, row) = before.unwrap_or((i64::MAX, i64::MAX));
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT json, timestamp, rowid FROM messages
+                 WHERE platform = ?1 AND channel_id = ?2
+                   AND (timestamp < ?3 OR (timestamp = ?3 AND rowid < ?4))
+                   AND timestamp >= ?5 AND timestamp <= ?6
+                 ORDER BY timestamp DESC, rowid DESC
+                 LIMIT ?7",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(
+                params![
+                    platform.to_string(),
+                    channel,
+                    ts,
+                    row,
+                    range.since.unwrap_or(i64::MIN),
+                    range.until.unwrap_or(i64::MAX),
+                    (limit + 1) as i64,
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                },
+            )
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        let has_more = rows.len() > limit;
+        let mut items = Vec::with_capacity(limit);
+        let mut last = None;
+        for (json, ts, rowid) in rows.into_iter().take(limit) {
+            items.push(decode(&json)?);
+            last = Some((ts, rowid));
+        }
+        Ok(Paginated {
+            items,
+            has_more,
+            next_cursor: last
+                .filter(|_| has_more)
+                .map(|(ts, rowid)| format!("{STORED_PREFIX}{ts}:{rowid}")),
+            prev_cursor: None,
+        })
+    }
+
+    pub fn search(&self, q: &LocalQuery) -> Result<LocalResults, AgentError> {
+        let mut sql = String::from(
+            "SELECT m.json, c.name FROM messages m
+             LEFT JOIN channels c ON c.platform = m.platform AND c.id = m.channel_id",
+        );
+        let mut conds = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        let text = match_expression(&q.query);
+        if let Some(ref expr) = text {
+            sql.push_str(" JOIN messages_fts ON messages_fts.rowid = m.rowid");
+            conds.push("messages_fts MATCH ?");
+            args.push(Value::Text(expr.clone()));
+        }
+        if let Some(platform) = q.platform {
+            conds.push("m.platform = ?");
+            args.push(Value::Text(platform.to_string()));
+        }
+        if let Some(ref from) = q.query.from {
+            conds.push("m.sender LIKE ? ESCAPE '\\'");
+            args.push(Value::Text(format!("%{}%", like_escape(from))));
+        }
+        if let Some(ref channel) = q.query.channel {
+            let channel = channel.trim_start_matches('#');
+            conds.push("(m.channel_id = ? OR c.name = ? COLLATE NOCASE)");
+            args.push(Value::Text(channel.to_string()));
+            args.push(Value::Text(channel.to_string()));
+        }
+        if q.query.has_attachment {
+            conds.push("m.has_attachment = 1");
+        }
+        if let Some(since) = q.query.range.since {
+            conds.push("m.timestamp >= ?");
+            args.push(Value::Integer(since));
+        }
+        if let Some(until) = q.query.range.until {
+            conds.push("m.timestamp <= ?");
+            args.push(Value::Integer(until));
+        }
+        if !conds.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conds.join(" AND "));
+        }
+        match (q.order, &text) {
+            (SearchOrder::Relevance, Some(_)) => {
+                sql.push_str(&format!(" ORDER BY {RANK}, m.timestamp DESC"));
+            }
+            _ => sql.push_str(" ORDER BY m.timestamp DESC, m.rowid DESC"),
+        }
+        sql.push_str(" LIMIT ? OFFSET ?");
+        args.push(Value::Integer((q.limit + 1) as i64));
+        args.push(Value::Integer(q.offset as i64));
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&sql).map_err(db_error)?;
+        let rows = stmt
+            .query_map(params_from_iter(args), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        let has_more = rows.len() > q.limit;
+        let items = rows
+            .into_iter()
+            .take(q.limit)
+            .map(|(json, name)| {
+                let message = decode(&json)?;
+                Ok(InboxItem {
+                    channel: name.unwrap_or_else(|| message.channel_id.clone()),
+                    message,
+                })
+            })
+            .collect::<Result<Vec<_>, AgentError>>()?;
+        let next_cursor = has_more.then(|| (q.offset + items.len()).to_string());
+        Ok(LocalResults {
+            items,
+            has_more,
+            next_cursor,
+            order: q.order,
+            ignored: Vec::new(),
+        })
+    }
+}
+
+impl AgentService {
+    // Searches the store across every platform, whether or not the platform
+    // is connected or can search at all.
+    pub async fn local_search(
+        &self,
+        query: &str,
+        platform: Option<Platform>,
+        limit: usize,
+        order: SearchOrder,
+        cursor: Option<&str>,
+        range: TimeRange,
+    ) -> Result<LocalResults, AgentError> {
+        let store = self.store().ok_or_else(|| {
+            AgentError::platform_not_available("the local message store is not enabled")
+                .with_suggestion("Set NEXUS_STORE=1 and restart Nexus")
+        })?;
+        let parsed = parse_search(query, range)?;
+        let ignored: Vec<SearchClause> = parsed
+            .clauses()
+            .into_iter()
+            .filter(|c| *c == SearchClause::IsUnread)
+            .collect();
+        let parsed = parsed.without(&ignored);
+        if parsed.is_empty() {
+            return Err(AgentError::invalid_input(
+                "nothing left to search after dropping is:unread",
+            )
+            .with_argument("query"));
+        }
+        // Terms with no letters or digits are dropped from the full-text
+        // match, so without a filter the query would return everything.
+        let filtered = parsed.from.is_some()
+            || parsed.channel.is_some()
+            || parsed.has_attachment
+            || !parsed.range.is_empty();
+        if match_expression(&parsed).is_none() && !filtered {
+            return Err(AgentError::invalid_input(format!(
+                "'{query}' has no letters or digits to search for"
+            ))
+            .with_argument("query"));
+        }
+        let (cursor, skip) = cursor::resume_point(cursor)?;
+        let offset = match cursor.as_deref() {
+            Some(c) => c.parse().map_err(|_| {
+                AgentError::invalid_input(format!("invalid cursor: {c}")).with_argument("cursor")
+            })?,
+            None => 0,
+        };
+
+        let request = LocalQuery {
+            query: parsed,
+            platform,
+            order,
+            limit: limit.clamp(1, 100),
+            offset,
+        };
+        let mut results = store.run(move |s| s.search(&request)).await?;
+        if let Some(guard) = self.guard() {
+            guard.retain_by(&mut results.items, |i| &i.message).await;
+        }
+        results.ignored = ignored;
+        skip_shown(&mut results.items, skip);
+        info!(
+            query,
+            count = results.items.len(),
+            has_more = results.has_more,
+            "searched local store"
+        );
+        Ok(results)
+    }
+
+    // Only first pages fall back to the store, since a platform's cursor
+    // means nothing to it; stored pages continue with cursors of their own.
+    pub async fn read_messages_or_stored(
+        &self,
+        platform: Platform,
+        channel: &str,
+        limit: usize,
+        cursor: Option<&str>,
+        anchor: Option<ReadAnchor>,
+        range: TimeRange,
+    ) -> Result<ReadPage, AgentError> {
+        let (inner, skip) = cursor::resume_point(cursor)?;
+        let page_size = if limit == 0 { 20 } else { limit.clamp(1, 200) };
+        if let (Some(store), Some(c)) = (self.store(), inner.as_deref()) {
+            if let Some(before) = c.strip_prefix(STORED_PREFIX) {
+                validate_not_empty(channel, "channel")?;
+                self.guard_stored_read(platform, channel).await?;
+                let before = parse_stored_cursor(before)?;
+                let chat = channel.to_string();
+                let mut page = store
+                    .run(move |s| s.channel_page(platform, &chat, page_size, Some(before), range))
+                    .await?;
+                skip_shown(&mut page.items, skip);
+                return Ok(ReadPage {
+                    page,
+                    offline: Some("continuing from stored messages".to_string()),
+                });
+            }
+        }
+
+        let first_page = inner.is_none() && anchor.is_none();
+        match self
+            .read_messages(platform, channel, limit, cursor, anchor, range)
+            .await
+        {
+            Ok(page) => Ok(ReadPage {
+                page,
+                offline: None,
+            }),
+            Err(e) if first_page && unreachable(&e) => {
+                let Some(store) = self.store() else {
+                    return Err(e);
+                };
+                self.guard_stored_read(platform, channel).await?;
+                let chat = channel.to_string();
+                let mut page = store
+                    .run(move |s| s.channel_page(platform, &chat, page_size, None, range))
+                    .await?;
+                if page.items.is_empty() {
+                    return Err(e);
+                }
+                skip_shown(&mut page.items, skip);
+                warn!(%platform, channel, error = %e, "platform unreachable, reading from store");
+                Ok(ReadPage {
+                    page,
+                    offline: Some(ErrorResponse::from(&e).to_compact()),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Stored reads skip the adapters and their policy layer.
+    async fn guard_stored_read(&self, platform: Platform, channel: &str) -> Result<(), AgentError> {
+        match self.guard() {
+            Some(guard) => {
+                guard
+                    .check(platform, "read_messages", Access::Read, Some(channel))
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Pulls recent history for the busiest channels on every platform into
+    // the store. The reads go through the adapters' layers like any other,
+    // so the limiter and policy still apply.
+    pub async fn sync_store(&self) -> Result<SyncReport, AgentError> {
+        let store = self.store().ok_or_else(|| {
+            AgentError::platform_not_available("the local message store is not enabled")
+        })?;
+        let mut report = SyncReport::default();
+        for platform in self.available_platforms() {
+            let mut channels = match self.list_channels(platform, 100).await {
+                Ok(channels) => channels,
+                Err(AgentError::NotImplemented { .. }) => continue,
+                Err(e) => {
+                    warn!(%platform, error = %e, "sync: listing channels failed");
+                    report.failed.push(PlatformFailure {
+                        platform,
+                        error: ErrorResponse::from(&e),
+                    });
+                    continue;
+                }
+            };
+            let listed = channels.clone();
+            if let Err(e) = store.run(move |s| s.record_channels(&listed)).await {
+                warn!(%platform, error = %e, "sync: storing channels failed");
+            }
+            channels.sort_by_key(|c| Reverse((c.last_message_date, c.unread_count)));
+            channels.truncate(SYNC_CHANNELS);
+
+            let syncs = channels
+                .into_iter()
+                .map(|ch| async move { self.sync_channel(store, platform, &ch.id).await });
+            let results: Vec<_> = stream::iter(syncs)
+                .buffer_unordered(SYNC_CONCURRENCY)
+                .collect()
+                .await;
+            let mut first_err = None;
+            for result in results {
+                match result {
+                    Ok(n) => {
+                        report.channels += 1;
+                        report.messages += n;
+                    }
+                    Err(AgentError::NotImplemented { .. }) => {}
+                    Err(e) => {
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+            if let Some(e) = first_err {
+                warn!(%platform, error = %e, "sync: reading a channel failed");
+                report.failed.push(PlatformFailure {
+                    platform,
+                    error: ErrorResponse::from(&e),
+                });
+            }
+        }
+        info!(
+            channels = report.channels,
+            messages = report.messages,
+            failed = report.failed.len(),
+            "synced message store"
+        );
+        Ok(report)
+    }
+
+    // Reads a channel back to where its last complete sync reached, however
+    // many pages that takes. The mark only moves once a run gets there, so a
+    // run cut short by an error or a restart is redone rather than leaving a
+    // gap. A channel synced for the first time gets SYNC_PAGES pages.
+    async fn sync_channel(
+        &self,
+        store: &Arc<MessageStore>,
+        platform: Platform,
+        channel: &str,
+    ) -> Result<usize, AgentError> {
+        let chat = channel.to_string();
+        let mark = store
+            .run(move |s| match s.sync_mark(platform, &chat)? {
+                Some(mark) => Ok(Some(mark)),
+                // Stores from before marks were kept.
+                None => s.newest(platform, &chat),
+            })
+            .await?;
+        let pages = if mark.is_some() { usize::MAX } else { SYNC_PAGES };
+        let range = TimeRange::new(mark, None);
+        let mut cursor: Option<String> = None;
+        let mut synced = 0;
+        let mut top = None;
+        for _ in 0..pages {
+            let page = self
+                .read_messages(
+                    platform,
+                    channel,
+                    SYNC_PAGE_SIZE,
+                    cursor.as_deref(),
+                    None,
+                    range,
+                )
+                .await?;
+            top = top.max(page.items.iter().map(|m| m.timestamp).max());
+            let more = page.has_more && !page.items.is_empty();
+            // StoreLayer records these too when it is in the stack; writing
+            // them here keeps a sync useful without it.
+            let items = page.items;
+            synced += store.run(move |s| s.record(&items)).await?;
+            match page.next_cursor {
+                Some(next) if more && cursor.as_ref() != Some(&next) => cursor = Some(next),
+                _ => break,
+            }
+        }
+        if let Some(top) = top {
+            let chat = channel.to_string();
+            store.run(move |s| s.set_sync_mark(platform, &chat, top)).await?;
+        }
+        Ok(synced)
+    }
+}
+
+pub async fn sync_periodically(agent: std::sync::Arc<AgentService>, every: Duration) {
+    let mut tick = tokio::time::interval(every);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        if let Err(e) = agent.sync_store().await {
+            warn!(error = %e, "message store sync failed");
+        }
+    }
+}
+
+// Errors that mean the platform is down or out of reach, rather than that
+// the request was wrong or not allowed.
+fn unreachable(err: &AgentError) -> bool {
+    matches!(
+        err,
+        AgentError::Network { .. }
+            | AgentError::RateLimited { .. }
+            | AgentError::Session { .. }
+            | AgentError::PlatformNotAvailable { .. }
+    )
+}
+
+// Terms are matched as whole (stemmed) words and phrases as phrases, all
+// required. Quoting each one keeps FTS5 operators in user input inert.
+fn match_expression(q: &SearchQuery) -> Option<String> {
+    let parts: Vec<String> = q
+        .terms
+        .iter()
+        .chain(q.phrases.iter())
+        .filter(|t| t.chars().any(char::is_alphanumeric))
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn like_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn parse_stored_cursor(s: &str) -> Result<(i64, i64), AgentError> {
+    s.split_once(':')
+        .and_then(|(ts, row)| Some((ts.parse().ok()?, row.parse().ok()?)))
+        .ok_or_else(|| {
+            AgentError::invalid_input(format!("invalid cursor: {STORED_PREFIX}{s}"))
+                .with_argument("cursor")
+        })
+}
+
+fn decode(json: &str) -> Result<Message, AgentError> {
+    serde_json::from_str(json)
+        .map_err(|e| AgentError::internal(format!("corrupt stored message: {e}")))
+}
+
+fn db_error(e: rusqlite::Error) -> AgentError {
+    AgentError::internal(format!("message store: {e}"))
+}
@@ -0,0 +1,178 @@
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use nexus_domain::TimeRange;
+use nexus_error::AgentError;
+
+static TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+pub fn set_timezone(tz: Tz) {
+    let _ = TIMEZONE.set(tz);
+}
+
+pub fn timezone() -> Tz {
+    TIMEZONE.get().copied().unwrap_or(Tz::UTC)
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz, AgentError> {
+    name.trim().parse::<Tz>().map_err(|_| {
+        AgentError::invalid_input(format!(
+            "unknown timezone '{name}' (expected an IANA name like 'Europe/Berlin' or 'UTC')"
+        ))
+    })
+}
+
+pub fn now_ts() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// A bare date used as `until` covers the whole day.
+pub fn parse_range(since: Option<&str>, until: Option<&str>) -> Result<TimeRange, AgentError> {
+    let now = now_ts();
+    let since = since
+        .map(|s| parse_bound(s, now, false).map_err(|e| bound_error("since", e)))
+        .transpose()?;
+    let until = until
+        .map(|s| parse_bound(s, now, true).map_err(|e| bound_error("until", e)))
+        .transpose()?;
+
+    if let (Some(s), Some(u)) = (since, until) {
+        if s > u {
+            return Err(
+                AgentError::invalid_input("'since' must not be later than 'until'").with_argument("since"),
+            );
+        }
+    }
+
+    Ok(TimeRange::new(since, until))
+}
+
+// Start of the given moment; bare dates resolve to midnight in the configured timezone.
+pub fn parse_instant(input: &str) -> Result<i64, AgentError> {
+    parse_bound(input, now_ts(), false)
+}
+
+// A moment ahead of now: relative spans count forward ("2h", "in 30m"),
+// everything else parses as for `since`.
+pub fn parse_future(input: &str) -> Result<i64, AgentError> {
+    let now = now_ts();
+    let s = input.trim();
+    let relative = s.strip_prefix("in ").or_else(|| s.strip_prefix('+')).unwrap_or(s);
+    if let Some(secs) = parse_relative(relative.trim()) {
+        return Ok(now + secs);
+    }
+    parse_bound(s, now, false)
+}
+
+fn parse_bound(input: &str, now: i64, end_of_day: bool) -> Result<i64, AgentError> {
+    let s = input.trim();
+    if s.is_empty() {
+        return Err(AgentError::invalid_input("empty date"));
+    }
+
+    let tz = timezone();
+    match s.to_lowercase().as_str() {
+        "now" => return Ok(now),
+        "today" | "yesterday" => {
+            let today = tz
+                .timestamp_opt(now, 0)
+                .single()
+                .map(|dt| dt.date_naive())
+                .ok_or_else(|| AgentError::internal("current time out of range"))?;
+            let day = if s.eq_ignore_ascii_case("yesterday") {
+                today.pred_opt().unwrap_or(today)
+            } else {
+                today
+            };
+            return day_bound(day, end_of_day);
+        }
+        _ => {}
+    }
+
+    if let Some(secs) = parse_relative(s) {
+        return Ok(now - secs);
+    }
+
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        return s
+            .parse::<i64>()
+            .map_err(|e| AgentError::invalid_input(format!("invalid unix timestamp '{s}': {e}")));
+    }
+
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.timestamp());
+    }
+
+    for fmt in DATETIME_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+            return local_to_ts(naive);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return day_bound(date, end_of_day);
+    }
+
+    Err(AgentError::invalid_input(format!(
+        "unrecognized date '{s}' (use ISO 8601 like 2025-01-15 or 2025-01-15T10:30:00Z, or relative like 2d)"
+    )))
+}
+
+fn parse_relative(s: &str) -> Option<i64> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    if split == 0 {
+        return None;
+    }
+    let (num, unit) = s.split_at(split);
+    let n: i64 = num.parse().ok()?;
+    let unit_secs = match unit.trim() {
+        "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hr" | "hrs" => 3600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "week" | "weeks" => 604_800,
+        _ => return None,
+    };
+    n.checked_mul(unit_secs)
+}
+
+fn day_bound(date: NaiveDate, end_of_day: bool) -> Result<i64, AgentError> {
+    if !end_of_day {
+        return local_to_ts(date.and_time(NaiveTime::MIN));
+    }
+    let next = date
+        .checked_add_signed(Duration::days(1))
+        .ok_or_else(|| AgentError::invalid_input("date out of range"))?;
+    Ok(local_to_ts(next.and_time(NaiveTime::MIN))? - 1)
+}
+
+fn local_to_ts(naive: NaiveDateTime) -> Result<i64, AgentError> {
+    timezone()
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp())
+        .ok_or_else(|| AgentError::invalid_input(format!("{naive} does not exist in {}", timezone())))
+}
+
+fn bound_error(name: &str, err: AgentError) -> AgentError {
+    let err = match err {
+        AgentError::InvalidInput { message, ctx } => AgentError::InvalidInput {
+            message: format!("{name}: {message}"),
+            ctx,
+        },
+        other => other,
+    };
+    err.with_argument(name)
+}